@@ -0,0 +1,191 @@
+extern crate proc_macro;
+
+use crate::functions::*;
+use crate::to_sql::{KeyType, StructFieldData};
+use proc_macro2::{Ident, TokenStream};
+use quote::quote;
+use syn::{Generics, Visibility};
+
+impl StructFieldData {
+    /// Returns the value a field of the builder starts with when it doesn't need to be set, or
+    /// `None` when the field is required.
+    ///
+    /// `Option` fields start as `None`, fields with the `default` attribute with their default
+    /// value, and integer primary keys as `0`, which marks them as not set yet.
+    fn builder_default(&self) -> Option<TokenStream> {
+        if is_option(&self.rust_type) {
+            return Some(quote!(None));
+        }
+        if self.default {
+            return Some(quote!(Default::default()));
+        }
+        match get_ident_name_from_path(&self.rust_type) {
+            Ok(ident)
+                if self.key_type == KeyType::PrimaryKey
+                    && (ident == "i16" || ident == "i32" || ident == "i64") =>
+            {
+                Some(quote!(0))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Builds the `{Struct}Builder` struct, which sets the fields of a struct one by one.
+///
+/// Every required field has a type parameter, which is `()` until the field is set and a tuple
+/// with its value afterwards, so `build()` only exists once all required fields are set, and a
+/// required field can't be set twice.
+pub(crate) fn build_builder(
+    name: &Ident,
+    generics: &Generics,
+    visibility: &Visibility,
+    field_list: &[StructFieldData],
+) -> syn::Result<TokenStream> {
+    if !generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(
+            generics,
+            "the 'builder' attribute can't be used on a generic struct",
+        ));
+    }
+    let builder_name = Ident::new(&format!("{}Builder", name), name.span());
+    let doc = format!(
+        "Builds a [`{}`] field by field. Created by `{}::builder()`, and turned into the struct \
+         with `build()` once all required fields are set.",
+        name, name
+    );
+    let required: Vec<&StructFieldData> = field_list
+        .iter()
+        .filter(|field| field.builder_default().is_none())
+        .collect();
+    let params: Vec<Ident> = (0..required.len())
+        .map(|i| Ident::new(&format!("__Required{}", i), name.span()))
+        .collect();
+
+    let builder_fields = field_list.iter().map(|field| {
+        let field_name = field.name.rust_name();
+        let rust_type = &field.rust_type;
+        match required
+            .iter()
+            .position(|other| other.name.rust_name() == field_name)
+        {
+            Some(i) => {
+                let param = &params[i];
+                quote!(#field_name: #param)
+            }
+            None => quote!(#field_name: #rust_type),
+        }
+    });
+    let initial_values = field_list.iter().map(|field| {
+        let field_name = field.name.rust_name();
+        let value = field.builder_default().unwrap_or_else(|| quote!(()));
+        quote!(#field_name: #value)
+    });
+
+    let required_setters = required.iter().enumerate().map(|(i, field)| {
+        let field_name = field.name.rust_name();
+        let rust_type = &field.rust_type;
+        let other_params: Vec<&Ident> = params
+            .iter()
+            .enumerate()
+            .filter(|(j, _)| *j != i)
+            .map(|(_, param)| param)
+            .collect();
+        let before =
+            params.iter().enumerate().map(
+                |(j, param)| {
+                    if j == i {
+                        quote!(())
+                    } else {
+                        quote!(#param)
+                    }
+                },
+            );
+        let after = params.iter().enumerate().map(|(j, param)| {
+            if j == i {
+                quote!((#rust_type,))
+            } else {
+                quote!(#param)
+            }
+        });
+        let values = field_list.iter().map(|other| {
+            let other_name = other.name.rust_name();
+            if other_name == field_name {
+                quote!(#other_name: (#field_name,))
+            } else {
+                quote!(#other_name: self.#other_name)
+            }
+        });
+        let doc = format!("Sets the required field `{}`.", field_name);
+        quote!(
+            impl<#(#other_params),*> #builder_name<#(#before),*> {
+                #[doc = #doc]
+                pub fn #field_name(self, #field_name: #rust_type) -> #builder_name<#(#after),*> {
+                    #builder_name {
+                        #(#values),*
+                    }
+                }
+            }
+        )
+    });
+    let optional_setters = field_list
+        .iter()
+        .filter(|field| field.builder_default().is_some())
+        .map(|field| {
+            let field_name = field.name.rust_name();
+            let rust_type = &field.rust_type;
+            let doc = format!("Sets the field `{}`.", field_name);
+            quote!(
+                #[doc = #doc]
+                pub fn #field_name(mut self, #field_name: #rust_type) -> Self {
+                    self.#field_name = #field_name;
+                    self
+                }
+            )
+        });
+    let set_params = required.iter().map(|field| {
+        let rust_type = &field.rust_type;
+        quote!((#rust_type,))
+    });
+    let built_values = field_list.iter().map(|field| {
+        let field_name = field.name.rust_name();
+        if field.builder_default().is_some() {
+            quote!(#field_name: self.#field_name)
+        } else {
+            quote!(#field_name: self.#field_name.0)
+        }
+    });
+    let build_doc = format!("Returns the [`{}`] with the values that are set.", name);
+
+    Ok(quote!(
+        #[doc = #doc]
+        #[must_use]
+        #visibility struct #builder_name<#(#params = ()),*> {
+            #(#builder_fields),*
+        }
+
+        impl #name {
+            /// Returns a builder with none of the required fields set.
+            #visibility fn builder() -> #builder_name {
+                #builder_name {
+                    #(#initial_values),*
+                }
+            }
+        }
+
+        impl<#(#params),*> #builder_name<#(#params),*> {
+            #(#optional_setters)*
+        }
+
+        #(#required_setters)*
+
+        impl #builder_name<#(#set_params),*> {
+            #[doc = #build_doc]
+            pub fn build(self) -> #name {
+                #name {
+                    #(#built_values),*
+                }
+            }
+        }
+    ))
+}
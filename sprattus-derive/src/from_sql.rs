@@ -1,7 +1,151 @@
-use proc_macro2::{Ident, Literal};
+use crate::functions::{add_type_param_bounds, identifier_name, type_to_string};
+use proc_macro2::{Ident, Literal, TokenStream};
+use quote::quote;
+use syn::{parse_quote, FieldsUnnamed, Generics, Path, Type};
 
-#[derive(Debug)]
 pub(crate) struct SqlField {
     pub rust_name: Ident,
     pub sql_name: Literal,
+    pub rust_type: Type,
+    pub default: bool,
+    pub from: Option<Path>,
+}
+
+impl SqlField {
+    /// Returns the type that is read from the row for the field, which applies the conversion
+    /// function of the `from` attribute.
+    pub(crate) fn read_type(&self, struct_name: &Ident) -> TokenStream {
+        let rust_type = &self.rust_type;
+        match &self.from {
+            Some(_) => {
+                let conversion = conversion_name(struct_name, &self.rust_name);
+                quote!(sprattus::convert::FromSqlConverted<#rust_type, #conversion>)
+            }
+            None => quote!(#rust_type),
+        }
+    }
+
+    /// Returns the expression converting the value read from the row into the value of the field.
+    pub(crate) fn converted_value(&self) -> TokenStream {
+        match &self.from {
+            Some(_) => quote!(.map(sprattus::convert::FromSqlConverted::into_inner)),
+            None => TokenStream::new(),
+        }
+    }
+}
+
+/// Returns the name of the type implementing the `from` conversion of a field.
+fn conversion_name(struct_name: &Ident, field_name: &Ident) -> Ident {
+    Ident::new(
+        &format!(
+            "__Sprattus{}{}FromSql",
+            struct_name,
+            identifier_name(field_name)
+        ),
+        field_name.span(),
+    )
+}
+
+/// Builds the implementations of `FromSqlConversion` for the fields with the `from` attribute.
+pub(crate) fn build_from_sql_conversions(struct_name: &Ident, fields: &[SqlField]) -> TokenStream {
+    let conversions = fields.iter().filter_map(|field| {
+        let from = field.from.as_ref()?;
+        let rust_type = &field.rust_type;
+        let conversion = conversion_name(struct_name, &field.rust_name);
+        Some(quote!(
+            #[doc(hidden)]
+            #[allow(non_camel_case_types)]
+            struct #conversion;
+
+            impl sprattus::convert::FromSqlConversion<#rust_type> for #conversion {
+                fn from_sql(
+                    ty: &sprattus::types::Type,
+                    raw: &[u8],
+                ) -> Result<#rust_type, Box<dyn std::error::Error + Sync + Send>> {
+                    sprattus::convert::from_sql(#from, ty, raw)
+                }
+
+                fn from_sql_null(
+                    ty: &sprattus::types::Type,
+                ) -> Result<#rust_type, Box<dyn std::error::Error + Sync + Send>> {
+                    sprattus::convert::from_sql_null(#from, ty)
+                }
+
+                fn accepts(ty: &sprattus::types::Type) -> bool {
+                    sprattus::convert::from_sql_accepts::<#rust_type, _, _, _>(#from, ty)
+                }
+            }
+        ))
+    });
+    quote!(#(#conversions)*)
+}
+
+/// Returns the generics of a struct with the bounds needed to read its fields from a row.
+pub(crate) fn from_sql_generics(generics: &Generics) -> Generics {
+    add_type_param_bounds(
+        generics,
+        &[parse_quote!(for<'__sprattus> FromSqlItem<'__sprattus>)],
+    )
+}
+
+/// Builds the `FromSql` implementation for a tuple struct, which maps its fields to the columns of
+/// the row by position. For single field tuple structs (newtypes), `FromSqlItem` is implemented as
+/// well, so the newtype can be used as the type of a field in other structs.
+pub(crate) fn build_tuple_from_sql_implementation(
+    name: &Ident,
+    generics: &Generics,
+    fields: &FieldsUnnamed,
+) -> TokenStream {
+    let struct_name = name.to_string();
+    let field_lines = fields.unnamed.iter().enumerate().map(|(index, field)| {
+        let field_name = index.to_string();
+        let index = Literal::usize_unsuffixed(index);
+        let rust_type = type_to_string(&field.ty);
+        quote!(
+            row.try_get(#index).map_err(|error| {
+                let column_name = row.columns().get(#index).map_or("", |column| column.name());
+                Error::mapping(row, column_name, #struct_name, #field_name, #rust_type, error)
+            })?
+        )
+    });
+    let bounded_generics = from_sql_generics(generics);
+    let (impl_generics, _, where_clause) = bounded_generics.split_for_impl();
+    let (_, ty_generics, _) = generics.split_for_impl();
+    let mut tokens = quote! {
+        impl #impl_generics FromSql for #name #ty_generics #where_clause {
+            fn from_row(row: &Row) -> Result<Self, Error> where Self: Sized {
+                Ok(Self(#(#field_lines),*))
+            }
+        }
+    };
+    if fields.unnamed.len() == 1 {
+        let inner_type = &fields.unnamed[0].ty;
+        let mut item_generics = add_type_param_bounds(
+            generics,
+            &[parse_quote!(sprattus::FromSqlItem<'__sprattus>)],
+        );
+        item_generics.params.insert(0, parse_quote!('__sprattus));
+        let (impl_generics, _, where_clause) = item_generics.split_for_impl();
+        tokens.extend(quote! {
+            impl #impl_generics sprattus::FromSqlItem<'__sprattus> for #name #ty_generics #where_clause {
+                fn from_sql(
+                    ty: &sprattus::types::Type,
+                    raw: &'__sprattus [u8],
+                ) -> Result<Self, Box<dyn std::error::Error + Sync + Send>> {
+                    <#inner_type as sprattus::FromSqlItem<'__sprattus>>::from_sql(ty, raw).map(#name)
+                }
+
+                fn from_sql_null(
+                    ty: &sprattus::types::Type,
+                ) -> Result<Self, Box<dyn std::error::Error + Sync + Send>> {
+                    <#inner_type as sprattus::FromSqlItem<'__sprattus>>::from_sql_null(ty).map(#name)
+                }
+
+                fn accepts(ty: &sprattus::types::Type) -> bool {
+                    <#inner_type as sprattus::FromSqlItem<'__sprattus>>::accepts(ty)
+                }
+            }
+        });
+    }
+    tokens
 }
@@ -1,207 +1,516 @@
 extern crate proc_macro;
 
-use crate::to_sql::KeyType::{NoKey, PrimaryKey, PrimaryKeyCandidate};
+use crate::to_sql::KeyType::{NoKey, PrimaryKey};
 use crate::to_sql::*;
-use proc_macro2::TokenTree::{Group, Ident as Ident2, Punct};
-use proc_macro2::{Ident, Literal, Span, TokenTree};
+use proc_macro2::{Ident, Literal, TokenStream, TokenTree};
+use quote::{quote, quote_spanned, ToTokens};
+use syn::spanned::Spanned;
 use syn::PathArguments::AngleBracketed;
-use syn::Type::Path;
-use syn::{Attribute, Field, GenericArgument, Type};
+use syn::Type::{Path, Reference};
+use syn::{
+    Attribute, Data, DeriveInput, Field, GenericArgument, Generics, Lit, Meta, NestedMeta, Type,
+    TypeParamBound, Variant,
+};
 
-pub(crate) fn get_field_name(field: &Field) -> Ident {
+/// The keys that can be used in `#[sprattus(...)]` attributes on a struct, and whether they take a value.
+const STRUCT_KEYS: &[(&str, bool)] = &[
+    ("table", true),
+    ("view", true),
+    ("materialized_view", true),
+    ("history_table", true),
+    ("insert_model", false),
+    ("builder", false),
+    ("lifecycle", false),
+    ("of", true),
+    ("auto_primary_key", false),
+    ("no_primary_key", false),
+    ("unique", true),
+    ("quote", false),
+];
+
+/// The keys that can be used in `#[sprattus(...)]` attributes on a variant of an enum deriving
+/// `SqlTextEnum`, and whether they take a value.
+const VARIANT_KEYS: &[(&str, bool)] = &[("rename", true)];
+
+/// The keys that can be used more than once on the same struct or field.
+const REPEATABLE_KEYS: &[&str] = &["unique"];
+
+/// The keys that can be used in `#[sprattus(...)]` attributes on a field, and whether they take a value.
+const FIELD_KEYS: &[(&str, bool)] = &[
+    ("primary_key", false),
+    ("name", true),
+    ("pg_type", true),
+    ("default", false),
+    ("default_on_none", false),
+    ("immutable", false),
+    ("created_at", false),
+    ("updated_at", false),
+    ("tenant_key", false),
+    ("masked", false),
+    ("with", true),
+    ("from", true),
+    ("unsigned", true),
+    ("validate", false),
+];
+
+/// The generic types of which the Postgres type doesn't depend on their type parameters.
+const OWN_TYPE_GENERICS: &[&str] = &["HashMap", "Json", "Geometry", "Geography"];
+
+///
+/// Adds bounds to every type parameter of a struct, like the derive macros of the standard library
+/// do, so fields with a generic type can be converted from and to Postgres values.
+///
+pub(crate) fn add_type_param_bounds(generics: &Generics, bounds: &[TypeParamBound]) -> Generics {
+    let mut generics = generics.clone();
+    for param in generics.type_params_mut() {
+        param.bounds.extend(bounds.iter().cloned());
+    }
+    generics
+}
+
+pub(crate) fn get_field_name(field: &Field) -> syn::Result<Ident> {
     match &field.ident {
-        Some(ident) => ident.clone(),
-        _ => panic!("Could not find a name for one of the fields in your struct"),
+        Some(ident) => Ok(ident.clone()),
+        _ => Err(syn::Error::new_spanned(
+            field,
+            "could not find a name for this field",
+        )),
     }
 }
 
-#[allow(clippy::unnecessary_operation)]
-pub(crate) fn get_table_name_from_attributes(attributes: Vec<Attribute>) -> Option<String> {
-    for attribute in attributes {
-        match attribute.path.segments.first() {
-            Some(segment) => {
-                if !segment.ident.to_string().eq("sql") {
-                    continue;
+pub(crate) fn validate_struct_attributes(attributes: &[Attribute]) -> syn::Result<()> {
+    validate_attributes(attributes, STRUCT_KEYS, (FIELD_KEYS, "field"))
+}
+
+pub(crate) fn validate_field_attributes(field: &Field) -> syn::Result<()> {
+    validate_attributes(&field.attrs, FIELD_KEYS, (STRUCT_KEYS, "struct"))
+}
+
+pub(crate) fn validate_enum_attributes(attributes: &[Attribute]) -> syn::Result<()> {
+    validate_attributes(attributes, &[], (VARIANT_KEYS, "variant"))
+}
+
+pub(crate) fn validate_variant_attributes(variant: &Variant) -> syn::Result<()> {
+    validate_attributes(&variant.attrs, VARIANT_KEYS, (&[], "enum"))
+}
+
+///
+/// Checks that all keys in the sprattus attributes are known, only occur once and have a value
+/// when they require one. Keys that are only allowed elsewhere, like a field key on a struct, are
+/// reported with the place where they belong.
+///
+fn validate_attributes(
+    attributes: &[Attribute],
+    allowed: &[(&str, bool)],
+    (elsewhere, place): (&[(&str, bool)], &str),
+) -> syn::Result<()> {
+    let mut seen: Vec<Ident> = Vec::new();
+    for attribute in attributes.iter().filter(|a| is_sprattus_attribute(a)) {
+        for (key, value) in parse_attribute_items(attribute)? {
+            let takes_value = match allowed.iter().find(|(name, _)| key == name) {
+                Some((_, takes_value)) => *takes_value,
+                None if elsewhere.iter().any(|(name, _)| key == name) => {
+                    return Err(syn::Error::new(
+                        key.span(),
+                        format!("the `{}` attribute can only be used on a {}", key, place),
+                    ));
                 }
+                None => return Err(unknown_attribute_error(&key, allowed)),
+            };
+            if takes_value && value.is_none() {
+                return Err(syn::Error::new(
+                    key.span(),
+                    format!("the `{}` attribute requires a value", key),
+                ));
             }
-            None => continue,
-        }
-        for item in attribute.tokens {
-            match item {
-                Group(group) => {
-                    for token in group.stream() {
-                        match token {
-                            Ident2(ident) => {
-                                if !ident.to_string().eq("table") {
-                                    break;
-                                }
-                            }
-                            Punct(punct) => {
-                                if punct.as_char() != '=' {
-                                    break;
-                                }
-                            }
-                            TokenTree::Literal(literal) => {
-                                return Some(literal.to_string().replace("\"", ""));
-                            }
-                            _ => break,
-                        }
-                    }
-                }
-                _ => break,
+            if !takes_value && value.is_some() {
+                return Err(syn::Error::new(
+                    key.span(),
+                    format!("the `{}` attribute does not take a value", key),
+                ));
+            }
+            if seen.contains(&key) && !REPEATABLE_KEYS.iter().any(|name| key == name) {
+                return Err(syn::Error::new(
+                    key.span(),
+                    format!("duplicate `{}` attribute", key),
+                ));
             }
+            seen.push(key);
         }
     }
-    None
+    Ok(())
 }
 
-pub(crate) fn get_key_value_of_attribute(tokens: proc_macro2::Group) -> (Ident, Option<Literal>) {
-    let mut name: Ident = Ident::new("temp", Span::call_site());
-    for token in tokens.stream() {
-        match token {
-            Ident2(ident) => {
-                name = ident;
-            }
-            Punct(punct) => {
-                if punct.as_char() != '=' {
-                    return (name, None);
-                }
-            }
-            TokenTree::Literal(literal) => {
-                return (name, Some(literal));
+///
+/// Returns the error of an unknown key, listing the allowed keys and suggesting the one that was
+/// likely meant when the key looks like a typo.
+///
+fn unknown_attribute_error(key: &Ident, allowed: &[(&str, bool)]) -> syn::Error {
+    let key_name = key.to_string();
+    let names: Vec<&str> = allowed.iter().map(|(name, _)| *name).collect();
+    let suggestion = names
+        .iter()
+        .map(|name| (edit_distance(&key_name, name), *name))
+        .filter(|(distance, name)| *distance <= std::cmp::max(1, name.len() / 3))
+        .min_by_key(|(distance, _)| *distance);
+    let message = match suggestion {
+        Some((_, name)) => format!(
+            "unknown attribute `{}`, did you mean `{}`? expected one of: {}",
+            key,
+            name,
+            names.join(", ")
+        ),
+        None => format!(
+            "unknown attribute `{}`, expected one of: {}",
+            key,
+            names.join(", ")
+        ),
+    };
+    syn::Error::new(key.span(), message)
+}
+
+///
+/// Returns the number of insertions, deletions, substitutions and transpositions of adjacent
+/// characters needed to turn one string into the other.
+///
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    // distances[i][j] is the distance between the first i characters of a and the first j
+    // characters of b.
+    let mut distances = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in distances.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, distance) in distances[0].iter_mut().enumerate() {
+        *distance = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let substitution = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+            let mut distance = (distances[i - 1][j] + 1)
+                .min(distances[i][j - 1] + 1)
+                .min(distances[i - 1][j - 1] + substitution);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(distances[i - 2][j - 2] + 1);
             }
-            _ => {}
+            distances[i][j] = distance;
         }
     }
-    (name, None)
+    distances[a.len()][b.len()]
+}
+
+///
+/// Parses the comma separated `key` and `key = "value"` items of a sprattus attribute.
+///
+fn parse_attribute_items(attribute: &Attribute) -> syn::Result<Vec<(Ident, Option<Literal>)>> {
+    let list = match attribute.parse_meta()? {
+        Meta::List(list) => list,
+        meta => {
+            return Err(syn::Error::new_spanned(
+                meta,
+                "expected an attribute like `#[sprattus(key = \"value\")]`",
+            ))
+        }
+    };
+    list.nested
+        .iter()
+        .map(|item| match item {
+            NestedMeta::Meta(Meta::Path(path)) => Ok((attribute_key(path)?, None)),
+            NestedMeta::Meta(Meta::NameValue(name_value)) => Ok((
+                attribute_key(&name_value.path)?,
+                Some(attribute_value(&name_value.lit)?),
+            )),
+            // The rules of `validate(...)` are parsed by `find_field_validations`.
+            NestedMeta::Meta(Meta::List(list)) if list.path.is_ident("validate") => {
+                Ok((attribute_key(&list.path)?, None))
+            }
+            NestedMeta::Meta(Meta::List(list)) => Err(syn::Error::new_spanned(
+                list,
+                "expected `key` or `key = \"value\"`",
+            )),
+            NestedMeta::Lit(literal) => Err(syn::Error::new_spanned(
+                literal,
+                "expected an attribute name",
+            )),
+        })
+        .collect()
 }
 
-pub(crate) fn generate_argument_list(length: usize) -> String {
-    let mut prepared_arguments_list = String::new();
-    for i in 1..=length {
-        if i == length {
-            prepared_arguments_list.push_str(format!("${}", i).as_str());
-        } else {
-            prepared_arguments_list.push_str(format!("${},", i).as_str());
+/// Returns the key of an attribute item, which is a single identifier.
+fn attribute_key(path: &syn::Path) -> syn::Result<Ident> {
+    match path.segments.first() {
+        Some(segment) if path.segments.len() == 1 && path.leading_colon.is_none() => {
+            Ok(segment.ident.clone())
         }
+        _ => Err(syn::Error::new_spanned(path, "expected an attribute name")),
     }
-    prepared_arguments_list
-}
-pub(crate) fn generate_field_list(field_list: &[String]) -> String {
-    let mut field_list_str = String::new();
-    for (i, field) in field_list.iter().enumerate() {
-        let field = if !field.starts_with('"') {
-            format!("\"{}\"", field.as_str())
-        } else {
-            field.clone()
-        };
-        if i == field_list.len() - 1 {
-            field_list_str.push_str(field.as_str());
-        } else {
-            field_list_str.push_str(format!("{},", field.as_str()).as_str());
+}
+
+/// Returns the value of an attribute item as the literal token it was written as.
+fn attribute_value(literal: &Lit) -> syn::Result<Literal> {
+    match literal.to_token_stream().into_iter().next() {
+        Some(TokenTree::Literal(value)) => Ok(value),
+        _ => Err(syn::Error::new_spanned(literal, "expected a literal")),
+    }
+}
+
+///
+/// Makes sure that no two fields are mapped to the same column.
+///
+pub(crate) fn check_unique_columns<'a>(
+    columns: impl Iterator<Item = (&'a Ident, String)>,
+) -> syn::Result<()> {
+    let mut seen: Vec<(&Ident, String)> = Vec::new();
+    for (field, column) in columns {
+        if let Some((other, _)) = seen.iter().find(|(_, seen)| *seen == column) {
+            return Err(syn::Error::new(
+                field.span(),
+                format!(
+                    "the column `{}` is already used by the field `{}`",
+                    column, other
+                ),
+            ));
         }
+        seen.push((field, column));
     }
-    field_list_str
+    Ok(())
+}
+
+pub(crate) fn get_table_name_from_attributes(attributes: Vec<Attribute>) -> Option<String> {
+    get_struct_attribute_value(attributes, "table")
+}
+
+pub(crate) fn get_struct_attribute_value(attributes: Vec<Attribute>, key: &str) -> Option<String> {
+    find_attribute_value(&attributes, key).map(|literal| literal_value(&literal))
+}
+
+/// Returns the values of all occurrences of a key that can be repeated, like `unique`.
+pub(crate) fn get_struct_attribute_values(attributes: &[Attribute], key: &str) -> Vec<Literal> {
+    get_attribute_items(attributes)
+        .into_iter()
+        .filter(|(name, _)| name == key)
+        .filter_map(|(_, value)| value)
+        .collect()
+}
+
+pub(crate) fn has_struct_attribute(attributes: &[Attribute], key: &str) -> bool {
+    get_attribute_items(attributes)
+        .iter()
+        .any(|(name, _)| name == key)
+}
+
+///
+/// Collects the items of all sprattus attributes, so keys can be combined in a single attribute
+/// like `#[sprattus(primary_key, name = "prod_id")]` as well as spread over multiple attributes.
+/// Malformed attributes are skipped here, they are reported by the validation.
+///
+fn get_attribute_items(attributes: &[Attribute]) -> Vec<(Ident, Option<Literal>)> {
+    attributes
+        .iter()
+        .filter(|a| is_sprattus_attribute(a))
+        .filter_map(|attribute| parse_attribute_items(attribute).ok())
+        .flatten()
+        .collect()
+}
+
+fn find_attribute_value(attributes: &[Attribute], key: &str) -> Option<Literal> {
+    get_attribute_items(attributes)
+        .into_iter()
+        .find(|(name, _)| name == key)
+        .and_then(|(_, value)| value)
 }
 
-pub(crate) fn get_ident_name_from_path(path: &Type) -> Ident {
+pub(crate) fn get_ident_name_from_path(path: &Type) -> syn::Result<Ident> {
     match path {
-        Path(path) => match path.path.get_ident() {
-            Some(ident) => ident.clone(),
-            None => {
-                // Handle generic types like Option<T>.
-                if let Some(path_segement) = &path.path.segments.first() {
-                    if let AngleBracketed(arguments) = &path_segement.arguments {
-                        if let Some(GenericArgument::Type(generic_type)) = arguments.args.first() {
-                            return get_ident_name_from_path(generic_type);
-                        }
+        Path(type_path) => match type_path.path.segments.last() {
+            Some(path_segment) => {
+                // Handle generic types like Option<T> and Cow<'a, T>, skipping lifetimes.
+                // Generic types with a Postgres type of their own are not unwrapped.
+                let arguments = match &path_segment.arguments {
+                    AngleBracketed(arguments)
+                        if !OWN_TYPE_GENERICS
+                            .contains(&path_segment.ident.to_string().as_str()) =>
+                    {
+                        Some(arguments)
+                    }
+                    _ => None,
+                };
+                if let Some(arguments) = arguments {
+                    let generic_type = arguments.args.iter().find_map(|argument| match argument {
+                        GenericArgument::Type(generic_type) => Some(generic_type),
+                        _ => None,
+                    });
+                    if let Some(generic_type) = generic_type {
+                        return get_ident_name_from_path(generic_type);
                     }
                 }
-                panic!("Could not infer type information of your struct")
+                Ok(path_segment.ident.clone())
             }
+            None => Err(syn::Error::new_spanned(
+                path,
+                "could not infer the type of this field",
+            )),
         },
-        _ => panic!("not found a path"),
+        // Borrowed fields like `&'a str` have the Postgres type of the referenced type.
+        Reference(reference) => get_ident_name_from_path(&reference.elem),
+        _ => Err(syn::Error::new_spanned(
+            path,
+            "unsupported type, use the 'pg_type' attribute to specify the Postgres type",
+        )),
     }
 }
 
-pub(crate) fn is_sprattus_attribute(attribute: &Attribute) -> bool {
-    match attribute.path.get_ident() {
-        Some(name) => name.eq("sql"),
+/// Returns whether a type is an `Option`, of which the values can be `NULL`.
+pub(crate) fn is_option(rust_type: &Type) -> bool {
+    match rust_type {
+        Path(type_path) => matches!(
+            type_path.path.segments.last(),
+            Some(segment) if segment.ident == "Option"
+        ),
         _ => false,
     }
 }
 
-pub(crate) fn generate_argument_list_with_types(fields: &[StructFieldData]) -> String {
-    let mut prepared_arguments_list = String::new();
-    for (i, pg_type) in fields.iter().map(|field| &field.pg_field_type).enumerate() {
-        if i == (fields.len() - 1) {
-            prepared_arguments_list.push_str(format!("${}::{}", i + 1, pg_type).as_str());
-        } else {
-            prepared_arguments_list.push_str(format!("${}::{},", i + 1, pg_type).as_str());
-        }
+///
+/// Formats a type the way it is written in Rust code, for use in messages.
+///
+pub(crate) fn type_to_string(rust_type: &Type) -> String {
+    quote!(#rust_type)
+        .to_string()
+        .replace(" < ", "<")
+        .replace(" >", ">")
+        .replace(" :: ", "::")
+        .replace("& ", "&")
+}
+
+/// Returns whether the attribute is a `#[sprattus(...)]` attribute, or its deprecated alias
+/// `#[sql(...)]`.
+pub(crate) fn is_sprattus_attribute(attribute: &Attribute) -> bool {
+    attribute.path.is_ident("sprattus") || attribute.path.is_ident("sql")
+}
+
+///
+/// Emits a deprecation warning pointing at the first `#[sql(...)]` attribute of a struct or its
+/// fields. Procedural macros can't emit warnings themselves on stable Rust, so this generates the
+/// use of a deprecated item instead.
+///
+pub(crate) fn deprecated_attribute_warning(input: &DeriveInput) -> TokenStream {
+    let field_attributes = match &input.data {
+        Data::Struct(data) => data.fields.iter().flat_map(|field| &field.attrs).collect(),
+        _ => Vec::new(),
+    };
+    let attribute = input
+        .attrs
+        .iter()
+        .chain(field_attributes)
+        .find(|attribute| attribute.path.is_ident("sql"));
+    match attribute {
+        Some(attribute) => quote_spanned!(attribute.path.span() =>
+            const _: () = {
+                #[deprecated(note = "the `sql` attribute is renamed to `sprattus`, use `#[sprattus(...)]` instead")]
+                struct SqlAttribute;
+                let _ = SqlAttribute;
+            };
+        ),
+        None => TokenStream::new(),
     }
-    prepared_arguments_list
 }
 
 pub(crate) fn find_field_table_name(field: &Field) -> Option<Literal> {
-    for attribute in field.attrs.clone() {
-        if !is_sprattus_attribute(&attribute) {
-            continue;
-        }
-        for token in attribute.tokens {
-            match token {
-                Group(group) => match get_key_value_of_attribute(group) {
-                    (ident, Some(name)) => {
-                        if ident.to_string().eq("name") {
-                            return Some(name);
-                        }
-                    }
-                    _ => break,
-                },
-                _ => {
-                    break;
-                }
-            }
-        }
+    find_field_attribute_value(field, "name")
+}
+
+pub(crate) fn find_field_pg_type(field: &Field) -> Option<String> {
+    find_field_attribute_value(field, "pg_type").map(|literal| literal_value(&literal))
+}
+
+/// Returns the path of the conversion function given with the `with` or `from` attribute.
+pub(crate) fn find_field_conversion(field: &Field, key: &str) -> syn::Result<Option<syn::Path>> {
+    match find_field_attribute_value(field, key) {
+        Some(literal) => syn::parse_str(&literal_value(&literal))
+            .map(Some)
+            .map_err(|_| {
+                syn::Error::new(
+                    literal.span(),
+                    format!("the '{}' attribute requires the path of a function", key),
+                )
+            }),
+        None => Ok(None),
     }
-    None
 }
 
-pub(crate) fn find_key_type(field: &Field) -> KeyType {
-    for attribute in field.attrs.clone() {
-        if !is_sprattus_attribute(&attribute) {
-            continue;
+/// The conversion functions and Postgres type of a `u16` or `u64` field with the `unsigned`
+/// attribute.
+pub(crate) struct UnsignedMapping {
+    pub with: syn::Path,
+    pub from: syn::Path,
+    pub pg_type: &'static str,
+}
+
+/// Returns the mapping given with the `unsigned` attribute, which is either `"int"` or
+/// `"numeric"`.
+pub(crate) fn find_unsigned_mapping(field: &Field) -> syn::Result<Option<UnsignedMapping>> {
+    let literal = match find_field_attribute_value(field, "unsigned") {
+        Some(literal) => literal,
+        None => return Ok(None),
+    };
+    if has_field_attribute(field, "with") || has_field_attribute(field, "from") {
+        return Err(syn::Error::new(
+            literal.span(),
+            "the 'unsigned' attribute can't be combined with the 'with' and 'from' attributes",
+        ));
+    }
+    let rust_type = get_ident_name_from_path(&field.ty)?.to_string();
+    let (with, from, pg_type) = match (literal_value(&literal).as_str(), rust_type.as_str()) {
+        ("int", "u16") => (quote!(to_int), quote!(from_int), "INT4"),
+        ("int", "u64") => (quote!(to_int), quote!(from_int), "INT8"),
+        ("numeric", "u16") | ("numeric", "u64") => {
+            (quote!(to_numeric), quote!(from_numeric), "NUMERIC")
         }
-        for token in attribute.tokens {
-            match token {
-                Group(group) => match get_key_value_of_attribute(group) {
-                    (ident, Some(_name)) => {
-                        if ident.to_string().eq("primary_key") {
-                            return PrimaryKey;
-                        }
-                    }
-                    (ident, None) => {
-                        if ident.to_string().eq("primary_key") {
-                            return PrimaryKey;
-                        }
-                    }
-                },
-                _ => {
-                    break;
-                }
-            }
+        ("int", _) | ("numeric", _) => {
+            return Err(syn::Error::new_spanned(
+                &field.ty,
+                "the 'unsigned' attribute can only be used on a u16 or u64 field",
+            ))
         }
-    }
-    if let Some(name) = &field.ident {
-        if name.to_string().contains("id") {
-            return PrimaryKeyCandidate;
+        _ => {
+            return Err(syn::Error::new(
+                literal.span(),
+                "the 'unsigned' attribute must be \"int\" or \"numeric\"",
+            ))
         }
+    };
+    Ok(Some(UnsignedMapping {
+        with: syn::parse_quote!(sprattus::unsigned::#with),
+        from: syn::parse_quote!(sprattus::unsigned::#from),
+        pg_type,
+    }))
+}
+
+/// Returns the text of a variant given with the `rename` attribute.
+pub(crate) fn find_variant_rename(variant: &Variant) -> Option<Literal> {
+    find_attribute_value(&variant.attrs, "rename")
+}
+
+fn find_field_attribute_value(field: &Field, key: &str) -> Option<Literal> {
+    find_attribute_value(&field.attrs, key)
+}
+
+pub(crate) fn has_field_attribute(field: &Field, key: &str) -> bool {
+    has_struct_attribute(&field.attrs, key)
+}
+
+pub(crate) fn find_key_type(field: &Field) -> KeyType {
+    if has_field_attribute(field, "primary_key") {
+        PrimaryKey
+    } else {
+        NoKey
     }
-    NoKey
 }
 
-pub(crate) fn get_postgres_datatype(rust_type: String) -> String {
-    match rust_type.as_str() {
+pub(crate) fn get_postgres_datatype(rust_type: &Ident) -> syn::Result<String> {
+    let pg_type = match rust_type.to_string().as_str() {
         "bool" => String::from("BOOL"),
         "str" => String::from("VARCHAR"),
         "i8" => String::from("CHAR"),
@@ -218,6 +527,80 @@ pub(crate) fn get_postgres_datatype(rust_type: String) -> String {
         "NaiveDateTime" => String::from("TIMESTAMP"),
         "Json" => String::from("JSON"),
         "MacAddress" => String::from("MACADDR"),
-        _ => panic!("unsupported type"),
+        "Geometry" => String::from("GEOMETRY"),
+        "Geography" => String::from("GEOGRAPHY"),
+        "HashMap" | "Hstore" => String::from("HSTORE"),
+        "u16" | "u64" => {
+            return Err(syn::Error::new(
+                rust_type.span(),
+                format!(
+                    "unsupported type {}, use the 'unsigned' attribute to store it as \"int\" or \"numeric\"",
+                    rust_type
+                ),
+            ))
+        }
+        _ => {
+            return Err(syn::Error::new(
+                rust_type.span(),
+                format!(
+                    "unsupported type {}, use the 'pg_type' attribute to specify the Postgres type",
+                    rust_type
+                ),
+            ))
+        }
+    };
+    Ok(pg_type)
+}
+
+///
+/// Returns the Postgres name of a field without the `r#` prefix of raw identifiers.
+///
+pub(crate) fn identifier_name(ident: &Ident) -> String {
+    ident.to_string().trim_start_matches("r#").to_string()
+}
+
+///
+/// Returns the value of a string literal, as given in an attribute like `name = "value"`.
+///
+pub(crate) fn literal_value(literal: &Literal) -> String {
+    match syn::parse_str::<syn::LitStr>(&literal.to_string()) {
+        Ok(string) => string.value(),
+        Err(_) => literal.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use syn::parse_quote;
+
+    #[test]
+    fn computes_edit_distances() {
+        assert_eq!(edit_distance("table", "table"), 0);
+        assert_eq!(edit_distance("tabel", "table"), 1);
+        assert_eq!(edit_distance("pg_typ", "pg_type"), 1);
+        assert_eq!(edit_distance("primary", "primary_key"), 4);
+        assert_eq!(edit_distance("", "view"), 4);
+    }
+
+    #[test]
+    fn parses_attribute_items() {
+        let attribute: Attribute = parse_quote!(#[sprattus(primary_key, name = "prod_id")]);
+        let items = parse_attribute_items(&attribute).unwrap();
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].0, "primary_key");
+        assert!(items[0].1.is_none());
+        assert_eq!(items[1].0, "name");
+        assert_eq!(literal_value(items[1].1.as_ref().unwrap()), "prod_id");
+    }
+
+    #[test]
+    fn suggests_similar_keys() {
+        let key: Ident = parse_quote!(tabel);
+        let error = unknown_attribute_error(&key, STRUCT_KEYS).to_string();
+        assert!(error.starts_with("unknown attribute `tabel`, did you mean `table`?"));
+        let key: Ident = parse_quote!(colour);
+        let error = unknown_attribute_error(&key, STRUCT_KEYS).to_string();
+        assert!(error.starts_with("unknown attribute `colour`, expected one of: table"));
     }
 }
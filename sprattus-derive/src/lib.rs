@@ -1,35 +1,98 @@
 extern crate proc_macro;
 
+mod builder;
 mod from_sql;
 mod functions;
+mod offline;
+mod projection;
+mod raw_sql;
+mod sql;
+mod text_enum;
 mod to_sql;
+mod validate;
 
-use crate::from_sql::SqlField;
+use crate::builder::*;
+use crate::from_sql::*;
 use crate::functions::*;
+use crate::offline::*;
+use crate::projection::*;
+use crate::raw_sql::*;
+use crate::sql::*;
+use crate::text_enum::*;
 use crate::to_sql::*;
-use proc_macro2::{Literal, TokenTree::Group};
+use crate::validate::*;
+use proc_macro2::Literal;
 use quote::quote;
 use syn::export::TokenStream2;
-use syn::{parse_macro_input, Data::Struct, DeriveInput};
+use syn::{parse_macro_input, Data::Struct, DeriveInput, Fields, Path};
 
 /// Automatically implements the [`ToSql`](./trait.ToSql.html) trait for a given struct.
-#[proc_macro_derive(ToSql, attributes(sql))]
+#[proc_macro_derive(ToSql, attributes(sprattus, sql))]
 pub fn to_sql(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let derive_input = parse_macro_input!(input as DeriveInput);
+    let warning = deprecated_attribute_warning(&derive_input);
+    expand_to_sql(derive_input)
+        .map(|tokens| quote!(#warning #tokens))
+        .unwrap_or_else(|error| error.to_compile_error())
+        .into()
+}
 
+fn expand_to_sql(derive_input: DeriveInput) -> syn::Result<TokenStream2> {
     let name = &derive_input.ident;
+    validate_struct_attributes(&derive_input.attrs)?;
 
+    // Views are read-only, so they only get an implementation of the Table trait.
+    let view_name = get_struct_attribute_value(derive_input.attrs.clone(), "view");
+    let materialized_view_name =
+        get_struct_attribute_value(derive_input.attrs.clone(), "materialized_view");
+    if view_name.is_some() && materialized_view_name.is_some() {
+        return Err(syn::Error::new(
+            name.span(),
+            "the 'view' and 'materialized_view' attributes can't be combined",
+        ));
+    }
+    let materialized = materialized_view_name.is_some();
+    let view_name = view_name.or(materialized_view_name);
     // Set table name to to either the defined attribute value, or fall back on the structs name
-    let table_name: String = match get_table_name_from_attributes(derive_input.attrs) {
-        Some(table_name) => table_name,
-        None => name.to_string(),
+    let table_name = TableName {
+        name: match get_table_name_from_attributes(derive_input.attrs.clone()) {
+            Some(table_name) => table_name,
+            None => name.to_string(),
+        },
+        verbatim: has_struct_attribute(&derive_input.attrs, "quote"),
     };
+    // The previous versions of updated and deleted rows are kept in the history table.
+    let history_table = get_struct_attribute_value(derive_input.attrs.clone(), "history_table")
+        .map(|name| TableName {
+            name,
+            verbatim: table_name.verbatim,
+        });
+    let insert_model = has_struct_attribute(&derive_input.attrs, "insert_model");
+    let unique_constraints = get_struct_attribute_values(&derive_input.attrs, "unique");
+    let auto_primary_key = has_struct_attribute(&derive_input.attrs, "auto_primary_key");
+    let no_primary_key = has_struct_attribute(&derive_input.attrs, "no_primary_key");
+    if history_table.is_some() && (view_name.is_some() || no_primary_key) {
+        return Err(syn::Error::new(
+            name.span(),
+            "the 'history_table' attribute can only be used on a table with a primary key",
+        ));
+    }
+    if auto_primary_key && no_primary_key {
+        return Err(syn::Error::new(
+            name.span(),
+            "the 'auto_primary_key' and 'no_primary_key' attributes can't be combined",
+        ));
+    }
     let mut fields_info: Vec<StructFieldData> = Vec::new();
 
     match derive_input.data {
         Struct(data) => {
+            if let Fields::Unnamed(fields) = &data.fields {
+                return build_newtype_to_sql_implementation(name, &derive_input.generics, fields);
+            }
             for field in data.fields.clone() {
-                let field_name = get_field_name(&field);
+                validate_field_attributes(&field)?;
+                let field_name = get_field_name(&field)?;
                 let field_name = match find_field_table_name(&field) {
                     Some(name) => StructName::Renamed {
                         original: (field_name),
@@ -38,106 +101,489 @@ pub fn to_sql(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
                     None => StructName::Named { name: (field_name) },
                 };
                 let key_type = find_key_type(&field);
-                let field_type = get_ident_name_from_path(&field.ty);
-                let pg_field_type = get_postgres_datatype(field_type.to_string());
+                let unsigned = find_unsigned_mapping(&field)?;
+                if unsigned.is_some() && key_type == KeyType::PrimaryKey {
+                    return Err(syn::Error::new_spanned(
+                        &field.ty,
+                        "the 'unsigned' attribute can't be used on a primary key, which is passed \
+                         to queries without conversion",
+                    ));
+                }
+                let pg_field_type = match find_field_pg_type(&field) {
+                    Some(pg_type) => pg_type,
+                    // Views and tables without a primary key are never written to, so they don't
+                    // need a Postgres type, unless it is needed to compare the tenant key with the
+                    // current tenant.
+                    None if (view_name.is_some() || no_primary_key)
+                        && !has_field_attribute(&field, "tenant_key") =>
+                    {
+                        String::new()
+                    }
+                    // Timestamp fields are set by the database, so their values are never sent.
+                    None if has_field_attribute(&field, "created_at")
+                        || has_field_attribute(&field, "updated_at") =>
+                    {
+                        String::new()
+                    }
+                    None => match &unsigned {
+                        Some(unsigned) => String::from(unsigned.pg_type),
+                        None => get_postgres_datatype(&get_ident_name_from_path(&field.ty)?)?,
+                    },
+                };
 
+                let masked = has_field_attribute(&field, "masked");
+                if masked && get_ident_name_from_path(&field.ty)? != "String" {
+                    return Err(syn::Error::new_spanned(
+                        &field.ty,
+                        "the 'masked' attribute can only be used on a String field, because the \
+                         masked value is text",
+                    ));
+                }
+                let default_on_none = has_field_attribute(&field, "default_on_none");
+                if default_on_none && !is_option(&field.ty) {
+                    return Err(syn::Error::new_spanned(
+                        &field.ty,
+                        "the 'default_on_none' attribute can only be used on an Option field",
+                    ));
+                }
                 fields_info.push(StructFieldData {
                     name: (field_name),
                     key_type,
-                    field_type,
+                    immutable: has_field_attribute(&field, "immutable"),
+                    created_at: has_field_attribute(&field, "created_at"),
+                    updated_at: has_field_attribute(&field, "updated_at"),
+                    tenant_key: has_field_attribute(&field, "tenant_key"),
+                    masked,
+                    default: has_field_attribute(&field, "default"),
+                    default_on_none,
+                    with: match unsigned {
+                        Some(unsigned) => Some(unsigned.with),
+                        None => find_field_conversion(&field, "with")?,
+                    },
+                    validations: find_field_validations(&field)?,
+                    rust_type: field.ty,
                     pg_field_type,
+                    visibility: field.vis,
                 })
             }
         }
-        _ => panic!(format!(
-            "Deriving on {}, which is not a struct, is not supported",
-            name.to_string()
-        )),
+        _ => {
+            return Err(syn::Error::new(
+                name.span(),
+                format!(
+                    "Deriving on {}, which is not a struct, is not supported",
+                    name
+                ),
+            ))
+        }
+    };
+    check_unique_columns(
+        fields_info
+            .iter()
+            .map(|field| (field.name.rust_name(), field.name.sql_name())),
+    )?;
+    let mut tenant_keys = fields_info.iter().filter(|field| field.tenant_key);
+    if let (Some(tenant_key), Some(duplicate)) = (tenant_keys.next(), tenant_keys.next()) {
+        return Err(syn::Error::new(
+            duplicate.name.rust_name().span(),
+            format!(
+                "the 'tenant_key' attribute is already set on the field `{}`",
+                tenant_key.name.rust_name()
+            ),
+        ));
+    }
+    let primary_key = fields_info
+        .iter()
+        .find(|field| field.key_type == KeyType::PrimaryKey);
+    if let Some(primary_key) = primary_key.filter(|_| auto_primary_key || no_primary_key) {
+        return Err(syn::Error::new(
+            primary_key.name.rust_name().span(),
+            format!(
+                "the 'primary_key' attribute can't be combined with the '{}' attribute on the struct",
+                if auto_primary_key { "auto_primary_key" } else { "no_primary_key" }
+            ),
+        ));
+    }
+    if auto_primary_key {
+        // Opting in to the conventional primary key, the field named `id`.
+        match fields_info
+            .iter_mut()
+            .find(|field| field.name.rust_name() == "id")
+        {
+            Some(field) => field.key_type = KeyType::PrimaryKey,
+            None => {
+                return Err(syn::Error::new(
+                    name.span(),
+                    "the 'auto_primary_key' attribute requires a field named `id`",
+                ))
+            }
+        }
+    }
+    let builder = if has_struct_attribute(&derive_input.attrs, "builder") {
+        build_builder(
+            name,
+            &derive_input.generics,
+            &derive_input.vis,
+            &fields_info,
+        )?
+    } else {
+        TokenStream2::new()
     };
-    build_to_sql_implementation(&name, table_name, &mut fields_info)
+    // With the `lifecycle` annotation, the callbacks are implemented by hand, also for the
+    // insert model.
+    let lifecycle = has_struct_attribute(&derive_input.attrs, "lifecycle");
+    let lifecycle_implementation = if lifecycle {
+        TokenStream2::new()
+    } else {
+        build_lifecycle_implementation(name, &derive_input.generics)
+    };
+    if no_primary_key {
+        // Rows without a primary key can't be identified, so they can only be read and inserted.
+        let table_implementation = build_table_implementation(
+            name,
+            &derive_input.generics,
+            &derive_input.vis,
+            &table_name,
+            None,
+            &fields_info,
+        );
+        let insertable_implementation =
+            build_insertable_implementation(name, &derive_input.generics, &fields_info);
+        return Ok(quote!(
+            #table_implementation #insertable_implementation #lifecycle_implementation #builder
+        ));
+    }
+    if let Some(view_name) = view_name {
+        let view_name = TableName {
+            name: view_name,
+            verbatim: table_name.verbatim,
+        };
+        let table_implementation = build_table_implementation(
+            name,
+            &derive_input.generics,
+            &derive_input.vis,
+            &view_name,
+            None,
+            &fields_info,
+        );
+        let materialized_view_implementation = if materialized {
+            build_materialized_view_implementation(name, &derive_input.generics)
+        } else {
+            TokenStream2::new()
+        };
+        return Ok(quote!(#table_implementation #materialized_view_implementation #builder));
+    }
+    let unique_constraints = unique_constraints
+        .iter()
+        .map(|constraint| unique_columns(constraint, &fields_info))
+        .collect::<syn::Result<Vec<_>>>()?;
+    let to_sql_implementation = build_to_sql_implementation(
+        name,
+        &derive_input.generics,
+        &derive_input.vis,
+        &table_name,
+        history_table.as_ref(),
+        &fields_info,
+        insert_model,
+        &unique_constraints,
+    )?;
+    let insert_model_lifecycle_implementation = if insert_model && !lifecycle {
+        build_lifecycle_implementation(&insert_model_name(name), &derive_input.generics)
+    } else {
+        TokenStream2::new()
+    };
+    Ok(quote!(
+        #to_sql_implementation #lifecycle_implementation #insert_model_lifecycle_implementation
+        #builder
+    ))
+}
+
+/// Returns the Postgres names of the columns of the comma separated fields of a `unique`
+/// attribute.
+fn unique_columns(constraint: &Literal, fields: &[StructFieldData]) -> syn::Result<Vec<String>> {
+    let value = literal_value(constraint);
+    let columns = value
+        .split(',')
+        .map(|field_name| {
+            let field_name = field_name.trim();
+            fields
+                .iter()
+                .find(|field| field.name.rust_name() == field_name)
+                .map(|field| field.name.sql_name())
+                .ok_or_else(|| {
+                    syn::Error::new(
+                        constraint.span(),
+                        format!(
+                            "the 'unique' attribute refers to the unknown field `{}`",
+                            field_name
+                        ),
+                    )
+                })
+        })
+        .collect::<syn::Result<Vec<String>>>()?;
+    Ok(columns)
 }
 
 /// Automatically implements the [`FromSql`](./trait.FromSql.html) trait for a given struct.
-#[proc_macro_derive(FromSql, attributes(sql))]
+#[proc_macro_derive(FromSql, attributes(sprattus, sql))]
 pub fn from_sql(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
+    let warning = deprecated_attribute_warning(&input);
+    expand_from_sql(input)
+        .map(|tokens| quote!(#warning #tokens))
+        .unwrap_or_else(|error| error.to_compile_error())
+        .into()
+}
 
+fn expand_from_sql(input: DeriveInput) -> syn::Result<TokenStream2> {
     // Gather data.
     let name = &input.ident;
+    validate_struct_attributes(&input.attrs)?;
     let mut fields: Vec<SqlField> = Vec::new();
 
     if let Struct(data) = input.data {
-        'field_loop: for field in data.fields {
-            'attribute_loop: for attr in field.attrs {
-                if let Some(ident) = attr.path.segments.first() {
-                    if ident.ident.eq("sql") {
-                        // Attr is ours, let's parse it.
-                        for tokens in attr.tokens.into_iter() {
-                            let group = match tokens {
-                                Group(group) => group,
-                                _ => panic!("cannot find a group of tokens to parse"),
-                            };
-                            let (key, value) = get_key_value_of_attribute(group);
-                            match &field.ident {
-                                Some(ident) => {
-                                    // Validate if the rename attribute is used.
-                                    if key.eq("name") {
-                                        let sql_name = match value {
-                                            None => Literal::string(ident.to_string().as_str()),
-                                            Some(sql_value) => sql_value,
-                                        };
-                                        fields.push(SqlField {
-                                            rust_name: ident.clone(),
-                                            sql_name,
-                                        });
-                                        continue 'field_loop;
-                                    } else {
-                                        continue 'attribute_loop;
-                                    }
-                                }
-                                _ => panic!("Cannot implement FromSql on a tuple struct"),
-                            }
-                        }
-                    } else {
-                        continue 'attribute_loop;
-                    }
-                }
-            }
-            if let Some(ident) = &field.ident {
-                let name = &ident.to_string();
-                fields.push(SqlField {
-                    rust_name: ident.clone(),
-                    sql_name: Literal::string(name.as_str()),
-                });
-                continue 'field_loop;
-            }
+        if let Fields::Unnamed(fields) = &data.fields {
+            return Ok(build_tuple_from_sql_implementation(
+                name,
+                &input.generics,
+                fields,
+            ));
+        }
+        for field in data.fields {
+            validate_field_attributes(&field)?;
+            let rust_name = get_field_name(&field)?;
+            let sql_name = match find_field_table_name(&field) {
+                Some(sql_name) => sql_name,
+                None => Literal::string(identifier_name(&rust_name).as_str()),
+            };
+            fields.push(SqlField {
+                rust_name,
+                sql_name,
+                default: has_field_attribute(&field, "default"),
+                from: match find_unsigned_mapping(&field)? {
+                    Some(unsigned) => Some(unsigned.from),
+                    None => find_field_conversion(&field, "from")?,
+                },
+                rust_type: field.ty,
+            });
         }
     } else {
-        panic!(format!(
-            "Deriving on {}, which is not a struct, is not supported",
-            name.to_string()
-        ))
+        return Err(syn::Error::new(
+            name.span(),
+            format!(
+                "Deriving on {}, which is not a struct, is not supported",
+                name
+            ),
+        ));
     }
+    check_unique_columns(
+        fields
+            .iter()
+            .map(|field| (&field.rust_name, literal_value(&field.sql_name))),
+    )?;
+    check_offline_fields(name, &fields)?;
 
     // Build the lines for constructing the struct.
     let mut struct_lines: Vec<TokenStream2> = Vec::new();
-    for field in fields {
+    let struct_name = name.to_string();
+    let conversions = build_from_sql_conversions(name, &fields);
+    for (position, field) in fields.iter().enumerate() {
         let rust_name = &field.rust_name;
         let sql_name = &field.sql_name;
-        struct_lines.push(quote!(
-            #rust_name : row.try_get(#sql_name)?
-        ));
+        let position = Literal::usize_unsuffixed(position);
+        let field_name = rust_name.to_string();
+        let rust_type = type_to_string(&field.rust_type);
+        let read_type = field.read_type(name);
+        let converted_value = field.converted_value();
+        let map_error = quote!(
+            map_err(|error| Error::mapping(row, #sql_name, #struct_name, #field_name, #rust_type, error))
+        );
+        if field.default {
+            // Fall back on the default value when the column is not part of the row.
+            struct_lines.push(quote!(
+                #rust_name : match positions.get(#position) {
+                    Some(Some(index)) => row.try_get::<_, #read_type>(*index)#converted_value.#map_error?,
+                    _ => Default::default(),
+                }
+            ));
+        } else {
+            // Without a position, the column is looked up by name, which fails with the error
+            // that the column is missing.
+            struct_lines.push(quote!(
+                #rust_name : match positions.get(#position) {
+                    Some(Some(index)) => row.try_get::<_, #read_type>(*index),
+                    _ => row.try_get::<_, #read_type>(#sql_name),
+                }#converted_value.#map_error?
+            ));
+        }
     }
+    let sql_names = fields.iter().map(|field| &field.sql_name);
+    let column_checks = fields.iter().map(|field| {
+        let sql_name = &field.sql_name;
+        let field_name = field.rust_name.to_string();
+        let rust_type = type_to_string(&field.rust_type);
+        let read_type = field.read_type(name);
+        let mismatch = |column_type| {
+            quote!(mismatches.push(sprattus::ColumnMismatch {
+                field_name: #field_name,
+                rust_type: #rust_type,
+                column_name: #sql_name,
+                column_type: #column_type,
+            }))
+        };
+        let wrong_type = mismatch(quote!(Some(column.type_().name().to_string())));
+        // Fields with a default value don't need a column.
+        let missing = if field.default {
+            quote!({})
+        } else {
+            mismatch(quote!(None))
+        };
+        quote!(
+            match columns.iter().find(|column| column.name() == #sql_name) {
+                Some(column) => {
+                    if !<#read_type as sprattus::FromSqlItem<'_>>::accepts(column.type_()) {
+                        #wrong_type
+                    }
+                }
+                None => #missing,
+            }
+        )
+    });
 
     // Build the output.
+    let bounded_generics = from_sql_generics(&input.generics);
+    let (impl_generics, _, where_clause) = bounded_generics.split_for_impl();
+    let (_, ty_generics, _) = input.generics.split_for_impl();
     let expanded = quote! {
-        impl FromSql for #name {
+        #conversions
+
+        impl #impl_generics FromSql for #name #ty_generics #where_clause {
             fn from_row(row: &Row) -> Result<Self, Error> where Self: Sized {
+                Self::from_row_at(row, &Self::column_positions(row.columns()))
+            }
+
+            fn column_positions(columns: &[sprattus::Column]) -> Vec<Option<usize>> where Self: Sized {
+                let names: &[&str] = &[#(#sql_names),*];
+                names
+                    .iter()
+                    .map(|name| columns.iter().position(|column| column.name() == *name))
+                    .collect()
+            }
+
+            fn from_row_at(row: &Row, positions: &[Option<usize>]) -> Result<Self, Error> where Self: Sized {
                 Ok(Self {
                     #(#struct_lines),*
                 })
             }
+
+            fn check_columns(columns: &[sprattus::Column]) -> Vec<sprattus::ColumnMismatch> where Self: Sized {
+                let mut mismatches = Vec::new();
+                #(#column_checks)*
+                mismatches
+            }
         }
     };
-    expanded.into()
+    Ok(expanded)
+}
+
+/// Implements the [`SqlTextEnum`](./trait.SqlTextEnum.html) trait for an enum, which stores every
+/// variant as text.
+#[proc_macro_derive(SqlTextEnum, attributes(sprattus))]
+pub fn sql_text_enum(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_sql_text_enum(input)
+        .unwrap_or_else(|error| error.to_compile_error())
+        .into()
+}
+
+/// Creates a [`SqlQuery`](./struct.SqlQuery.html) for a struct from SQL in which `{table}` and
+/// `{columns}` are replaced by its table and columns, checking at compile time that the `$n`
+/// placeholders match the given arguments.
+#[proc_macro]
+pub fn sql(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as SqlInput);
+    expand_sql(input)
+        .unwrap_or_else(|error| error.to_compile_error())
+        .into()
+}
+
+/// Automatically implements the [`Projection`](./trait.Projection.html) trait for a given struct.
+#[proc_macro_derive(Projection, attributes(sprattus, sql))]
+pub fn projection(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let derive_input = parse_macro_input!(input as DeriveInput);
+    let warning = deprecated_attribute_warning(&derive_input);
+    expand_projection(derive_input)
+        .map(|tokens| quote!(#warning #tokens))
+        .unwrap_or_else(|error| error.to_compile_error())
+        .into()
+}
+
+fn expand_projection(derive_input: DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &derive_input.ident;
+    validate_struct_attributes(&derive_input.attrs)?;
+
+    let parent: Path = match get_struct_attribute_value(derive_input.attrs, "of") {
+        Some(parent) => syn::parse_str(parent.as_str()).map_err(|_| {
+            syn::Error::new(
+                name.span(),
+                format!("'{}' is not a valid type for the 'of' attribute", parent),
+            )
+        })?,
+        None => {
+            return Err(syn::Error::new(
+                name.span(),
+                format!(
+                    "Deriving Projection on {} requires the 'of' attribute with the parent struct",
+                    name
+                ),
+            ))
+        }
+    };
+    match derive_input.data {
+        Struct(data) => {
+            let fields: Vec<_> = data.fields.into_iter().collect();
+            build_projection_implementation(
+                name,
+                &derive_input.generics,
+                &parent,
+                fields.as_slice(),
+            )
+        }
+        _ => Err(syn::Error::new(
+            name.span(),
+            format!(
+                "Deriving on {}, which is not a struct, is not supported",
+                name
+            ),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use syn::parse_quote;
+
+    #[test]
+    fn masks_only_text_fields() {
+        let input: DeriveInput = parse_quote!(
+            struct Card {
+                #[sprattus(primary_key)]
+                id: i32,
+                #[sprattus(masked)]
+                number: i64,
+            }
+        );
+        let error = expand_to_sql(input).unwrap_err().to_string();
+        assert!(error.starts_with("the 'masked' attribute can only be used on a String field"));
+        let input: DeriveInput = parse_quote!(
+            struct Card {
+                #[sprattus(primary_key)]
+                id: i32,
+                #[sprattus(masked)]
+                number: Option<String>,
+            }
+        );
+        assert!(expand_to_sql(input).is_ok());
+    }
 }
@@ -0,0 +1,214 @@
+//! Checks the code against the queries that are prepared on a database, when the crate is built
+//! with `SPRATTUS_OFFLINE=true`.
+
+use crate::from_sql::SqlField;
+use crate::functions::literal_value;
+use proc_macro2::{Ident, Span};
+use syn::{LitStr, Type};
+
+/// The environment variable that enables the offline checks.
+const OFFLINE_VARIABLE: &str = "SPRATTUS_OFFLINE";
+
+/// The name of the file with the offline data, in the root of the crate that is built.
+#[cfg(feature = "offline")]
+const OFFLINE_DATA_FILE: &str = "sprattus-data.json";
+
+/// A query of the offline data, as it was prepared on the database.
+#[cfg_attr(not(feature = "offline"), allow(dead_code))]
+pub(crate) struct OfflineQuery {
+    entity: String,
+    template: Option<String>,
+    sql: String,
+    columns: Vec<String>,
+}
+
+/// Returns whether the offline checks are enabled.
+fn is_enabled() -> bool {
+    match std::env::var(OFFLINE_VARIABLE) {
+        Ok(value) => value == "true" || value == "1",
+        Err(_) => false,
+    }
+}
+
+/// Returns the prepared queries when the offline checks are enabled.
+#[cfg(feature = "offline")]
+fn offline_queries() -> syn::Result<Option<Vec<OfflineQuery>>> {
+    if !is_enabled() {
+        return Ok(None);
+    }
+    let error = |message: String| syn::Error::new(Span::call_site(), message);
+    let directory = std::env::var("CARGO_MANIFEST_DIR").map_err(|e| error(e.to_string()))?;
+    let path = std::path::Path::new(&directory).join(OFFLINE_DATA_FILE);
+    let contents = std::fs::read_to_string(&path).map_err(|e| {
+        error(format!(
+            "cannot read {}, which is written by `Prepare::write`: {}",
+            path.display(),
+            e
+        ))
+    })?;
+    parse_offline_data(&contents)
+        .map(Some)
+        .map_err(|message| error(format!("invalid {}: {}", path.display(), message)))
+}
+
+/// Without the `offline` feature, there is no offline data to check against.
+#[cfg(not(feature = "offline"))]
+fn offline_queries() -> syn::Result<Option<Vec<OfflineQuery>>> {
+    if is_enabled() {
+        return Err(syn::Error::new(
+            Span::call_site(),
+            format!(
+                "{} is set, but the `offline` feature of sprattus is not enabled",
+                OFFLINE_VARIABLE
+            ),
+        ));
+    }
+    Ok(None)
+}
+
+/// Parses the offline data written by `Prepare::write`.
+#[cfg(feature = "offline")]
+fn parse_offline_data(contents: &str) -> Result<Vec<OfflineQuery>, String> {
+    use serde_json::Value;
+
+    let data: Value = serde_json::from_str(contents).map_err(|e| e.to_string())?;
+    let string = |value: &Value, key: &str| {
+        value[key]
+            .as_str()
+            .map(String::from)
+            .ok_or_else(|| format!("expected a string for `{}`", key))
+    };
+    data["queries"]
+        .as_array()
+        .ok_or_else(|| String::from("expected a list of `queries`"))?
+        .iter()
+        .map(|query| {
+            Ok(OfflineQuery {
+                entity: string(query, "entity")?,
+                template: query["template"].as_str().map(String::from),
+                sql: string(query, "sql")?,
+                columns: query["columns"]
+                    .as_array()
+                    .ok_or_else(|| String::from("expected a list of `columns`"))?
+                    .iter()
+                    .map(|column| string(column, "name"))
+                    .collect::<Result<_, _>>()?,
+            })
+        })
+        .collect()
+}
+
+/// Checks that a query of the `sql!` macro is prepared.
+pub(crate) fn check_offline_query(entity: &Type, template: &LitStr) -> syn::Result<()> {
+    match offline_queries()? {
+        Some(queries) => find_query(&queries, entity, &template.value())
+            .map_err(|message| syn::Error::new(template.span(), message)),
+        None => Ok(()),
+    }
+}
+
+fn find_query(queries: &[OfflineQuery], entity: &Type, template: &str) -> Result<(), String> {
+    let entity = entity_name(entity);
+    let prepared = queries
+        .iter()
+        .any(|query| query.entity == entity && query.template.as_deref() == Some(template));
+    if prepared {
+        Ok(())
+    } else {
+        Err(format!(
+            "the query is not prepared for `{}`, prepare the queries again with `Prepare::write`",
+            entity
+        ))
+    }
+}
+
+/// Checks that every field of a struct is returned by the prepared queries of the struct.
+pub(crate) fn check_offline_fields(name: &Ident, fields: &[SqlField]) -> syn::Result<()> {
+    match offline_queries()? {
+        Some(queries) => check_fields(&queries, name, fields),
+        None => Ok(()),
+    }
+}
+
+fn check_fields(queries: &[OfflineQuery], name: &Ident, fields: &[SqlField]) -> syn::Result<()> {
+    let entity = name.to_string();
+    for query in queries.iter().filter(|query| query.entity == entity) {
+        // Fields with a default value don't need a column.
+        for field in fields.iter().filter(|field| !field.default) {
+            let column = literal_value(&field.sql_name);
+            if !query.columns.contains(&column) {
+                return Err(syn::Error::new(
+                    field.rust_name.span(),
+                    format!(
+                        "the column `{}` is not returned by the prepared query `{}`, prepare the \
+                         queries again with `Prepare::write`",
+                        column, query.sql
+                    ),
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Returns the name of the struct of a type, without its path and type parameters.
+fn entity_name(entity: &Type) -> String {
+    match entity {
+        Type::Path(path) => path
+            .path
+            .segments
+            .last()
+            .map(|segment| segment.ident.to_string())
+            .unwrap_or_default(),
+        _ => String::new(),
+    }
+}
+
+#[cfg(all(test, feature = "offline"))]
+mod tests {
+    use super::*;
+    use proc_macro2::Literal;
+    use syn::parse_quote;
+
+    const DATA: &str = r#"{
+        "queries": [
+            {
+                "entity": "Product",
+                "template": "SELECT {columns} FROM {table}",
+                "sql": "SELECT \"prod_id\",\"title\" FROM \"products\"",
+                "columns": [{"name": "prod_id", "type": "int4"}, {"name": "title", "type": "text"}],
+                "params": []
+            }
+        ]
+    }"#;
+
+    fn field(name: &str, default: bool) -> SqlField {
+        SqlField {
+            rust_name: Ident::new(name, Span::call_site()),
+            sql_name: Literal::string(name),
+            rust_type: parse_quote!(String),
+            default,
+            from: None,
+        }
+    }
+
+    #[test]
+    fn finds_prepared_queries() {
+        let queries = parse_offline_data(DATA).unwrap();
+        let entity: Type = parse_quote!(crate::Product);
+        assert!(find_query(&queries, &entity, "SELECT {columns} FROM {table}").is_ok());
+        assert!(find_query(&queries, &entity, "SELECT * FROM {table}").is_err());
+    }
+
+    #[test]
+    fn checks_the_fields_against_the_prepared_columns() {
+        let queries = parse_offline_data(DATA).unwrap();
+        let name = Ident::new("Product", Span::call_site());
+        let fields = vec![field("prod_id", false), field("title", false)];
+        assert!(check_fields(&queries, &name, &fields).is_ok());
+        let fields = vec![field("prod_id", false), field("price", true)];
+        assert!(check_fields(&queries, &name, &fields).is_ok());
+        let fields = vec![field("prod_id", false), field("price", false)];
+        assert!(check_fields(&queries, &name, &fields).is_err());
+    }
+}
@@ -0,0 +1,87 @@
+extern crate proc_macro;
+
+use crate::from_sql::from_sql_generics;
+use crate::functions::*;
+use crate::sql::*;
+use proc_macro2::{Ident, TokenStream};
+use quote::quote;
+use syn::{parse_quote, Field, Generics, Path, PathArguments};
+
+/// Builds the `Projection` implementation, which takes the table from the parent struct.
+pub(crate) fn build_projection_implementation(
+    name: &Ident,
+    generics: &Generics,
+    parent: &Path,
+    fields: &[Field],
+) -> syn::Result<TokenStream> {
+    let mut field_names: Vec<String> = Vec::with_capacity(fields.len());
+    for field in fields {
+        validate_field_attributes(field)?;
+        field_names.push(match find_field_table_name(field) {
+            Some(sql_name) => literal_value(&sql_name),
+            None => identifier_name(&get_field_name(field)?),
+        });
+    }
+    let field_list_string = quote_identifier_list(field_names.as_slice());
+    let field_checks = build_parent_field_checks(parent, fields)?;
+
+    let mut bounded_generics = from_sql_generics(generics);
+    bounded_generics
+        .make_where_clause()
+        .predicates
+        .push(parse_quote!(#parent: ToSql));
+    let (impl_generics, _, where_clause) = bounded_generics.split_for_impl();
+    let (_, ty_generics, _) = generics.split_for_impl();
+    let tokens = quote!(
+        impl #impl_generics Projection for #name #ty_generics #where_clause {
+            type Parent = #parent;
+
+            #[inline]
+            fn get_projected_fields() -> &'static str {
+                #field_list_string
+            }
+        }
+
+        #field_checks
+    );
+    Ok(tokens)
+}
+
+///
+/// Makes sure that every projected field, that is not renamed, exists in the parent by referring
+/// to the column constant generated for the parent.
+///
+fn build_parent_field_checks(parent: &Path, fields: &[Field]) -> syn::Result<TokenStream> {
+    let mut columns = parent.clone();
+    let last_segment = match columns.segments.last_mut() {
+        Some(segment) => segment,
+        None => {
+            return Err(syn::Error::new_spanned(
+                parent,
+                "the 'of' attribute does not contain a type",
+            ))
+        }
+    };
+    last_segment.ident = Ident::new(
+        &format!("{}Columns", last_segment.ident),
+        last_segment.ident.span(),
+    );
+    // The columns struct is never generic.
+    last_segment.arguments = PathArguments::None;
+    let mut constants = Vec::new();
+    for field in fields
+        .iter()
+        .filter(|field| find_field_table_name(field).is_none())
+    {
+        let field_name = get_field_name(field)?;
+        constants.push(Ident::new(
+            &identifier_name(&field_name).to_uppercase(),
+            field_name.span(),
+        ));
+    }
+    Ok(quote!(
+        const _: () = {
+            #(let _ = #columns::#constants;)*
+        };
+    ))
+}
@@ -0,0 +1,213 @@
+extern crate proc_macro;
+
+use crate::offline::check_offline_query;
+use proc_macro2::TokenStream;
+use quote::quote;
+use std::collections::BTreeSet;
+use syn::parse::{Parse, ParseStream};
+use syn::punctuated::Punctuated;
+use syn::{Expr, LitStr, Token, Type};
+
+/// The keys that can be used between braces in the SQL of the `sql!` macro.
+const SQL_KEYS: &[&str] = &["table", "columns"];
+
+/// The input of the `sql!` macro: the struct, the SQL and the arguments of its placeholders.
+pub(crate) struct SqlInput {
+    entity: Type,
+    sql: LitStr,
+    args: Vec<Expr>,
+}
+
+impl Parse for SqlInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let entity = input.parse()?;
+        input.parse::<Token![,]>()?;
+        let sql = input.parse()?;
+        let args = if input.is_empty() {
+            Vec::new()
+        } else {
+            input.parse::<Token![,]>()?;
+            Punctuated::<Expr, Token![,]>::parse_terminated(input)?
+                .into_iter()
+                .collect()
+        };
+        Ok(SqlInput { entity, sql, args })
+    }
+}
+
+/// A piece of the SQL of the `sql!` macro, which is either literal SQL or a key that is replaced
+/// by the metadata of the struct.
+#[derive(Debug, PartialEq)]
+enum Piece {
+    Literal(String),
+    Key(String),
+}
+
+/// Splits the SQL into literal pieces and the keys between braces, and collects the numbers of
+/// the `$n` placeholders. String literals and quoted identifiers are copied as they are, so
+/// braces and dollar signs within them are neither keys nor placeholders.
+fn parse_sql(sql: &str) -> Result<(Vec<Piece>, BTreeSet<usize>), String> {
+    let mut pieces = Vec::new();
+    let mut placeholders = BTreeSet::new();
+    let mut literal = String::new();
+    let mut chars = sql.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' | '"' => {
+                literal.push(c);
+                loop {
+                    match chars.next() {
+                        Some(next) => {
+                            literal.push(next);
+                            if next == c {
+                                break;
+                            }
+                        }
+                        None => return Err(format!("unterminated quote `{}`", c)),
+                    }
+                }
+            }
+            '{' => {
+                let mut key = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(next) => key.push(next),
+                        None => return Err(String::from("unterminated `{`")),
+                    }
+                }
+                if !SQL_KEYS.contains(&key.as_str()) {
+                    return Err(format!(
+                        "unknown key `{{{}}}`, expected one of: {}",
+                        key,
+                        SQL_KEYS
+                            .iter()
+                            .map(|key| format!("{{{}}}", key))
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    ));
+                }
+                pieces.push(Piece::Literal(std::mem::take(&mut literal)));
+                pieces.push(Piece::Key(key));
+            }
+            '$' => {
+                literal.push(c);
+                let mut number = String::new();
+                while let Some(digit) = chars.peek().filter(|next| next.is_ascii_digit()) {
+                    number.push(*digit);
+                    chars.next();
+                }
+                literal.push_str(&number);
+                if let Ok(number) = number.parse() {
+                    placeholders.insert(number);
+                }
+            }
+            _ => literal.push(c),
+        }
+    }
+    pieces.push(Piece::Literal(literal));
+    pieces.retain(|piece| piece != &Piece::Literal(String::new()));
+    Ok((pieces, placeholders))
+}
+
+/// Checks that the placeholders are numbered `$1` up to the number of arguments, without gaps.
+fn check_placeholders(placeholders: &BTreeSet<usize>, arg_count: usize) -> Result<(), String> {
+    if let Some(missing) = (1..=arg_count).find(|n| !placeholders.contains(n)) {
+        return Err(format!(
+            "{} given, but the placeholder ${} is not used",
+            arguments(arg_count),
+            missing
+        ));
+    }
+    if let Some(extra) = placeholders.iter().find(|n| **n == 0 || **n > arg_count) {
+        return Err(format!(
+            "the placeholder ${} has no argument, {} given",
+            extra,
+            arguments(arg_count)
+        ));
+    }
+    Ok(())
+}
+
+fn arguments(count: usize) -> String {
+    match count {
+        1 => String::from("1 argument is"),
+        count => format!("{} arguments are", count),
+    }
+}
+
+/// Expands the `sql!` macro into a `SqlQuery`, of which the SQL is assembled from the literal
+/// pieces and the metadata of the struct.
+pub(crate) fn expand_sql(input: SqlInput) -> syn::Result<TokenStream> {
+    let (pieces, placeholders) = parse_sql(&input.sql.value())
+        .map_err(|message| syn::Error::new(input.sql.span(), message))?;
+    check_placeholders(&placeholders, input.args.len())
+        .map_err(|message| syn::Error::new(input.sql.span(), message))?;
+    check_offline_query(&input.entity, &input.sql)?;
+    let entity = &input.entity;
+    let pieces = pieces.iter().map(|piece| match piece {
+        Piece::Literal(literal) => quote!(#literal),
+        Piece::Key(key) if key == "table" => {
+            quote!(<#entity as sprattus::Table>::get_table_name())
+        }
+        Piece::Key(_) => quote!(<#entity as sprattus::Table>::get_all_fields()),
+    });
+    let args = input.args.iter();
+    let template = &input.sql;
+    Ok(quote!(
+        sprattus::SqlQuery::<#entity>::new(
+            #template,
+            [#(#pieces),*].concat(),
+            vec![#(#args as &(dyn sprattus::ToSqlItem + Sync)),*],
+        )
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_keys_and_counts_placeholders() {
+        let (pieces, placeholders) =
+            parse_sql("SELECT {columns} FROM {table} WHERE a = $1 AND b = $2").unwrap();
+        assert_eq!(
+            pieces,
+            vec![
+                Piece::Literal(String::from("SELECT ")),
+                Piece::Key(String::from("columns")),
+                Piece::Literal(String::from(" FROM ")),
+                Piece::Key(String::from("table")),
+                Piece::Literal(String::from(" WHERE a = $1 AND b = $2")),
+            ]
+        );
+        assert_eq!(placeholders.into_iter().collect::<Vec<_>>(), vec![1, 2]);
+    }
+
+    #[test]
+    fn skips_quoted_text() {
+        let (pieces, placeholders) = parse_sql("SELECT '{\"a\": \"$1\"}'::jsonb").unwrap();
+        assert_eq!(
+            pieces,
+            vec![Piece::Literal(String::from(
+                "SELECT '{\"a\": \"$1\"}'::jsonb"
+            ))]
+        );
+        assert!(placeholders.is_empty());
+    }
+
+    #[test]
+    fn rejects_unknown_keys() {
+        assert!(parse_sql("SELECT * FROM {tabel}").is_err());
+    }
+
+    #[test]
+    fn checks_the_placeholder_count() {
+        let placeholders = parse_sql("a = $1 AND b = $1").unwrap().1;
+        assert!(check_placeholders(&placeholders, 1).is_ok());
+        assert!(check_placeholders(&placeholders, 2).is_err());
+        let placeholders = parse_sql("a = $1 AND b = $3").unwrap().1;
+        assert!(check_placeholders(&placeholders, 3).is_err());
+        assert!(check_placeholders(&placeholders, 1).is_err());
+    }
+}
@@ -0,0 +1,121 @@
+//! The SQL strings of the generated implementations.
+//!
+//! These functions only work on strings, independent of the parsed struct, so they can be tested
+//! without expanding a derive macro.
+//!
+//! All table and column names are quoted, so they keep their casing and can be reserved keywords.
+
+///
+/// Quotes a single identifier, escaping any double quotes within it.
+///
+pub(crate) fn quote_identifier(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+/// The name of a table, as given in the `table` or `view` attribute.
+pub(crate) struct TableName {
+    pub name: String,
+    /// Whether the name is a single identifier, which is quoted as a whole, even when it
+    /// contains a dot.
+    pub verbatim: bool,
+}
+
+impl TableName {
+    ///
+    /// Quotes the table name, which may be qualified with a schema like `"public.products"`,
+    /// unless it is verbatim.
+    ///
+    pub(crate) fn quoted(&self) -> String {
+        if self.verbatim {
+            return quote_identifier(&self.name);
+        }
+        self.name
+            .split('.')
+            .map(quote_identifier)
+            .collect::<Vec<String>>()
+            .join(".")
+    }
+}
+
+///
+/// Quotes a list of identifiers and joins them with commas.
+///
+pub(crate) fn quote_identifier_list(names: &[String]) -> String {
+    names
+        .iter()
+        .map(|name| quote_identifier(name))
+        .collect::<Vec<String>>()
+        .join(",")
+}
+
+///
+/// Generates a comma separated list of placeholders, like `$1,$2,$3`.
+///
+pub(crate) fn generate_argument_list(length: usize) -> String {
+    (1..=length)
+        .map(|i| format!("${}", i))
+        .collect::<Vec<String>>()
+        .join(",")
+}
+
+///
+/// Generates a comma separated list of placeholders cast to the given Postgres types,
+/// like `$1::INT,$2::VARCHAR`.
+///
+pub(crate) fn generate_argument_list_with_types(pg_types: &[&str]) -> String {
+    pg_types
+        .iter()
+        .enumerate()
+        .map(|(i, pg_type)| format!("${}::{}", i + 1, pg_type))
+        .collect::<Vec<String>>()
+        .join(",")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quotes_identifiers() {
+        assert_eq!(quote_identifier("title"), "\"title\"");
+        assert_eq!(quote_identifier("User"), "\"User\"");
+        assert_eq!(quote_identifier("say \"hi\""), "\"say \"\"hi\"\"\"");
+    }
+
+    #[test]
+    fn quotes_identifier_lists() {
+        let names = vec![String::from("prod_id"), String::from("order")];
+        assert_eq!(quote_identifier_list(&names), "\"prod_id\",\"order\"");
+        assert_eq!(quote_identifier_list(&[]), "");
+    }
+
+    #[test]
+    fn quotes_table_names() {
+        let table_name = |name: &str, verbatim| TableName {
+            name: name.to_string(),
+            verbatim,
+        };
+        assert_eq!(table_name("products", false).quoted(), "\"products\"");
+        assert_eq!(
+            table_name("public.Products", false).quoted(),
+            "\"public\".\"Products\""
+        );
+        assert_eq!(table_name("sales.2020", true).quoted(), "\"sales.2020\"");
+    }
+
+    #[test]
+    fn generates_argument_lists() {
+        assert_eq!(generate_argument_list(0), "");
+        assert_eq!(generate_argument_list(1), "$1");
+        assert_eq!(generate_argument_list(3), "$1,$2,$3");
+    }
+
+    #[test]
+    fn generates_argument_lists_with_types() {
+        assert_eq!(generate_argument_list_with_types(&[]), "");
+        assert_eq!(
+            generate_argument_list_with_types(&["INT", "DOUBLE PRECISION"]),
+            "$1::INT,$2::DOUBLE PRECISION"
+        );
+    }
+}
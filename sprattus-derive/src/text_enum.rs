@@ -0,0 +1,123 @@
+extern crate proc_macro;
+
+use crate::functions::*;
+use proc_macro2::{Ident, TokenStream};
+use quote::quote;
+use syn::{Data, DeriveInput, Fields};
+
+/// Builds the implementations of `SqlTextEnum`, `ToSqlItem` and `FromSqlItem` for an enum with
+/// unit variants, of which every variant is stored as a text value.
+pub(crate) fn expand_sql_text_enum(input: DeriveInput) -> syn::Result<TokenStream> {
+    let name = &input.ident;
+    validate_enum_attributes(&input.attrs)?;
+    let data = match &input.data {
+        Data::Enum(data) => data,
+        _ => {
+            return Err(syn::Error::new(
+                name.span(),
+                format!(
+                    "Deriving SqlTextEnum on {}, which is not an enum, is not supported",
+                    name
+                ),
+            ))
+        }
+    };
+    if !input.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &input.generics,
+            "deriving SqlTextEnum on a generic enum is not supported",
+        ));
+    }
+    if data.variants.is_empty() {
+        return Err(syn::Error::new(
+            name.span(),
+            "deriving SqlTextEnum requires at least one variant",
+        ));
+    }
+    let mut variants: Vec<(&Ident, String)> = Vec::new();
+    for variant in data.variants.iter() {
+        validate_variant_attributes(variant)?;
+        if !matches!(variant.fields, Fields::Unit) {
+            return Err(syn::Error::new_spanned(
+                variant,
+                "only variants without fields can be stored as text",
+            ));
+        }
+        let text = match find_variant_rename(variant) {
+            Some(literal) => literal_value(&literal),
+            None => variant.ident.to_string(),
+        };
+        if let Some((other, _)) = variants.iter().find(|(_, other)| *other == text) {
+            return Err(syn::Error::new(
+                variant.ident.span(),
+                format!(
+                    "the variant `{}` is stored as `{}`, like the variant `{}`",
+                    variant.ident, text, other
+                ),
+            ));
+        }
+        variants.push((&variant.ident, text));
+    }
+
+    let enum_name = name.to_string();
+    let to_text = variants
+        .iter()
+        .map(|(variant, text)| quote!(#name::#variant => #text));
+    let from_text = variants
+        .iter()
+        .map(|(variant, text)| quote!(#text => Ok(#name::#variant)));
+    let texts = variants.iter().map(|(_, text)| text);
+    Ok(quote!(
+        impl sprattus::SqlTextEnum for #name {
+            const VARIANTS: &'static [&'static str] = &[#(#texts),*];
+
+            fn to_text(&self) -> &'static str {
+                match self {
+                    #(#to_text,)*
+                }
+            }
+
+            fn from_text(text: &str) -> Result<Self, sprattus::UnknownVariant> {
+                match text {
+                    #(#from_text,)*
+                    _ => Err(sprattus::UnknownVariant {
+                        enum_name: #enum_name,
+                        value: text.to_string(),
+                        expected: <Self as sprattus::SqlTextEnum>::VARIANTS,
+                    }),
+                }
+            }
+        }
+
+        impl sprattus::ToSqlItem for #name {
+            fn to_sql(
+                &self,
+                ty: &sprattus::types::Type,
+                out: &mut sprattus::export::BytesMut,
+            ) -> Result<sprattus::types::IsNull, Box<dyn std::error::Error + Sync + Send>> {
+                let text = <Self as sprattus::SqlTextEnum>::to_text(self);
+                <&str as sprattus::ToSqlItem>::to_sql(&text, ty, out)
+            }
+
+            fn accepts(ty: &sprattus::types::Type) -> bool {
+                <&str as sprattus::ToSqlItem>::accepts(ty)
+            }
+
+            sprattus::types::to_sql_checked!();
+        }
+
+        impl<'__sprattus> sprattus::FromSqlItem<'__sprattus> for #name {
+            fn from_sql(
+                ty: &sprattus::types::Type,
+                raw: &'__sprattus [u8],
+            ) -> Result<Self, Box<dyn std::error::Error + Sync + Send>> {
+                let text = <&str as sprattus::FromSqlItem>::from_sql(ty, raw)?;
+                Ok(<Self as sprattus::SqlTextEnum>::from_text(text)?)
+            }
+
+            fn accepts(ty: &sprattus::types::Type) -> bool {
+                <&str as sprattus::FromSqlItem>::accepts(ty)
+            }
+        }
+    ))
+}
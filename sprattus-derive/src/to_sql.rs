@@ -1,13 +1,16 @@
 extern crate proc_macro;
 
+use crate::from_sql::from_sql_generics;
 use crate::functions::*;
+use crate::sql::*;
+use crate::validate::*;
 use proc_macro2::{Ident, Literal, TokenStream};
 use quote::quote;
+use syn::{parse_quote, FieldsUnnamed, Generics, Path, Type, Visibility};
 
 #[derive(Debug, Eq, PartialEq)]
 pub(crate) enum KeyType {
     PrimaryKey,
-    PrimaryKeyCandidate,
     NoKey,
 }
 
@@ -18,8 +21,161 @@ pub(crate) enum StructName {
 pub(crate) struct StructFieldData {
     pub name: StructName,
     pub key_type: KeyType,
-    pub field_type: Ident,
+    pub immutable: bool,
+    pub created_at: bool,
+    pub updated_at: bool,
+    pub tenant_key: bool,
+    pub masked: bool,
+    pub default: bool,
+    pub default_on_none: bool,
+    pub with: Option<Path>,
+    pub validations: Vec<Validation>,
+    pub rust_type: Type,
     pub pg_field_type: String,
+    pub visibility: Visibility,
+}
+
+impl StructName {
+    /// Returns the name of the field in the Rust struct.
+    pub(crate) fn rust_name(&self) -> &Ident {
+        match self {
+            StructName::Renamed { original, .. } => original,
+            StructName::Named { name } => name,
+        }
+    }
+
+    /// Returns the unquoted name of the column in Postgres.
+    pub(crate) fn sql_name(&self) -> String {
+        match self {
+            StructName::Renamed { new, .. } => literal_value(new),
+            StructName::Named { name } => identifier_name(name),
+        }
+    }
+}
+
+impl StructFieldData {
+    /// Returns whether the value of the field is written by the client, instead of being set to
+    /// `now()` by the database.
+    pub(crate) fn is_written(&self) -> bool {
+        !self.created_at && !self.updated_at
+    }
+
+    /// Returns the expression referencing the value of the field as a Postgres value, applying
+    /// the conversion function of the `with` attribute.
+    fn value(&self, struct_name: &Ident) -> TokenStream {
+        let name = &self.name;
+        match &self.with {
+            Some(_) => {
+                let rust_type = &self.rust_type;
+                let conversion = conversion_name(struct_name, name.rust_name());
+                quote!(sprattus::convert::ToSqlConverted::<#rust_type, #conversion>::from_ref(&self.#name))
+            }
+            None => quote!(&self.#name),
+        }
+    }
+
+    /// Returns the expression telling whether the field is inserted as `DEFAULT`.
+    fn insert_default(&self) -> TokenStream {
+        let name = &self.name;
+        if self.default_on_none {
+            quote!(self.#name.is_none())
+        } else {
+            quote!(false)
+        }
+    }
+}
+
+/// Builds the `get_insert_defaults` method of the written fields, when one of them has the
+/// `default_on_none` attribute.
+fn build_insert_defaults(written_fields: &[&StructFieldData]) -> TokenStream {
+    if !written_fields.iter().any(|field| field.default_on_none) {
+        return TokenStream::new();
+    }
+    let defaults = written_fields.iter().map(|field| field.insert_default());
+    quote!(
+        #[inline]
+        fn get_insert_defaults(&self) -> Vec<bool> {
+            vec![#(#defaults),*]
+        }
+    )
+}
+
+/// Builds the `has_primary_key_value` method, telling whether the primary key is set, for primary
+/// keys that are `Option`s or integers. Other primary keys are always considered to be set.
+///
+/// Primary keys that are `Option`s are inserted as well, so a row can be created with a given
+/// primary key, or with one that is generated by the database.
+fn build_primary_key_value_methods(primary_key: &StructName, rust_type: &Type) -> TokenStream {
+    if is_option(rust_type) {
+        return quote!(
+            #[inline]
+            fn has_primary_key_value(&self) -> bool {
+                self.#primary_key.is_some()
+            }
+
+            #[inline]
+            fn inserts_primary_key() -> bool {
+                true
+            }
+        );
+    }
+    let is_set = match get_ident_name_from_path(rust_type) {
+        Ok(ident) if ident == "i16" || ident == "i32" || ident == "i64" => {
+            quote!(self.#primary_key != 0)
+        }
+        _ => return TokenStream::new(),
+    };
+    quote!(
+        #[inline]
+        fn has_primary_key_value(&self) -> bool {
+            #is_set
+        }
+    )
+}
+
+/// Returns the name of the type implementing the `with` conversion of a field.
+fn conversion_name(struct_name: &Ident, field_name: &Ident) -> Ident {
+    Ident::new(
+        &format!(
+            "__Sprattus{}{}ToSql",
+            struct_name,
+            identifier_name(field_name)
+        ),
+        field_name.span(),
+    )
+}
+
+/// Builds the implementations of `ToSqlConversion` for the fields with the `with` attribute.
+fn build_conversions(struct_name: &Ident, field_list: &[StructFieldData]) -> TokenStream {
+    let conversions = field_list.iter().filter_map(|field| {
+        let with = field.with.as_ref()?;
+        let rust_type = &field.rust_type;
+        let conversion = conversion_name(struct_name, field.name.rust_name());
+        Some(quote!(
+            #[doc(hidden)]
+            #[allow(non_camel_case_types)]
+            struct #conversion;
+
+            impl sprattus::convert::ToSqlConversion<#rust_type> for #conversion {
+                fn to_sql(
+                    value: &#rust_type,
+                    ty: &sprattus::types::Type,
+                    out: &mut sprattus::export::BytesMut,
+                ) -> Result<sprattus::types::IsNull, Box<dyn std::error::Error + Sync + Send>> {
+                    sprattus::convert::to_sql(#with, value, ty, out)
+                }
+
+                fn accepts(ty: &sprattus::types::Type) -> bool {
+                    sprattus::convert::to_sql_accepts::<#rust_type, _, _>(#with, ty)
+                }
+
+                fn fmt(value: &#rust_type, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                    sprattus::convert::fmt(#with, value, f)
+                }
+            }
+        ))
+    });
+    quote!(#(#conversions)*)
 }
 
 impl quote::ToTokens for StructName {
@@ -37,63 +193,137 @@ impl quote::ToTokens for StructName {
     }
 }
 
-impl ToString for StructName {
-    fn to_string(&self) -> String {
-        match self {
-            StructName::Renamed { new, .. } => new.to_string(),
-            StructName::Named { name } => name.to_string(),
-        }
-    }
-}
-
+#[allow(clippy::too_many_arguments)]
 pub(crate) fn build_to_sql_implementation(
     name: &Ident,
-    table_name: String,
-    field_list: &mut Vec<StructFieldData>,
-) -> proc_macro::TokenStream {
-    let (primary_key, primary_key_type) = field_list
+    generics: &Generics,
+    visibility: &Visibility,
+    table_name: &TableName,
+    history_table: Option<&TableName>,
+    field_list: &[StructFieldData],
+    insert_model: bool,
+    unique_constraints: &[Vec<String>],
+) -> syn::Result<TokenStream> {
+    let mut primary_keys = field_list
         .iter()
         .filter(|field| field.key_type == KeyType::PrimaryKey)
-        .map(|field| (&field.name, &field.field_type))
-        .next()
-        .unwrap_or_else(|| {
-            panic!("no field field with the 'primary_key' attribute found");
-        });
-    let primary_key_string = primary_key.to_string();
-    let arguments_list_with_types = generate_argument_list_with_types(&field_list);
+        .map(|field| (&field.name, &field.rust_type));
+    let primary_key_value = field_list
+        .iter()
+        .find(|field| field.key_type == KeyType::PrimaryKey)
+        .map(|field| field.value(name));
+    let (primary_key, primary_key_type) =
+        match primary_keys.next() {
+            Some(primary_key) => primary_key,
+            None => return Err(syn::Error::new(
+                name.span(),
+                "no field with the 'primary_key' attribute found, annotate the primary key with \
+                 #[sprattus(primary_key)] or use #[sprattus(no_primary_key)] on tables without one",
+            )),
+        };
+    if let Some((duplicate, _)) = primary_keys.next() {
+        return Err(syn::Error::new(
+            duplicate.rust_name().span(),
+            format!(
+                "the 'primary_key' attribute is already set on the field `{}`",
+                primary_key.rust_name()
+            ),
+        ));
+    }
+    let primary_key_string = quote_identifier(&primary_key.sql_name());
+    let has_primary_key_value = build_primary_key_value_methods(primary_key, primary_key_type);
 
-    let non_pk_field_list: Vec<&StructName> = field_list
+    // Timestamp fields are set by the database, so their values are never sent.
+    let written_field_list: Vec<&StructFieldData> = field_list
         .iter()
-        .filter(|field| field.key_type != KeyType::PrimaryKey)
-        .map(|field| &field.name)
+        .filter(|field| field.key_type != KeyType::PrimaryKey && field.is_written())
         .collect();
-
-    let field_list_string = generate_field_list(
-        non_pk_field_list
+    // The primary key and the written fields, in the order in which they are declared.
+    let written_field_list_with_pk: Vec<&StructFieldData> = field_list
+        .iter()
+        .filter(|field| field.key_type == KeyType::PrimaryKey || field.is_written())
+        .collect();
+    let arguments_list_with_types = generate_argument_list_with_types(
+        &written_field_list_with_pk
             .iter()
-            .map(|item| item.to_string())
-            .collect::<Vec<String>>()
-            .as_slice(),
+            .map(|field| field.pg_field_type.as_str())
+            .collect::<Vec<&str>>(),
     );
+    let all_values: Vec<TokenStream> = written_field_list_with_pk
+        .iter()
+        .map(|field| field.value(name))
+        .collect();
 
-    let all_fields_list_string = generate_field_list(
-        field_list
+    // Immutable fields are only written when a row is created.
+    let update_field_list: Vec<&StructName> = written_field_list
+        .iter()
+        .filter(|field| !field.immutable)
+        .map(|field| &field.name)
+        .collect();
+    let update_values = written_field_list
+        .iter()
+        .filter(|field| !field.immutable)
+        .map(|field| field.value(name));
+    let update_field_list_string = quote_identifier_list(
+        update_field_list
             .iter()
-            .map(|field| field.name.to_string())
+            .map(|item| item.sql_name())
             .collect::<Vec<String>>()
             .as_slice(),
     );
-    let field_list_len = non_pk_field_list.len();
-    let prepared_arguments_list = generate_argument_list(field_list_len);
+    let update_field_list_len = update_field_list.len();
+    let field_values = field_list.iter().map(|field| {
+        let sql_name = field.name.sql_name();
+        let value = field.value(name);
+        quote!(FieldValue { name: #sql_name, value: #value })
+    });
+    let unique_constraints = unique_constraints
+        .iter()
+        .map(|columns| quote!(&[#(#columns),*]));
+    let columns = field_list.iter().map(|field| {
+        let rust_name = identifier_name(field.name.rust_name());
+        let sql_name = field.name.sql_name();
+        let pg_type = match field.pg_field_type.as_str() {
+            "" => quote!(None),
+            pg_type => quote!(Some(#pg_type)),
+        };
+        let primary_key = field.key_type == KeyType::PrimaryKey;
+        let nullable = is_option(&field.rust_type);
+        let generated = !field.is_written();
+        quote!(ColumnMeta {
+            rust_name: #rust_name,
+            sql_name: #sql_name,
+            pg_type: #pg_type,
+            primary_key: #primary_key,
+            nullable: #nullable,
+            generated: #generated,
+        })
+    });
+    let table_implementation = build_table_implementation(
+        name,
+        generics,
+        visibility,
+        table_name,
+        history_table,
+        field_list,
+    );
+    let insertable_implementation = build_insertable_implementation(name, generics, field_list);
+    let insert_model = if insert_model {
+        build_insert_model(name, generics, visibility, field_list)
+    } else {
+        TokenStream::new()
+    };
 
+    let bounded_generics =
+        add_type_param_bounds(generics, &[parse_quote!(ToSqlItem), parse_quote!(Sync)]);
+    let (impl_generics, _, where_clause) = bounded_generics.split_for_impl();
+    let (_, ty_generics, _) = generics.split_for_impl();
     let tokens = quote!(
-        impl ToSql for #name {
-
-            #[inline]
-            fn get_table_name() -> &'static str {
-                stringify!(#table_name)
-            }
+        #table_implementation
+        #insertable_implementation
+        #insert_model
 
+        impl #impl_generics Identifiable for #name #ty_generics #where_clause {
             #[inline]
             fn get_primary_key() -> &'static str {
                 #primary_key_string
@@ -102,36 +332,39 @@ pub(crate) fn build_to_sql_implementation(
             type PK = #primary_key_type;
 
             #[inline]
-            fn get_primary_key_value(&self) -> Self::PK
-            where
-                Self::PK: ToSqlItem + Sized + Sync
-            {
-                self.#primary_key
+            fn get_primary_key_value(&self) -> &Self::PK {
+                &self.#primary_key
             }
 
+            #has_primary_key_value
+        }
+
+        impl #impl_generics Updatable for #name #ty_generics #where_clause {
             #[inline]
-            fn get_all_fields() -> &'static str {
-                #all_fields_list_string
+            fn get_update_fields() -> &'static str {
+                #update_field_list_string
             }
 
             #[inline]
-            fn get_fields() -> &'static str {
-               #field_list_string
+            fn get_update_params(&self) -> Vec<&(dyn ToSqlItem + Sync)> {
+                vec![#primary_key_value,#(#update_values),*]
             }
 
             #[inline]
-            fn get_values_of_all_fields(&self) -> Vec<&(dyn ToSqlItem + Sync)> {
-                vec![&self.#primary_key,#(&self.#non_pk_field_list),*]
+            fn get_update_argument_count() -> usize {
+                #update_field_list_len
             }
+        }
 
+        impl #impl_generics ToSql for #name #ty_generics #where_clause {
             #[inline]
-            fn get_query_params(&self) -> Vec<&(dyn ToSqlItem + Sync)> {
-                vec![#(&self.#non_pk_field_list),*]
+            fn get_values_of_all_fields(&self) -> Vec<&(dyn ToSqlItem + Sync)> {
+                vec![#(#all_values),*]
             }
 
             #[inline]
-            fn get_prepared_arguments_list() -> &'static str {
-                #prepared_arguments_list
+            fn get_field_values(&self) -> Vec<FieldValue<'_>> {
+                vec![#(#field_values),*]
             }
 
             #[inline]
@@ -139,11 +372,338 @@ pub(crate) fn build_to_sql_implementation(
                 #arguments_list_with_types
             }
 
+            #[inline]
+            fn get_columns() -> &'static [ColumnMeta] {
+                &[#(#columns),*]
+            }
+
+            #[inline]
+            fn get_unique_constraints() -> &'static [&'static [&'static str]] {
+                &[#(#unique_constraints),*]
+            }
+        }
+    );
+    Ok(tokens)
+}
+
+/// Builds the implementation of the `Insertable` trait, which writes all fields except the primary
+/// key and the timestamp fields, together with the conversions of the fields with the `with`
+/// attribute.
+pub(crate) fn build_insertable_implementation(
+    name: &Ident,
+    generics: &Generics,
+    field_list: &[StructFieldData],
+) -> TokenStream {
+    // Timestamp fields are set by the database, so their values are never sent.
+    let written_field_list: Vec<&StructFieldData> = field_list
+        .iter()
+        .filter(|field| field.key_type != KeyType::PrimaryKey && field.is_written())
+        .collect();
+    let field_list_string = quote_identifier_list(
+        written_field_list
+            .iter()
+            .map(|field| field.name.sql_name())
+            .collect::<Vec<String>>()
+            .as_slice(),
+    );
+    let values = written_field_list.iter().map(|field| field.value(name));
+    let field_list_len = written_field_list.len();
+    let prepared_arguments_list = generate_argument_list(field_list_len);
+    let insert_defaults = build_insert_defaults(&written_field_list);
+    let created_at_fields = field_list
+        .iter()
+        .filter(|field| field.created_at)
+        .map(|field| quote_identifier(&field.name.sql_name()));
+    let updated_at_fields = field_list
+        .iter()
+        .filter(|field| field.updated_at)
+        .map(|field| quote_identifier(&field.name.sql_name()));
+    let conversions = build_conversions(name, field_list);
+    let validate = build_validate_method(field_list);
+
+    let bounded_generics =
+        add_type_param_bounds(generics, &[parse_quote!(ToSqlItem), parse_quote!(Sync)]);
+    let (impl_generics, _, where_clause) = bounded_generics.split_for_impl();
+    let (_, ty_generics, _) = generics.split_for_impl();
+    quote!(
+        #conversions
+
+        impl #impl_generics Insertable for #name #ty_generics #where_clause {
+            #[inline]
+            fn get_fields() -> &'static str {
+               #field_list_string
+            }
+
+            #[inline]
+            fn get_query_params(&self) -> Vec<&(dyn ToSqlItem + Sync)> {
+                vec![#(#values),*]
+            }
+
+            #insert_defaults
+
+            #[inline]
+            fn get_prepared_arguments_list() -> &'static str {
+                #prepared_arguments_list
+            }
+
             #[inline]
             fn get_argument_count() -> usize {
                 #field_list_len
             }
+
+            #[inline]
+            fn get_created_at_fields() -> &'static [&'static str] {
+                &[#(#created_at_fields),*]
+            }
+
+            #[inline]
+            fn get_updated_at_fields() -> &'static [&'static str] {
+                &[#(#updated_at_fields),*]
+            }
+
+            #validate
+        }
+    )
+}
+
+/// Builds the implementation of the `Lifecycle` trait with the default callbacks, which do
+/// nothing.
+pub(crate) fn build_lifecycle_implementation(name: &Ident, generics: &Generics) -> TokenStream {
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    quote!(
+        impl #impl_generics Lifecycle for #name #ty_generics #where_clause {}
+    )
+}
+
+/// Builds the implementation of the `MaterializedView` trait, which marks a view that can be
+/// refreshed.
+pub(crate) fn build_materialized_view_implementation(
+    name: &Ident,
+    generics: &Generics,
+) -> TokenStream {
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    quote!(
+        impl #impl_generics MaterializedView for #name #ty_generics #where_clause {}
+    )
+}
+
+/// Builds the implementation of the `Table` trait for a table or view, together with its columns
+/// struct.
+pub(crate) fn build_table_implementation(
+    name: &Ident,
+    generics: &Generics,
+    visibility: &Visibility,
+    table_name: &TableName,
+    history_table: Option<&TableName>,
+    field_list: &[StructFieldData],
+) -> TokenStream {
+    let table_name_string = table_name.quoted();
+    let all_fields_list_string = quote_identifier_list(
+        field_list
+            .iter()
+            .map(|field| field.name.sql_name())
+            .collect::<Vec<String>>()
+            .as_slice(),
+    );
+    let column_names = field_list.iter().map(|field| {
+        let rust_name = identifier_name(field.name.rust_name());
+        let sql_name = quote_identifier(&field.name.sql_name());
+        quote!(#rust_name => Some(#sql_name),)
+    });
+    let tenant_key = field_list
+        .iter()
+        .find(|field| field.tenant_key)
+        .map(|field| {
+            let column = quote_identifier(&field.name.sql_name());
+            let pg_type = &field.pg_field_type;
+            quote!(
+                #[inline]
+                fn get_tenant_key() -> Option<(&'static str, &'static str)> {
+                    Some((#column, #pg_type))
+                }
+            )
+        });
+    let masked_fields: Vec<String> = field_list
+        .iter()
+        .filter(|field| field.masked)
+        .map(|field| quote_identifier(&field.name.sql_name()))
+        .collect();
+    let masked_fields = if masked_fields.is_empty() {
+        TokenStream::new()
+    } else {
+        quote!(
+            #[inline]
+            fn get_masked_fields() -> &'static [&'static str] {
+                &[#(#masked_fields),*]
+            }
+        )
+    };
+    let history_table = history_table.map(|history_table| {
+        let history_table = history_table.quoted();
+        quote!(
+            #[inline]
+            fn get_history_table() -> Option<&'static str> {
+                Some(#history_table)
+            }
+        )
+    });
+    let columns_struct = build_columns_struct(name, visibility, &table_name.name, field_list);
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    quote!(
+        #columns_struct
+
+        impl #impl_generics Table for #name #ty_generics #where_clause {
+            #[inline]
+            fn get_table_name() -> &'static str {
+                #table_name_string
+            }
+
+            #[inline]
+            fn get_all_fields() -> &'static str {
+                #all_fields_list_string
+            }
+
+            fn get_column_name(field: &str) -> Option<&'static str> {
+                match field {
+                    #(#column_names)*
+                    _ => None,
+                }
+            }
+
+            #tenant_key
+
+            #masked_fields
+
+            #history_table
+        }
+    )
+}
+
+/// Builds the `{Struct}Columns` companion struct, which holds the name of the table and a constant
+/// with the Postgres name of every column.
+fn build_columns_struct(
+    name: &Ident,
+    visibility: &Visibility,
+    table_name: &str,
+    field_list: &[StructFieldData],
+) -> TokenStream {
+    let columns_name = Ident::new(&format!("{}Columns", name), name.span());
+    let doc = format!("Table and column names of [`{}`].", name);
+    let constants = field_list.iter().map(|field| {
+        let rust_name = field.name.rust_name();
+        let constant_name = identifier_name(rust_name).to_uppercase();
+        let constant = Ident::new(&constant_name, rust_name.span());
+        let sql_name = field.name.sql_name();
+        quote!(pub const #constant: &'static str = #sql_name;)
+    });
+    quote!(
+        #[doc = #doc]
+        #visibility struct #columns_name;
+
+        #[allow(dead_code)]
+        impl #columns_name {
+            /// The name of the table.
+            pub const TABLE: &'static str = #table_name;
+            #(#constants)*
+        }
+    )
+}
+
+/// Returns the name of the `New{Struct}` insert model of a struct.
+pub(crate) fn insert_model_name(name: &Ident) -> Ident {
+    Ident::new(&format!("New{}", name), name.span())
+}
+
+/// Builds the `New{Struct}` insert model, which contains all fields except the primary key and the
+/// timestamp fields.
+fn build_insert_model(
+    name: &Ident,
+    generics: &Generics,
+    visibility: &Visibility,
+    field_list: &[StructFieldData],
+) -> TokenStream {
+    let model_name = insert_model_name(name);
+    let doc = format!(
+        "The values needed to insert a new [`{}`], without its primary key.",
+        name
+    );
+    let fields: Vec<&StructFieldData> = field_list
+        .iter()
+        .filter(|field| field.key_type != KeyType::PrimaryKey && field.is_written())
+        .collect();
+    let field_names: Vec<&StructName> = fields.iter().map(|field| &field.name).collect();
+    let field_values = fields.iter().map(|field| field.value(name));
+    let insert_defaults = build_insert_defaults(&fields);
+    // The insert model has the fields of the struct, so it is checked with the same rules.
+    let validate = build_validate_method(fields.iter().copied());
+    let field_types = fields.iter().map(|field| &field.rust_type);
+    let field_visibilities = fields.iter().map(|field| &field.visibility);
+    // The model needs to be readable from a row as well.
+    let bounded_generics = add_type_param_bounds(
+        &from_sql_generics(generics),
+        &[parse_quote!(ToSqlItem), parse_quote!(Sync)],
+    );
+    let (impl_generics, _, where_clause) = bounded_generics.split_for_impl();
+    let (_, ty_generics, _) = generics.split_for_impl();
+    let struct_where_clause = &generics.where_clause;
+    quote!(
+        #[doc = #doc]
+        #[derive(Debug)]
+        #visibility struct #model_name #generics #struct_where_clause {
+            #(#field_visibilities #field_names: #field_types),*
+        }
+
+        impl #impl_generics InsertModel for #model_name #ty_generics #where_clause {
+            type Model = #name #ty_generics;
+
+            #[inline]
+            fn get_query_params(&self) -> Vec<&(dyn ToSqlItem + Sync)> {
+                vec![#(#field_values),*]
+            }
+
+            #insert_defaults
+
+            #validate
+        }
+    )
+}
+
+/// Builds a `ToSqlItem` implementation for a newtype, delegating to the wrapped type, so it can be
+/// used as the type of a field (or primary key) in other structs.
+pub(crate) fn build_newtype_to_sql_implementation(
+    name: &Ident,
+    generics: &Generics,
+    fields: &FieldsUnnamed,
+) -> syn::Result<TokenStream> {
+    if fields.unnamed.len() != 1 {
+        return Err(syn::Error::new_spanned(
+            fields,
+            format!(
+                "deriving ToSql on tuple struct {} is only supported for structs with exactly one field",
+                name
+            ),
+        ));
+    }
+    let inner_type = &fields.unnamed[0].ty;
+    let bounded_generics = add_type_param_bounds(generics, &[parse_quote!(sprattus::ToSqlItem)]);
+    let (impl_generics, _, where_clause) = bounded_generics.split_for_impl();
+    let (_, ty_generics, _) = generics.split_for_impl();
+    let tokens = quote!(
+        impl #impl_generics sprattus::ToSqlItem for #name #ty_generics #where_clause {
+            fn to_sql(
+                &self,
+                ty: &sprattus::types::Type,
+                out: &mut sprattus::export::BytesMut,
+            ) -> Result<sprattus::types::IsNull, Box<dyn std::error::Error + Sync + Send>> {
+                sprattus::ToSqlItem::to_sql(&self.0, ty, out)
+            }
+
+            fn accepts(ty: &sprattus::types::Type) -> bool {
+                <#inner_type as sprattus::ToSqlItem>::accepts(ty)
+            }
+
+            sprattus::types::to_sql_checked!();
         }
     );
-    tokens.into()
+    Ok(tokens)
 }
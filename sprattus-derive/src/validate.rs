@@ -0,0 +1,144 @@
+extern crate proc_macro;
+
+use crate::functions::*;
+use crate::to_sql::StructFieldData;
+use proc_macro2::{Ident, TokenStream};
+use quote::{quote, ToTokens};
+use syn::{Expr, Field, Lit, Meta, NestedMeta};
+
+/// A rule of the `validate` attribute of a field, like `length(max = 255)`.
+pub(crate) struct Validation {
+    /// The name of the rule, `length` or `range`.
+    rule: Ident,
+    min: Option<TokenStream>,
+    max: Option<TokenStream>,
+}
+
+/// Returns the rules of the `validate` attribute of a field, like
+/// `#[sprattus(validate(length(max = 255), range(min = 0)))]`.
+pub(crate) fn find_field_validations(field: &Field) -> syn::Result<Vec<Validation>> {
+    let mut validations = Vec::new();
+    for attribute in field.attrs.iter().filter(|a| is_sprattus_attribute(a)) {
+        let list = match attribute.parse_meta()? {
+            Meta::List(list) => list,
+            _ => continue,
+        };
+        for item in list.nested.iter() {
+            if let NestedMeta::Meta(Meta::List(validate)) = item {
+                if validate.path.is_ident("validate") {
+                    for rule in validate.nested.iter() {
+                        validations.push(parse_validation(rule)?);
+                    }
+                }
+            }
+        }
+    }
+    Ok(validations)
+}
+
+/// Parses a single rule, which is `length` or `range` with a `min` and/or a `max` bound.
+fn parse_validation(rule: &NestedMeta) -> syn::Result<Validation> {
+    let list = match rule {
+        NestedMeta::Meta(Meta::List(list))
+            if list.path.is_ident("length") || list.path.is_ident("range") =>
+        {
+            list
+        }
+        _ => {
+            return Err(syn::Error::new_spanned(
+                rule,
+                "expected `length(min = .., max = ..)` or `range(min = .., max = ..)`",
+            ))
+        }
+    };
+    let rule = list.path.get_ident().cloned().unwrap();
+    let mut validation = Validation {
+        rule,
+        min: None,
+        max: None,
+    };
+    for bound in list.nested.iter() {
+        let name_value = match bound {
+            NestedMeta::Meta(Meta::NameValue(name_value))
+                if name_value.path.is_ident("min") || name_value.path.is_ident("max") =>
+            {
+                name_value
+            }
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    bound,
+                    "expected `min = value` or `max = value`",
+                ))
+            }
+        };
+        // Values that aren't a single literal, like a negative number, are written as a string.
+        let value = match &name_value.lit {
+            Lit::Str(value) => value.parse::<Expr>()?.into_token_stream(),
+            value => value.into_token_stream(),
+        };
+        if name_value.path.is_ident("min") {
+            validation.min = Some(value);
+        } else {
+            validation.max = Some(value);
+        }
+    }
+    if validation.min.is_none() && validation.max.is_none() {
+        return Err(syn::Error::new_spanned(
+            list,
+            format!(
+                "the `{}` rule requires a `min` or `max` bound",
+                validation.rule
+            ),
+        ));
+    }
+    Ok(validation)
+}
+
+/// Builds the `validate` method of the `Insertable` or `InsertModel` trait, when one of the fields
+/// has the `validate` attribute.
+pub(crate) fn build_validate_method<'a>(
+    field_list: impl IntoIterator<Item = &'a StructFieldData>,
+) -> TokenStream {
+    let field_list: Vec<&StructFieldData> = field_list
+        .into_iter()
+        .filter(|field| !field.validations.is_empty())
+        .collect();
+    if field_list.is_empty() {
+        return TokenStream::new();
+    }
+    let checks = field_list.into_iter().flat_map(|field| {
+        let name = field.name.rust_name();
+        let field_name = identifier_name(name);
+        let optional = is_option(&field.rust_type);
+        field.validations.iter().map(move |validation| {
+            let rule = &validation.rule;
+            let min = bound(&validation.min);
+            let max = bound(&validation.max);
+            let check = quote!(
+                if let Some(message) = sprattus::validate::#rule(value, #min, #max) {
+                    fields.push(sprattus::FieldError { field: #field_name, message });
+                }
+            );
+            // Fields that are `None` have no value to check.
+            if optional {
+                quote!(if let Some(value) = &self.#name { #check })
+            } else {
+                quote!({ let value = &self.#name; #check })
+            }
+        })
+    });
+    quote!(
+        fn validate(&self) -> Result<(), sprattus::ValidationError> {
+            let mut fields = Vec::new();
+            #(#checks)*
+            sprattus::ValidationError::check(fields)
+        }
+    )
+}
+
+fn bound(value: &Option<TokenStream>) -> TokenStream {
+    match value {
+        Some(value) => quote!(Some(#value)),
+        None => quote!(None),
+    }
+}
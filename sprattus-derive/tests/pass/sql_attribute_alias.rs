@@ -0,0 +1,18 @@
+#![allow(deprecated)]
+
+use sprattus::*;
+
+#[derive(ToSql, FromSql)]
+#[sql(table = "products")]
+struct Product {
+    #[sql(primary_key)]
+    prod_id: i32,
+    #[sql(name = "title")]
+    name: String,
+}
+
+fn main() {
+    assert_eq!(Product::get_table_name(), "\"products\"");
+    assert_eq!(Product::get_primary_key(), "\"prod_id\"");
+    assert_eq!(Product::get_fields(), "\"title\"");
+}
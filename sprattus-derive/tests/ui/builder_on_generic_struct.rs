@@ -0,0 +1,12 @@
+use sprattus::*;
+
+#[derive(ToSql)]
+#[sprattus(builder)]
+struct Product<T> {
+    #[sprattus(primary_key)]
+    prod_id: i32,
+    #[sprattus(pg_type = "VARCHAR")]
+    title: T,
+}
+
+fn main() {}
@@ -0,0 +1,10 @@
+use sprattus::*;
+
+#[derive(ToSql)]
+#[sprattus(auto_primary_key, no_primary_key)]
+struct Product {
+    id: i32,
+    title: String,
+}
+
+fn main() {}
@@ -0,0 +1,11 @@
+use sprattus::*;
+
+#[derive(ToSql)]
+struct Product {
+    #[sprattus(primary_key)]
+    prod_id: i32,
+    #[sprattus(default_on_none)]
+    price: f64,
+}
+
+fn main() {}
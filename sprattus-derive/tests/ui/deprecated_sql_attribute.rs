@@ -0,0 +1,13 @@
+#![deny(deprecated)]
+
+use sprattus::*;
+
+#[derive(ToSql)]
+#[sql(table = "products")]
+struct Product {
+    #[sprattus(primary_key)]
+    prod_id: i32,
+    title: String,
+}
+
+fn main() {}
@@ -0,0 +1,10 @@
+use sprattus::*;
+
+#[derive(ToSql)]
+#[sprattus(table = "products", primary_key)]
+struct Product {
+    prod_id: i32,
+    title: String,
+}
+
+fn main() {}
@@ -0,0 +1,10 @@
+use sprattus::*;
+
+#[derive(ToSql)]
+#[sprattus(table)]
+struct Product {
+    #[sprattus(primary_key)]
+    prod_id: i32,
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use sprattus::*;
+
+#[derive(ToSql)]
+struct Product {
+    prod_id: i32,
+    title: String,
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use sprattus::*;
+
+#[derive(ToSql)]
+enum Product {
+    Book,
+    Movie,
+}
+
+fn main() {}
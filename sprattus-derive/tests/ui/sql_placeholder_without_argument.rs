@@ -0,0 +1,12 @@
+use sprattus::*;
+
+#[derive(ToSql, FromSql)]
+struct Product {
+    #[sprattus(primary_key)]
+    prod_id: i32,
+    title: String,
+}
+
+fn main() {
+    let _ = sql!(Product, "SELECT {columns} FROM {table} WHERE prod_id BETWEEN $1 AND $2", &1);
+}
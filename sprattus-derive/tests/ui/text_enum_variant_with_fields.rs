@@ -0,0 +1,9 @@
+use sprattus::*;
+
+#[derive(SqlTextEnum)]
+enum Status {
+    Paid,
+    Refunded(i32),
+}
+
+fn main() {}
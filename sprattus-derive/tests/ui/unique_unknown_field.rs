@@ -0,0 +1,11 @@
+use sprattus::*;
+
+#[derive(ToSql)]
+#[sprattus(unique = "title, isbn")]
+struct Product {
+    #[sprattus(primary_key)]
+    prod_id: i32,
+    title: String,
+}
+
+fn main() {}
@@ -0,0 +1,10 @@
+use sprattus::*;
+
+#[derive(ToSql)]
+struct Product {
+    #[sprattus(primary_key, primary)]
+    prod_id: i32,
+    title: String,
+}
+
+fn main() {}
@@ -0,0 +1,11 @@
+use sprattus::*;
+
+#[derive(ToSql)]
+struct Product {
+    #[sprattus(primary_key)]
+    prod_id: i32,
+    #[sprattus(validate(size(max = 255)))]
+    title: String,
+}
+
+fn main() {}
@@ -0,0 +1,10 @@
+use sprattus::*;
+
+#[derive(ToSql)]
+struct Account {
+    #[sprattus(primary_key)]
+    id: u64,
+    balance: i64,
+}
+
+fn main() {}
@@ -0,0 +1,12 @@
+use sprattus::*;
+
+struct Price(f64);
+
+#[derive(ToSql)]
+struct Product {
+    #[sprattus(primary_key)]
+    prod_id: i32,
+    price: Price,
+}
+
+fn main() {}
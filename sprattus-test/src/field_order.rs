@@ -0,0 +1,69 @@
+use sprattus::*;
+
+/// A struct of which the primary key is not the first field.
+#[derive(Eq, PartialEq, Debug, ToSql, FromSql)]
+#[sprattus(table = "labels")]
+struct Label {
+    name: String,
+    #[sprattus(primary_key)]
+    id: Option<i32>,
+    color: String,
+}
+
+pub async fn test_primary_key_after_other_fields(conn: Connection) -> Result<(), Error> {
+    print!("\n Testing if a primary key after other fields is supported ... \n\n");
+
+    // Setup table
+    conn.batch_execute(
+        "DROP TABLE IF EXISTS labels;
+        CREATE TABLE labels (
+	    name varchar NOT NULL,
+	    id serial NOT NULL PRIMARY KEY,
+	    color varchar NOT NULL);",
+    )
+    .await?;
+
+    // Insert test
+    let labels = [
+        Label {
+            name: String::from("bug"),
+            id: None,
+            color: String::from("red"),
+        },
+        Label {
+            name: String::from("feature"),
+            id: Some(10),
+            color: String::from("green"),
+        },
+    ];
+    let created = conn.create_multiple(&labels).await?;
+    assert_eq!(created[0].name, "bug");
+    assert!(created[0].id.is_some());
+    assert_eq!(created[1], labels[1]);
+    println!("Insert succeeded");
+
+    // Update test
+    let updated = vec![Label {
+        name: String::from("enhancement"),
+        id: Some(10),
+        color: String::from("blue"),
+    }];
+    assert_eq!(conn.update_multiple(&updated).await?, updated);
+    println!("Update succeeded");
+
+    // Upsert test
+    let upserted = Label {
+        name: String::from("enhancement"),
+        id: Some(10),
+        color: String::from("purple"),
+    };
+    let rows = conn
+        .upsert_multiple_with(
+            std::slice::from_ref(&upserted),
+            OnConflict::UpdateColumns(&["color"]),
+        )
+        .await?;
+    assert_eq!(rows, vec![Upserted::Updated(upserted)]);
+    println!("Upsert succeeded");
+    Ok(())
+}
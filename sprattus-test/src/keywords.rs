@@ -3,7 +3,7 @@ use sprattus::*;
 /// This struct just contains keywords as names, just to test.
 #[derive(Eq, PartialEq, Debug, ToSql, FromSql)]
 struct Collate {
-    #[sql(primary_key)]
+    #[sprattus(primary_key)]
     id: i32,
     column: bool,
     desc: bool,
@@ -0,0 +1,133 @@
+use sprattus::*;
+use std::sync::Mutex;
+
+/// The callbacks that were invoked, in order.
+static CALLBACKS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+fn record(callback: &str) {
+    CALLBACKS.lock().unwrap().push(callback.to_string());
+}
+
+fn take_callbacks() -> Vec<String> {
+    std::mem::take(&mut *CALLBACKS.lock().unwrap())
+}
+
+#[derive(Eq, PartialEq, Debug, ToSql, FromSql)]
+#[sprattus(table = "counters", insert_model, lifecycle)]
+struct Counter {
+    #[sprattus(primary_key)]
+    id: i32,
+    name: String,
+    hits: i32,
+}
+
+impl Lifecycle for Counter {
+    fn before_insert(&self) -> Result<(), Error> {
+        record("before_insert");
+        Ok(())
+    }
+
+    fn after_insert(&self) {
+        record("after_insert");
+    }
+
+    fn before_update(&self) -> Result<(), Error> {
+        record("before_update");
+        if self.hits >= 2 {
+            return Err(Error::Source("the counter is full".into()));
+        }
+        Ok(())
+    }
+
+    fn after_update(&self) {
+        record("after_update");
+    }
+}
+
+impl Lifecycle for NewCounter {
+    fn before_insert(&self) -> Result<(), Error> {
+        record("new_before_insert");
+        if self.name.is_empty() {
+            return Err(Error::Source("a counter needs a name".into()));
+        }
+        Ok(())
+    }
+}
+
+/// A log line, which has no primary key.
+#[derive(Debug, ToSql)]
+#[sprattus(table = "counter_log", no_primary_key, lifecycle)]
+struct LogLine {
+    line: String,
+}
+
+impl Lifecycle for LogLine {
+    fn before_insert(&self) -> Result<(), Error> {
+        record("log_before_insert");
+        Ok(())
+    }
+
+    fn after_insert(&self) {
+        record("log_after_insert");
+    }
+}
+
+pub async fn test_lifecycle_callbacks(conn: Connection) -> Result<(), Error> {
+    print!("\n Testing if the lifecycle callbacks are invoked around every write ... \n\n");
+
+    // Setup tables
+    conn.batch_execute(
+        "DROP TABLE IF EXISTS counters;
+        CREATE TABLE counters (
+	    id serial NOT NULL PRIMARY KEY,
+	    name varchar NOT NULL,
+	    hits int4 NOT NULL);
+        DROP TABLE IF EXISTS counter_log;
+        CREATE TABLE counter_log (line varchar NOT NULL);",
+    )
+    .await?;
+    take_callbacks();
+
+    // Insert model test
+    let counter: Counter = conn
+        .create_new(&NewCounter {
+            name: String::from("visits"),
+            hits: 0,
+        })
+        .await?;
+    assert_eq!(take_callbacks(), vec!["new_before_insert", "after_insert"]);
+    assert!(conn
+        .create_new(&NewCounter {
+            name: String::new(),
+            hits: 0,
+        })
+        .await
+        .is_err());
+    assert_eq!(take_callbacks(), vec!["new_before_insert"]);
+    println!("Create from an insert model succeeded");
+
+    // Increment test
+    conn.increment::<Counter, _>(&counter.id, "hits", &2)
+        .await?;
+    assert_eq!(take_callbacks(), vec!["before_update", "after_update"]);
+    assert!(conn
+        .increment::<Counter, _>(&counter.id, "hits", &1)
+        .await
+        .is_err());
+    assert_eq!(take_callbacks(), vec!["before_update"]);
+    assert_eq!(conn.find::<Counter>(&counter.id).await?.hits, 2);
+    println!("Increment succeeded");
+
+    // Insert test
+    conn.insert(&LogLine {
+        line: String::from("visits was created"),
+    })
+    .await?;
+    assert_eq!(
+        take_callbacks(),
+        vec!["log_before_insert", "log_after_insert"]
+    );
+    println!("Insert succeeded");
+
+    Ok(())
+}
@@ -0,0 +1,111 @@
+use sprattus::*;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+#[derive(Eq, PartialEq, Debug, Clone, ToSql, FromSql)]
+#[sprattus(table = "authors")]
+struct Author {
+    #[sprattus(primary_key)]
+    id: i32,
+    name: String,
+}
+
+/// Collects every statement of the connection, as all of them are slower than a threshold of zero.
+#[derive(Clone, Default)]
+struct Statements(Arc<Mutex<Vec<String>>>);
+
+impl ConnectionObserver for Statements {
+    fn on_slow_query(&self, sql: &str, _duration: Duration) {
+        self.0.lock().unwrap().push(sql.to_string());
+    }
+}
+
+pub async fn test_loads_are_batched(conn: Connection) -> Result<(), Error> {
+    print!("\n Testing if loads of concurrent tasks are combined into one query ... \n\n");
+
+    // Setup table
+    conn.batch_execute(
+        "DROP TABLE IF EXISTS authors;
+        CREATE TABLE authors (
+	    id int4 NOT NULL PRIMARY KEY,
+	    name varchar NOT NULL);
+        INSERT INTO authors (id, name) VALUES (1, 'Tolkien'), (2, 'Le Guin'), (3, 'Pratchett');",
+    )
+    .await?;
+    let authors = [
+        Author {
+            id: 1,
+            name: String::from("Tolkien"),
+        },
+        Author {
+            id: 2,
+            name: String::from("Le Guin"),
+        },
+        Author {
+            id: 3,
+            name: String::from("Pratchett"),
+        },
+    ];
+
+    let statements = Statements::default();
+    let observed = Connection::builder()
+        .host("localhost")
+        .user("postgres")
+        .observer(statements.clone())
+        .slow_query_threshold(Duration::ZERO)
+        .connect()
+        .await?;
+    let loader = Loader::<Author>::new(&observed);
+
+    // Every load runs in its own task, which can run on any thread of the runtime. The key 2 is
+    // loaded twice, and there is no author with the key 4.
+    let tasks: Vec<_> = [3, 2, 4, 2, 1]
+        .iter()
+        .map(|&id| {
+            let loader = loader.clone();
+            tokio::spawn(async move { loader.load(id).await })
+        })
+        .collect();
+    let mut loaded = Vec::new();
+    for task in tasks {
+        loaded.push(task.await.expect("the load panicked")?);
+    }
+    assert_eq!(
+        loaded,
+        vec![
+            Some(authors[2].clone()),
+            Some(authors[1].clone()),
+            None,
+            Some(authors[1].clone()),
+            Some(authors[0].clone()),
+        ]
+    );
+    let executed = statements.0.lock().unwrap().clone();
+    assert_eq!(
+        executed.len(),
+        1,
+        "expected a single query, got {:?}",
+        executed
+    );
+    assert!(executed[0].contains("= ANY($1)"));
+    println!("Batched loads succeeded");
+
+    // The delay collects loads that are requested later.
+    statements.0.lock().unwrap().clear();
+    let loader = Loader::<Author>::new(&observed).delay(Duration::from_millis(500));
+    let first = tokio::spawn({
+        let loader = loader.clone();
+        async move { loader.load(1).await }
+    });
+    tokio::time::sleep(Duration::from_millis(10)).await;
+    let second = loader.load(3).await?;
+    assert_eq!(
+        first.await.expect("the load panicked")?,
+        Some(authors[0].clone())
+    );
+    assert_eq!(second, Some(authors[2].clone()));
+    assert_eq!(statements.0.lock().unwrap().len(), 1);
+    println!("Delayed loads succeeded");
+
+    Ok(())
+}
@@ -1,20 +1,36 @@
+use crate::field_order::test_primary_key_after_other_fields;
 use crate::keywords::test_if_keywords_are_escaped;
+use crate::lifecycle::test_lifecycle_callbacks;
+use crate::loader::test_loads_are_batched;
+use crate::queue::test_lost_jobs_are_left_alone;
+use crate::search_path::test_search_path_on_connect;
+use crate::string_keys::test_string_primary_keys;
+use crate::test_db::test_changes_are_rolled_back;
+use crate::transactions::test_transactions;
 use chrono::*;
 use sprattus::*;
 
+mod field_order;
 mod keywords;
+mod lifecycle;
+mod loader;
+mod queue;
+mod search_path;
+mod string_keys;
+mod test_db;
+mod transactions;
 
 #[derive(FromSql, ToSql, Eq, PartialEq, Debug)]
-#[sql(table = "reorder")]
+#[sprattus(table = "reorder")]
 struct Reorder {
-    #[sql(primary_key)]
-    #[sql(name = "prod_id")]
+    #[sprattus(primary_key)]
+    #[sprattus(name = "prod_id")]
     id: i32,
     date_low: NaiveDate,
-    #[sql(name = "quan_low")]
+    #[sprattus(name = "quan_low")]
     quantity_low: i32,
     date_reordered: Option<NaiveDate>,
-    #[sql(name = "quan_reordered")]
+    #[sprattus(name = "quan_reordered")]
     quantity_reordered: Option<i32>,
     date_expected: Option<NaiveDate>,
 }
@@ -41,7 +57,7 @@ async fn main() -> Result<(), Error> {
     let reorders = vec![
         Reorder {
             id: 1,
-            date_low: NaiveDate::from_ymd(1944, 11, 6),
+            date_low: NaiveDate::from_ymd_opt(1944, 11, 6).unwrap(),
             quantity_low: 0,
             date_reordered: None,
             quantity_reordered: Some(10001),
@@ -49,7 +65,7 @@ async fn main() -> Result<(), Error> {
         },
         Reorder {
             id: 2,
-            date_low: NaiveDate::from_ymd(1945, 5, 5),
+            date_low: NaiveDate::from_ymd_opt(1945, 5, 5).unwrap(),
             quantity_low: 0,
             date_reordered: None,
             quantity_reordered: Some(1),
@@ -57,7 +73,7 @@ async fn main() -> Result<(), Error> {
         },
         Reorder {
             id: 3,
-            date_low: NaiveDate::from_ymd(1969, 11, 6),
+            date_low: NaiveDate::from_ymd_opt(1969, 11, 6).unwrap(),
             quantity_low: 0,
             date_reordered: None,
             quantity_reordered: Some(300),
@@ -65,7 +81,7 @@ async fn main() -> Result<(), Error> {
         },
         Reorder {
             id: 4,
-            date_low: NaiveDate::from_ymd(1989, 11, 6),
+            date_low: NaiveDate::from_ymd_opt(1989, 11, 6).unwrap(),
             quantity_low: 0,
             date_reordered: None,
             quantity_reordered: Some(4),
@@ -73,7 +89,7 @@ async fn main() -> Result<(), Error> {
         },
         Reorder {
             id: 5,
-            date_low: NaiveDate::from_ymd(1998, 11, 26),
+            date_low: NaiveDate::from_ymd_opt(1998, 11, 26).unwrap(),
             quantity_low: 0,
             date_reordered: None,
             quantity_reordered: Some(5),
@@ -83,15 +99,15 @@ async fn main() -> Result<(), Error> {
     let reorders_update = vec![
         Reorder {
             id: 1,
-            date_low: NaiveDate::from_ymd(1944, 11, 6),
+            date_low: NaiveDate::from_ymd_opt(1944, 11, 6).unwrap(),
             quantity_low: 0,
             date_reordered: None,
             quantity_reordered: Some(20002),
-            date_expected: Some(NaiveDate::from_ymd(1944, 11, 7)),
+            date_expected: Some(NaiveDate::from_ymd_opt(1944, 11, 7).unwrap()),
         },
         Reorder {
             id: 2,
-            date_low: NaiveDate::from_ymd(1945, 5, 5),
+            date_low: NaiveDate::from_ymd_opt(1945, 5, 5).unwrap(),
             quantity_low: 0,
             date_reordered: None,
             quantity_reordered: None,
@@ -99,7 +115,7 @@ async fn main() -> Result<(), Error> {
         },
         Reorder {
             id: 3,
-            date_low: NaiveDate::from_ymd(1969, 11, 6),
+            date_low: NaiveDate::from_ymd_opt(1969, 11, 6).unwrap(),
             quantity_low: 0,
             date_reordered: None,
             quantity_reordered: Some(300),
@@ -107,7 +123,7 @@ async fn main() -> Result<(), Error> {
         },
         Reorder {
             id: 4,
-            date_low: NaiveDate::from_ymd(1989, 11, 6),
+            date_low: NaiveDate::from_ymd_opt(1989, 11, 6).unwrap(),
             quantity_low: 0,
             date_reordered: None,
             quantity_reordered: Some(4),
@@ -115,11 +131,11 @@ async fn main() -> Result<(), Error> {
         },
         Reorder {
             id: 5,
-            date_low: NaiveDate::from_ymd(1998, 11, 26),
+            date_low: NaiveDate::from_ymd_opt(1998, 11, 26).unwrap(),
             quantity_low: 0,
             date_reordered: None,
             quantity_reordered: Some(3),
-            date_expected: Some(NaiveDate::from_ymd(1998, 12, 26)),
+            date_expected: Some(NaiveDate::from_ymd_opt(1998, 12, 26).unwrap()),
         },
     ];
 
@@ -145,7 +161,15 @@ async fn main() -> Result<(), Error> {
     assert_eq!(deleted_reorders, reorders_update);
     println!("Delete succeeded");
 
-    test_if_keywords_are_escaped(conn).await?;
+    test_if_keywords_are_escaped(conn.clone()).await?;
+    test_string_primary_keys(conn.clone()).await?;
+    test_primary_key_after_other_fields(conn.clone()).await?;
+    test_changes_are_rolled_back(conn.clone()).await?;
+    test_transactions(conn.clone()).await?;
+    test_loads_are_batched(conn.clone()).await?;
+    test_search_path_on_connect(conn.clone()).await?;
+    test_lifecycle_callbacks(conn.clone()).await?;
+    test_lost_jobs_are_left_alone(conn).await?;
 
     print!("\n Done!\n");
     Ok(())
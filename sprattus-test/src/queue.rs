@@ -0,0 +1,37 @@
+use sprattus::queue::Queue;
+use sprattus::*;
+use std::time::Duration;
+
+pub async fn test_lost_jobs_are_left_alone(conn: Connection) -> Result<(), Error> {
+    print!("\n Testing if a worker that lost its job can't complete or fail it ... \n\n");
+
+    // Setup table
+    conn.batch_execute("DROP TABLE IF EXISTS mail_jobs;")
+        .await?;
+    // Without a visibility timeout, a dequeued job is visible to the next worker right away, like
+    // a job of a worker that took too long.
+    let queue = Queue::new(&conn, "mail_jobs").visibility_timeout(Duration::from_secs(0));
+    queue.create_table().await?;
+    queue.enqueue(&String::from("welcome mail")).await?;
+
+    let lost = queue.dequeue::<String>().await?.expect("a job");
+    let taken = queue.dequeue::<String>().await?.expect("the job again");
+    assert_eq!(lost.id, taken.id);
+    assert_eq!(taken.attempts, 2);
+    assert!(!queue.fail(&lost, "timed out").await?);
+    assert!(!queue.complete(&lost).await?);
+    println!("Lost job was left alone");
+
+    assert!(queue.fail(&taken, "mail server unavailable").await?);
+    let retried = queue.dequeue::<String>().await?.expect("the failed job");
+    assert_eq!(
+        retried.last_error.as_deref(),
+        Some("mail server unavailable")
+    );
+    assert!(queue.complete(&retried).await?);
+    assert!(queue.dequeue::<String>().await?.is_none());
+    println!("Complete succeeded");
+
+    conn.batch_execute("DROP TABLE mail_jobs;").await?;
+    Ok(())
+}
@@ -0,0 +1,49 @@
+use sprattus::*;
+
+/// A struct of which the table is only found through the search path.
+#[derive(Eq, PartialEq, Debug, ToSql, FromSql)]
+#[sprattus(table = "invoices")]
+struct Invoice {
+    #[sprattus(primary_key)]
+    number: String,
+    amount: i32,
+}
+
+pub async fn test_search_path_on_connect(conn: Connection) -> Result<(), Error> {
+    print!("\n Testing if the search path of the builder is applied on connect ... \n\n");
+
+    // Setup table
+    conn.batch_execute(
+        "DROP SCHEMA IF EXISTS tenant_a CASCADE;
+        CREATE SCHEMA tenant_a;
+        CREATE TABLE tenant_a.invoices (
+	    number varchar NOT NULL PRIMARY KEY,
+	    amount int4 NOT NULL);
+        INSERT INTO tenant_a.invoices VALUES ('2024-001', 250);",
+    )
+    .await?;
+
+    let tenant = Connection::builder()
+        .host("localhost")
+        .user("postgres")
+        .search_path(&["tenant_a", "public"])
+        .connect()
+        .await?;
+    assert_eq!(
+        tenant.search_path(),
+        Some(vec![String::from("tenant_a"), String::from("public")])
+    );
+    let invoices = tenant.fetch_all::<Invoice>().await?;
+    assert_eq!(
+        invoices,
+        vec![Invoice {
+            number: String::from("2024-001"),
+            amount: 250,
+        }]
+    );
+    println!("Search path on connect succeeded");
+
+    tenant.close().await;
+    conn.batch_execute("DROP SCHEMA tenant_a CASCADE;").await?;
+    Ok(())
+}
@@ -0,0 +1,72 @@
+use sprattus::*;
+
+/// A struct with a primary key that is not `Copy`.
+#[derive(Eq, PartialEq, Debug, ToSql, FromSql)]
+#[sprattus(table = "countries")]
+struct Country {
+    #[sprattus(primary_key)]
+    code: String,
+    name: String,
+}
+
+pub async fn test_string_primary_keys(conn: Connection) -> Result<(), Error> {
+    print!("\n Testing if text primary keys are supported ... \n\n");
+
+    let fixture = [
+        Country {
+            code: String::from("NL"),
+            name: String::from("Netherlands"),
+        },
+        Country {
+            code: String::from("BE"),
+            name: String::from("Belgium"),
+        },
+    ];
+    let update_fixture = vec![
+        Country {
+            code: String::from("NL"),
+            name: String::from("The Netherlands"),
+        },
+        Country {
+            code: String::from("BE"),
+            name: String::from("Kingdom of Belgium"),
+        },
+    ];
+    // Setup table
+    conn.batch_execute(
+        "DROP TABLE IF EXISTS countries;
+        CREATE TABLE countries (
+	    code varchar NOT NULL PRIMARY KEY,
+	    name varchar NOT NULL);",
+    )
+    .await?;
+
+    // Insert test
+    conn.execute(
+        "INSERT INTO countries (code, name) VALUES ($1, $2), ($3, $4)",
+        &[
+            &fixture[0].code,
+            &fixture[0].name,
+            &fixture[1].code,
+            &fixture[1].name,
+        ],
+    )
+    .await?;
+    println!("Insert succeeded");
+
+    // Update test
+    let updated_item = conn.update(&update_fixture[0]).await?;
+    assert_eq!(updated_item, update_fixture[0]);
+    let updated_items = conn.update_multiple(&update_fixture).await?;
+    assert_eq!(updated_items, update_fixture);
+    println!("Update succeeded");
+
+    // Delete test
+    let deleted_item = conn.delete(&update_fixture[0]).await?;
+    assert_eq!(deleted_item, update_fixture[0]);
+    let deleted_items = conn.delete_multiple(&update_fixture[1..]).await?;
+    assert_eq!(deleted_items, &update_fixture[1..]);
+    println!("Delete succeeded");
+
+    Ok(())
+}
@@ -0,0 +1,42 @@
+use sprattus::test::TestDb;
+use sprattus::*;
+
+#[derive(Eq, PartialEq, Debug, ToSql, FromSql)]
+#[sprattus(table = "notes")]
+struct Note {
+    #[sprattus(primary_key)]
+    id: i32,
+    body: String,
+}
+
+pub async fn test_changes_are_rolled_back(conn: Connection) -> Result<(), Error> {
+    print!("\n Testing if the changes of a test database are rolled back ... \n\n");
+
+    // Setup table
+    conn.batch_execute(
+        "DROP TABLE IF EXISTS notes;
+        CREATE TABLE notes (
+	    id serial NOT NULL PRIMARY KEY,
+	    body varchar NOT NULL);",
+    )
+    .await?;
+
+    let note = Note {
+        id: 1,
+        body: String::from("Discarded"),
+    };
+    let db = TestDb::with_connection(&conn).await?;
+    assert_eq!(db.create(&note).await?, note);
+    // Transactions within the test become savepoints.
+    let transaction = db.transaction().await?;
+    transaction.delete(&note).await?;
+    transaction.rollback().await?;
+    assert_eq!(db.find::<Note>(&1).await?, note);
+    println!("Create within the test succeeded");
+
+    db.rollback().await?;
+    let notes = conn.fetch_all::<Note>().await?;
+    assert!(notes.is_empty());
+    println!("Rollback succeeded");
+    Ok(())
+}
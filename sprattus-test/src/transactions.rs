@@ -0,0 +1,49 @@
+use sprattus::*;
+
+#[derive(Eq, PartialEq, Debug, ToSql, FromSql)]
+#[sprattus(table = "payments")]
+struct Payment {
+    #[sprattus(primary_key)]
+    id: i32,
+    amount: i32,
+}
+
+pub async fn test_transactions(conn: Connection) -> Result<(), Error> {
+    print!("\n Testing if transactions are isolated from each other ... \n\n");
+
+    // Setup table
+    conn.batch_execute(
+        "DROP TABLE IF EXISTS payments;
+        CREATE TABLE payments (
+	    id serial NOT NULL PRIMARY KEY,
+	    amount int4 NOT NULL);",
+    )
+    .await?;
+
+    let dropped = Payment { id: 0, amount: 10 };
+    let committed = Payment { id: 0, amount: 20 };
+
+    // The rollback of a dropped transaction is sent before the next transaction begins.
+    let transaction = conn.transaction().await?;
+    transaction.create(&dropped).await?;
+    drop(transaction);
+    let transaction = conn.transaction().await?;
+    let committed = transaction.create(&committed).await?;
+    transaction.commit().await?;
+    let payments = conn.fetch_all::<Payment>().await?;
+    assert_eq!(payments, vec![committed]);
+    println!("Begin after a dropped transaction succeeded");
+
+    // Other clones of the connection don't join an open transaction.
+    let transaction = conn.transaction().await?;
+    let error = conn.fetch_all::<Payment>().await.unwrap_err();
+    assert!(matches!(error.inner(), Error::TransactionInProgress));
+    assert!(matches!(
+        conn.transaction().await,
+        Err(Error::TransactionInProgress)
+    ));
+    transaction.rollback().await?;
+    assert_eq!(conn.fetch_all::<Payment>().await?.len(), 1);
+    println!("Statements outside of an open transaction are rejected");
+    Ok(())
+}
@@ -0,0 +1,130 @@
+//! Benchmarks of reading rows into structs and of the statements modifying many rows at once.
+//!
+//! The benchmarks run against the database of the `SPRATTUS_BENCH_DATABASE` environment variable,
+//! or a local database, within a transaction that is rolled back at the end.
+use criterion::{criterion_group, criterion_main, Criterion};
+use sprattus::test::TestDb;
+use sprattus::*;
+use std::env;
+use std::time::{Duration, Instant};
+use tokio::runtime::Runtime;
+
+const ROWS: i32 = 1000;
+
+#[derive(FromSql, ToSql, Debug)]
+#[sprattus(table = "bench_products")]
+struct Product {
+    #[sprattus(primary_key)]
+    prod_id: i32,
+    title: String,
+    price: f64,
+    stock: i32,
+}
+
+fn products() -> Vec<Product> {
+    (1..=ROWS)
+        .map(|prod_id| Product {
+            prod_id,
+            title: format!("product {}", prod_id),
+            price: f64::from(prod_id) / 10.0,
+            stock: prod_id % 50,
+        })
+        .collect()
+}
+
+async fn test_db() -> TestDb {
+    let connection_string = env::var("SPRATTUS_BENCH_DATABASE")
+        .unwrap_or_else(|_| String::from("postgresql://localhost?user=postgres"));
+    let db = TestDb::new(connection_string.as_str())
+        .await
+        .expect("cannot connect to the benchmark database");
+    db.batch_execute(
+        "CREATE TEMPORARY TABLE bench_products (
+            prod_id SERIAL PRIMARY KEY,
+            title TEXT NOT NULL,
+            price DOUBLE PRECISION NOT NULL,
+            stock INT NOT NULL
+        )",
+    )
+    .await
+    .expect("cannot create the benchmark table");
+    db
+}
+
+fn from_row(c: &mut Criterion) {
+    let runtime = Runtime::new().unwrap();
+    let db = runtime.block_on(async {
+        let db = test_db().await;
+        db.create_multiple(&products()).await.unwrap();
+        db
+    });
+    c.bench_function("fetch_all 1000 rows", |b| {
+        b.to_async(&runtime)
+            .iter(|| async { db.fetch_all::<Product>().await.unwrap() })
+    });
+    c.bench_function("query_multiple 1000 rows", |b| {
+        b.to_async(&runtime).iter(|| async {
+            db.query_multiple::<Product>("SELECT * FROM bench_products", &[])
+                .await
+                .unwrap()
+        })
+    });
+}
+
+fn bulk(c: &mut Criterion) {
+    let runtime = Runtime::new().unwrap();
+    let db = runtime.block_on(test_db());
+    c.bench_function("create_multiple 1000 rows", |b| {
+        b.to_async(&runtime).iter_custom(|iterations| {
+            let db = &db;
+            async move {
+                let mut elapsed = Duration::default();
+                for _ in 0..iterations {
+                    let items = products();
+                    let start = Instant::now();
+                    let created = db.create_multiple(&items).await.unwrap();
+                    elapsed += start.elapsed();
+                    db.delete_multiple(&created).await.unwrap();
+                }
+                elapsed
+            }
+        })
+    });
+    c.bench_function("update_multiple 1000 rows", |b| {
+        b.to_async(&runtime).iter_custom(|iterations| {
+            let db = &db;
+            async move {
+                let mut items = db.create_multiple(&products()).await.unwrap();
+                let mut elapsed = Duration::default();
+                for _ in 0..iterations {
+                    for item in &mut items {
+                        item.stock += 1;
+                    }
+                    let start = Instant::now();
+                    items = db.update_multiple(&items).await.unwrap();
+                    elapsed += start.elapsed();
+                }
+                db.delete_multiple(&items).await.unwrap();
+                elapsed
+            }
+        })
+    });
+    c.bench_function("delete_multiple 1000 rows", |b| {
+        b.to_async(&runtime).iter_custom(|iterations| {
+            let db = &db;
+            async move {
+                let mut elapsed = Duration::default();
+                for _ in 0..iterations {
+                    let created = db.create_multiple(&products()).await.unwrap();
+                    let start = Instant::now();
+                    db.delete_multiple(&created).await.unwrap();
+                    elapsed += start.elapsed();
+                }
+                elapsed
+            }
+        })
+    });
+}
+
+criterion_group!(benches, from_row, bulk);
+criterion_main!(benches);
@@ -0,0 +1,266 @@
+//! Extractors for [actix-web](https://actix.rs) handlers.
+//!
+//! A [`Connection`](../struct.Connection.html) registered as app data can be taken as an argument
+//! of a handler. The [`Tx`](struct.Tx.html) extractor runs the statements of a handler within a
+//! transaction instead. The transaction is committed by the
+//! [`TransactionMiddleware`](struct.TransactionMiddleware.html) when the handler responds with a
+//! success or redirection status, and rolled back otherwise.
+//!
+//! While a transaction is open, every other clone of its connection fails with
+//! [`Error::TransactionInProgress`](../enum.Error.html#variant.TransactionInProgress), so a `Tx`
+//! takes a connection of its own from a pool registered as app data: a
+//! `bb8::Pool<PoolManager>` with the `with-bb8-0_8` feature or a
+//! `deadpool::managed::Pool<PoolManager>` with the `with-deadpool-0_9` feature, see the
+//! [`pool`](../pool/index.html) module. The connection returns to the pool when the transaction
+//! is finished. `Tx` and the middleware are only available with one of these features, and a `Tx`
+//! fails when no pool is registered.
+//!
+//! Errors of sprattus implement `ResponseError`, so they can be returned from a handler with `?`.
+//!
+//! Example:
+//! ```no_run
+//! use actix_web::{web, App, HttpResponse, HttpServer};
+//! use sprattus::*;
+//!
+//! #[derive(FromSql, ToSql)]
+//! #[sprattus(table = "products")]
+//! struct Product {
+//!     #[sprattus(primary_key)]
+//!     prod_id: i32,
+//!     title: String,
+//! }
+//!
+//! async fn count(conn: Connection) -> Result<HttpResponse, Error> {
+//!     let products = conn.fetch_all::<Product>().await?;
+//!     Ok(HttpResponse::Ok().body(products.len().to_string()))
+//! }
+//!
+//! #[actix_web::main]
+//! async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//!     let conn = Connection::new("postgresql://localhost?user=tg").await?;
+//!     HttpServer::new(move || {
+//!         App::new()
+//!             .app_data(web::Data::new(conn.clone()))
+//!             .route("/products/count", web::get().to(count))
+//!     })
+//!     .bind("127.0.0.1:8080")?
+//!     .run()
+//!     .await?;
+//!     Ok(())
+//! }
+//! ```
+#[cfg(any(feature = "with-bb8-0_8", feature = "with-deadpool-0_9"))]
+use crate::pool::PoolManager;
+use crate::*;
+use actix_web::dev::Payload;
+#[cfg(any(feature = "with-bb8-0_8", feature = "with-deadpool-0_9"))]
+use actix_web::dev::{Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::error::ErrorInternalServerError;
+#[cfg(any(feature = "with-bb8-0_8", feature = "with-deadpool-0_9"))]
+use actix_web::HttpMessage;
+use actix_web::{web, FromRequest, HttpRequest, ResponseError};
+use futures_util::future::{ready, Ready};
+#[cfg(any(feature = "with-bb8-0_8", feature = "with-deadpool-0_9"))]
+use futures_util::future::{FutureExt, LocalBoxFuture};
+#[cfg(any(feature = "with-bb8-0_8", feature = "with-deadpool-0_9"))]
+use std::any::Any;
+#[cfg(any(feature = "with-bb8-0_8", feature = "with-deadpool-0_9"))]
+use std::ops::Deref;
+#[cfg(any(feature = "with-bb8-0_8", feature = "with-deadpool-0_9"))]
+use std::task::{Context, Poll};
+
+impl ResponseError for Error {}
+
+impl FromRequest for Connection {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(request: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(match request.app_data::<web::Data<Connection>>() {
+            Some(connection) => Ok(Connection::clone(connection)),
+            None => Err(ErrorInternalServerError(
+                "no sprattus::Connection is registered as app data",
+            )),
+        })
+    }
+}
+
+/// A connection within a transaction, extracted from a request.
+///
+/// The connection is taken from a pool registered as app data, see the
+/// [module documentation](index.html).
+///
+/// The transaction is committed or rolled back by the
+/// [`TransactionMiddleware`](struct.TransactionMiddleware.html) once the handler responded.
+/// Without the middleware, the transaction is rolled back when the request is dropped.
+///
+/// Example:
+/// ```no_run
+/// use actix_web::{web, App, HttpResponse, HttpServer};
+/// use sprattus::actix::{TransactionMiddleware, Tx};
+/// use sprattus::pool::PoolManager;
+/// use sprattus::*;
+///
+/// #[derive(FromSql, ToSql)]
+/// #[sprattus(table = "products")]
+/// struct Product {
+///     #[sprattus(primary_key)]
+///     prod_id: i32,
+///     title: String,
+/// }
+///
+/// async fn rename(tx: Tx, path: web::Path<(i32, String)>) -> Result<HttpResponse, Error> {
+///     let (prod_id, title) = path.into_inner();
+///     let mut product = tx.find::<Product>(&prod_id).await?;
+///     product.title = title;
+///     tx.update(&product).await?;
+///     Ok(HttpResponse::NoContent().finish())
+/// }
+///
+/// #[actix_web::main]
+/// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     let manager = PoolManager::new("postgresql://localhost?user=tg")?;
+///     let pool = bb8::Pool::builder().max_size(8).build(manager).await?;
+///     HttpServer::new(move || {
+///         App::new()
+///             .app_data(web::Data::new(pool.clone()))
+///             .wrap(TransactionMiddleware)
+///             .route("/products/{id}/{title}", web::put().to(rename))
+///     })
+///     .bind("127.0.0.1:8080")?
+///     .run()
+///     .await?;
+///     Ok(())
+/// }
+/// ```
+#[cfg(any(feature = "with-bb8-0_8", feature = "with-deadpool-0_9"))]
+pub struct Tx {
+    connection: Connection,
+}
+
+// The transaction of a `Tx`, stored in the extensions of the request until it is finished by the
+// middleware, together with the pooled connection it runs on, which returns to the pool after the
+// transaction.
+#[cfg(any(feature = "with-bb8-0_8", feature = "with-deadpool-0_9"))]
+struct PendingTransaction {
+    transaction: Transaction,
+    _pooled: Box<dyn Any>,
+}
+
+/// Takes the connection for a transaction from a pool registered as app data.
+#[cfg(any(feature = "with-bb8-0_8", feature = "with-deadpool-0_9"))]
+async fn checkout(request: &HttpRequest) -> Result<(Connection, Box<dyn Any>), actix_web::Error> {
+    #[cfg(feature = "with-bb8-0_8")]
+    if let Some(pool) = request.app_data::<web::Data<bb8::Pool<PoolManager>>>() {
+        let pooled = pool.get_owned().await.map_err(ErrorInternalServerError)?;
+        return Ok((Connection::clone(&pooled), Box::new(pooled)));
+    }
+    #[cfg(feature = "with-deadpool-0_9")]
+    if let Some(pool) = request.app_data::<web::Data<deadpool::managed::Pool<PoolManager>>>() {
+        let pooled = pool.get().await.map_err(ErrorInternalServerError)?;
+        return Ok((Connection::clone(&pooled), Box::new(pooled)));
+    }
+    Err(ErrorInternalServerError(
+        "a sprattus::actix::Tx needs a pool of connections registered as app data",
+    ))
+}
+
+#[cfg(any(feature = "with-bb8-0_8", feature = "with-deadpool-0_9"))]
+impl FromRequest for Tx {
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(request: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let request = request.clone();
+        async move {
+            let (connection, pooled) = checkout(&request).await?;
+            let transaction = connection.transaction().await?;
+            let connection = Connection::clone(&transaction);
+            request.extensions_mut().insert(PendingTransaction {
+                transaction,
+                _pooled: pooled,
+            });
+            Ok(Tx { connection })
+        }
+        .boxed_local()
+    }
+}
+
+#[cfg(any(feature = "with-bb8-0_8", feature = "with-deadpool-0_9"))]
+impl Deref for Tx {
+    type Target = Connection;
+
+    fn deref(&self) -> &Connection {
+        &self.connection
+    }
+}
+
+/// Middleware finishing the transactions of [`Tx`](struct.Tx.html) extractors.
+///
+/// The transaction is committed when the response has a success or redirection status and rolled
+/// back otherwise. When committing fails, the response is replaced by the error.
+#[derive(Clone, Copy, Debug, Default)]
+#[cfg(any(feature = "with-bb8-0_8", feature = "with-deadpool-0_9"))]
+pub struct TransactionMiddleware;
+
+#[cfg(any(feature = "with-bb8-0_8", feature = "with-deadpool-0_9"))]
+impl<S, B> Transform<S, ServiceRequest> for TransactionMiddleware
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = actix_web::Error;
+    type Transform = TransactionService<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(TransactionService { service }))
+    }
+}
+
+/// The service created by the [`TransactionMiddleware`](struct.TransactionMiddleware.html).
+#[cfg(any(feature = "with-bb8-0_8", feature = "with-deadpool-0_9"))]
+pub struct TransactionService<S> {
+    service: S,
+}
+
+#[cfg(any(feature = "with-bb8-0_8", feature = "with-deadpool-0_9"))]
+impl<S, B> Service<ServiceRequest> for TransactionService<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&self, context: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(context)
+    }
+
+    fn call(&self, request: ServiceRequest) -> Self::Future {
+        let response = self.service.call(request);
+        async move {
+            // When the service fails, the transaction is rolled back as the request is dropped.
+            let response = response.await?;
+            let pending = response
+                .request()
+                .extensions_mut()
+                .remove::<PendingTransaction>();
+            if let Some(PendingTransaction { transaction, .. }) = pending {
+                let status = response.status();
+                if status.is_success() || status.is_redirection() {
+                    transaction.commit().await?;
+                } else {
+                    transaction.rollback().await?;
+                }
+            }
+            Ok(response)
+        }
+        .boxed_local()
+    }
+}
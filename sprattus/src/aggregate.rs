@@ -0,0 +1,206 @@
+use crate::connection::Access;
+use crate::select::{column_name, where_clause, Argument};
+use crate::*;
+use std::marker::PhantomData;
+
+/// A query computing aggregates over the rows of a table or view, optionally per group, created
+/// by [`Connection::aggregate`](struct.Connection.html#method.aggregate).
+///
+/// The columns of the groups are selected with their own names, followed by the aggregates in
+/// the order in which they are added. An aggregate is named after its function and field, like
+/// `count` or `sum_price`, unless it is renamed with [`alias`](#method.alias). The rows are read
+/// into a struct implementing [`FromSql`](trait.FromSql.html) with fields of these names.
+///
+/// The types of the aggregates follow Postgres, e.g. `count` returns a `BIGINT`, and `sum`
+/// returns a `BIGINT` for an `INT` column and a `NUMERIC` for a `BIGINT` column.
+///
+/// Example:
+/// ```no_run
+/// use sprattus::*;
+///
+/// #[derive(FromSql, ToSql, Debug)]
+/// #[sprattus(table = "products")]
+/// struct Product {
+///     #[sprattus(primary_key)]
+///     prod_id: i32,
+///     category: i32,
+///     price: f64,
+/// }
+///
+/// #[derive(FromSql, Debug)]
+/// struct CategoryStats {
+///     category: i32,
+///     count: i64,
+///     sum_price: f64,
+/// }
+///
+/// #[tokio::main]
+/// async fn main() -> Result<(), Error> {
+///     let conn = Connection::new("postgresql://localhost?user=tg").await?;
+///     // Executes `SELECT "category",count(*) AS "count",sum("price") AS "sum_price"
+///     // FROM "products" WHERE "price" > $1 GROUP BY "category"`.
+///     let stats = conn
+///         .aggregate::<Product>()
+///         .filter(r#""price" > $1"#, &[&10.0])
+///         .group_by("category")
+///         .select_count()
+///         .select_sum("price")
+///         .fetch::<CategoryStats>()
+///         .await?;
+///     dbg!(stats);
+///     Ok(())
+/// }
+/// ```
+pub struct Aggregate<'a, T> {
+    connection: &'a Connection,
+    conditions: Vec<String>,
+    args: Vec<Argument<'a>>,
+    groups: Vec<&'static str>,
+    aggregates: Vec<(String, String)>,
+    table_type: PhantomData<T>,
+}
+
+impl<'a, T> Aggregate<'a, T>
+where
+    T: Table,
+{
+    pub(crate) fn new(connection: &'a Connection) -> Self {
+        Self {
+            connection,
+            conditions: Vec::new(),
+            args: Vec::new(),
+            groups: Vec::new(),
+            aggregates: Vec::new(),
+            table_type: PhantomData,
+        }
+    }
+
+    /// Adds a condition to the `WHERE` clause of the query, see
+    /// [`Select::filter`](struct.Select.html#method.filter).
+    pub fn filter(mut self, condition: &str, args: &[&'a (dyn ToSqlItem + Sync)]) -> Self {
+        self.conditions.push(condition.to_string());
+        self.args
+            .extend(args.iter().map(|arg| Argument::Borrowed(*arg)));
+        self
+    }
+
+    /// Groups the rows by the column of a field, which is selected as well.
+    ///
+    /// # Panics
+    ///
+    /// Panics when the struct has no field with the given name.
+    pub fn group_by(mut self, field: &str) -> Self {
+        self.groups.push(column_name::<T>(field));
+        self
+    }
+
+    /// Selects the number of rows, named `count`.
+    pub fn select_count(mut self) -> Self {
+        self.aggregates
+            .push((String::from("count(*)"), String::from("count")));
+        self
+    }
+
+    /// Selects the sum of the column of a field, named `sum_` followed by the name of the field.
+    ///
+    /// # Panics
+    ///
+    /// Panics when the struct has no field with the given name.
+    pub fn select_sum(self, field: &str) -> Self {
+        self.select_function("sum", field)
+    }
+
+    /// Selects the average of the column of a field, named `avg_` followed by the name of the
+    /// field.
+    ///
+    /// # Panics
+    ///
+    /// Panics when the struct has no field with the given name.
+    pub fn select_avg(self, field: &str) -> Self {
+        self.select_function("avg", field)
+    }
+
+    /// Selects the smallest value of the column of a field, named `min_` followed by the name of
+    /// the field.
+    ///
+    /// # Panics
+    ///
+    /// Panics when the struct has no field with the given name.
+    pub fn select_min(self, field: &str) -> Self {
+        self.select_function("min", field)
+    }
+
+    /// Selects the largest value of the column of a field, named `max_` followed by the name of
+    /// the field.
+    ///
+    /// # Panics
+    ///
+    /// Panics when the struct has no field with the given name.
+    pub fn select_max(self, field: &str) -> Self {
+        self.select_function("max", field)
+    }
+
+    /// Renames the aggregate that was selected last.
+    ///
+    /// # Panics
+    ///
+    /// Panics when no aggregate is selected yet.
+    pub fn alias(mut self, name: &str) -> Self {
+        match self.aggregates.last_mut() {
+            Some((_, alias)) => *alias = name.to_string(),
+            None => panic!("alias is called before an aggregate is selected"),
+        }
+        self
+    }
+
+    fn select_function(mut self, function: &str, field: &str) -> Self {
+        let expression = format!("{}({})", function, column_name::<T>(field));
+        self.aggregates
+            .push((expression, format!("{}_{}", function, field)));
+        self
+    }
+
+    /// Returns the SQL of the query.
+    pub fn sql(&self) -> String {
+        let mut columns: Vec<String> = self.groups.iter().map(|group| group.to_string()).collect();
+        columns.extend(
+            self.aggregates
+                .iter()
+                .map(|(expression, alias)| format!("{} AS {}", expression, ident(alias))),
+        );
+        let mut sql = format!(
+            "SELECT {columns} FROM {table_name}",
+            columns = columns.join(","),
+            table_name = self.connection.table_name::<T>()
+        );
+        sql.push_str(where_clause::<T>(&self.conditions).as_str());
+        if !self.groups.is_empty() {
+            sql.push_str(" GROUP BY ");
+            sql.push_str(self.groups.join(",").as_str());
+        }
+        sql
+    }
+
+    /// Returns the SQL of the query, together with its arguments.
+    pub(crate) fn into_parts(self) -> (String, Vec<Argument<'a>>) {
+        (self.sql(), self.args)
+    }
+
+    /// Executes the query, returning a row for every group, or a single row when the rows are
+    /// not grouped.
+    pub async fn fetch<R>(self) -> Result<Vec<R>, Error>
+    where
+        R: FromSql,
+    {
+        let args: Vec<&(dyn ToSqlItem + Sync)> =
+            self.args.iter().map(Argument::as_sql_item).collect();
+        let sql = self.sql();
+        self.connection
+            .query_multiple_with(sql.as_str(), args.as_slice(), Access::Read)
+            .await
+            .map_err(
+                self.connection
+                    .in_statement::<T>(StatementKind::Select, &sql),
+            )
+    }
+}
@@ -0,0 +1,421 @@
+//! A synchronous wrapper around the async [`Connection`](../struct.Connection.html).
+//!
+//! The blocking client owns a single threaded tokio runtime and drives every call to completion
+//! on it, so it can be used from CLI tools and scripts that do not run an async runtime themselves.
+//!
+//! Example:
+//! ```no_run
+//! use sprattus::*;
+//!
+//! #[derive(FromSql, ToSql, Debug)]
+//! #[sprattus(table = "fruits")]
+//! struct Fruit {
+//!     #[sprattus(primary_key)]
+//!     id: i32,
+//!     name: String
+//! }
+//!
+//! fn main() -> Result<(), Error> {
+//!     let conn = blocking::Connection::new("postgresql://localhost?user=tg")?;
+//!     let fruit = conn.create(&Fruit { id: 0, name: String::from("apple") })?;
+//!     dbg!(fruit);
+//!     Ok(())
+//! }
+//! ```
+//!
+//! # Note
+//!
+//! The methods of this client must not be called from within an async runtime, because they block
+//! the current thread until the query is completed.
+use crate::*;
+use tokio::runtime::{Builder, Runtime};
+
+/// Blocking client for Postgres database manipulation.
+pub struct Connection {
+    runtime: Runtime,
+    connection: crate::Connection,
+}
+
+impl Connection {
+    ///
+    /// Creates a new connection to the database.
+    ///
+    /// Example:
+    /// ```no_run
+    /// use sprattus::*;
+    ///
+    ///# fn main() -> Result<(), Error> {
+    /// let conn = blocking::Connection::new("postgresql://localhost?user=tg")?;
+    ///# return Ok(())
+    ///# }
+    /// ```
+    pub fn new(connection_string: &str) -> Result<Self, Error> {
+        let runtime = Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .expect("failed to build the tokio runtime of the blocking connection");
+        let connection = runtime.block_on(crate::Connection::new(connection_string))?;
+        Ok(Self {
+            runtime,
+            connection,
+        })
+    }
+
+    /// See [`Connection::with_returning`](../struct.Connection.html#method.with_returning).
+    pub fn with_returning(mut self, returning: Returning) -> Self {
+        self.connection = self.connection.with_returning(returning);
+        self
+    }
+
+    /// See [`Connection::with_table`](../struct.Connection.html#method.with_table).
+    pub fn with_table<T: Table>(mut self, table_name: &str) -> Result<Self, Error> {
+        self.connection = self.connection.with_table::<T>(table_name)?;
+        Ok(self)
+    }
+
+    /// See [`Connection::with_statement_cache`](../struct.Connection.html#method.with_statement_cache).
+    pub fn with_statement_cache(mut self, capacity: usize) -> Self {
+        self.connection = self.connection.with_statement_cache(capacity);
+        self
+    }
+
+    /// See [`Connection::with_retry`](../struct.Connection.html#method.with_retry).
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.connection = self.connection.with_retry(retry);
+        self
+    }
+
+    /// See [`Connection::enable_sql_log`](../struct.Connection.html#method.enable_sql_log).
+    pub fn enable_sql_log(mut self, level: log::Level, parameters: LogParameters) -> Self {
+        self.connection = self.connection.enable_sql_log(level, parameters);
+        self
+    }
+
+    /// See [`Connection::set_write_hook`](../struct.Connection.html#method.set_write_hook).
+    pub fn set_write_hook<F>(&mut self, hook: F)
+    where
+        F: Fn(WriteEvent) + Send + Sync + 'static,
+    {
+        self.connection.set_write_hook(hook);
+    }
+
+    /// Blocking version of [`Connection::set_search_path`](../struct.Connection.html#method.set_search_path).
+    pub fn set_search_path(&self, schemas: &[&str]) -> Result<(), Error> {
+        let connection = &self.connection;
+        self.runtime.block_on(connection.set_search_path(schemas))
+    }
+
+    /// Blocking version of [`Connection::ping`](../struct.Connection.html#method.ping).
+    pub fn ping(&self) -> Result<(), Error> {
+        let connection = &self.connection;
+        self.runtime.block_on(connection.ping())
+    }
+
+    /// Blocking version of [`Connection::is_healthy`](../struct.Connection.html#method.is_healthy).
+    pub fn is_healthy(&self) -> bool {
+        let connection = &self.connection;
+        self.runtime.block_on(connection.is_healthy())
+    }
+
+    /// Blocking version of [`Connection::close`](../struct.Connection.html#method.close).
+    pub fn close(&self) {
+        let connection = &self.connection;
+        self.runtime.block_on(connection.close())
+    }
+
+    /// Blocking version of [`Connection::execute`](../struct.Connection.html#method.execute).
+    pub fn execute(
+        &mut self,
+        sql: &str,
+        args: &[&(dyn ToSqlItem + Sync)],
+    ) -> Result<ExecuteResult, Error> {
+        let connection = &self.connection;
+        self.runtime.block_on(connection.execute(sql, args))
+    }
+
+    /// Blocking version of [`Connection::batch_execute`](../struct.Connection.html#method.batch_execute).
+    pub fn batch_execute(&self, sql: &str) -> Result<(), Error> {
+        let connection = &self.connection;
+        self.runtime.block_on(connection.batch_execute(sql))
+    }
+
+    /// Blocking version of [`Connection::query_multiple`](../struct.Connection.html#method.query_multiple).
+    pub fn query_multiple<T>(
+        &mut self,
+        sql: &str,
+        args: &[&(dyn ToSqlItem + Sync)],
+    ) -> Result<Vec<T>, Error>
+    where
+        T: FromSql,
+    {
+        let connection = &self.connection;
+        self.runtime.block_on(connection.query_multiple(sql, args))
+    }
+
+    /// Blocking version of [`Connection::query_dynamic`](../struct.Connection.html#method.query_dynamic).
+    pub fn query_dynamic(
+        &mut self,
+        sql: &str,
+        args: &[&(dyn ToSqlItem + Sync)],
+    ) -> Result<Vec<DynamicRow>, Error> {
+        let connection = &self.connection;
+        self.runtime.block_on(connection.query_dynamic(sql, args))
+    }
+
+    /// Blocking version of [`Connection::query_json`](../struct.Connection.html#method.query_json).
+    #[cfg(feature = "with-serde_json-1")]
+    pub fn query_json(
+        &mut self,
+        sql: &str,
+        args: &[&(dyn ToSqlItem + Sync)],
+    ) -> Result<Vec<serde_json::Value>, Error> {
+        let connection = &self.connection;
+        self.runtime.block_on(connection.query_json(sql, args))
+    }
+
+    /// Blocking version of [`Connection::query_typed`](../struct.Connection.html#method.query_typed).
+    pub fn query_typed<T>(
+        &mut self,
+        sql: &str,
+        args: &[(&(dyn ToSqlItem + Sync), types::Type)],
+    ) -> Result<Vec<T>, Error>
+    where
+        T: FromSql,
+    {
+        let connection = &self.connection;
+        self.runtime.block_on(connection.query_typed(sql, args))
+    }
+
+    /// Blocking version of [`Connection::query`](../struct.Connection.html#method.query).
+    pub fn query<T>(&self, sql: &str, args: &[&(dyn ToSqlItem + Sync)]) -> Result<T, Error>
+    where
+        T: FromSql,
+    {
+        let connection = &self.connection;
+        self.runtime.block_on(connection.query(sql, args))
+    }
+
+    /// Blocking version of [`Connection::fetch_all`](../struct.Connection.html#method.fetch_all).
+    pub fn fetch_all<T>(&self) -> Result<Vec<T>, Error>
+    where
+        T: Table + FromSql,
+    {
+        let connection = &self.connection;
+        self.runtime.block_on(connection.fetch_all())
+    }
+
+    /// Blocking version of [`Connection::fetch_where`](../struct.Connection.html#method.fetch_where).
+    pub fn fetch_where<T>(
+        &mut self,
+        condition: &str,
+        args: &[&(dyn ToSqlItem + Sync)],
+    ) -> Result<Vec<T>, Error>
+    where
+        T: Table + FromSql,
+    {
+        let connection = &self.connection;
+        self.runtime
+            .block_on(connection.fetch_where(condition, args))
+    }
+
+    /// Blocking version of [`Connection::upsert_multiple_with`](../struct.Connection.html#method.upsert_multiple_with).
+    pub fn upsert_multiple_with<T>(
+        &mut self,
+        items: &[T],
+        on_conflict: OnConflict<'_>,
+    ) -> Result<Vec<Upserted<T>>, Error>
+    where
+        T: Sized + ToSql + FromSql,
+    {
+        let connection = &self.connection;
+        self.runtime
+            .block_on(connection.upsert_multiple_with(items, on_conflict))
+    }
+
+    /// Blocking version of [`Connection::update`](../struct.Connection.html#method.update).
+    pub fn update<T>(&self, item: &T) -> Result<T, Error>
+    where
+        T: FromSql + ToSql,
+    {
+        let connection = &self.connection;
+        self.runtime.block_on(connection.update(item))
+    }
+
+    /// Blocking version of [`Connection::try_update`](../struct.Connection.html#method.try_update).
+    pub fn try_update<T>(&self, item: &T) -> Result<Option<T>, Error>
+    where
+        T: FromSql + ToSql,
+    {
+        let connection = &self.connection;
+        self.runtime.block_on(connection.try_update(item))
+    }
+
+    /// Blocking version of [`Connection::update_where`](../struct.Connection.html#method.update_where).
+    pub fn update_where<T>(
+        &mut self,
+        item: &T,
+        condition: &str,
+        args: &[&(dyn ToSqlItem + Sync)],
+    ) -> Result<Option<T>, Error>
+    where
+        T: FromSql + ToSql,
+    {
+        let connection = &self.connection;
+        self.runtime
+            .block_on(connection.update_where(item, condition, args))
+    }
+
+    /// Blocking version of [`Connection::update_count`](../struct.Connection.html#method.update_count).
+    pub fn update_count<T>(&self, item: &T) -> Result<ExecuteResult, Error>
+    where
+        T: FromSql + ToSql,
+    {
+        let connection = &self.connection;
+        self.runtime.block_on(connection.update_count(item))
+    }
+
+    /// Blocking version of [`Connection::update_multiple`](../struct.Connection.html#method.update_multiple).
+    pub fn update_multiple<T>(&self, items: &[T]) -> Result<Vec<T>, Error>
+    where
+        T: Sized + ToSql + FromSql,
+    {
+        let connection = &self.connection;
+        self.runtime.block_on(connection.update_multiple(items))
+    }
+
+    /// Blocking version of [`Connection::increment`](../struct.Connection.html#method.increment).
+    pub fn increment<T, V>(
+        &mut self,
+        primary_key: &T::PK,
+        field: &str,
+        amount: &V,
+    ) -> Result<T, Error>
+    where
+        T: ToSql + FromSql,
+        V: ToSqlItem + Sync,
+    {
+        let connection = &self.connection;
+        self.runtime
+            .block_on(connection.increment(primary_key, field, amount))
+    }
+
+    /// Blocking version of [`Connection::create`](../struct.Connection.html#method.create).
+    pub fn create<T>(&self, item: &T) -> Result<T, Error>
+    where
+        T: Sized + ToSql + FromSql,
+    {
+        let connection = &self.connection;
+        self.runtime.block_on(connection.create(item))
+    }
+
+    /// Blocking version of [`Connection::insert`](../struct.Connection.html#method.insert).
+    pub fn insert<T>(&self, item: &T) -> Result<ExecuteResult, Error>
+    where
+        T: Insertable + Lifecycle,
+    {
+        let connection = &self.connection;
+        self.runtime.block_on(connection.insert(item))
+    }
+
+    /// Blocking version of [`Connection::save`](../struct.Connection.html#method.save).
+    pub fn save<T>(&self, item: &T) -> Result<T, Error>
+    where
+        T: ToSql + FromSql,
+    {
+        let connection = &self.connection;
+        self.runtime.block_on(connection.save(item))
+    }
+
+    /// Blocking version of [`Connection::create_multiple`](../struct.Connection.html#method.create_multiple).
+    pub fn create_multiple<T>(&self, items: &[T]) -> Result<Vec<T>, Error>
+    where
+        T: Sized + ToSql + FromSql,
+    {
+        let connection = &self.connection;
+        self.runtime.block_on(connection.create_multiple(items))
+    }
+
+    /// Blocking version of [`Connection::create_multiple_ids`](../struct.Connection.html#method.create_multiple_ids).
+    pub fn create_multiple_ids<T>(&self, items: &[T]) -> Result<Vec<T::PK>, Error>
+    where
+        T: ToSql,
+        T::PK: for<'a> FromSqlItem<'a>,
+    {
+        let connection = &self.connection;
+        self.runtime.block_on(connection.create_multiple_ids(items))
+    }
+
+    /// Blocking version of [`Connection::create_multiple_lenient`](../struct.Connection.html#method.create_multiple_lenient).
+    pub fn create_multiple_lenient<T>(
+        &mut self,
+        items: &[T],
+    ) -> Result<Vec<Result<T, Error>>, Error>
+    where
+        T: Sized + ToSql + FromSql,
+    {
+        let connection = &self.connection;
+        self.runtime
+            .block_on(connection.create_multiple_lenient(items))
+    }
+
+    /// Blocking version of [`Connection::delete`](../struct.Connection.html#method.delete).
+    pub fn delete<T>(&self, item: &T) -> Result<T, Error>
+    where
+        T: FromSql + ToSql,
+    {
+        let connection = &self.connection;
+        self.runtime.block_on(connection.delete(item))
+    }
+
+    /// Blocking version of [`Connection::try_delete`](../struct.Connection.html#method.try_delete).
+    pub fn try_delete<T>(&self, item: &T) -> Result<Option<T>, Error>
+    where
+        T: FromSql + ToSql,
+    {
+        let connection = &self.connection;
+        self.runtime.block_on(connection.try_delete(item))
+    }
+
+    /// Blocking version of [`Connection::delete_where`](../struct.Connection.html#method.delete_where).
+    pub fn delete_where<T>(
+        &mut self,
+        item: &T,
+        condition: &str,
+        args: &[&(dyn ToSqlItem + Sync)],
+    ) -> Result<Option<T>, Error>
+    where
+        T: FromSql + ToSql,
+    {
+        let connection = &self.connection;
+        self.runtime
+            .block_on(connection.delete_where(item, condition, args))
+    }
+
+    /// Blocking version of [`Connection::delete_count`](../struct.Connection.html#method.delete_count).
+    pub fn delete_count<T>(&self, item: &T) -> Result<ExecuteResult, Error>
+    where
+        T: FromSql + ToSql,
+    {
+        let connection = &self.connection;
+        self.runtime.block_on(connection.delete_count(item))
+    }
+
+    /// Blocking version of [`Connection::delete_multiple`](../struct.Connection.html#method.delete_multiple).
+    pub fn delete_multiple<T>(&self, items: &[T]) -> Result<Vec<T>, Error>
+    where
+        T: FromSql + ToSql,
+    {
+        let connection = &self.connection;
+        self.runtime.block_on(connection.delete_multiple(items))
+    }
+
+    /// Blocking version of [`Connection::delete_multiple_count`](../struct.Connection.html#method.delete_multiple_count).
+    pub fn delete_multiple_count<T>(&self, items: &[T]) -> Result<ExecuteResult, Error>
+    where
+        T: FromSql + ToSql,
+    {
+        let connection = &self.connection;
+        self.runtime
+            .block_on(connection.delete_multiple_count(items))
+    }
+}
@@ -0,0 +1,384 @@
+use crate::observer::Observer;
+use crate::spawn::{default_spawner, Spawner};
+use crate::*;
+use futures_util::future::{BoxFuture, FutureExt};
+use std::env;
+use std::fmt;
+use std::fs;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio_postgres::config::ChannelBinding;
+use tokio_postgres::Config;
+
+type PasswordFn = Arc<dyn Fn() -> BoxFuture<'static, Result<String, Error>> + Send + Sync>;
+
+/// Configures and establishes a [`Connection`](struct.Connection.html).
+///
+/// Create it with [`Connection::builder`](struct.Connection.html#method.builder), or from a
+/// connection string with [`from_connection_string`](#method.from_connection_string).
+///
+/// Example:
+/// ```no_run
+/// use sprattus::*;
+///
+///# #[tokio::main]
+///# async fn main() -> Result<(), Error> {
+/// // Connects over the unix socket in /var/run/postgresql.
+/// let conn = Connection::builder()
+///     .socket_dir("/var/run/postgresql")
+///     .user("tg")
+///     .dbname("shop")
+///     .connect()
+///     .await?;
+///# return Ok(())
+///# }
+/// ```
+#[derive(Clone, Default)]
+pub struct ConnectionBuilder {
+    config: Config,
+    password_fn: Option<PasswordFn>,
+    spawner: Option<Spawner>,
+    observer: Option<Arc<dyn ConnectionObserver>>,
+    slow_query_threshold: Option<Duration>,
+    search_path: Option<Vec<String>>,
+}
+
+impl fmt::Debug for ConnectionBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ConnectionBuilder")
+            .field("config", &self.config)
+            .field("password_fn", &self.password_fn.as_ref().map(|_| "Fn"))
+            .field("spawner", &self.spawner.as_ref().map(|_| "Fn"))
+            .field(
+                "observer",
+                &self.observer.as_ref().map(|_| "ConnectionObserver"),
+            )
+            .field("slow_query_threshold", &self.slow_query_threshold)
+            .field("search_path", &self.search_path)
+            .finish()
+    }
+}
+
+impl ConnectionBuilder {
+    /// Creates a builder without any settings.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    ///
+    /// Creates a builder from a connection string, which is either a URL like
+    /// `postgresql://localhost/shop?user=tg` or a list of settings like
+    /// `host=/var/run/postgresql user=tg`. A host starting with a `/` is the directory of a unix
+    /// socket.
+    ///
+    /// A list of settings can refer to a service with `service=name`, of which the settings are
+    /// read from the service file. Settings in the connection string override the settings of the
+    /// service.
+    ///
+    pub fn from_connection_string(connection_string: &str) -> Result<Self, Error> {
+        let connection_string = resolve_service(connection_string)?;
+        Ok(Self {
+            config: Config::from_str(connection_string.as_str())?,
+            ..Self::default()
+        })
+    }
+
+    ///
+    /// Creates a builder with the settings of a service in the service file.
+    ///
+    /// The service is looked up in the file named by the `PGSERVICEFILE` environment variable,
+    /// or `~/.pg_service.conf`, followed by `pg_service.conf` in the directory named by the
+    /// `PGSYSCONFDIR` environment variable.
+    ///
+    /// Example:
+    /// ```no_run
+    /// use sprattus::*;
+    ///
+    ///# #[tokio::main]
+    ///# async fn main() -> Result<(), Error> {
+    /// let conn = ConnectionBuilder::service("shop")?.connect().await?;
+    ///# return Ok(())
+    ///# }
+    /// ```
+    pub fn service(name: &str) -> Result<Self, Error> {
+        Self::from_connection_string(format!("service={}", name).as_str())
+    }
+
+    /// Sets the host to connect to. A host starting with a `/` is the directory of a unix socket.
+    pub fn host(mut self, host: &str) -> Self {
+        self.config.host(host);
+        self
+    }
+
+    /// Sets the directory containing the unix socket of the server, like `/var/run/postgresql`.
+    #[cfg(unix)]
+    pub fn socket_dir(mut self, directory: impl AsRef<Path>) -> Self {
+        self.config.host_path(directory);
+        self
+    }
+
+    /// Sets the port to connect to, or the port in the name of the unix socket.
+    pub fn port(mut self, port: u16) -> Self {
+        self.config.port(port);
+        self
+    }
+
+    /// Sets the user to authenticate with.
+    pub fn user(mut self, user: &str) -> Self {
+        self.config.user(user);
+        self
+    }
+
+    /// Sets the password to authenticate with.
+    pub fn password(mut self, password: impl AsRef<[u8]>) -> Self {
+        self.config.password(password);
+        self
+    }
+
+    ///
+    /// Sets a function that provides the password every time a connection is established, for
+    /// short-lived credentials like AWS IAM authentication tokens or credentials issued by Vault.
+    /// The password of the function replaces the password set with [`password`](#method.password).
+    ///
+    /// A builder can be passed to
+    /// [`ConnectionManager::add_builder`](struct.ConnectionManager.html#method.add_builder), so a
+    /// fresh password is requested when the manager reconnects.
+    ///
+    /// Example:
+    /// ```no_run
+    /// use sprattus::*;
+    ///
+    /// async fn fetch_iam_token() -> Result<String, Error> {
+    ///     // Request a token from the identity provider.
+    ///     Ok(String::from("token"))
+    /// }
+    ///
+    ///# #[tokio::main]
+    ///# async fn main() -> Result<(), Error> {
+    /// let conn = Connection::builder()
+    ///     .host("shop.rds.amazonaws.com")
+    ///     .user("tg")
+    ///     .password_fn(fetch_iam_token)
+    ///     .connect()
+    ///     .await?;
+    ///# return Ok(())
+    ///# }
+    /// ```
+    pub fn password_fn<F, Fut>(mut self, password_fn: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<String, Error>> + Send + 'static,
+    {
+        self.password_fn = Some(Arc::new(move || password_fn().boxed()));
+        self
+    }
+
+    ///
+    /// Sets whether SCRAM channel binding is used when authenticating, which protects against
+    /// a server that forwards the authentication. The default is `ChannelBinding::Prefer`.
+    ///
+    /// Channel binding requires a TLS connection, so `ChannelBinding::Require` fails to connect
+    /// without one.
+    ///
+    pub fn channel_binding(mut self, channel_binding: ChannelBinding) -> Self {
+        self.config.channel_binding(channel_binding);
+        self
+    }
+
+    /// Sets the name of the database.
+    pub fn dbname(mut self, dbname: &str) -> Self {
+        self.config.dbname(dbname);
+        self
+    }
+
+    ///
+    /// Sets the function that spawns the background tasks of the connection, like the task
+    /// driving the connection to the database, so the connection runs on another async runtime
+    /// than tokio, like async-std or smol. Without it, the tasks are spawned with `tokio::spawn`,
+    /// which requires the `runtime-tokio` feature that is enabled by default.
+    ///
+    /// The socket to the database is opened by tokio-postgres with the network types of tokio,
+    /// which need the reactor of a tokio runtime. async-std provides one with its `tokio1`
+    /// feature, and smol with the async-compat crate.
+    ///
+    /// Example:
+    /// ```ignore
+    /// use sprattus::*;
+    ///
+    /// fn main() -> Result<(), Error> {
+    ///     smol::block_on(async_compat::Compat::new(async {
+    ///         let conn = Connection::builder()
+    ///             .host("localhost")
+    ///             .user("tg")
+    ///             .spawner(|task| smol::spawn(task).detach())
+    ///             .connect()
+    ///             .await?;
+    ///         conn.ping().await
+    ///     }))
+    /// }
+    /// ```
+    pub fn spawner<F>(mut self, spawner: F) -> Self
+    where
+        F: Fn(BoxFuture<'static, ()>) + Send + Sync + 'static,
+    {
+        self.spawner = Some(Arc::new(spawner));
+        self
+    }
+
+    /// Sets the observer that receives the events of the connection, see
+    /// [`ConnectionObserver`](trait.ConnectionObserver.html).
+    pub fn observer<O>(mut self, observer: O) -> Self
+    where
+        O: ConnectionObserver + 'static,
+    {
+        self.observer = Some(Arc::new(observer));
+        self
+    }
+
+    /// Reports the statements that take longer than the threshold to the
+    /// [`observer`](#method.observer).
+    pub fn slow_query_threshold(mut self, threshold: Duration) -> Self {
+        self.slow_query_threshold = Some(threshold);
+        self
+    }
+
+    ///
+    /// Sets the schemas in which tables without a schema are looked up, which is applied when
+    /// connecting, before the connection is returned. See
+    /// [`Connection::set_search_path`](struct.Connection.html#method.set_search_path).
+    ///
+    /// Example:
+    /// ```no_run
+    /// use sprattus::*;
+    ///
+    ///# #[tokio::main]
+    ///# async fn main() -> Result<(), Error> {
+    /// let conn = Connection::builder()
+    ///     .host("localhost")
+    ///     .user("tg")
+    ///     .search_path(&["tenant_a", "public"])
+    ///     .connect()
+    ///     .await?;
+    /// assert_eq!(
+    ///     conn.search_path(),
+    ///     Some(vec![String::from("tenant_a"), String::from("public")])
+    /// );
+    ///# return Ok(())
+    ///# }
+    /// ```
+    pub fn search_path(mut self, schemas: &[&str]) -> Self {
+        self.search_path = if schemas.is_empty() {
+            None
+        } else {
+            Some(schemas.iter().map(|schema| schema.to_string()).collect())
+        };
+        self
+    }
+
+    /// Connects to the database.
+    pub async fn connect(&self) -> Result<Connection, Error> {
+        let spawner = match &self.spawner {
+            Some(spawner) => spawner.clone(),
+            None => default_spawner()?,
+        };
+        let observer = self.observer.as_ref().map(|observer| Observer {
+            observer: observer.clone(),
+            slow_query_threshold: self.slow_query_threshold,
+        });
+        let connection = match &self.password_fn {
+            Some(password_fn) => {
+                let password = password_fn().await?;
+                let mut config = self.config.clone();
+                config.password(password);
+                Connection::connect(&config, spawner, observer).await?
+            }
+            None => Connection::connect(&self.config, spawner, observer).await?,
+        };
+        if let Some(search_path) = &self.search_path {
+            connection.apply_search_path(search_path.clone()).await?;
+        }
+        Ok(connection)
+    }
+}
+
+/// Replaces the `service=name` setting of a connection string with the settings of the service.
+fn resolve_service(connection_string: &str) -> Result<String, Error> {
+    let settings: Vec<&str> = connection_string.split_whitespace().collect();
+    let service = match settings
+        .iter()
+        .find(|setting| setting.starts_with("service="))
+    {
+        Some(setting) => &setting["service=".len()..],
+        None => return Ok(connection_string.to_string()),
+    };
+    let keys: Vec<&str> = settings
+        .iter()
+        .filter_map(|setting| setting.split('=').next())
+        .collect();
+    let mut resolved: Vec<String> = read_service(service)?
+        .into_iter()
+        .filter(|(key, _)| !keys.contains(&key.as_str()))
+        .map(|(key, value)| {
+            format!(
+                "{}='{}'",
+                key,
+                value.replace('\\', "\\\\").replace('\'', "\\'")
+            )
+        })
+        .collect();
+    resolved.extend(
+        settings
+            .iter()
+            .filter(|setting| !setting.starts_with("service="))
+            .map(|setting| setting.to_string()),
+    );
+    Ok(resolved.join(" "))
+}
+
+/// Reads the settings of a service from the first service file that defines it.
+fn read_service(service: &str) -> Result<Vec<(String, String)>, Error> {
+    let user_file = match env::var_os("PGSERVICEFILE") {
+        Some(file) => Some(PathBuf::from(file)),
+        None => env::var_os("HOME").map(|home| Path::new(&home).join(".pg_service.conf")),
+    };
+    let system_file =
+        env::var_os("PGSYSCONFDIR").map(|dir| Path::new(&dir).join("pg_service.conf"));
+    for file in user_file.iter().chain(system_file.iter()) {
+        if let Ok(contents) = fs::read_to_string(file) {
+            if let Some(settings) = parse_service(&contents, service) {
+                return Ok(settings);
+            }
+        }
+    }
+    Err(Error::Config(format!(
+        "the service `{}` is not defined in a service file",
+        service
+    )))
+}
+
+/// Returns the settings in the `[service]` section of a service file.
+fn parse_service(contents: &str, service: &str) -> Option<Vec<(String, String)>> {
+    let mut settings = None;
+    for line in contents.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if line.starts_with('[') && line.ends_with(']') {
+            if settings.is_some() {
+                break;
+            }
+            if line[1..line.len() - 1].trim() == service {
+                settings = Some(Vec::new());
+            }
+            continue;
+        }
+        if let (Some(settings), Some(separator)) = (settings.as_mut(), line.find('=')) {
+            let (key, value) = line.split_at(separator);
+            settings.push((key.trim().to_string(), value[1..].trim().to_string()));
+        }
+    }
+    settings
+}
@@ -0,0 +1,164 @@
+use crate::*;
+use std::error;
+use std::io;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Stops sending statements to a database that fails or responds slowly, so they don't pile up
+/// while the database recovers. Enabled with
+/// [`Connection::with_circuit_breaker`](struct.Connection.html#method.with_circuit_breaker).
+///
+/// The circuit starts closed, sending every statement. After the given number of consecutive
+/// failed statements it opens, and statements fail immediately with
+/// [`Error::CircuitOpen`](enum.Error.html#variant.CircuitOpen) during the cooldown. After the
+/// cooldown, the circuit is half open: a single statement is sent to try the database, which
+/// closes the circuit when it succeeds and opens it again when it fails.
+///
+/// A statement fails when the database cannot be reached or does not respond in time, or
+/// reports that it is out of resources or shutting down. Errors caused by the statement itself,
+/// like a violated constraint, show that the database works and don't count. With a
+/// [latency threshold](#method.latency_threshold), a statement that takes longer than the
+/// threshold counts as a failure as well.
+///
+/// Example:
+/// ```no_run
+/// use sprattus::*;
+/// use std::time::Duration;
+///
+///# #[tokio::main]
+///# async fn main() -> Result<(), Error> {
+/// let breaker = CircuitBreaker::new(5, Duration::from_secs(30))
+///     .latency_threshold(Duration::from_secs(2));
+/// let conn = Connection::new("postgresql://localhost?user=tg")
+///     .await?
+///     .with_circuit_breaker(breaker);
+/// match conn.ping().await {
+///     Err(Error::CircuitOpen) => eprintln!("the database is not called for now"),
+///     result => result?,
+/// }
+///# return Ok(())
+///# }
+/// ```
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    failure_threshold: u32,
+    cooldown: Duration,
+    latency_threshold: Option<Duration>,
+    state: Mutex<State>,
+}
+
+/// The state of a [`CircuitBreaker`](struct.CircuitBreaker.html).
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CircuitState {
+    /// Statements are sent to the database.
+    Closed,
+    /// Statements fail immediately, until the cooldown is over.
+    Open,
+    /// A single statement is sent to the database, to find out whether it recovered.
+    HalfOpen,
+}
+
+#[derive(Debug)]
+enum State {
+    Closed { failures: u32 },
+    Open { until: Instant },
+    // The time at which the statement trying the database was sent.
+    HalfOpen { since: Instant },
+}
+
+impl CircuitBreaker {
+    /// Creates a circuit breaker that opens after the given number of consecutive failed
+    /// statements, for the duration of the cooldown.
+    pub fn new(failure_threshold: u32, cooldown: Duration) -> Self {
+        Self {
+            failure_threshold: failure_threshold.max(1),
+            cooldown,
+            latency_threshold: None,
+            state: Mutex::new(State::Closed { failures: 0 }),
+        }
+    }
+
+    /// Counts statements that take longer than the threshold as failures.
+    pub fn latency_threshold(mut self, threshold: Duration) -> Self {
+        self.latency_threshold = Some(threshold);
+        self
+    }
+
+    /// Returns the current state of the circuit.
+    pub fn state(&self) -> CircuitState {
+        match *self.state.lock().unwrap() {
+            State::Closed { .. } => CircuitState::Closed,
+            State::Open { until } if until > Instant::now() => CircuitState::Open,
+            State::Open { .. } | State::HalfOpen { .. } => CircuitState::HalfOpen,
+        }
+    }
+
+    ///
+    /// Returns whether a statement may be sent, or `Error::CircuitOpen` when it must fail.
+    ///
+    /// When the statement trying the database does not finish, because its future is dropped, no
+    /// outcome is recorded. Another statement is allowed to try after the cooldown then.
+    ///
+    pub(crate) fn check(&self) -> Result<(), Error> {
+        let mut state = self.state.lock().unwrap();
+        let now = Instant::now();
+        match *state {
+            State::Closed { .. } => Ok(()),
+            State::Open { until } if until > now => Err(Error::CircuitOpen),
+            State::HalfOpen { since } if now.duration_since(since) < self.cooldown => {
+                Err(Error::CircuitOpen)
+            }
+            State::Open { .. } | State::HalfOpen { .. } => {
+                *state = State::HalfOpen { since: now };
+                Ok(())
+            }
+        }
+    }
+
+    /// Records the outcome of a statement.
+    pub(crate) fn record(&self, latency: Duration, error: Option<&Error>) {
+        let slow = self
+            .latency_threshold
+            .is_some_and(|threshold| latency > threshold);
+        let failed = slow || error.is_some_and(is_failure);
+        let mut state = self.state.lock().unwrap();
+        *state = match (&*state, failed) {
+            (_, false) => State::Closed { failures: 0 },
+            (State::Closed { failures }, true) if failures + 1 < self.failure_threshold => {
+                State::Closed {
+                    failures: failures + 1,
+                }
+            }
+            (_, true) => {
+                log::warn!(
+                    "the circuit breaker opens for {:?} after a failed statement",
+                    self.cooldown
+                );
+                State::Open {
+                    until: Instant::now() + self.cooldown,
+                }
+            }
+        };
+    }
+}
+
+/// Returns whether the error shows that the database is unavailable, rather than that the
+/// statement is wrong.
+pub(crate) fn is_failure(error: &Error) -> bool {
+    match error {
+        Error::Timeout => true,
+        // Class 08 covers connection exceptions, 53 insufficient resources and 57 operator
+        // intervention, like a shutdown. Errors without a code fail in the client, which only
+        // counts when the database could not be reached.
+        Error::Postgres(error) => match error.code() {
+            Some(code) => ["08", "53", "57"]
+                .iter()
+                .any(|class| code.code().starts_with(class)),
+            None => {
+                error.is_closed()
+                    || error::Error::source(error).is_some_and(|source| source.is::<io::Error>())
+            }
+        },
+        _ => false,
+    }
+}
@@ -1,24 +1,99 @@
+use crate::error::{statement_error, unique_violation};
+use crate::history::{generate_history_statement, history_clause};
+use crate::ident::{ident, table_ident, validate_table_name};
+#[cfg(feature = "with-serde_json-1")]
+use crate::listen::decode_payload;
+use crate::listen::{dispatch_notification, Listener, Listeners};
+use crate::observer::Observer;
+use crate::select::column_name;
+use crate::spawn::Spawner;
+use crate::sql_log::SqlLog;
+use crate::statement_cache::{is_stale_statement, StatementCache};
+use crate::tenant::{tenant_condition, tenant_filter, TENANT_SETTING};
+use crate::traits::from_rows;
+use crate::transaction::{ClientGuard, Transactions};
+use crate::upsert::{generate_conflict_clause, INSERTED_COLUMN, UPSERT_ALIAS};
+use crate::write_hook::{self, WriteHook};
 use crate::*;
-use futures_util::future::FutureExt;
-use futures_util::future::TryFutureExt;
+use futures_util::future::{self, BoxFuture, FutureExt, TryFutureExt};
+use futures_util::stream::{self, Stream, StreamExt};
 use std::collections::HashMap;
-use std::sync::Arc;
+use std::future::Future;
+use std::hash::Hash;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use strfmt::strfmt;
-use tokio;
-use tokio_postgres::*;
+use tokio::sync::{mpsc, oneshot};
+use tokio_postgres::types::Type;
+use tokio_postgres::{AsyncMessage, Client, Config, NoTls, Notification, Statement};
+
+const PING_STATEMENT: &str = "SELECT 1";
+/// The column with the primary key of a row, selected by `find_ordered`.
+const KEY_COLUMN: &str = "sprattus_key";
+#[cfg(feature = "runtime-tokio")]
+const PING_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Whether a statement only reads rows, so the retry policy may send it again.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum Access {
+    /// The statement is generated to read rows, like the statement of `find`.
+    Read,
+    /// The statement may write, like every statement written by hand.
+    Write,
+}
 
 /// Client for Postgres database manipulation.
 ///
 ///
 #[derive(Clone)]
 pub struct Connection {
-    client: Arc<Client>,
+    pub(crate) client: Arc<Client>,
+    listeners: Listeners,
+    returning: Returning,
+    pub(crate) masking: Masking,
+    // The quoted table names set with `with_table`, by the quoted table name of the struct.
+    table_names: Arc<HashMap<&'static str, String>>,
+    sql_log: Option<SqlLog>,
+    write_hook: Option<WriteHook>,
+    search_path: Arc<Mutex<Option<Vec<String>>>>,
+    pub(crate) transactions: Arc<Transactions>,
+    // The id of the transaction this clone takes part in.
+    pub(crate) transaction: Option<usize>,
+    statement_cache: Option<Arc<StatementCache>>,
+    circuit_breaker: Option<Arc<CircuitBreaker>>,
+    observer: Option<Observer>,
+    #[cfg(feature = "runtime-tokio")]
+    retry: Option<Arc<RetryPolicy>>,
+    // Whether writes may be retried, set with `idempotent`.
+    #[cfg(feature = "runtime-tokio")]
+    idempotent: bool,
+    pub(crate) spawner: Spawner,
+    driver: Arc<Driver>,
+}
+
+// Connections are shared between the tasks of a multi-threaded runtime.
+const _: fn() = || {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<Connection>();
+    assert_send_sync::<Transaction>();
+};
+
+/// The task that drives the connection to the database, shared by all clones of a connection.
+struct Driver {
+    closed: AtomicBool,
+    shutdown: Mutex<Option<oneshot::Sender<()>>>,
+    // Signaled when the task is finished.
+    finished: Mutex<Option<oneshot::Receiver<()>>>,
 }
 
 impl Connection {
     ///
     /// Creates a new connection to the database.
     ///
+    /// The connection string is either a URL or a list of settings, see
+    /// [`ConnectionBuilder::from_connection_string`](struct.ConnectionBuilder.html#method.from_connection_string).
+    ///
     /// Example:
     /// ```no_run
     /// use sprattus::*;
@@ -26,417 +101,3632 @@ impl Connection {
     ///# #[tokio::main]
     ///# async fn main() -> Result<(), Error> {
     /// let conn = Connection::new("postgresql://localhost?user=tg").await?;
+    /// // Connects over a unix socket.
+    /// let conn = Connection::new("host=/var/run/postgresql user=tg").await?;
     ///# return Ok(())
     ///# }
     /// ```
     pub async fn new(connection_string: &str) -> Result<Self, Error> {
-        let (client, connection) = tokio_postgres::connect(connection_string, NoTls).await?;
+        ConnectionBuilder::from_connection_string(connection_string)?
+            .connect()
+            .await
+    }
+
+    /// Returns a builder to configure a connection setting by setting.
+    pub fn builder() -> ConnectionBuilder {
+        ConnectionBuilder::new()
+    }
+
+    ///
+    /// Creates a new connection to the database, of which the background tasks are spawned with
+    /// the given function instead of `tokio::spawn`, so the connection runs on another async
+    /// runtime, like async-std or smol. See
+    /// [`ConnectionBuilder::spawner`](struct.ConnectionBuilder.html#method.spawner).
+    ///
+    /// The socket to the database is opened by tokio-postgres with the network types of tokio,
+    /// which need the reactor of a tokio runtime. async-std provides one with its `tokio1`
+    /// feature, and smol with the async-compat crate.
+    ///
+    /// Example, with the `attributes` and `tokio1` features of async-std:
+    /// ```ignore
+    /// use sprattus::*;
+    ///
+    /// #[async_std::main]
+    /// async fn main() -> Result<(), Error> {
+    ///     let conn = Connection::new_with_spawner("postgresql://localhost?user=tg", |task| {
+    ///         async_std::task::spawn(task);
+    ///     })
+    ///     .await?;
+    ///     conn.ping().await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn new_with_spawner<F>(connection_string: &str, spawner: F) -> Result<Self, Error>
+    where
+        F: Fn(BoxFuture<'static, ()>) + Send + Sync + 'static,
+    {
+        ConnectionBuilder::from_connection_string(connection_string)?
+            .spawner(spawner)
+            .connect()
+            .await
+    }
+
+    /// Connects to the database with the given configuration, spawning the task driving the
+    /// connection with the spawner.
+    pub(crate) async fn connect(
+        config: &Config,
+        spawner: Spawner,
+        observer: Option<Observer>,
+    ) -> Result<Self, Error> {
+        let (client, mut connection) = config.connect(NoTls).await?;
 
-        let connection = connection
-            .map_err(|e| panic!("connection error: {}", e))
-            .map(|conn| conn.unwrap());
-        tokio::spawn(connection);
+        let listeners: Listeners = Arc::new(Mutex::new(Vec::new()));
+        let notification_listeners = listeners.clone();
+        // The error that closed the connection, reported to the observer.
+        let connection_error = Arc::new(Mutex::new(None));
+        let messages_error = connection_error.clone();
+        let messages = stream::poll_fn(move |cx| connection.poll_message(cx));
+        let messages = messages.for_each(move |message| {
+            match message {
+                Ok(AsyncMessage::Notification(notification)) => {
+                    dispatch_notification(&notification_listeners, notification)
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    log::error!("connection error: {}", e);
+                    *messages_error.lock().unwrap() = Some(Error::Postgres(e));
+                }
+            }
+            future::ready(())
+        });
+        // The connection is dropped, closing the socket, when a shutdown is signaled.
+        let (shutdown, shutdown_signal) = oneshot::channel();
+        let (finished, finished_signal) = oneshot::channel();
+        let driver_observer = observer.clone();
+        spawner(
+            future::select(Box::pin(messages), shutdown_signal)
+                .map(move |_| {
+                    if let Some(observer) = driver_observer {
+                        let error = connection_error.lock().unwrap().take();
+                        observer.observer.on_disconnect(error.as_ref());
+                    }
+                    let _ = finished.send(());
+                })
+                .boxed(),
+        );
+        if let Some(observer) = &observer {
+            observer.observer.on_connect();
+        }
         Ok(Self {
             client: Arc::new(client),
+            listeners,
+            returning: Returning::default(),
+            masking: Masking::default(),
+            table_names: Arc::new(HashMap::new()),
+            sql_log: None,
+            write_hook: None,
+            search_path: Arc::new(Mutex::new(None)),
+            transactions: Arc::new(Transactions::new()),
+            transaction: None,
+            statement_cache: None,
+            circuit_breaker: None,
+            observer,
+            #[cfg(feature = "runtime-tokio")]
+            retry: None,
+            #[cfg(feature = "runtime-tokio")]
+            idempotent: false,
+            spawner,
+            driver: Arc::new(Driver {
+                closed: AtomicBool::new(false),
+                shutdown: Mutex::new(Some(shutdown)),
+                finished: Mutex::new(Some(finished_signal)),
+            }),
         })
     }
-    /// Executes a statement, returning the number of rows modified.
+
+    /// Returns the client, or `Error::Closed` when the connection is closed.
     ///
-    /// If the statement does not modify any rows (e.g. `SELECT`), 0 is returned.
+    /// While another clone of the connection has a transaction open,
+    /// `Error::TransactionInProgress` is returned instead.
+    pub(crate) async fn client(&self) -> Result<ClientGuard<'_>, Error> {
+        if self.driver.closed.load(Ordering::SeqCst) {
+            return Err(Error::Closed);
+        }
+        self.transactions
+            .statement(&self.client, self.transaction)
+            .await
+    }
+
+    /// Returns a clone of the connection taking part in the given transaction.
+    pub(crate) fn joining(&self, transaction: usize) -> Self {
+        Self {
+            transaction: Some(transaction),
+            ..self.clone()
+        }
+    }
+
+    /// Returns whether this clone of the connection takes part in the open transaction.
+    pub(crate) fn in_transaction(&self) -> bool {
+        self.transactions.is_open(self.transaction)
+    }
+
+    /// Runs a statement returning rows, using the statement cache when it is enabled.
+    pub(crate) async fn query_rows(
+        &self,
+        sql: &str,
+        args: &[&(dyn ToSqlItem + Sync)],
+        access: Access,
+    ) -> Result<Vec<Row>, Error> {
+        self.retry(access, move || {
+            self.guard(sql, async move {
+                let client = self.client().await?;
+                let cache = match &self.statement_cache {
+                    Some(cache) => cache,
+                    None => return Ok(client.query(sql, args).await?),
+                };
+                let statement = cache.prepare(&client, sql).await?;
+                match client.query(&statement, args).await {
+                    Err(error) if is_stale_statement(&error) => {
+                        let statement = self.prepare_again(cache, sql, error).await?;
+                        Ok(client.query(&statement, args).await?)
+                    }
+                    result => Ok(result?),
+                }
+            })
+        })
+        .await
+    }
+
+    /// Runs a statement returning exactly one row, using the statement cache when it is enabled.
+    pub(crate) async fn query_row(
+        &self,
+        sql: &str,
+        args: &[&(dyn ToSqlItem + Sync)],
+        access: Access,
+    ) -> Result<Row, Error> {
+        self.retry(access, move || {
+            self.guard(sql, async move {
+                let client = self.client().await?;
+                let cache = match &self.statement_cache {
+                    Some(cache) => cache,
+                    None => return Ok(client.query_one(sql, args).await?),
+                };
+                let statement = cache.prepare(&client, sql).await?;
+                match client.query_one(&statement, args).await {
+                    Err(error) if is_stale_statement(&error) => {
+                        let statement = self.prepare_again(cache, sql, error).await?;
+                        Ok(client.query_one(&statement, args).await?)
+                    }
+                    result => Ok(result?),
+                }
+            })
+        })
+        .await
+    }
+
+    /// Runs a statement returning the number of modified rows, using the statement cache when
+    /// it is enabled.
+    async fn execute_statement(
+        &self,
+        sql: &str,
+        args: &[&(dyn ToSqlItem + Sync)],
+        access: Access,
+    ) -> Result<u64, Error> {
+        self.retry(access, move || {
+            self.guard(sql, async move {
+                let client = self.client().await?;
+                let cache = match &self.statement_cache {
+                    Some(cache) => cache,
+                    None => return Ok(client.execute(sql, args).await?),
+                };
+                let statement = cache.prepare(&client, sql).await?;
+                match client.execute(&statement, args).await {
+                    Err(error) if is_stale_statement(&error) => {
+                        let statement = self.prepare_again(cache, sql, error).await?;
+                        Ok(client.execute(&statement, args).await?)
+                    }
+                    result => Ok(result?),
+                }
+            })
+        })
+        .await
+    }
+
     ///
-    /// # Panics
+    /// Runs a statement, retrying it with the retry policy of the connection when it may be sent
+    /// again: when it only reads or is tagged idempotent. Statements within a transaction are not
+    /// retried, as a failure aborts the transaction.
     ///
-    /// Panics if the number of parameters provided does not match the number expected.
-    pub async fn execute(&self, sql: &str, args: &[&(dyn ToSqlItem + Sync)]) -> Result<u64, Error> {
-        let client = &self.client;
-        client.execute(sql, args).await
+    #[cfg_attr(not(feature = "runtime-tokio"), allow(unused_variables))]
+    async fn retry<R, F, Fut>(&self, access: Access, statement: F) -> Result<R, Error>
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = Result<R, Error>>,
+    {
+        #[cfg(feature = "runtime-tokio")]
+        if let Some(retry) = &self.retry {
+            if !self.in_transaction() && retry.may_retry(access, self.idempotent) {
+                return retry.run(statement).await;
+            }
+        }
+        statement().await
     }
 
-    /// Executes a sequence of SQL statements using the simple query protocol.
     ///
-    /// Statements should be separated by semicolons. If an error occurs, execution of the sequence will stop at that
-    /// point. This is intended for use when, for example, initializing a database schema.
+    /// Runs a statement, unless the circuit breaker is open, and records its outcome with the
+    /// circuit breaker and its duration with the observer.
     ///
-    /// # Warning
+    pub(crate) async fn guard<R>(
+        &self,
+        sql: &str,
+        statement: impl Future<Output = Result<R, Error>>,
+    ) -> Result<R, Error> {
+        if self.circuit_breaker.is_none() && self.observer.is_none() {
+            return statement.await;
+        }
+        if let Some(circuit_breaker) = &self.circuit_breaker {
+            circuit_breaker.check()?;
+        }
+        let started = Instant::now();
+        let result = statement.await;
+        let duration = started.elapsed();
+        if let Some(circuit_breaker) = &self.circuit_breaker {
+            circuit_breaker.record(duration, result.as_ref().err());
+        }
+        if let Some(observer) = &self.observer {
+            observer.statement_finished(sql, duration);
+        }
+        result
+    }
+
+    /// Reports a connection established again by a
+    /// [`ConnectionManager`](struct.ConnectionManager.html) to the observer.
+    pub(crate) fn notify_reconnect(&self) {
+        if let Some(observer) = &self.observer {
+            observer.observer.on_reconnect();
+        }
+    }
+
     ///
-    /// Prepared statements should be use for any query which contains user-specified data, as they provided the
-    /// functionality to safely embed that data in the request. Do not form statements via string concatenation and pass
-    /// them to this method!
-    pub async fn batch_execute(&self, sql: &str) -> Result<(), Error> {
-        let client = &self.client;
-        let result = { client.batch_execute(&sql) };
-        result.await
+    /// Prepares a cached statement again after the tables it uses changed.
+    ///
+    /// Within a transaction, the failed statement aborted the transaction, so running it again
+    /// is pointless. The statement is only removed from the cache then, and the error is returned.
+    ///
+    async fn prepare_again(
+        &self,
+        cache: &StatementCache,
+        sql: &str,
+        error: tokio_postgres::Error,
+    ) -> Result<Statement, Error> {
+        cache.invalidate(sql);
+        if self.in_transaction() {
+            return Err(error.into());
+        }
+        let client = self.client().await?;
+        Ok(cache.prepare(&client, sql).await?)
     }
 
     ///
-    /// Query multiple rows of a table.
+    /// Closes the connection, for all of its clones.
+    ///
+    /// Waits for the statements that are already sent to the database to finish, before the
+    /// connection is closed. Statements executed after the connection is closed return
+    /// [`Error::Closed`](enum.Error.html#variant.Closed). Closing a closed connection does
+    /// nothing.
     ///
     /// Example:
     /// ```no_run
-    ///# use sprattus::*;
-    ///# use tokio::prelude::*;
-    ///#
-    ///# #[derive(FromSql, Eq, PartialEq, Debug)]
-    ///# struct Product {
-    ///#     #[sql(primary_key)]
-    ///#     prod_id: i32,
-    ///#     title: String
+    /// use sprattus::*;
+    ///
+    ///# #[tokio::main]
+    ///# async fn main() -> Result<(), Error> {
+    /// let conn = Connection::new("postgresql://localhost?user=tg").await?;
+    /// conn.ping().await?;
+    /// conn.close().await;
+    /// assert!(conn.ping().await.is_err());
+    ///# return Ok(())
     ///# }
+    /// ```
+    pub async fn close(&self) {
+        if self.driver.closed.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        // The database answers statements in the order they are sent, so once this statement is
+        // answered, the statements sent before it are finished.
+        let _ = self.client.simple_query("").await;
+        if let Some(shutdown) = self.driver.shutdown.lock().unwrap().take() {
+            let _ = shutdown.send(());
+        }
+        let finished = self.driver.finished.lock().unwrap().take();
+        if let Some(finished) = finished {
+            let _ = finished.await;
+        }
+    }
+
+    /// Returns whether the connection is closed with [`close`](#method.close).
+    pub fn is_closed(&self) -> bool {
+        self.driver.closed.load(Ordering::SeqCst)
+    }
+
+    /// Returns whether the connection can no longer be used, because it is closed with
+    /// [`close`](#method.close) or the socket to the database is closed.
+    #[cfg(any(feature = "with-bb8-0_8", feature = "with-deadpool-0_9"))]
+    pub(crate) fn is_broken(&self) -> bool {
+        self.is_closed() || self.client.is_closed()
+    }
+
+    ///
+    /// Logs the SQL of every statement executed on the connection with the [`log`] crate,
+    /// at the given level.
+    ///
+    /// With [`LogParameters::Inline`](enum.LogParameters.html#variant.Inline), the placeholders in
+    /// the SQL are replaced with the debug representation of the parameters. Use
+    /// [`LogParameters::Redacted`](enum.LogParameters.html#variant.Redacted) to keep the values of
+    /// parameters out of the logs.
+    ///
+    /// [`log`]: https://docs.rs/log
+    ///
+    /// Example:
+    /// ```no_run
+    /// use sprattus::*;
+    ///
+    ///# #[tokio::main]
+    ///# async fn main() -> Result<(), Error> {
+    /// let conn = Connection::new("postgresql://localhost?user=tg")
+    ///     .await?
+    ///     .enable_sql_log(log::Level::Debug, LogParameters::Redacted);
+    ///# return Ok(())
+    ///# }
+    /// ```
+    pub fn enable_sql_log(mut self, level: log::Level, parameters: LogParameters) -> Self {
+        self.sql_log = Some(SqlLog::new(level, parameters));
+        self
+    }
+
+    pub(crate) fn log_sql(&self, sql: &str, args: &[&(dyn ToSqlItem + Sync)]) {
+        if let Some(sql_log) = &self.sql_log {
+            sql_log.log(sql, args);
+        }
+    }
+
+    ///
+    /// Sets a function that is called for every row created, updated or deleted by
+    /// [`create`](#method.create), [`update`](#method.update), [`delete`](#method.delete) and
+    /// their variants, so audit logging can be implemented in a single place.
+    ///
+    /// The values of the row are serialized with their debug representation. To provide the
+    /// values of a row before it is updated, the row is selected before the update statement is
+    /// executed. The hook is called once the statement succeeded, also when it is executed within
+    /// a transaction that is rolled back later on.
+    ///
+    /// Example:
+    /// ```no_run
+    /// use sprattus::*;
+    ///
+    ///# #[tokio::main]
+    ///# async fn main() -> Result<(), Error> {
+    /// let mut conn = Connection::new("postgresql://localhost?user=tg").await?;
+    /// conn.set_write_hook(|event: WriteEvent| {
+    ///     println!(
+    ///         "{:?} {} {}: {:?} -> {:?}",
+    ///         event.operation, event.table, event.primary_key, event.before, event.after
+    ///     );
+    /// });
+    ///# return Ok(())
+    ///# }
+    /// ```
+    pub fn set_write_hook<F>(&mut self, hook: F)
+    where
+        F: Fn(WriteEvent) + Send + Sync + 'static,
+    {
+        self.write_hook = Some(Arc::new(hook));
+    }
+
+    fn notify_write<T: ToSql>(
+        &self,
+        operation: WriteOperation,
+        before: Option<&T>,
+        after: Option<&T>,
+    ) {
+        if let Some(hook) = &self.write_hook {
+            let row = before.or(after).expect("a write event without a row");
+            hook(WriteEvent {
+                table: T::get_table_name(),
+                operation,
+                primary_key: format!("{:?}", row.get_primary_key_value()),
+                before: before.map(write_hook::serialize),
+                after: after.map(write_hook::serialize),
+            });
+        }
+    }
+
+    /// Notifies the write hook of a created row of which only the primary key was returned.
+    fn notify_create_key<T: ToSql>(&self, item: &T, primary_key: &T::PK) {
+        if let Some(hook) = &self.write_hook {
+            // Only the written fields are known, the others are set by the database.
+            let values: Vec<FieldValue<'_>> = item
+                .get_field_values()
+                .into_iter()
+                .filter(|field| {
+                    T::get_fields()
+                        .split(',')
+                        .any(|column| column.trim_matches('"') == field.name)
+                })
+                .collect();
+            hook(WriteEvent {
+                table: T::get_table_name(),
+                operation: WriteOperation::Create,
+                primary_key: format!("{:?}", primary_key),
+                before: None,
+                after: Some(write_hook::serialize_values(&values)),
+            });
+        }
+    }
+
+    ///
+    /// Selects the current rows of items that are about to be updated, when a write hook is set.
+    ///
+    async fn select_before_update<T>(&self, items: &[T]) -> Result<Vec<T>, Error>
+    where
+        T: ToSql + FromSql,
+    {
+        if self.write_hook.is_none() {
+            return Ok(Vec::new());
+        }
+        let sql = format!(
+            "SELECT {fields} FROM {table_name} WHERE {primary_key} = ANY($1){tenant}",
+            fields = T::get_all_fields(),
+            table_name = self.table_name::<T>(),
+            primary_key = T::get_primary_key(),
+            tenant = tenant_filter::<T>(None),
+        );
+        let primary_keys: Vec<&T::PK> = items
+            .iter()
+            .map(|item| item.get_primary_key_value())
+            .collect();
+        self.query_multiple_with(sql.as_str(), &[&primary_keys], Access::Read)
+            .await
+            .map_err(self.in_statement::<T>(StatementKind::Select, &sql))
+    }
+
+    fn notify_updates<T: ToSql>(&self, before: &[T], after: &[T]) {
+        if self.write_hook.is_none() {
+            return;
+        }
+        for item in after {
+            let primary_key = format!("{:?}", item.get_primary_key_value());
+            let previous = before
+                .iter()
+                .find(|row| format!("{:?}", row.get_primary_key_value()) == primary_key);
+            self.notify_write(WriteOperation::Update, previous, Some(item));
+        }
+    }
+
+    ///
+    /// Sets the schemas in which tables without a schema are looked up, so the tables of structs
+    /// resolve against the intended schema without qualifying every table name.
+    /// An empty list restores the default search path of the server.
+    ///
+    /// The search path is stored on the connection, and shared by all of its clones, so it can be
+    /// applied again when the connection is re-established. To apply it to every new connection,
+    /// set it on the builder with
+    /// [`ConnectionBuilder::search_path`](struct.ConnectionBuilder.html#method.search_path).
+    ///
+    /// # Note
+    ///
+    /// When the search path is set within a transaction that is rolled back, Postgres reverts it
+    /// to its previous value.
+    ///
+    /// Example:
+    /// ```no_run
+    /// use sprattus::*;
+    ///
     ///# #[tokio::main]
     ///# async fn main() -> Result<(), Error> {
-    ///#
     /// let conn = Connection::new("postgresql://localhost?user=tg").await?;
-    ///#
-    ///#
-    ///#
-    /// let product_list : Vec<Product> =
-    ///    conn.query_multiple("SELECT * FROM Products LIMIT 3", &[]).await?;
-    /// assert_eq!(product_list,
-    ///     vec!(
-    ///    Product {
-    ///	    prod_id : 1,
-    ///	    title : String::from("ACADEMY ACADEMY")
-    ///    },
-    ///	Product {
-    ///	   prod_id : 2,
-    ///	   title : String::from("ACADEMY ACE")
-    ///    },
-    ///	Product {
-    ///	    prod_id : 3,
-    ///	    title : String::from("ACADEMY ADAPTATION")
-    ///	}));
-    ///# Ok(())
+    /// // Executes `SET search_path TO "tenant_a","public"`.
+    /// conn.set_search_path(&["tenant_a", "public"]).await?;
+    ///# return Ok(())
+    ///# }
+    /// ```
+    pub async fn set_search_path(&self, schemas: &[&str]) -> Result<(), Error> {
+        self.apply_search_path(schemas.iter().map(|schema| schema.to_string()).collect())
+            .await
+    }
+
+    /// Sets the search path and stores it on the connection.
+    pub(crate) async fn apply_search_path(&self, schemas: Vec<String>) -> Result<(), Error> {
+        self.batch_execute(search_path_statement(&schemas).as_str())
+            .await?;
+        *self.search_path.lock().unwrap() = if schemas.is_empty() {
+            None
+        } else {
+            Some(schemas)
+        };
+        Ok(())
+    }
+
+    /// Returns the schemas set with [`set_search_path`](#method.set_search_path), or `None` when
+    /// the default search path of the server is used.
+    pub fn search_path(&self) -> Option<Vec<String>> {
+        self.search_path.lock().unwrap().clone()
+    }
+
+    ///
+    /// Sets the columns returned when creating, updating or deleting rows.
+    ///
+    /// Example:
+    /// ```no_run
+    /// use sprattus::*;
+    ///
+    ///# #[tokio::main]
+    ///# async fn main() -> Result<(), Error> {
+    /// // Only return the columns of the fields of a struct, instead of `RETURNING *`.
+    /// let conn = Connection::new("postgresql://localhost?user=tg")
+    ///     .await?
+    ///     .with_returning(Returning::Fields);
+    ///# return Ok(())
     ///# }
     /// ```
-    pub async fn query_multiple<T>(
+    pub fn with_returning(mut self, returning: Returning) -> Self {
+        self.returning = returning;
+        self
+    }
+
+    ///
+    /// Sets whether the columns of fields with the `masked` attribute are masked when rows are
+    /// selected. They are masked by default, so code that needs the full values has to ask for
+    /// them explicitly.
+    ///
+    /// Only the statements selecting the fields of a struct, like `find` and `select`, mask the
+    /// columns. Rows returned by statements that create, update or delete rows are not masked, and
+    /// neither are the rows of queries written by hand. A masked value that is written back
+    /// replaces the full value, so rows that are updated should be read unmasked.
+    ///
+    /// Example:
+    /// ```no_run
+    /// use sprattus::*;
+    ///
+    /// #[derive(FromSql, ToSql, Debug)]
+    /// #[sprattus(table = "customers")]
+    /// struct Customer {
+    ///     #[sprattus(primary_key)]
+    ///     customerid: i32,
+    ///     #[sprattus(masked)]
+    ///     creditcard: String,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Error> {
+    ///     let conn = Connection::new("postgresql://localhost?user=tg").await?;
+    ///     // Executes `SELECT "customerid",'****' || right("creditcard", 4) AS "creditcard"
+    ///     // FROM "customers" WHERE "customerid" = $1`.
+    ///     let customer = conn.find::<Customer>(&1).await?;
+    ///     assert!(customer.creditcard.starts_with("****"));
+    ///
+    ///     let billing = conn.clone().with_masking(Masking::Unmasked);
+    ///     let customer = billing.find::<Customer>(&1).await?;
+    ///     dbg!(customer.creditcard);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn with_masking(mut self, masking: Masking) -> Self {
+        self.masking = masking;
+        self
+    }
+
+    ///
+    /// Uses another table for the rows of `T`, like a table of a shard or a table with a suffix,
+    /// instead of the table the struct is derived for.
+    ///
+    /// All statements generated for `T` use the given table, which may be qualified with a
+    /// schema like `archive.products`. The name is quoted, so it keeps its casing. Write hooks
+    /// still receive the table the struct is derived for.
+    ///
+    /// Returns [`Error::Config`](enum.Error.html#variant.Config) when the name is not a valid
+    /// identifier.
+    ///
+    /// Example:
+    /// ```no_run
+    /// use sprattus::*;
+    ///
+    /// #[derive(FromSql, ToSql, Debug)]
+    /// #[sprattus(table = "products")]
+    /// struct Product {
+    ///     #[sprattus(primary_key)]
+    ///     prod_id: i32,
+    ///     title: String,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Error> {
+    ///     let conn = Connection::new("postgresql://localhost?user=tg").await?;
+    ///     let product = Product {
+    ///         prod_id: 0,
+    ///         title: String::from("Sprattus"),
+    ///     };
+    ///     // Executes `INSERT INTO "products_2024" ("title") values ($1) RETURNING *`.
+    ///     let created = conn
+    ///         .clone()
+    ///         .with_table::<Product>("products_2024")?
+    ///         .create(&product)
+    ///         .await?;
+    ///     dbg!(created);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn with_table<T: Table>(mut self, table_name: &str) -> Result<Self, Error> {
+        validate_table_name(table_name)?;
+        Arc::make_mut(&mut self.table_names).insert(T::get_table_name(), table_ident(table_name));
+        Ok(self)
+    }
+
+    /// Returns the quoted name of the table of `T`, which is the table set with `with_table`, or
+    /// the table the struct is derived for.
+    pub(crate) fn table_name<T: Table>(&self) -> &str {
+        match self.table_names.get(T::get_table_name()) {
+            Some(table_name) => table_name.as_str(),
+            None => T::get_table_name(),
+        }
+    }
+
+    /// Returns a function that adds a statement generated for `T` to an error, to be passed to
+    /// `map_err`.
+    pub(crate) fn in_statement<'a, T: Table>(
+        &'a self,
+        kind: StatementKind,
+        sql: &'a str,
+    ) -> impl FnOnce(Error) -> Error + 'a {
+        statement_error(kind, self.table_name::<T>(), sql)
+    }
+
+    ///
+    /// Enables a cache of prepared statements, holding up to the given number of statements.
+    ///
+    /// Every statement is prepared the first time it is run and reused afterwards, saving a round
+    /// trip to the database. When the cache is full, the statement that was prepared first is
+    /// removed. Postgres rejects a prepared statement after a change of the tables it selects
+    /// from; such a statement is prepared again and rerun transparently, except within a
+    /// transaction, where the error is returned and the statement is prepared again when it is
+    /// used next.
+    ///
+    /// The cache is shared by all clones of the connection.
+    ///
+    /// Example:
+    /// ```no_run
+    /// use sprattus::*;
+    ///
+    ///# #[tokio::main]
+    ///# async fn main() -> Result<(), Error> {
+    /// let conn = Connection::new("postgresql://localhost?user=tg")
+    ///     .await?
+    ///     .with_statement_cache(100);
+    ///# return Ok(())
+    ///# }
+    /// ```
+    pub fn with_statement_cache(mut self, capacity: usize) -> Self {
+        self.statement_cache = Some(Arc::new(StatementCache::new(capacity)));
+        self
+    }
+
+    ///
+    /// Enables a circuit breaker, which makes statements fail immediately with
+    /// [`Error::CircuitOpen`](enum.Error.html#variant.CircuitOpen) for a while after too many
+    /// failed or slow statements, see [`CircuitBreaker`](struct.CircuitBreaker.html).
+    ///
+    /// The circuit breaker is shared by all clones of the connection.
+    ///
+    pub fn with_circuit_breaker(mut self, circuit_breaker: CircuitBreaker) -> Self {
+        self.circuit_breaker = Some(Arc::new(circuit_breaker));
+        self
+    }
+
+    ///
+    /// Enables retrying statements that fail because the database can't be reached or does not
+    /// respond in time, see [`RetryPolicy`](struct.RetryPolicy.html).
+    ///
+    /// The policy applies to the returned connection and its clones, so a policy for a single
+    /// call is set on a clone, like `conn.clone().with_retry(policy).find::<Product>(&1)`.
+    ///
+    #[cfg(feature = "runtime-tokio")]
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = Some(Arc::new(retry));
+        self
+    }
+
+    ///
+    /// Returns a clone of the connection of which all statements are tagged idempotent, so the
+    /// [`RetryPolicy`](struct.RetryPolicy.html) of the connection retries its writes and queries
+    /// written by hand as well.
+    ///
+    /// Only tag writes that have the same effect when they are executed twice, like setting a
+    /// column to a value or an upsert, and not incrementing a column or inserting a row with a
+    /// generated key.
+    ///
+    #[cfg(feature = "runtime-tokio")]
+    pub fn idempotent(&self) -> Self {
+        let mut connection = self.clone();
+        connection.idempotent = true;
+        connection
+    }
+
+    /// Returns the state of the circuit breaker, or `None` when it is not enabled.
+    pub fn circuit_state(&self) -> Option<CircuitState> {
+        self.circuit_breaker
+            .as_ref()
+            .map(|circuit_breaker| circuit_breaker.state())
+    }
+
+    /// Removes all statements from the statement cache, so they are prepared again.
+    pub fn clear_statement_cache(&self) {
+        if let Some(cache) = &self.statement_cache {
+            cache.clear();
+        }
+    }
+
+    /// Executes a statement, returning the number of rows modified and the first keyword of the
+    /// statement.
+    ///
+    /// If the statement does not modify any rows (e.g. `SELECT`), 0 is returned.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of parameters provided does not match the number expected.
+    ///
+    /// Example:
+    /// ```no_run
+    /// use sprattus::*;
+    ///
+    ///# #[tokio::main]
+    ///# async fn main() -> Result<(), Error> {
+    /// let conn = Connection::new("postgresql://localhost?user=tg").await?;
+    /// let result = conn
+    ///     .execute("UPDATE Products SET price = $1 WHERE prod_id = $2", &[&9.99, &1])
+    ///     .await?;
+    /// if result.is_empty() {
+    ///     println!("product 1 does not exist");
+    /// }
+    ///# return Ok(())
+    ///# }
+    /// ```
+    pub async fn execute(
+        &self,
+        sql: &str,
+        args: &[&(dyn ToSqlItem + Sync)],
+    ) -> Result<ExecuteResult, Error> {
+        self.log_sql(sql, args);
+        let rows_affected = self.execute_statement(sql, args, Access::Write).await?;
+        Ok(ExecuteResult::new(sql, rows_affected))
+    }
+
+    /// Executes a sequence of SQL statements using the simple query protocol.
+    ///
+    /// Statements should be separated by semicolons. If an error occurs, execution of the sequence will stop at that
+    /// point. This is intended for use when, for example, initializing a database schema.
+    ///
+    /// # Warning
+    ///
+    /// Prepared statements should be use for any query which contains user-specified data, as they provided the
+    /// functionality to safely embed that data in the request. Do not form statements via string concatenation and pass
+    /// them to this method!
+    pub async fn batch_execute(&self, sql: &str) -> Result<(), Error> {
+        self.log_sql(sql, &[]);
+        let client = self.client().await?;
+        let result = { client.batch_execute(sql) };
+        Ok(result.await?)
+    }
+
+    ///
+    /// Checks that the database responds, by executing `SELECT 1`.
+    ///
+    /// Returns [`Error::Timeout`](enum.Error.html#variant.Timeout) when the database does not
+    /// respond within five seconds. The timeout requires the `runtime-tokio` feature.
+    ///
+    /// Example:
+    /// ```no_run
+    /// use sprattus::*;
+    ///
+    ///# #[tokio::main]
+    ///# async fn main() -> Result<(), Error> {
+    /// let conn = Connection::new("postgresql://localhost?user=tg").await?;
+    /// conn.ping().await?;
+    ///# return Ok(())
+    ///# }
+    /// ```
+    pub async fn ping(&self) -> Result<(), Error> {
+        self.log_sql(PING_STATEMENT, &[]);
+        self.guard(PING_STATEMENT, async {
+            let client = self.client().await?;
+            let ping = client.simple_query(PING_STATEMENT);
+            #[cfg(feature = "runtime-tokio")]
+            let ping = tokio::time::timeout(PING_TIMEOUT, ping)
+                .await
+                .map_err(|_| Error::Timeout)?;
+            #[cfg(not(feature = "runtime-tokio"))]
+            let ping = ping.await;
+            ping?;
+            Ok(())
+        })
+        .await
+    }
+
+    ///
+    /// Returns whether the database responds to a [`ping`](#method.ping), for use in readiness
+    /// checks or to decide whether a connection should be replaced.
+    ///
+    /// Example:
+    /// ```no_run
+    /// use sprattus::*;
+    ///
+    ///# #[tokio::main]
+    ///# async fn main() -> Result<(), Error> {
+    /// let conn = Connection::new("postgresql://localhost?user=tg").await?;
+    /// if !conn.is_healthy().await {
+    ///     eprintln!("the database is not available");
+    /// }
+    ///# return Ok(())
+    ///# }
+    /// ```
+    pub async fn is_healthy(&self) -> bool {
+        self.ping().await.is_ok()
+    }
+
+    ///
+    /// Executes multiple parameterized statements, returning the number of rows modified by each.
+    ///
+    /// The statements are pipelined: they are all sent to the database without waiting for the
+    /// result of the previous statement, which saves a round-trip per statement.
+    /// Because the statements are independent, a failing statement does not stop the others.
+    ///
+    /// Example:
+    /// ```no_run
+    /// use sprattus::*;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Error> {
+    ///     let conn = Connection::new("postgresql://localhost?user=tg").await?;
+    ///     let results = conn
+    ///         .execute_batch(&[
+    ///             ("UPDATE Products SET price = $1 WHERE prod_id = $2", &[&9.99, &1]),
+    ///             ("DELETE FROM Products WHERE prod_id = $1", &[&2]),
+    ///         ])
+    ///         .await;
+    ///     for result in results {
+    ///         println!("modified {} rows", result?.rows_affected);
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn execute_batch(
+        &self,
+        statements: &[(&str, &[&(dyn ToSqlItem + Sync)])],
+    ) -> Vec<Result<ExecuteResult, Error>> {
+        future::join_all(statements.iter().map(|(sql, args)| {
+            self.log_sql(sql, args);
+            self.execute_statement(sql, args, Access::Write)
+                .map_ok(move |rows_affected| ExecuteResult::new(sql, rows_affected))
+        }))
+        .await
+    }
+
+    ///
+    /// Query multiple rows of a table.
+    ///
+    /// Example:
+    /// ```no_run
+    ///# use sprattus::*;
+    ///#
+    ///# #[derive(FromSql, Eq, PartialEq, Debug)]
+    ///# struct Product {
+    ///#     #[sprattus(primary_key)]
+    ///#     prod_id: i32,
+    ///#     title: String
+    ///# }
+    ///# #[tokio::main]
+    ///# async fn main() -> Result<(), Error> {
+    ///#
+    /// let conn = Connection::new("postgresql://localhost?user=tg").await?;
+    ///#
+    ///#
+    ///#
+    /// let product_list : Vec<Product> =
+    ///    conn.query_multiple("SELECT * FROM Products LIMIT 3", &[]).await?;
+    /// assert_eq!(product_list,
+    ///     vec!(
+    ///    Product {
+    ///        prod_id : 1,
+    ///        title : String::from("ACADEMY ACADEMY")
+    ///    },
+    ///    Product {
+    ///       prod_id : 2,
+    ///       title : String::from("ACADEMY ACE")
+    ///    },
+    ///    Product {
+    ///        prod_id : 3,
+    ///        title : String::from("ACADEMY ADAPTATION")
+    ///    }));
+    ///# Ok(())
+    ///# }
+    /// ```
+    pub async fn query_multiple<T>(
+        &self,
+        sql: &str,
+        args: &[&(dyn ToSqlItem + Sync)],
+    ) -> Result<Vec<T>, Error>
+    where
+        T: FromSql,
+    {
+        self.query_multiple_with(sql, args, Access::Write).await
+    }
+
+    /// Queries multiple rows, which the retry policy may send again when the statement only
+    /// reads.
+    pub(crate) async fn query_multiple_with<T>(
+        &self,
+        sql: &str,
+        args: &[&(dyn ToSqlItem + Sync)],
+        access: Access,
+    ) -> Result<Vec<T>, Error>
+    where
+        T: FromSql,
+    {
+        self.log_sql(sql, args);
+        self.query_rows(sql, args, access)
+            .map(|rows| from_rows(&rows?))
+            .await
+    }
+
+    ///
+    /// Queries rows of which the columns are only known at runtime.
+    ///
+    /// Example:
+    /// ```no_run
+    /// use sprattus::*;
+    ///
+    ///# #[tokio::main]
+    ///# async fn main() -> Result<(), Error> {
+    /// let conn = Connection::new("postgresql://localhost?user=tg").await?;
+    /// let rows = conn.query_dynamic("SELECT * FROM Products LIMIT 3", &[]).await?;
+    /// for row in rows {
+    ///     let title: String = row.get("title")?;
+    ///     println!("{} has the columns {:?}", title, row.columns());
+    /// }
+    ///# Ok(())
+    ///# }
+    /// ```
+    pub async fn query_dynamic(
+        &self,
+        sql: &str,
+        args: &[&(dyn ToSqlItem + Sync)],
+    ) -> Result<Vec<DynamicRow>, Error> {
+        self.log_sql(sql, args);
+        let rows = self.query_rows(sql, args, Access::Write).await?;
+        Ok(rows.into_iter().map(DynamicRow::new).collect())
+    }
+
+    ///
+    /// Queries rows as JSON objects, with a key for every column.
+    ///
+    /// The rows are converted with the `to_jsonb` function of Postgres, so every column type
+    /// that Postgres can represent in JSON is supported.
+    ///
+    /// Example:
+    /// ```no_run
+    /// use sprattus::*;
+    ///
+    ///# #[tokio::main]
+    ///# async fn main() -> Result<(), Error> {
+    /// let conn = Connection::new("postgresql://localhost?user=tg").await?;
+    /// // Executes `SELECT to_jsonb(q) FROM (SELECT * FROM Products LIMIT 3) AS q`.
+    /// let rows = conn.query_json("SELECT * FROM Products LIMIT 3", &[]).await?;
+    /// for row in rows {
+    ///     println!("{}", row["title"]);
+    /// }
+    ///# Ok(())
+    ///# }
+    /// ```
+    #[cfg(feature = "with-serde_json-1")]
+    pub async fn query_json(
+        &self,
+        sql: &str,
+        args: &[&(dyn ToSqlItem + Sync)],
+    ) -> Result<Vec<serde_json::Value>, Error> {
+        let sql = format!("SELECT to_jsonb(q) FROM ({}) AS q", sql);
+        self.log_sql(sql.as_str(), args);
+        self.query_rows(sql.as_str(), args, Access::Write)
+            .await?
+            .iter()
+            .map(|row| Ok(row.try_get(0)?))
+            .collect()
+    }
+
+    ///
+    /// Queries multiple rows with parameters of which the Postgres type is given explicitly.
+    ///
+    /// Postgres infers the type of every parameter from the statement, which fails with
+    /// "could not determine data type of parameter" when the statement leaves it open, like
+    /// `unnest($1)`. It also picks `JSON` where a `JSONB` is wanted, for example. The given types
+    /// are used instead when the statement is prepared.
+    ///
+    /// Example:
+    /// ```no_run
+    /// use sprattus::*;
+    /// use sprattus::types::Type;
+    ///
+    /// #[derive(FromSql, Debug)]
+    /// struct Product {
+    ///     prod_id: i32,
+    ///     title: String,
+    /// }
+    ///
+    ///# #[tokio::main]
+    ///# async fn main() -> Result<(), Error> {
+    /// let conn = Connection::new("postgresql://localhost?user=tg").await?;
+    /// let ids = vec![1, 2, 3];
+    /// let products: Vec<Product> = conn
+    ///     .query_typed(
+    ///         "SELECT * FROM Products WHERE prod_id IN (SELECT unnest($1))",
+    ///         &[(&ids, Type::INT4_ARRAY)],
+    ///     )
+    ///     .await?;
+    /// dbg!(products);
+    ///# Ok(())
+    ///# }
+    /// ```
+    pub async fn query_typed<T>(
+        &self,
+        sql: &str,
+        args: &[(&(dyn ToSqlItem + Sync), Type)],
+    ) -> Result<Vec<T>, Error>
+    where
+        T: FromSql,
+    {
+        let (values, types): (Vec<_>, Vec<_>) = args.iter().cloned().unzip();
+        self.log_sql(sql, &values);
+        let client = self.client().await?;
+        let statement = client.prepare_typed(sql, &types).await?;
+        client
+            .query(&statement, &values)
+            .map(|rows| from_rows(&rows?))
+            .await
+    }
+
+    ///
+    /// Prepares a statement once, returning a [`PreparedQuery`](struct.PreparedQuery.html) that
+    /// executes it with new arguments, and can be shared across tasks.
+    ///
+    /// Unlike the [statement cache](#method.with_statement_cache), which prepares every statement
+    /// the connection executes, this prepares a single hot statement explicitly.
+    ///
+    /// Example:
+    /// ```no_run
+    /// use sprattus::*;
+    ///
+    /// #[derive(FromSql, Debug)]
+    /// struct Product {
+    ///     prod_id: i32,
+    ///     title: String,
+    /// }
+    ///
+    ///# #[tokio::main]
+    ///# async fn main() -> Result<(), Error> {
+    /// let conn = Connection::new("postgresql://localhost?user=tg").await?;
+    /// let by_price = conn
+    ///     .prepare_typed::<Product>("SELECT prod_id, title FROM Products WHERE price < $1")
+    ///     .await?;
+    /// let cheap = by_price.fetch(&[&5.0]).await?;
+    /// let cheaper = by_price.fetch(&[&2.5]).await?;
+    /// dbg!(cheap, cheaper);
+    ///# Ok(())
+    ///# }
+    /// ```
+    pub async fn prepare_typed<T>(&self, sql: &str) -> Result<PreparedQuery<T>, Error> {
+        PreparedQuery::prepare(self, sql).await
+    }
+
+    ///
+    /// Query multiple rows, passing a list of values as a single array parameter.
+    ///
+    /// Use `= ANY($1)` instead of `IN (...)` in the query, so the number of placeholders does not
+    /// grow with the number of values.
+    ///
+    /// Example:
+    /// ```no_run
+    /// use sprattus::*;
+    ///
+    /// #[derive(FromSql, Debug)]
+    /// struct Product {
+    ///     prod_id: i32,
+    ///     title: String
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Error> {
+    ///     let conn = Connection::new("postgresql://localhost?user=tg").await?;
+    ///     let ids: Vec<i32> = (1..=1000).collect();
+    ///     let sql = "SELECT * FROM Products WHERE prod_id = ANY($1)";
+    ///     let products: Vec<Product> = conn.query_in(sql, &ids).await?;
+    ///     dbg!(products);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn query_in<T, V>(&self, sql: &str, values: &[V]) -> Result<Vec<T>, Error>
+    where
+        T: FromSql,
+        V: ToSqlItem + Sync,
+    {
+        self.query_multiple(sql, &[&values]).await
+    }
+
+    ///
+    /// Get a single row of a table.
+    ///
+    /// Example:
+    /// ```no_run
+    /// use sprattus::*;
+    ///
+    /// #[derive(FromSql, Eq, PartialEq, Debug)]
+    /// struct Product {
+    ///     #[sprattus(primary_key)]
+    ///     prod_id: i32,
+    ///     title: String
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Error> {
+    ///     let conn = Connection::new("postgresql://localhost?user=tg").await?;
+    ///     let product : Product = conn.query("SELECT * FROM Products LIMIT 1", &[]).await?;
+    ///     assert_eq!(product, Product{ prod_id: 1, title: String::from("ACADEMY ACADEMY")});
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn query<T>(&self, sql: &str, args: &[&(dyn ToSqlItem + Sync)]) -> Result<T, Error>
+    where
+        T: FromSql,
+    {
+        self.query_with(sql, args, Access::Write).await
+    }
+
+    /// Queries a single row, which the retry policy may send again when the statement only reads.
+    pub(crate) async fn query_with<T>(
+        &self,
+        sql: &str,
+        args: &[&(dyn ToSqlItem + Sync)],
+        access: Access,
+    ) -> Result<T, Error>
+    where
+        T: FromSql,
+    {
+        self.log_sql(sql, args);
+        T::from_row(&self.query_row(sql, args, access).await?)
+    }
+
+    ///
+    /// Query all rows of the table of a projection, selecting only the projected columns.
+    ///
+    /// Example:
+    /// ```no_run
+    /// use sprattus::*;
+    ///
+    /// #[derive(FromSql, ToSql, Debug)]
+    /// #[sprattus(table = "products")]
+    /// struct Product {
+    ///     #[sprattus(primary_key)]
+    ///     prod_id: i32,
+    ///     title: String,
+    ///     price: f64,
+    /// }
+    ///
+    /// #[derive(FromSql, Projection, Debug)]
+    /// #[sprattus(of = "Product")]
+    /// struct ProductTitle {
+    ///     prod_id: i32,
+    ///     title: String,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Error> {
+    ///     let conn = Connection::new("postgresql://localhost?user=tg").await?;
+    ///     // Executes `SELECT "prod_id","title" FROM "products"`.
+    ///     let titles = conn.select_projection::<ProductTitle>().await?;
+    ///     dbg!(titles);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn select_projection<T>(&self) -> Result<Vec<T>, Error>
+    where
+        T: Projection,
+    {
+        let mut sql = format!(
+            "SELECT {fields} FROM {table_name}",
+            fields = T::get_projected_fields(),
+            table_name = self.table_name::<T::Parent>(),
+        );
+        if let Some(condition) = tenant_condition::<T::Parent>(None) {
+            sql.push_str(" WHERE ");
+            sql.push_str(condition.as_str());
+        }
+        self.query_multiple_with(sql.as_str(), &[], Access::Read)
+            .await
+    }
+
+    ///
+    /// Start a query selecting rows of a table or view.
+    ///
+    /// Example:
+    /// ```no_run
+    /// use sprattus::*;
+    ///
+    /// #[derive(FromSql, ToSql, Debug)]
+    /// #[sprattus(view = "product_summary")]
+    /// struct ProductSummary {
+    ///     category: i32,
+    ///     product_count: i64,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Error> {
+    ///     let conn = Connection::new("postgresql://localhost?user=tg").await?;
+    ///     // Executes `SELECT "category","product_count" FROM "product_summary" WHERE product_count > $1`.
+    ///     let summaries = conn
+    ///         .select::<ProductSummary>()
+    ///         .filter("product_count > $1", &[&100i64])
+    ///         .fetch()
+    ///         .await?;
+    ///     dbg!(summaries);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn select<T>(&self) -> Select<'_, T>
+    where
+        T: Table + FromSql,
+    {
+        Select::new(self)
+    }
+
+    ///
+    /// Names a query with a common table expression, so it can be referred to by the query
+    /// created with [`With::select`](struct.With.html#method.select). See
+    /// [`With`](struct.With.html) for an example.
+    ///
+    pub fn with<'a, Q>(&'a self, name: &str, query: Q) -> With<'a>
+    where
+        Q: Into<Subquery<'a>>,
+    {
+        With::new(self).with(name, query)
+    }
+
+    ///
+    /// Creates a query computing aggregates over the rows of a table or view, like the number of
+    /// rows per category. See [`Aggregate`](struct.Aggregate.html) for an example.
+    ///
+    pub fn aggregate<T>(&self) -> Aggregate<'_, T>
+    where
+        T: Table,
+    {
+        Aggregate::new(self)
+    }
+
+    ///
+    /// Get all rows of a table or view, selecting only the columns of the fields of the struct.
+    ///
+    /// Example:
+    /// ```no_run
+    /// use sprattus::*;
+    ///
+    /// #[derive(FromSql, ToSql, Debug)]
+    /// #[sprattus(table = "products")]
+    /// struct Product {
+    ///     #[sprattus(primary_key)]
+    ///     prod_id: i32,
+    ///     title: String
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Error> {
+    ///     let conn = Connection::new("postgresql://localhost?user=tg").await?;
+    ///     // Executes `SELECT "prod_id","title" FROM "products"`.
+    ///     let products = conn.fetch_all::<Product>().await?;
+    ///     dbg!(products);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn fetch_all<T>(&self) -> Result<Vec<T>, Error>
+    where
+        T: Table + FromSql,
+    {
+        self.select::<T>().fetch().await
+    }
+
+    ///
+    /// Get the rows of a table or view matching a condition, selecting only the columns of the
+    /// fields of the struct.
+    ///
+    /// Example:
+    /// ```no_run
+    /// use sprattus::*;
+    ///
+    /// #[derive(FromSql, ToSql, Debug)]
+    /// #[sprattus(table = "products")]
+    /// struct Product {
+    ///     #[sprattus(primary_key)]
+    ///     prod_id: i32,
+    ///     title: String
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Error> {
+    ///     let conn = Connection::new("postgresql://localhost?user=tg").await?;
+    ///     // Executes `SELECT "prod_id","title" FROM "products" WHERE prod_id < $1`.
+    ///     let products = conn.fetch_where::<Product>("prod_id < $1", &[&10]).await?;
+    ///     dbg!(products);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn fetch_where<T>(
+        &self,
+        condition: &str,
+        args: &[&(dyn ToSqlItem + Sync)],
+    ) -> Result<Vec<T>, Error>
+    where
+        T: Table + FromSql,
+    {
+        self.select::<T>().filter(condition, args).fetch().await
+    }
+
+    ///
+    /// Find a single row of a table by its primary key.
+    ///
+    /// Example:
+    /// ```no_run
+    /// use sprattus::*;
+    ///
+    /// #[derive(FromSql, ToSql, Eq, PartialEq, Debug)]
+    /// struct Product {
+    ///     #[sprattus(primary_key)]
+    ///     prod_id: i32,
+    ///     title: String
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Error> {
+    ///     let conn = Connection::new("postgresql://localhost?user=tg").await?;
+    ///     let product = conn.find::<Product>(&1).await?;
+    ///     assert_eq!(product, Product{ prod_id: 1, title: String::from("ACADEMY ACADEMY")});
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn find<T>(&self, primary_key: &T::PK) -> Result<T, Error>
+    where
+        T: Identifiable + FromSql,
+    {
+        let sql = generate_find_statement::<T>(self.table_name::<T>(), self.masking);
+        self.query_with(sql.as_str(), &[primary_key], Access::Read)
+            .await
+            .map_err(self.in_statement::<T>(StatementKind::Select, &sql))
+    }
+
+    ///
+    /// Find a single row of a table by its primary key, and lock it against updates and deletes
+    /// by other transactions with `FOR UPDATE`, until the end of the current transaction.
+    ///
+    /// Waits until the row is no longer locked by another transaction. Outside a transaction, the
+    /// lock is released as soon as the row is returned.
+    ///
+    /// Example:
+    /// ```no_run
+    /// use sprattus::*;
+    ///
+    /// #[derive(FromSql, ToSql, Debug)]
+    /// #[sprattus(table = "inventory")]
+    /// struct Inventory {
+    ///     #[sprattus(primary_key)]
+    ///     prod_id: i32,
+    ///     quan_in_stock: i32,
+    ///     sales: i32,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Error> {
+    ///     let conn = Connection::new("postgresql://localhost?user=tg").await?;
+    ///     let transaction = conn.transaction().await?;
+    ///     let mut inventory = transaction.find_for_update::<Inventory>(&1).await?;
+    ///     inventory.quan_in_stock -= 1;
+    ///     inventory.sales += 1;
+    ///     transaction.update(&inventory).await?;
+    ///     transaction.commit().await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn find_for_update<T>(&self, primary_key: &T::PK) -> Result<T, Error>
+    where
+        T: Identifiable + FromSql,
+    {
+        let sql = format!(
+            "{} FOR UPDATE",
+            generate_find_statement::<T>(self.table_name::<T>(), self.masking)
+        );
+        self.query(sql.as_str(), &[primary_key])
+            .await
+            .map_err(self.in_statement::<T>(StatementKind::Select, &sql))
+    }
+
+    ///
+    /// Find the rows of a table with the given primary keys.
+    ///
+    /// The keys are sent as a single array parameter, so any number of keys can be given.
+    ///
+    /// Example:
+    /// ```no_run
+    /// use sprattus::*;
+    ///
+    /// #[derive(FromSql, ToSql, Debug)]
+    /// struct Product {
+    ///     #[sprattus(primary_key)]
+    ///     prod_id: i32,
+    ///     title: String
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Error> {
+    ///     let conn = Connection::new("postgresql://localhost?user=tg").await?;
+    ///     let products = conn.find_multiple::<Product>(&[1, 2, 3]).await?;
+    ///     dbg!(products);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn find_multiple<T>(&self, primary_keys: &[T::PK]) -> Result<Vec<T>, Error>
+    where
+        T: Identifiable + FromSql,
+    {
+        let sql = format!(
+            "SELECT {fields} FROM {table_name} WHERE {primary_key} = ANY($1){tenant}",
+            fields = self.masking.columns::<T>(),
+            table_name = self.table_name::<T>(),
+            primary_key = T::get_primary_key(),
+            tenant = tenant_filter::<T>(None),
+        );
+        self.query_multiple_with(sql.as_str(), &[&primary_keys], Access::Read)
+            .await
+            .map_err(self.in_statement::<T>(StatementKind::Select, &sql))
+    }
+
+    ///
+    /// Find the rows of a table with the given primary keys, returned in the order of the keys.
+    ///
+    /// Every key results in the row with that key, or `None` when the table has no such row. A
+    /// key that is given more than once results in its row at every position, while the row is
+    /// only transferred once.
+    ///
+    /// Example:
+    /// ```no_run
+    /// use sprattus::*;
+    ///
+    /// #[derive(FromSql, ToSql, Debug)]
+    /// struct Product {
+    ///     #[sprattus(primary_key)]
+    ///     prod_id: i32,
+    ///     title: String
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Error> {
+    ///     let conn = Connection::new("postgresql://localhost?user=tg").await?;
+    ///     let products = conn.find_ordered::<Product>(&[3, -1, 1]).await?;
+    ///     assert_eq!(products[0].as_ref().map(|product| product.prod_id), Some(3));
+    ///     assert!(products[1].is_none());
+    ///     assert_eq!(products[2].as_ref().map(|product| product.prod_id), Some(1));
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn find_ordered<T>(&self, primary_keys: &[T::PK]) -> Result<Vec<Option<T>>, Error>
+    where
+        T: Identifiable + FromSql,
+        T::PK: for<'a> FromSqlItem<'a> + Eq + Hash,
+    {
+        let sql = format!(
+            "SELECT {fields},{primary_key} AS {key} FROM {table_name} \
+             WHERE {primary_key} = ANY($1){tenant}",
+            fields = self.masking.columns::<T>(),
+            primary_key = T::get_primary_key(),
+            key = KEY_COLUMN,
+            table_name = self.table_name::<T>(),
+            tenant = tenant_filter::<T>(None),
+        );
+        self.log_sql(sql.as_str(), &[&primary_keys]);
+        self.query_rows(sql.as_str(), &[&primary_keys], Access::Read)
+            .await
+            .and_then(|rows| {
+                let rows = rows
+                    .into_iter()
+                    .map(|row| Ok((row.try_get(KEY_COLUMN)?, row)))
+                    .collect::<Result<HashMap<T::PK, Row>, Error>>()?;
+                in_key_order(primary_keys, &rows, T::from_row)
+            })
+            .map_err(self.in_statement::<T>(StatementKind::Select, &sql))
+    }
+
+    ///
+    /// Find a single row of a table or view by the value of one of its fields.
+    ///
+    /// The field is given by its name in the Rust struct, and is translated into the name of the
+    /// column it is mapped to.
+    ///
+    /// # Panics
+    ///
+    /// Panics when the struct has no field with the given name.
+    ///
+    /// Example:
+    /// ```no_run
+    /// use sprattus::*;
+    ///
+    /// #[derive(FromSql, ToSql, Eq, PartialEq, Debug)]
+    /// struct Product {
+    ///     #[sprattus(primary_key)]
+    ///     prod_id: i32,
+    ///     title: String
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Error> {
+    ///     let conn = Connection::new("postgresql://localhost?user=tg").await?;
+    ///     let title = String::from("ACADEMY ACADEMY");
+    ///     let product = conn.find_by::<Product, _>("title", &title).await?;
+    ///     assert_eq!(product, Product{ prod_id: 1, title });
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn find_by<T, V>(&self, field: &str, value: &V) -> Result<T, Error>
+    where
+        T: Table + FromSql,
+        V: ToSqlItem + Sync,
+    {
+        let condition = generate_field_condition::<T>(field);
+        self.select::<T>()
+            .filter(condition.as_str(), &[value])
+            .fetch_one()
+            .await
+    }
+
+    ///
+    /// Find all rows of a table or view with the given value for one of its fields.
+    ///
+    /// The field is given by its name in the Rust struct, and is translated into the name of the
+    /// column it is mapped to.
+    ///
+    /// # Panics
+    ///
+    /// Panics when the struct has no field with the given name.
+    ///
+    /// Example:
+    /// ```no_run
+    /// use sprattus::*;
+    ///
+    /// #[derive(FromSql, ToSql, Debug)]
+    /// struct Product {
+    ///     #[sprattus(primary_key)]
+    ///     prod_id: i32,
+    ///     title: String,
+    ///     category: i32,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Error> {
+    ///     let conn = Connection::new("postgresql://localhost?user=tg").await?;
+    ///     let products = conn.find_all_by::<Product, _>("category", &5).await?;
+    ///     dbg!(products);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn find_all_by<T, V>(&self, field: &str, value: &V) -> Result<Vec<T>, Error>
+    where
+        T: Table + FromSql,
+        V: ToSqlItem + Sync,
+    {
+        let condition = generate_field_condition::<T>(field);
+        self.select::<T>()
+            .filter(condition.as_str(), &[value])
+            .fetch()
+            .await
+    }
+
+    ///
+    /// Returns the previous versions of a row, which are kept in the table given with the
+    /// `history_table` annotation, the most recent version first.
+    ///
+    /// Every update and delete of a row copies the row as it was before into the history table.
+    /// The history table has the columns of the struct, and a `changed_at` column that is set to
+    /// the time of the change by its default value, like `changed_at timestamptz NOT NULL DEFAULT
+    /// clock_timestamp()`.
+    ///
+    /// Returns an error when the struct has no history table.
+    ///
+    /// Example:
+    /// ```no_run
+    /// use sprattus::*;
+    ///
+    /// #[derive(FromSql, ToSql, Debug)]
+    /// #[sprattus(table = "products", history_table = "products_history")]
+    /// struct Product {
+    ///     #[sprattus(primary_key)]
+    ///     prod_id: i32,
+    ///     title: String
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Error> {
+    ///     let conn = Connection::new("postgresql://localhost?user=tg").await?;
+    ///     conn.update(&Product { prod_id: 50, title: String::from("Rust ORM") }).await?;
+    ///     for version in conn.history_of::<Product>(&50).await? {
+    ///         println!("was {}", version.title);
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn history_of<T>(&self, primary_key: &T::PK) -> Result<Vec<T>, Error>
+    where
+        T: Identifiable + FromSql,
+    {
+        let history_table = T::get_history_table().ok_or_else(|| {
+            Error::Config(format!(
+                "the table {} has no history table",
+                self.table_name::<T>()
+            ))
+        })?;
+        let sql = generate_history_statement::<T>(history_table, self.masking);
+        self.query_multiple_with(sql.as_str(), &[primary_key], Access::Read)
+            .await
+            .map_err(self.in_statement::<T>(StatementKind::Select, &sql))
+    }
+
+    ///
+    /// Update a single rust value in the database.
+    ///
+    /// Example:
+    /// ```no_run
+    /// use sprattus::*;
+    ///
+    /// #[derive(FromSql, ToSql, Eq, PartialEq, Debug)]
+    /// struct Product {
+    ///     #[sprattus(primary_key)]
+    ///     prod_id: i32,
+    ///     title: String
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Error> {
+    ///     let conn = Connection::new("postgresql://localhost?user=tg").await?;
+    ///     // Change a existing record in the database.
+    ///     conn.update(&Product { prod_id : 50, title: String::from("Rust ORM")}).await?;
+    ///
+    ///     let product : Product = conn.query("SELECT * FROM Products where prod_id = 50", &[]).await?;
+    ///     assert_eq!(product, Product{ prod_id: 50, title: String::from("Rust ORM")});
+    ///     // Change it back to it's original value.
+    ///     conn.update(&Product { prod_id : 50, title: String::from("ACADEMY BAKED")}).await?;
+    ///
+    ///     let product : Product = conn.query("SELECT * FROM Products where prod_id = 50", &[]).await?;
+    ///     assert_eq!(product, Product{ prod_id: 50, title: String::from("ACADEMY BAKED")});
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn update<T: traits::FromSql + traits::ToSql>(&self, item: &T) -> Result<T, Error> {
+        item.validate()?;
+        item.before_update()?;
+        let sql = self.update_statement::<T>("");
+        let before = self
+            .select_before_update(std::slice::from_ref(item))
+            .await?;
+        self.log_sql(sql.as_str(), item.get_update_params().as_slice());
+
+        let updated = self
+            .query_row(
+                sql.as_str(),
+                item.get_update_params().as_slice(),
+                Access::Write,
+            )
+            .await
+            .map_err(unique_violation::<T>)
+            .and_then(|row| T::from_row(&row))
+            .map_err(self.in_statement::<T>(StatementKind::Update, &sql))?;
+        updated.after_update();
+        self.notify_updates(&before, std::slice::from_ref(&updated));
+        Ok(updated)
+    }
+
+    ///
+    /// Updates a single rust value in the database, returning `None` when no row with its primary
+    /// key exists, where [`update`](#method.update) returns an error.
+    ///
+    /// Example:
+    /// ```no_run
+    /// use sprattus::*;
+    ///
+    /// #[derive(FromSql, ToSql, Debug)]
+    /// struct Product {
+    ///     #[sprattus(primary_key)]
+    ///     prod_id: i32,
+    ///     title: String
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Error> {
+    ///     let conn = Connection::new("postgresql://localhost?user=tg").await?;
+    ///     let product = Product { prod_id: 50, title: String::from("Rust ORM") };
+    ///     match conn.try_update(&product).await? {
+    ///         Some(product) => println!("updated {:?}", product),
+    ///         None => println!("product 50 does not exist"),
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn try_update<T>(&self, item: &T) -> Result<Option<T>, Error>
+    where
+        T: traits::FromSql + traits::ToSql,
+    {
+        item.validate()?;
+        item.before_update()?;
+        let sql = self.update_statement::<T>("");
+        let before = self
+            .select_before_update(std::slice::from_ref(item))
+            .await?;
+        self.log_sql(sql.as_str(), item.get_update_params().as_slice());
+        let updated = self
+            .query_rows(
+                sql.as_str(),
+                item.get_update_params().as_slice(),
+                Access::Write,
+            )
+            .await
+            .map_err(unique_violation::<T>)
+            .and_then(|rows| rows.first().map(T::from_row).transpose())
+            .map_err(self.in_statement::<T>(StatementKind::Update, &sql))?;
+        let updated = match updated {
+            Some(updated) => updated,
+            None => return Ok(None),
+        };
+        updated.after_update();
+        self.notify_updates(&before, std::slice::from_ref(&updated));
+        Ok(Some(updated))
+    }
+
+    ///
+    /// Updates a single rust value in the database, but only when its row also matches the given
+    /// condition, like a status guard. Returns `None` when no row matched, instead of an error.
+    ///
+    /// The value of the primary key is bound to `$1`, followed by the values of the updated
+    /// fields, so the placeholders of the condition start at `$n`, where `n` is
+    /// `T::get_update_argument_count() + 2`.
+    ///
+    /// Example:
+    /// ```no_run
+    /// use sprattus::*;
+    ///
+    /// #[derive(FromSql, ToSql, Debug)]
+    /// #[sprattus(table = "orders")]
+    /// struct Order {
+    ///     #[sprattus(primary_key)]
+    ///     orderid: i32,
+    ///     status: String,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Error> {
+    ///     let conn = Connection::new("postgresql://localhost?user=tg").await?;
+    ///     let order = Order { orderid: 1, status: String::from("shipped") };
+    ///     // Executes `UPDATE "orders" SET "status" = $2 WHERE "orderid" = $1
+    ///     // AND ("status" = $3) RETURNING *`.
+    ///     match conn.update_where(&order, "\"status\" = $3", &[&"paid"]).await? {
+    ///         Some(order) => println!("shipped {:?}", order),
+    ///         None => println!("the order is not paid yet"),
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn update_where<T>(
+        &self,
+        item: &T,
+        condition: &str,
+        args: &[&(dyn ToSqlItem + Sync)],
+    ) -> Result<Option<T>, Error>
+    where
+        T: traits::FromSql + traits::ToSql,
+    {
+        item.validate()?;
+        item.before_update()?;
+        let sql = self.update_statement::<T>(format!(" AND ({})", condition).as_str());
+        let mut params = item.get_update_params();
+        params.extend_from_slice(args);
+        let before = self
+            .select_before_update(std::slice::from_ref(item))
+            .await?;
+        self.log_sql(sql.as_str(), params.as_slice());
+        let updated = self
+            .query_rows(sql.as_str(), params.as_slice(), Access::Write)
+            .await
+            .map_err(unique_violation::<T>)
+            .and_then(|rows| rows.first().map(T::from_row).transpose())
+            .map_err(self.in_statement::<T>(StatementKind::Update, &sql))?;
+        let updated = match updated {
+            Some(updated) => updated,
+            None => return Ok(None),
+        };
+        updated.after_update();
+        self.notify_updates(&before, std::slice::from_ref(&updated));
+        Ok(Some(updated))
+    }
+
+    ///
+    /// Updates a single rust value in the database without returning the updated row.
+    ///
+    /// The result tells whether a row with the primary key of the item exists, without
+    /// transferring the row.
+    ///
+    /// Example:
+    /// ```no_run
+    /// use sprattus::*;
+    ///
+    /// #[derive(FromSql, ToSql, Debug)]
+    /// struct Product {
+    ///     #[sprattus(primary_key)]
+    ///     prod_id: i32,
+    ///     title: String
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Error> {
+    ///     let conn = Connection::new("postgresql://localhost?user=tg").await?;
+    ///     let product = Product { prod_id: 50, title: String::from("Rust ORM") };
+    ///     if conn.update_count(&product).await?.is_empty() {
+    ///         println!("product 50 does not exist");
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn update_count<T>(&self, item: &T) -> Result<ExecuteResult, Error>
+    where
+        T: traits::FromSql + traits::ToSql,
+    {
+        item.validate()?;
+        item.before_update()?;
+        let sql = generate_update_statement::<T>(self.table_name::<T>(), "");
+        let before = self
+            .select_before_update(std::slice::from_ref(item))
+            .await?;
+        let result = self
+            .execute(sql.as_str(), item.get_update_params().as_slice())
+            .await
+            .map_err(unique_violation::<T>)
+            .map_err(self.in_statement::<T>(StatementKind::Update, &sql))?;
+        // The updated row isn't returned, so the hook receives the values of the item.
+        if !result.is_empty() {
+            item.after_update();
+            self.notify_updates(&before, std::slice::from_ref(item));
+        }
+        Ok(result)
+    }
+
+    /// Returns the statement that updates a single row, with an additional condition appended to
+    /// the `WHERE` clause.
+    fn update_statement<T: traits::ToSql>(&self, condition: &str) -> String {
+        generate_update_returning_statement::<T>(self.table_name::<T>(), condition, self.returning)
+    }
+
+    ///
+    /// Update multiple rust values in the database.
+    ///
+    /// Example:
+    /// ```no_run
+    /// use sprattus::*;
+    ///
+    /// #[derive(FromSql, ToSql, Eq, PartialEq, Debug)]
+    /// struct Product {
+    ///     #[sprattus(primary_key)]
+    ///     prod_id: i32,
+    ///     title: String
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Error> {
+    ///     let conn = Connection::new("postgresql://localhost?user=tg").await?;
+    ///     let new_products = vec!(
+    ///             Product{ prod_id: 60, title: String::from("Rust ACADEMY") },
+    ///             Product{ prod_id: 61, title: String::from("SQL ACADEMY") },
+    ///             Product{ prod_id: 62, title: String::from("Backend development training") },
+    ///         );
+    ///     // Change a existing record in the database.
+    ///     conn.update_multiple(&new_products).await?;
+    ///     let sql = "SELECT * FROM Products where prod_id in (60, 61, 62)";
+    ///     let products: Vec<Product> = conn.query_multiple(sql, &[]).await?;
+    ///     assert_eq!(products, new_products);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn update_multiple<T>(&self, items: &[T]) -> Result<Vec<T>, Error>
+    where
+        T: Sized + ToSql + FromSql,
+    {
+        for item in items {
+            item.validate()?;
+            item.before_update()?;
+        }
+        // TODO: change this to a const fn, see https://github.com/rust-lang/rust/issues/57563
+        let sql_template = if T::get_update_argument_count() == 1 {
+            "UPDATE {table_name} AS P SET {fields} = temp_table.{inner_fields}{timestamps} FROM \
+             (VALUES {prepared_placeholders}) as temp_table({written_fields}) \
+             WHERE P.{primary_key} = temp_table.{primary_key}{tenant} \
+             RETURNING {returning}"
+        } else {
+            "UPDATE {table_name} AS P SET ({fields}) = (temp_table.{inner_fields}){timestamps} FROM \
+             (VALUES {prepared_placeholders}) as temp_table({written_fields}) \
+             WHERE P.{primary_key} = temp_table.{primary_key}{tenant} \
+             RETURNING {returning}"
+        };
+        let placeholders = generate_prepared_arguments_list_with_types::<T>(
+            T::get_argument_count() + 1,
+            items.len(),
+        );
+        let inner_fields = T::get_update_fields().replace(",", ",temp_table.");
+        let returning = self.returning.columns::<T>(Some("P"));
+        let timestamps = generate_update_timestamps::<T>();
+        let tenant = tenant_filter::<T>(Some("P"));
+        let mut sql_vars = HashMap::with_capacity(12);
+        sql_vars.insert(String::from("returning"), returning.as_str());
+        sql_vars.insert(String::from("timestamps"), timestamps.as_str());
+        sql_vars.insert(String::from("tenant"), tenant.as_str());
+        sql_vars.insert(String::from("table_name"), self.table_name::<T>());
+        sql_vars.insert(String::from("inner_fields"), inner_fields.as_str());
+        sql_vars.insert(String::from("fields"), T::get_update_fields());
+        sql_vars.insert(String::from("primary_key"), T::get_primary_key());
+        let written_fields = generate_written_fields::<T>();
+        sql_vars.insert(String::from("written_fields"), written_fields.as_str());
+        sql_vars.insert(String::from("prepared_placeholders"), placeholders.as_str());
+        let history = history_clause::<T>(
+            self.table_name::<T>(),
+            &format!(
+                "{primary_key} IN (SELECT temp_table.{primary_key} FROM (VALUES {placeholders}) \
+             AS temp_table({written_fields})){tenant}",
+                primary_key = T::get_primary_key(),
+                placeholders = placeholders,
+                written_fields = written_fields,
+                tenant = tenant_filter::<T>(None),
+            ),
+        );
+        let sql = history + &strfmt(sql_template, &sql_vars).unwrap();
+        let params: Vec<&(dyn ToSqlItem + Sync)> = items
+            .iter()
+            .flat_map(|item| item.get_values_of_all_fields())
+            .collect();
+        let before = self.select_before_update(items).await?;
+        self.log_sql(sql.as_str(), params.as_slice());
+        let updated: Vec<T> = self
+            .query_rows(sql.as_str(), params.as_slice(), Access::Write)
+            .map(|rows| from_rows(&rows.map_err(unique_violation::<T>)?))
+            .await
+            .map_err(self.in_statement::<T>(StatementKind::Update, &sql))?;
+        for item in &updated {
+            item.after_update();
+        }
+        self.notify_updates(&before, &updated);
+        Ok(updated)
+    }
+
+    ///
+    /// Adds an amount to a numeric field of a row, returning the updated row.
+    ///
+    /// The column is updated in a single statement, so concurrent increments can't overwrite
+    /// each other like they can when the row is read, modified and updated. Use a negative amount
+    /// to decrement the field. The type of the amount has to match the type of the column.
+    ///
+    /// The row is read before it is updated, to pass it to
+    /// [`Lifecycle::before_update`](trait.Lifecycle.html#method.before_update).
+    ///
+    /// # Panics
+    ///
+    /// Panics when the struct has no field with the given name.
+    ///
+    /// Example:
+    /// ```no_run
+    /// use sprattus::*;
+    ///
+    /// #[derive(FromSql, ToSql, Debug)]
+    /// #[sprattus(table = "inventory")]
+    /// struct Inventory {
+    ///     #[sprattus(primary_key)]
+    ///     prod_id: i32,
+    ///     quan_in_stock: i32,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Error> {
+    ///     let conn = Connection::new("postgresql://localhost?user=tg").await?;
+    ///     // Executes `UPDATE "inventory" SET "quan_in_stock" = "quan_in_stock" + $2
+    ///     // WHERE "prod_id" = $1 RETURNING *`.
+    ///     let inventory = conn.increment::<Inventory, _>(&1, "quan_in_stock", &-1).await?;
+    ///     dbg!(inventory);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn increment<T, V>(
+        &self,
+        primary_key: &T::PK,
+        field: &str,
+        amount: &V,
+    ) -> Result<T, Error>
+    where
+        T: ToSql + FromSql,
+        V: ToSqlItem + Sync,
+    {
+        let history = history_clause::<T>(
+            self.table_name::<T>(),
+            &format!("{} = $1{}", T::get_primary_key(), tenant_filter::<T>(None)),
+        );
+        let sql = format!(
+            "{history}UPDATE {table_name} SET {column} = {column} + $2{timestamps} \
+             WHERE {primary_key} = $1{tenant} RETURNING {returning}",
+            history = history,
+            table_name = self.table_name::<T>(),
+            column = column_name::<T>(field),
+            timestamps = generate_update_timestamps::<T>(),
+            primary_key = T::get_primary_key(),
+            tenant = tenant_filter::<T>(None),
+            returning = self.returning.columns::<T>(None),
+        );
+        let before = self.find::<T>(primary_key).await?;
+        before.before_update()?;
+        let updated: T = self
+            .query(sql.as_str(), &[primary_key, amount])
+            .await
+            .map_err(self.in_statement::<T>(StatementKind::Update, &sql))?;
+        updated.after_update();
+        self.notify_write(WriteOperation::Update, Some(&before), Some(&updated));
+        Ok(updated)
+    }
+
+    ///
+    /// Create a new row in the database.
+    ///
+    /// Example:
+    /// ```no_run
+    /// use sprattus::*;
+    ///
+    /// #[derive(FromSql, ToSql, Eq, PartialEq, Debug)]
+    /// struct Product {
+    ///     #[sprattus(primary_key)]
+    ///     prod_id: i32,
+    ///     title: String
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Error> {
+    ///     let conn = Connection::new("postgresql://localhost?user=tg").await?;
+    ///     let new_product = Product {prod_id: 0, title: String::from("Sql insert lesson")};
+    ///     let product = conn.create(&new_product).await?;
+    ///
+    ///     assert_eq!(new_product, product);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn create<T>(&self, item: &T) -> Result<T, Error>
+    where
+        T: Sized + ToSql + FromSql,
+    {
+        item.validate()?;
+        item.before_insert()?;
+        let (prepared_values, params) = generate_item_insert_rows(std::slice::from_ref(item));
+        let sql = generate_insert_statement::<T>(
+            self.table_name::<T>(),
+            &prepared_values,
+            self.returning,
+        );
+        self.log_sql(sql.as_str(), params.as_slice());
+
+        let created = self
+            .query_row(sql.as_str(), params.as_slice(), Access::Write)
+            .await
+            .map_err(unique_violation::<T>)
+            .and_then(|row| T::from_row(&row))
+            .map_err(self.in_statement::<T>(StatementKind::Insert, &sql))?;
+        created.after_insert();
+        self.notify_write(WriteOperation::Create, None, Some(&created));
+        Ok(created)
+    }
+
+    ///
+    /// Saves a rust value in the database, creating a new row when its primary key is not set and
+    /// updating its row otherwise. Returns the saved row, including the primary key that is
+    /// generated by the database for a new row.
+    ///
+    /// Whether the primary key is set is decided by
+    /// [`Identifiable::has_primary_key_value`](trait.Identifiable.html#method.has_primary_key_value),
+    /// which is `false` for a primary key that is `None` or `0`.
+    ///
+    /// Example:
+    /// ```no_run
+    /// use sprattus::*;
+    ///
+    /// #[derive(FromSql, ToSql, Debug)]
+    /// #[sprattus(table = "products")]
+    /// struct Product {
+    ///     #[sprattus(primary_key)]
+    ///     prod_id: Option<i32>,
+    ///     title: String,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Error> {
+    ///     let conn = Connection::new("postgresql://localhost?user=tg").await?;
+    ///     // Inserts a new row, of which the primary key is generated by the database.
+    ///     let mut product = conn
+    ///         .save(&Product { prod_id: None, title: String::from("Sql insert lesson") })
+    ///         .await?;
+    ///     product.title = String::from("Sql update lesson");
+    ///     // Updates the row.
+    ///     let product = conn.save(&product).await?;
+    ///     dbg!(product);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn save<T>(&self, item: &T) -> Result<T, Error>
+    where
+        T: ToSql + FromSql,
+    {
+        if item.has_primary_key_value() {
+            self.update(item).await
+        } else {
+            self.create(item).await
+        }
+    }
+
+    ///
+    /// Create a new row in the database, returning only its primary key.
+    ///
+    /// Example:
+    /// ```no_run
+    /// use sprattus::*;
+    ///
+    /// #[derive(FromSql, ToSql, Eq, PartialEq, Debug)]
+    /// struct Product {
+    ///     #[sprattus(primary_key)]
+    ///     prod_id: i32,
+    ///     title: String
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Error> {
+    ///     let conn = Connection::new("postgresql://localhost?user=tg").await?;
+    ///     let new_product = Product {prod_id: 0, title: String::from("Sql insert lesson")};
+    ///     let prod_id: i32 = conn.create_returning_key(&new_product).await?;
+    ///     dbg!(prod_id);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn create_returning_key<T>(&self, item: &T) -> Result<T::PK, Error>
+    where
+        T: ToSql,
+        T::PK: for<'a> FromSqlItem<'a>,
+    {
+        item.validate()?;
+        item.before_insert()?;
+        let (prepared_values, params) = generate_item_insert_rows(std::slice::from_ref(item));
+        let sql = format!(
+            "INSERT INTO {table_name} ({fields}) values {prepared_values} RETURNING {primary_key}",
+            table_name = self.table_name::<T>(),
+            fields = generate_insert_fields::<T>(T::inserts_primary_key()),
+            prepared_values = prepared_values,
+            primary_key = T::get_primary_key(),
+        );
+        self.log_sql(sql.as_str(), params.as_slice());
+        let primary_key: T::PK = self
+            .query_row(sql.as_str(), params.as_slice(), Access::Write)
+            .await
+            .map_err(unique_violation::<T>)
+            .and_then(|row| Ok(row.try_get(0)?))
+            .map_err(self.in_statement::<T>(StatementKind::Insert, &sql))?;
+        item.after_insert();
+        self.notify_create_key(item, &primary_key);
+        Ok(primary_key)
+    }
+
+    ///
+    /// Create a new row in the database from an insert model, which does not contain the primary key.
+    ///
+    /// Example:
+    /// ```no_run
+    /// use sprattus::*;
+    ///
+    /// #[derive(FromSql, ToSql, Eq, PartialEq, Debug)]
+    /// #[sprattus(insert_model)]
+    /// struct Product {
+    ///     #[sprattus(primary_key)]
+    ///     prod_id: i32,
+    ///     title: String
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Error> {
+    ///     let conn = Connection::new("postgresql://localhost?user=tg").await?;
+    ///     let new_product = NewProduct {title: String::from("Sql insert lesson")};
+    ///     let product: Product = conn.create_new(&new_product).await?;
+    ///
+    ///     assert_eq!(new_product.title, product.title);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn create_new<N>(&self, item: &N) -> Result<N::Model, Error>
+    where
+        N: InsertModel,
+    {
+        item.validate()?;
+        item.before_insert()?;
+        let (prepared_values, params) = generate_insert_rows::<N::Model, _>(std::iter::once((
+            item.get_query_params(),
+            item.get_insert_defaults(),
+        )));
+        let sql = format!(
+            "INSERT INTO {table_name} ({fields}) values {prepared_values} RETURNING {returning}",
+            table_name = self.table_name::<N::Model>(),
+            returning = self.returning.columns::<N::Model>(None),
+            fields = generate_insert_fields::<N::Model>(false),
+            prepared_values = prepared_values,
+        );
+        let created: N::Model = self
+            .query(sql.as_str(), params.as_slice())
+            .await
+            .map_err(unique_violation::<N::Model>)
+            .map_err(self.in_statement::<N::Model>(StatementKind::Insert, &sql))?;
+        created.after_insert();
+        self.notify_write(WriteOperation::Create, None, Some(&created));
+        Ok(created)
+    }
+
+    ///
+    /// Inserts a new row, without returning it. Unlike [`create`](#method.create), this only
+    /// needs an implementation of [`Insertable`](trait.Insertable.html), so rows can be added to
+    /// tables without a primary key, like a log.
+    ///
+    /// The primary key is never inserted, and the write hook is not notified, because the row
+    /// can't be identified.
+    ///
+    /// Example:
+    /// ```no_run
+    /// use sprattus::*;
+    ///
+    /// #[derive(ToSql, Debug)]
+    /// #[sprattus(table = "page_views", no_primary_key)]
+    /// struct PageView {
+    ///     path: String,
+    ///     #[sprattus(created_at)]
+    ///     viewed_at: Option<std::time::SystemTime>,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Error> {
+    ///     let conn = Connection::new("postgresql://localhost?user=tg").await?;
+    ///     let view = PageView { path: String::from("/products"), viewed_at: None };
+    ///     conn.insert(&view).await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn insert<T>(&self, item: &T) -> Result<ExecuteResult, Error>
+    where
+        T: Insertable + Lifecycle,
+    {
+        item.validate()?;
+        item.before_insert()?;
+        let (prepared_values, params) = generate_insert_rows::<T, _>(std::iter::once((
+            item.get_query_params(),
+            item.get_insert_defaults(),
+        )));
+        let sql = format!(
+            "INSERT INTO {table_name} ({fields}) values {prepared_values}",
+            table_name = self.table_name::<T>(),
+            fields = generate_insertable_fields::<T>(T::get_fields()),
+            prepared_values = prepared_values,
+        );
+        let result = self
+            .execute(sql.as_str(), params.as_slice())
+            .await
+            .map_err(self.in_statement::<T>(StatementKind::Insert, &sql))?;
+        item.after_insert();
+        Ok(result)
+    }
+
+    ///
+    /// Create new rows in the database.
+    ///
+    /// Example:
+    /// ```no_run
+    /// use sprattus::*;
+    ///
+    /// #[derive(FromSql, ToSql, Eq, PartialEq, Debug)]
+    /// struct Product {
+    ///     #[sprattus(primary_key)]
+    ///     prod_id: i32,
+    ///     title: String
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Error> {
+    ///     let conn = Connection::new("postgresql://localhost?user=tg").await?;
+    ///     let new_products = vec!(
+    ///         Product {prod_id: 0, title: String::from("Sql insert lesson")},
+    ///         Product {prod_id: 0, title: String::from("Rust macro lesson")},
+    ///         Product {prod_id: 0, title: String::from("Postgres data types lesson")}
+    ///     );
+    ///     let products = conn.create_multiple(&new_products).await?;
+    ///
+    ///     assert_eq!(&new_products, &products);
+    ///
+    ///     conn.delete_multiple(&products).await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn create_multiple<T>(&self, items: &[T]) -> Result<Vec<T>, Error>
+    where
+        T: Sized + ToSql + FromSql,
+    {
+        for item in items {
+            item.validate()?;
+            item.before_insert()?;
+        }
+        let (prepared_values, params) = generate_item_insert_rows(items);
+        let sql = format!(
+            "INSERT INTO {table_name} ({fields}) values {prepared_values} RETURNING {returning}",
+            table_name = self.table_name::<T>(),
+            returning = self.returning.columns::<T>(None),
+            fields = generate_insert_fields::<T>(T::inserts_primary_key()),
+            prepared_values = prepared_values,
+        );
+        self.log_sql(sql.as_str(), params.as_slice());
+        let created: Vec<T> = self
+            .query_rows(sql.as_str(), params.as_slice(), Access::Write)
+            .map(|rows| from_rows(&rows.map_err(unique_violation::<T>)?))
+            .await
+            .map_err(self.in_statement::<T>(StatementKind::Insert, &sql))?;
+        for item in &created {
+            item.after_insert();
+            self.notify_write(WriteOperation::Create, None, Some(item));
+        }
+        Ok(created)
+    }
+
+    ///
+    /// Inserts or updates multiple rows in a single statement, for example to synchronize a table
+    /// with an external source.
+    ///
+    /// Unlike [`create_multiple`](#method.create_multiple), the primary keys of the items are
+    /// inserted as well. Items with the primary key of an existing row are handled as described
+    /// by `on_conflict`. The written rows are returned, marked as inserted or updated. Rows that
+    /// are skipped because of `OnConflict::DoNothing` are not returned.
+    ///
+    /// # Panics
+    ///
+    /// Panics when a field of `OnConflict::UpdateColumns` does not exist.
+    ///
+    /// Example:
+    /// ```no_run
+    /// use sprattus::*;
+    ///
+    /// #[derive(FromSql, ToSql, Debug)]
+    /// struct Product {
+    ///     #[sprattus(primary_key)]
+    ///     prod_id: i32,
+    ///     title: String,
+    ///     price: f64,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Error> {
+    ///     let conn = Connection::new("postgresql://localhost?user=tg").await?;
+    ///     let products = vec![
+    ///         Product { prod_id: 1, title: String::from("ACADEMY ACADEMY"), price: 25.99 },
+    ///         Product { prod_id: 20001, title: String::from("RUST ACADEMY"), price: 9.99 },
+    ///     ];
+    ///     // Executes `INSERT INTO "Product" AS U ("prod_id","title","price") VALUES ($1,$2,$3),($4,$5,$6)
+    ///     // ON CONFLICT ("prod_id") DO UPDATE SET "price" = EXCLUDED."price"
+    ///     // RETURNING *, (xmax = 0) AS sprattus_inserted`.
+    ///     let rows = conn
+    ///         .upsert_multiple_with(&products, OnConflict::UpdateColumns(&["price"]))
+    ///         .await?;
+    ///     for row in rows {
+    ///         match row {
+    ///             Upserted::Inserted(product) => println!("inserted {:?}", product),
+    ///             Upserted::Updated(product) => println!("updated {:?}", product),
+    ///         }
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn upsert_multiple_with<T>(
+        &self,
+        items: &[T],
+        on_conflict: OnConflict<'_>,
+    ) -> Result<Vec<Upserted<T>>, Error>
+    where
+        T: Sized + ToSql + FromSql,
+    {
+        for item in items {
+            item.validate()?;
+            item.before_insert()?;
+        }
+        if items.is_empty() {
+            return Ok(Vec::new());
+        }
+        let (prepared_values, params) = generate_insert_rows::<T, _>(items.iter().map(|item| {
+            // The primary key is always inserted.
+            let defaults = insert_primary_key_default::<T>(item.get_insert_defaults(), false);
+            (item.get_values_of_all_fields(), defaults)
+        }));
+        let sql = format!(
+            "INSERT INTO {table_name} AS {alias} ({fields}) VALUES {prepared_values} \
+             {on_conflict} RETURNING {returning}, (xmax = 0) AS {inserted}",
+            table_name = self.table_name::<T>(),
+            alias = UPSERT_ALIAS,
+            fields = generate_insert_fields::<T>(true),
+            prepared_values = prepared_values,
+            on_conflict = generate_conflict_clause::<T>(on_conflict),
+            returning = self.returning.columns::<T>(None),
+            inserted = INSERTED_COLUMN,
+        );
+        let before = self.select_before_update(items).await?;
+        self.log_sql(sql.as_str(), params.as_slice());
+        let rows: Vec<(bool, T)> = self
+            .query_rows(sql.as_str(), params.as_slice(), Access::Write)
+            .await
+            .map_err(unique_violation::<T>)
+            .and_then(|rows| {
+                let inserted = rows
+                    .iter()
+                    .map(|row| row.try_get(INSERTED_COLUMN))
+                    .collect::<Result<Vec<bool>, _>>()?;
+                Ok(inserted.into_iter().zip(from_rows::<T>(&rows)?).collect())
+            })
+            .map_err(self.in_statement::<T>(StatementKind::Upsert, &sql))?;
+        let mut upserted = Vec::with_capacity(rows.len());
+        for (inserted, item) in rows {
+            if inserted {
+                item.after_insert();
+                self.notify_write(WriteOperation::Create, None, Some(&item));
+                upserted.push(Upserted::Inserted(item));
+            } else {
+                item.after_update();
+                self.notify_updates(&before, std::slice::from_ref(&item));
+                upserted.push(Upserted::Updated(item));
+            }
+        }
+        Ok(upserted)
+    }
+
+    ///
+    /// Create new rows in the database, returning only their primary keys in the order of the
+    /// items.
+    ///
+    /// This avoids sending and deserializing entire rows when only the generated keys are needed.
+    ///
+    /// Example:
+    /// ```no_run
+    /// use sprattus::*;
+    ///
+    /// #[derive(FromSql, ToSql, Eq, PartialEq, Debug)]
+    /// struct Product {
+    ///     #[sprattus(primary_key)]
+    ///     prod_id: i32,
+    ///     title: String
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Error> {
+    ///     let conn = Connection::new("postgresql://localhost?user=tg").await?;
+    ///     let new_products = vec!(
+    ///         Product {prod_id: 0, title: String::from("Sql insert lesson")},
+    ///         Product {prod_id: 0, title: String::from("Rust macro lesson")},
+    ///     );
+    ///     let prod_ids: Vec<i32> = conn.create_multiple_ids(&new_products).await?;
+    ///     dbg!(prod_ids);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn create_multiple_ids<T>(&self, items: &[T]) -> Result<Vec<T::PK>, Error>
+    where
+        T: ToSql,
+        T::PK: for<'a> FromSqlItem<'a>,
+    {
+        for item in items {
+            item.validate()?;
+            item.before_insert()?;
+        }
+        let (prepared_values, params) = generate_item_insert_rows(items);
+        let sql = format!(
+            "INSERT INTO {table_name} ({fields}) values {prepared_values} RETURNING {primary_key}",
+            table_name = self.table_name::<T>(),
+            fields = generate_insert_fields::<T>(T::inserts_primary_key()),
+            prepared_values = prepared_values,
+            primary_key = T::get_primary_key(),
+        );
+        self.log_sql(sql.as_str(), params.as_slice());
+        let primary_keys = self
+            .query_rows(sql.as_str(), params.as_slice(), Access::Write)
+            .await
+            .map_err(unique_violation::<T>)
+            .and_then(|rows| {
+                rows.iter()
+                    .map(|row| Ok(row.try_get(0)?))
+                    .collect::<Result<Vec<T::PK>, Error>>()
+            })
+            .map_err(self.in_statement::<T>(StatementKind::Insert, &sql))?;
+        for (item, primary_key) in items.iter().zip(&primary_keys) {
+            item.after_insert();
+            self.notify_create_key(item, primary_key);
+        }
+        Ok(primary_keys)
+    }
+
+    ///
+    /// Create new rows in the database, inserting every row under its own savepoint.
+    ///
+    /// Unlike [`create_multiple`](#method.create_multiple), a row that can't be inserted (e.g.
+    /// because it violates a constraint) does not abort the other rows. The result of every row
+    /// is returned in the order of the items.
+    ///
+    /// Example:
+    /// ```no_run
+    /// use sprattus::*;
+    ///
+    /// #[derive(FromSql, ToSql, Eq, PartialEq, Debug)]
+    /// struct Product {
+    ///     #[sprattus(primary_key)]
+    ///     prod_id: i32,
+    ///     title: String
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Error> {
+    ///     let conn = Connection::new("postgresql://localhost?user=tg").await?;
+    ///     let new_products = vec!(
+    ///         Product {prod_id: 0, title: String::from("Sql insert lesson")},
+    ///         Product {prod_id: 0, title: String::from("Rust macro lesson")},
+    ///     );
+    ///     for result in conn.create_multiple_lenient(&new_products).await? {
+    ///         match result {
+    ///             Ok(product) => println!("created {:?}", product),
+    ///             Err(error) => println!("failed: {}", error),
+    ///         }
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn create_multiple_lenient<T>(
+        &self,
+        items: &[T],
+    ) -> Result<Vec<Result<T, Error>>, Error>
+    where
+        T: Sized + ToSql + FromSql,
+    {
+        let transaction = self.transaction().await?;
+        let mut results = Vec::with_capacity(items.len());
+        for item in items {
+            let savepoint = transaction.transaction().await?;
+            let result = savepoint.create(item).await;
+            if result.is_ok() {
+                savepoint.commit().await?;
+            } else {
+                savepoint.rollback().await?;
+            }
+            results.push(result);
+        }
+        transaction.commit().await?;
+        Ok(results)
+    }
+
+    ///
+    /// Deletes a item.
+    ///
+    /// Example:
+    /// ```no_run
+    /// use sprattus::*;
+    ///
+    /// #[derive(FromSql, ToSql, Eq, PartialEq, Debug)]
+    /// struct Product {
+    ///     #[sprattus(primary_key)]
+    ///     prod_id: i32,
+    ///     title: String
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Error> {
+    ///     let conn = Connection::new("postgresql://localhost?user=tg").await?;
+    ///
+    ///     let new_product = Product {prod_id: 0, title: String::from("Sql insert lesson")};
+    ///     let product = conn.create(&new_product).await?;
+    ///     let deleted_product = conn.delete(&product).await?;
+    ///
+    ///     assert_eq!(&product, &deleted_product);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn delete<T: traits::FromSql + traits::ToSql>(&self, item: &T) -> Result<T, Error> {
+        item.before_delete()?;
+        let sql = generate_delete_statement::<T>(self.table_name::<T>(), self.returning);
+        self.log_sql(sql.as_str(), &[item.get_primary_key_value()]);
+        let deleted = self
+            .query_row(sql.as_str(), &[item.get_primary_key_value()], Access::Write)
+            .await
+            .and_then(|row| T::from_row(&row))
+            .map_err(self.in_statement::<T>(StatementKind::Delete, &sql))?;
+        deleted.after_delete();
+        self.notify_write(WriteOperation::Delete, Some(&deleted), None);
+        Ok(deleted)
+    }
+
+    ///
+    /// Deletes a item, returning `None` when its row is already gone, where
+    /// [`delete`](#method.delete) returns an error.
+    ///
+    /// Example:
+    /// ```no_run
+    /// use sprattus::*;
+    ///
+    /// #[derive(FromSql, ToSql, Debug)]
+    /// struct Product {
+    ///     #[sprattus(primary_key)]
+    ///     prod_id: i32,
+    ///     title: String
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Error> {
+    ///     let conn = Connection::new("postgresql://localhost?user=tg").await?;
+    ///     let product = conn.find::<Product>(&1).await?;
+    ///     assert!(conn.try_delete(&product).await?.is_some());
+    ///     // Deleting the row again is not an error.
+    ///     assert!(conn.try_delete(&product).await?.is_none());
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn try_delete<T>(&self, item: &T) -> Result<Option<T>, Error>
+    where
+        T: traits::FromSql + traits::ToSql,
+    {
+        item.before_delete()?;
+        let sql = generate_delete_statement::<T>(self.table_name::<T>(), self.returning);
+        self.log_sql(sql.as_str(), &[item.get_primary_key_value()]);
+        let deleted = self
+            .query_rows(sql.as_str(), &[item.get_primary_key_value()], Access::Write)
+            .await
+            .and_then(|rows| rows.first().map(T::from_row).transpose())
+            .map_err(self.in_statement::<T>(StatementKind::Delete, &sql))?;
+        let deleted = match deleted {
+            Some(deleted) => deleted,
+            None => return Ok(None),
+        };
+        deleted.after_delete();
+        self.notify_write(WriteOperation::Delete, Some(&deleted), None);
+        Ok(Some(deleted))
+    }
+
+    ///
+    /// Deletes a item, but only when its row also matches the given condition, like a status
+    /// guard. Returns `None` when no row matched, instead of an error.
+    ///
+    /// The value of the primary key is bound to `$1`, so the placeholders of the condition start
+    /// at `$2`.
+    ///
+    /// Example:
+    /// ```no_run
+    /// use sprattus::*;
+    ///
+    /// #[derive(FromSql, ToSql, Debug)]
+    /// #[sprattus(table = "orders")]
+    /// struct Order {
+    ///     #[sprattus(primary_key)]
+    ///     orderid: i32,
+    ///     status: String,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Error> {
+    ///     let conn = Connection::new("postgresql://localhost?user=tg").await?;
+    ///     let order = Order { orderid: 1, status: String::from("draft") };
+    ///     // Executes `DELETE FROM "orders" WHERE "orderid" IN ($1) AND ("status" = $2) RETURNING *`.
+    ///     let deleted = conn.delete_where(&order, "\"status\" = $2", &[&"draft"]).await?;
+    ///     assert!(deleted.is_some());
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn delete_where<T>(
+        &self,
+        item: &T,
+        condition: &str,
+        args: &[&(dyn ToSqlItem + Sync)],
+    ) -> Result<Option<T>, Error>
+    where
+        T: traits::FromSql + traits::ToSql,
+    {
+        item.before_delete()?;
+        let history = history_clause::<T>(
+            self.table_name::<T>(),
+            &format!(
+                "{} IN ($1){} AND ({})",
+                T::get_primary_key(),
+                tenant_filter::<T>(None),
+                condition
+            ),
+        );
+        let sql = format!(
+            "{history}DELETE FROM {table_name} WHERE {primary_key} IN ($1){tenant} AND ({condition}) RETURNING {returning}",
+            history = history,
+            table_name = self.table_name::<T>(),
+            returning = self.returning.columns::<T>(None),
+            primary_key = T::get_primary_key(),
+            tenant = tenant_filter::<T>(None),
+            condition = condition,
+        );
+        let mut params: Vec<&(dyn ToSqlItem + Sync)> = vec![item.get_primary_key_value()];
+        params.extend_from_slice(args);
+        self.log_sql(sql.as_str(), params.as_slice());
+        let deleted = self
+            .query_rows(sql.as_str(), params.as_slice(), Access::Write)
+            .await
+            .and_then(|rows| rows.first().map(T::from_row).transpose())
+            .map_err(self.in_statement::<T>(StatementKind::Delete, &sql))?;
+        let deleted = match deleted {
+            Some(deleted) => deleted,
+            None => return Ok(None),
+        };
+        deleted.after_delete();
+        self.notify_write(WriteOperation::Delete, Some(&deleted), None);
+        Ok(Some(deleted))
+    }
+
+    ///
+    /// Deletes a item without returning the deleted row.
+    ///
+    /// The result tells whether a row with the primary key of the item existed, without
+    /// transferring the row.
+    ///
+    /// Example:
+    /// ```no_run
+    /// use sprattus::*;
+    ///
+    /// #[derive(FromSql, ToSql, Debug)]
+    /// struct Product {
+    ///     #[sprattus(primary_key)]
+    ///     prod_id: i32,
+    ///     title: String
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Error> {
+    ///     let conn = Connection::new("postgresql://localhost?user=tg").await?;
+    ///     let product = Product { prod_id: 50, title: String::from("Rust ORM") };
+    ///     let result = conn.delete_count(&product).await?;
+    ///     assert_eq!(result.rows_affected, 1);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn delete_count<T>(&self, item: &T) -> Result<ExecuteResult, Error>
+    where
+        T: traits::FromSql + traits::ToSql,
+    {
+        self.delete_multiple_count(std::slice::from_ref(item)).await
+    }
+
+    ///
+    /// Deletes a list of items.
+    ///
+    /// Example:
+    /// ```no_run
+    /// use sprattus::*;
+    ///
+    /// #[derive(FromSql, ToSql, Eq, PartialEq, Debug)]
+    /// struct Product {
+    ///     #[sprattus(primary_key)]
+    ///     prod_id: i32,
+    ///     title: String
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Error> {
+    ///     let conn = Connection::new("postgresql://localhost?user=tg").await?;
+    ///     let new_products = vec!(
+    ///         Product {prod_id: 0, title: String::from("Sql insert lesson")},
+    ///         Product {prod_id: 0, title: String::from("Rust macro lesson")},
+    ///         Product {prod_id: 0, title: String::from("Postgres data types lesson")}
+    ///     );
+    ///     let created_products = conn.create_multiple(&new_products).await?;
+    ///
+    ///     let deleted_products = conn.delete_multiple(&created_products).await?;
+    ///     assert_eq!(&created_products, &deleted_products);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn delete_multiple<T>(&self, items: &[T]) -> Result<Vec<T>, Error>
+    where
+        T: traits::FromSql + traits::ToSql,
+    {
+        for item in items {
+            item.before_delete()?;
+        }
+        let sql = format!(
+            "{history}DELETE FROM {table_name} WHERE {primary_key} = ANY($1){tenant} RETURNING {returning}",
+            history = history_clause::<T>(self.table_name::<T>(), &generate_any_condition::<T>()),
+            table_name = self.table_name::<T>(),
+            returning = self.returning.columns::<T>(None),
+            primary_key = T::get_primary_key(),
+            tenant = tenant_filter::<T>(None),
+        );
+        let primary_keys: Vec<&T::PK> = items
+            .iter()
+            .map(|item| item.get_primary_key_value())
+            .collect();
+        let deleted: Vec<T> = self
+            .query_multiple(sql.as_str(), &[&primary_keys])
+            .await
+            .map_err(self.in_statement::<T>(StatementKind::Delete, &sql))?;
+        for item in &deleted {
+            item.after_delete();
+            self.notify_write(WriteOperation::Delete, Some(item), None);
+        }
+        Ok(deleted)
+    }
+
+    ///
+    /// Deletes a list of items without returning the deleted rows, returning the number of
+    /// deleted rows instead.
+    ///
+    /// Example:
+    /// ```no_run
+    /// use sprattus::*;
+    ///
+    /// #[derive(FromSql, ToSql, Debug)]
+    /// struct Product {
+    ///     #[sprattus(primary_key)]
+    ///     prod_id: i32,
+    ///     title: String
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Error> {
+    ///     let conn = Connection::new("postgresql://localhost?user=tg").await?;
+    ///     let products: Vec<Product> = conn.query_multiple("SELECT * FROM Products LIMIT 3", &[]).await?;
+    ///     let result = conn.delete_multiple_count(&products).await?;
+    ///     println!("deleted {} products", result.rows_affected);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn delete_multiple_count<T>(&self, items: &[T]) -> Result<ExecuteResult, Error>
+    where
+        T: traits::FromSql + traits::ToSql,
+    {
+        for item in items {
+            item.before_delete()?;
+        }
+        let sql = format!(
+            "{history}DELETE FROM {table_name} WHERE {primary_key} = ANY($1){tenant}",
+            history = history_clause::<T>(self.table_name::<T>(), &generate_any_condition::<T>()),
+            table_name = self.table_name::<T>(),
+            primary_key = T::get_primary_key(),
+            tenant = tenant_filter::<T>(None),
+        );
+        let primary_keys: Vec<&T::PK> = items
+            .iter()
+            .map(|item| item.get_primary_key_value())
+            .collect();
+        // The deleted rows aren't returned, so the hook receives the rows as they were selected
+        // before the delete.
+        let before = self.select_before_update(items).await?;
+        let result = self
+            .execute(sql.as_str(), &[&primary_keys])
+            .await
+            .map_err(self.in_statement::<T>(StatementKind::Delete, &sql))?;
+        // The deleted rows aren't returned, so the lifecycle hooks receive the items.
+        for item in items {
+            item.after_delete();
+        }
+        for item in &before {
+            self.notify_write(WriteOperation::Delete, Some(item), None);
+        }
+        Ok(result)
+    }
+
+    ///
+    /// Loads the rows of a fixture into the table of a struct, returning the created rows.
+    ///
+    /// The rows are created within a single transaction. Foreign keys that are declared as
+    /// `DEFERRABLE` are checked when the transaction is committed, other foreign keys require the
+    /// fixtures of referenced tables to be loaded first.
+    ///
+    /// Example:
+    /// ```no_run
+    /// use sprattus::*;
+    /// use serde::Deserialize;
+    ///
+    /// #[derive(FromSql, ToSql, Deserialize, Debug)]
+    /// #[sprattus(table = "products")]
+    /// struct Product {
+    ///     #[sprattus(primary_key)]
+    ///     prod_id: i32,
+    ///     title: String
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Error> {
+    ///     let conn = Connection::new("postgresql://localhost?user=tg").await?;
+    ///     let fixture = r#"[{"prod_id": 1, "title": "Rust ORM"}]"#;
+    ///     let products = conn.load_fixtures::<Product>(FixtureSource::Json(fixture)).await?;
+    ///     dbg!(products);
+    ///     Ok(())
+    /// }
+    /// ```
+    #[cfg(any(feature = "fixtures-json", feature = "fixtures-yaml"))]
+    pub async fn load_fixtures<T>(&self, source: FixtureSource<'_>) -> Result<Vec<T>, Error>
+    where
+        T: ToSql + FromSql + serde::de::DeserializeOwned,
+    {
+        crate::fixtures::load_fixtures(self, source).await
+    }
+
+    ///
+    /// Removes all rows from the table of a struct.
+    ///
+    /// Example:
+    /// ```no_run
+    /// use sprattus::*;
+    ///
+    /// #[derive(FromSql, ToSql, Debug)]
+    /// #[sprattus(table = "products")]
+    /// struct Product {
+    ///     #[sprattus(primary_key)]
+    ///     prod_id: i32,
+    ///     title: String
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Error> {
+    ///     let conn = Connection::new("postgresql://localhost?user=tg").await?;
+    ///     conn.truncate::<Product>().await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn truncate<T>(&self) -> Result<(), Error>
+    where
+        T: ToSql,
+    {
+        self.batch_execute(format!("TRUNCATE TABLE {}", self.table_name::<T>()).as_str())
+            .await
+    }
+
+    ///
+    /// Removes all rows from the given tables and restarts their identity columns and sequences.
+    ///
+    /// With [`Truncate::Cascade`](enum.Truncate.html#variant.Cascade), tables with foreign keys
+    /// referencing the given tables are truncated as well.
+    ///
+    /// Example:
+    /// ```no_run
+    /// use sprattus::*;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Error> {
+    ///     let conn = Connection::new("postgresql://localhost?user=tg").await?;
+    ///     // Executes `TRUNCATE TABLE "orders","customers" RESTART IDENTITY CASCADE`.
+    ///     conn.truncate_tables(&["orders", "customers"], Truncate::Cascade).await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn truncate_tables(&self, tables: &[&str], truncate: Truncate) -> Result<(), Error> {
+        let tables: Vec<String> = tables.iter().map(|table| table_ident(table)).collect();
+        let sql = format!(
+            "TRUNCATE TABLE {tables} RESTART IDENTITY {behavior}",
+            tables = tables.join(","),
+            behavior = truncate.as_sql(),
+        );
+        self.batch_execute(sql.as_str()).await
+    }
+
+    ///
+    /// Sets the sequence of the primary key of a table to continue after the highest key in the
+    /// table, for example after rows were created with explicit keys.
+    ///
+    /// Example:
+    /// ```no_run
+    /// use sprattus::*;
+    ///
+    /// #[derive(FromSql, ToSql, Debug)]
+    /// #[sprattus(table = "products")]
+    /// struct Product {
+    ///     #[sprattus(primary_key)]
+    ///     prod_id: i32,
+    ///     title: String
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Error> {
+    ///     let conn = Connection::new("postgresql://localhost?user=tg").await?;
+    ///     conn.reset_sequence::<Product>().await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn reset_sequence<T>(&self) -> Result<(), Error>
+    where
+        T: ToSql,
+    {
+        let sql = format!(
+            "SELECT setval(pg_get_serial_sequence($1, $2), COALESCE(MAX({primary_key}), 0) + 1, false) \
+             FROM {table_name}",
+            primary_key = T::get_primary_key(),
+            table_name = self.table_name::<T>(),
+        );
+        // The column name is passed without quotes, the table name is parsed as an identifier.
+        let column_name = T::get_primary_key().trim_matches('"').replace("\"\"", "\"");
+        self.execute(sql.as_str(), &[&self.table_name::<T>(), &column_name])
+            .await?;
+        Ok(())
+    }
+
+    ///
+    /// Starts a new transaction.
+    ///
+    /// Example:
+    /// ```no_run
+    /// use sprattus::*;
+    ///
+    /// #[derive(FromSql, ToSql, Eq, PartialEq, Debug)]
+    /// struct Product {
+    ///     #[sprattus(primary_key)]
+    ///     prod_id: i32,
+    ///     title: String
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Error> {
+    ///     let conn = Connection::new("postgresql://localhost?user=tg").await?;
+    ///     let transaction = conn.transaction().await?;
+    ///     transaction.create(&Product {prod_id: 0, title: String::from("Sql insert lesson")}).await?;
+    ///     transaction.commit().await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn transaction(&self) -> Result<Transaction, Error> {
+        Transaction::begin(self, "BEGIN").await
+    }
+
+    ///
+    /// Starts a new transaction with the given isolation level and access mode.
+    ///
+    /// When a transaction is already open, a savepoint is created instead, which keeps the
+    /// options of the open transaction.
+    ///
+    /// Example:
+    /// ```no_run
+    /// use sprattus::*;
+    ///
+    ///# #[tokio::main]
+    ///# async fn main() -> Result<(), Error> {
+    /// let conn = Connection::new("postgresql://localhost?user=tg").await?;
+    /// // Executes `BEGIN ISOLATION LEVEL REPEATABLE READ READ ONLY`.
+    /// let transaction = conn
+    ///     .transaction_with(TransactionOptions {
+    ///         isolation: Some(IsolationLevel::RepeatableRead),
+    ///         read_only: true,
+    ///         ..TransactionOptions::default()
+    ///     })
+    ///     .await?;
+    /// transaction.commit().await?;
+    ///# return Ok(())
+    ///# }
+    /// ```
+    pub async fn transaction_with(
+        &self,
+        options: TransactionOptions,
+    ) -> Result<Transaction, Error> {
+        Transaction::begin(self, options.begin_statement().as_str()).await
+    }
+
+    ///
+    /// Runs a function within a serializable transaction, which is committed when the function
+    /// succeeds. When the function or the commit fails with a serialization failure (SQLSTATE
+    /// `40001`), the transaction is rolled back and the function is run again, up to 5 times.
+    ///
+    /// The function receives a clone of the connection, on which all statements are part of the
+    /// transaction.
+    ///
+    /// Example:
+    /// ```no_run
+    /// use sprattus::*;
+    ///
+    /// #[derive(FromSql, ToSql, Debug)]
+    /// struct Account {
+    ///     #[sprattus(primary_key)]
+    ///     id: i32,
+    ///     balance: i64,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Error> {
+    ///     let conn = Connection::new("postgresql://localhost?user=tg").await?;
+    ///     let account = conn
+    ///         .retry_serializable(|conn| async move {
+    ///             let mut account = conn.find::<Account>(&1).await?;
+    ///             account.balance += 10;
+    ///             conn.update(&account).await
+    ///         })
+    ///         .await?;
+    ///     dbg!(account);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn retry_serializable<F, Fut, R>(&self, function: F) -> Result<R, Error>
+    where
+        F: FnMut(Connection) -> Fut,
+        Fut: Future<Output = Result<R, Error>>,
+    {
+        crate::transaction::retry_serializable(self, function).await
+    }
+
+    ///
+    /// Runs a function within a transaction in which statements are cancelled when they run
+    /// longer than the given timeout, even when the server has no default statement timeout.
+    /// The transaction is committed when the function succeeds and rolled back when it fails.
+    ///
+    /// The function receives a clone of the connection, on which all statements are part of the
+    /// transaction. A statement that exceeds the timeout fails with SQLSTATE `57014`
+    /// (`query_canceled`).
+    ///
+    /// # Note
+    ///
+    /// When a transaction is already open, the timeout applies until the end of that transaction.
+    ///
+    /// Example:
+    /// ```no_run
+    /// use sprattus::*;
+    /// use std::time::Duration;
+    ///
+    /// #[derive(FromSql, Debug)]
+    /// struct Report {
+    ///     category: i32,
+    ///     revenue: f64,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Error> {
+    ///     let conn = Connection::new("postgresql://localhost?user=tg").await?;
+    ///     let reports: Vec<Report> = conn
+    ///         .with_statement_timeout(Duration::from_secs(5), |conn| async move {
+    ///             conn.query_multiple(
+    ///                 "SELECT category, SUM(price)::FLOAT8 AS revenue FROM products GROUP BY category",
+    ///                 &[],
+    ///             )
+    ///             .await
+    ///         })
+    ///         .await?;
+    ///     dbg!(reports);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn with_statement_timeout<F, Fut, R>(
+        &self,
+        timeout: Duration,
+        function: F,
+    ) -> Result<R, Error>
+    where
+        F: FnOnce(Connection) -> Fut,
+        Fut: Future<Output = Result<R, Error>>,
+    {
+        // A timeout of 0 disables the timeout, so it is at least a millisecond.
+        let milliseconds = timeout.as_millis().max(1);
+        let transaction = self.transaction().await?;
+        transaction
+            .batch_execute(format!("SET LOCAL statement_timeout = {}", milliseconds).as_str())
+            .await?;
+        match function(Connection::clone(&transaction)).await {
+            Ok(value) => transaction.commit().await.map(|_| value),
+            Err(error) => {
+                transaction.rollback().await?;
+                Err(error)
+            }
+        }
+    }
+
+    ///
+    /// Starts a transaction in which the `app.tenant_id` setting holds the given tenant, so
+    /// row-level security policies of Postgres can restrict the rows to the ones of the tenant.
+    ///
+    /// Queries generated for structs with a `tenant_key` field are restricted to the current
+    /// tenant as well. Executing these queries outside of a tenant transaction results in an
+    /// error, because the setting is missing.
+    ///
+    /// Example:
+    /// ```no_run
+    /// use sprattus::*;
+    ///
+    /// #[derive(FromSql, ToSql, Debug)]
+    /// #[sprattus(table = "documents")]
+    /// struct Document {
+    ///     #[sprattus(primary_key)]
+    ///     id: i32,
+    ///     #[sprattus(tenant_key)]
+    ///     tenant_id: i32,
+    ///     body: String,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Error> {
+    ///     let conn = Connection::new("postgresql://localhost?user=tg").await?;
+    ///     let transaction = conn.with_tenant(42).await?;
+    ///     // Executes `SELECT "id","tenant_id","body" FROM "documents"
+    ///     // WHERE "tenant_id" = current_setting('app.tenant_id')::INT`.
+    ///     let documents = transaction.fetch_all::<Document>().await?;
+    ///     dbg!(documents);
+    ///     transaction.commit().await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn with_tenant(&self, tenant: impl ToString) -> Result<Transaction, Error> {
+        let tenant = tenant.to_string();
+        let transaction = self.transaction().await?;
+        transaction
+            .execute(
+                "SELECT set_config($1, $2, true)",
+                &[&TENANT_SETTING, &tenant],
+            )
+            .await?;
+        Ok(transaction)
+    }
+
+    ///
+    /// Declares a server side cursor for a query, which fetches the result in batches of
+    /// `batch_size` rows. This allows processing large tables with constant memory usage.
+    ///
+    /// Example:
+    /// ```no_run
+    /// use sprattus::*;
+    ///
+    /// #[derive(FromSql, Eq, PartialEq, Debug)]
+    /// struct Product {
+    ///     #[sprattus(primary_key)]
+    ///     prod_id: i32,
+    ///     title: String
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Error> {
+    ///     let conn = Connection::new("postgresql://localhost?user=tg").await?;
+    ///     let mut cursor = conn.cursor::<Product>("SELECT * FROM Products", &[], 1000).await?;
+    ///     while let Some(products) = cursor.next_batch().await? {
+    ///         dbg!(products.len());
+    ///     }
+    ///     cursor.close().await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn cursor<T>(
         &self,
         sql: &str,
         args: &[&(dyn ToSqlItem + Sync)],
-    ) -> Result<Vec<T>, Error>
+        batch_size: u32,
+    ) -> Result<Cursor<T>, Error>
     where
         T: FromSql,
     {
-        self.client
-            .query(sql, args)
-            .map(|rows| rows?.iter().map(|row| T::from_row(row)).collect())
-            .await
+        Cursor::declare(self, sql, args, batch_size).await
     }
 
     ///
-    /// Get a single row of a table.
+    /// Refreshes a materialized view, which replaces its rows with the current result of its
+    /// query.
+    ///
+    /// A concurrent refresh doesn't lock out queries on the view while it runs, but requires a
+    /// unique index on the view.
     ///
     /// Example:
     /// ```no_run
     /// use sprattus::*;
-    /// use tokio::prelude::*;
     ///
-    /// #[derive(FromSql, Eq, PartialEq, Debug)]
-    /// struct Product {
-    ///     #[sql(primary_key)]
-    ///     prod_id: i32,
-    ///     title: String
+    /// #[derive(ToSql, FromSql)]
+    /// #[sprattus(materialized_view = "product_summary")]
+    /// struct ProductSummary {
+    ///     category: i32,
+    ///     product_count: i64,
     /// }
     ///
     /// #[tokio::main]
     /// async fn main() -> Result<(), Error> {
     ///     let conn = Connection::new("postgresql://localhost?user=tg").await?;
-    ///     let product : Product = conn.query("SELECT * FROM Products LIMIT 1", &[]).await?;
-    ///     assert_eq!(product, Product{ prod_id: 1, title: String::from("ACADEMY ACADEMY")});
+    ///     conn.refresh_materialized_view::<ProductSummary>(true).await?;
     ///     Ok(())
     /// }
     /// ```
-    pub async fn query<T>(&self, sql: &str, args: &[&(dyn ToSqlItem + Sync)]) -> Result<T, Error>
+    pub async fn refresh_materialized_view<T>(&self, concurrently: bool) -> Result<(), Error>
     where
-        T: FromSql,
+        T: MaterializedView,
     {
-        let client = &self.client;
-        T::from_row(&client.query_one(sql, args).await?)
+        let sql = materialized_view::refresh_statement::<T>(concurrently);
+        self.execute(sql.as_str(), &[]).await?;
+        Ok(())
     }
 
     ///
-    /// Update a single rust value in the database.
+    /// Refreshes a materialized view after every interval, until the returned schedule is stopped
+    /// or dropped. A failed refresh is logged, and tried again after the next interval.
     ///
     /// Example:
     /// ```no_run
     /// use sprattus::*;
-    /// use tokio::prelude::*;
+    /// use std::time::Duration;
     ///
-    /// #[derive(FromSql, ToSql, Eq, PartialEq, Debug)]
-    /// struct Product {
-    ///     #[sql(primary_key)]
-    ///     prod_id: i32,
-    ///     title: String
+    /// #[derive(ToSql, FromSql)]
+    /// #[sprattus(materialized_view = "product_summary")]
+    /// struct ProductSummary {
+    ///     category: i32,
+    ///     product_count: i64,
     /// }
     ///
     /// #[tokio::main]
     /// async fn main() -> Result<(), Error> {
     ///     let conn = Connection::new("postgresql://localhost?user=tg").await?;
-    ///     // Change a existing record in the database.
-    ///     conn.update(&Product { prod_id : 50, title: String::from("Rust ORM")}).await?;
-    ///
-    ///     let product : Product = conn.query("SELECT * FROM Products where prod_id = 50", &[]).await?;
-    ///     assert_eq!(product, Product{ prod_id: 50, title: String::from("Rust ORM")});
-    ///     // Change it back to it's original value.
-    ///     conn.update(&Product { prod_id : 50, title: String::from("ACADEMY BAKED")}).await?;
-    ///
-    ///     let product : Product = conn.query("SELECT * FROM Products where prod_id = 50", &[]).await?;
-    ///     assert_eq!(product, Product{ prod_id: 50, title: String::from("ACADEMY BAKED")});
+    ///     let schedule = conn
+    ///         .refresh_materialized_view_every::<ProductSummary>(Duration::from_secs(60), true);
+    ///     tokio::time::sleep(Duration::from_secs(300)).await;
+    ///     schedule.stop();
     ///     Ok(())
     /// }
     /// ```
-    pub async fn update<T: traits::FromSql + traits::ToSql>(&self, item: &T) -> Result<T, Error>
+    #[cfg(feature = "runtime-tokio")]
+    pub fn refresh_materialized_view_every<T>(
+        &self,
+        interval: Duration,
+        concurrently: bool,
+    ) -> RefreshSchedule
     where
-        <T as traits::ToSql>::PK: tokio_postgres::types::ToSql,
+        T: MaterializedView,
     {
-        // FIXME: change this to a const fn, see https://github.com/rust-lang/rust/issues/57563
-        let sql_template = if T::get_prepared_arguments_list() == "$1" {
-            "UPDATE {table_name} SET {fields} = {prepared_values} WHERE {primary_key} = $1 RETURNING *"
-        } else {
-            "UPDATE {table_name} SET ({fields}) = ({prepared_values}) WHERE {primary_key} = $1 RETURNING *"
-        };
-        let mut sql_vars = HashMap::with_capacity(12);
-        sql_vars.insert(String::from("table_name"), T::get_table_name());
-        sql_vars.insert(String::from("fields"), T::get_fields());
-        sql_vars.insert(String::from("primary_key"), T::get_primary_key());
-        let prepared_values =
-            generate_single_prepared_arguments_list(2, T::get_argument_count() + 1);
-        sql_vars.insert(String::from("prepared_values"), prepared_values.as_ref());
-        let sql = strfmt(sql_template, &sql_vars).unwrap();
-        let client = &self.client;
-
-        T::from_row(
-            &client
-                .query_one(sql.as_str(), item.get_values_of_all_fields().as_slice())
-                .await?,
-        )
+        materialized_view::schedule::<T>(self, interval, concurrently)
     }
 
     ///
-    /// Update multiple rust values in the database.
+    /// Returns a [`Prepare`](struct.Prepare.html), which verifies queries against this database
+    /// and writes their metadata for builds without database access.
     ///
     /// Example:
     /// ```no_run
     /// use sprattus::*;
-    /// use tokio::prelude::*;
     ///
-    /// #[derive(FromSql, ToSql, Eq, PartialEq, Debug)]
+    /// #[derive(FromSql, Debug)]
     /// struct Product {
-    ///     #[sql(primary_key)]
     ///     prod_id: i32,
-    ///     title: String
+    ///     title: String,
     /// }
     ///
     /// #[tokio::main]
     /// async fn main() -> Result<(), Error> {
     ///     let conn = Connection::new("postgresql://localhost?user=tg").await?;
-    ///     let new_products = vec!(
-    ///             Product{ prod_id: 60, title: String::from("Rust ACADEMY") },
-    ///             Product{ prod_id: 61, title: String::from("SQL ACADEMY") },
-    ///             Product{ prod_id: 62, title: String::from("Backend development training") },
-    ///         );
-    ///     // Change a existing record in the database.
-    ///     conn.update_multiple(&new_products).await?;
-    ///     let sql = "SELECT * FROM Products where prod_id in (60, 61, 62)";
-    ///     let products: Vec<Product> = conn.query_multiple(sql, &[]).await?;
-    ///     assert_eq!(products, new_products);
+    ///     conn.prepare_offline()
+    ///         .query(&sql!(Product, "SELECT prod_id, title FROM products"))
+    ///         .write(OFFLINE_DATA_FILE)
+    ///         .await?;
     ///     Ok(())
     /// }
     /// ```
-    pub async fn update_multiple<T>(&self, items: &[T]) -> Result<Vec<T>, Error>
-    where
-        T: Sized + ToSql + FromSql,
-    {
-        // TODO: change this to a const fn, see https://github.com/rust-lang/rust/issues/57563
-        let sql_template = if T::get_prepared_arguments_list() == "$1" {
-            "UPDATE {table_name} AS P SET {fields} = temp_table.{inner_fields} FROM \
-             (VALUES {prepared_placeholders}) as temp_table({all_fields}) \
-             WHERE P.{primary_key} = temp_table.{primary_key} \
-             RETURNING *"
-        } else {
-            "UPDATE {table_name} AS P SET ({fields}) = (temp_table.{inner_fields}) FROM \
-             (VALUES {prepared_placeholders}) as temp_table({all_fields}) \
-             WHERE P.{primary_key} = temp_table.{primary_key} \
-             RETURNING *"
-        };
-        let placeholders = generate_prepared_arguments_list_with_types::<T>(
-            T::get_argument_count() + 1,
-            items.len(),
-        );
-        let inner_fields = T::get_fields().replace(",", ",temp_table.");
-        let mut sql_vars = HashMap::with_capacity(12);
-        sql_vars.insert(String::from("table_name"), T::get_table_name());
-        sql_vars.insert(String::from("inner_fields"), inner_fields.as_str());
-        sql_vars.insert(String::from("fields"), T::get_fields());
-        sql_vars.insert(String::from("primary_key"), T::get_primary_key());
-        sql_vars.insert(String::from("all_fields"), T::get_all_fields());
-        sql_vars.insert(String::from("prepared_placeholders"), placeholders.as_str());
-        let sql = strfmt(sql_template, &sql_vars).unwrap();
-        let params: Vec<&(dyn ToSqlItem + Sync)> = items
-            .iter()
-            .map(|item| item.get_values_of_all_fields())
-            .flatten()
-            .collect();
-        let client = &self.client;
-        client
-            .query(sql.as_str(), params.as_slice())
-            .map(|rows| rows?.iter().map(|row| T::from_row(row)).collect())
-            .await
+    #[cfg(feature = "offline")]
+    pub fn prepare_offline(&self) -> Prepare<'_> {
+        Prepare::new(self)
     }
 
     ///
-    /// Create a new row in the database.
+    /// Listens for notifications on a channel.
     ///
     /// Example:
     /// ```no_run
     /// use sprattus::*;
-    /// use tokio::prelude::*;
+    /// use futures_util::stream::StreamExt;
     ///
-    /// #[derive(FromSql, ToSql, Eq, PartialEq, Debug)]
-    /// struct Product {
-    ///     #[sql(primary_key)]
-    ///     prod_id: i32,
-    ///     title: String
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Error> {
+    ///     let conn = Connection::new("postgresql://localhost?user=tg").await?;
+    ///     let mut notifications = conn.listen("events").await?;
+    ///     conn.execute("SELECT pg_notify('events', 'hello')", &[]).await?;
+    ///     let notification = notifications.next().await.unwrap();
+    ///     assert_eq!(notification.payload(), "hello");
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn listen(&self, channel: &str) -> Result<impl Stream<Item = Notification>, Error> {
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        self.listeners.lock().unwrap().push(Listener {
+            channel: channel.to_string(),
+            sender,
+        });
+        self.batch_execute(format!("LISTEN {}", ident(channel)).as_str())
+            .await?;
+        Ok(stream::poll_fn(move |context| receiver.poll_recv(context)))
+    }
+
+    ///
+    /// Listens for notifications on a channel, decoding their payloads from JSON into T.
+    ///
+    /// A payload that can't be decoded doesn't end the stream, it is returned as an
+    /// [`Error::MalformedPayload`](enum.Error.html#variant.MalformedPayload) with the channel and
+    /// the payload, so it can be logged or skipped.
+    ///
+    /// Example:
+    /// ```no_run
+    /// use futures_util::stream::StreamExt;
+    /// use serde::Deserialize;
+    /// use sprattus::*;
+    ///
+    /// #[derive(Deserialize, Debug)]
+    /// struct OrderPlaced {
+    ///     order_id: i32,
+    ///     customer_id: i32,
     /// }
     ///
     /// #[tokio::main]
     /// async fn main() -> Result<(), Error> {
     ///     let conn = Connection::new("postgresql://localhost?user=tg").await?;
-    ///     let new_product = Product {prod_id: 0, title: String::from("Sql insert lesson")};
-    ///     let product = conn.create(&new_product).await?;
-    ///
-    ///     assert_eq!(new_product, product);
+    ///     let mut events = Box::pin(conn.listen_typed::<OrderPlaced>("orders").await?);
+    ///     conn.execute(
+    ///         r#"SELECT pg_notify('orders', '{"order_id": 1, "customer_id": 7}')"#,
+    ///         &[],
+    ///     )
+    ///     .await?;
+    ///     while let Some(event) = events.next().await {
+    ///         match event {
+    ///             Ok(event) => println!("order {} placed", event.order_id),
+    ///             Err(error) => eprintln!("skipping a notification: {}", error),
+    ///         }
+    ///     }
     ///     Ok(())
     /// }
     /// ```
-    pub async fn create<T>(&self, item: &T) -> Result<T, Error>
+    #[cfg(feature = "with-serde_json-1")]
+    pub async fn listen_typed<T>(
+        &self,
+        channel: &str,
+    ) -> Result<impl Stream<Item = Result<T, Error>>, Error>
     where
-        T: Sized + ToSql + FromSql,
+        T: serde::de::DeserializeOwned,
     {
-        let sql = format!(
-            "INSERT INTO {table_name} ({fields}) values ({prepared_values}) RETURNING *",
-            table_name = T::get_table_name(),
-            fields = T::get_fields(),
-            prepared_values = T::get_prepared_arguments_list(),
-        );
-        let client = &self.client;
-
-        T::from_row(
-            &client
-                .query_one(sql.as_str(), item.get_query_params().as_slice())
-                .await?,
-        )
+        let notifications = self.listen(channel).await?;
+        Ok(notifications
+            .map(|notification| decode_payload(notification.channel(), notification.payload())))
     }
 
     ///
-    /// Create new rows in the database.
+    /// Reads a large object, returning its content as a stream of chunks, so it doesn't have to
+    /// be held in memory at once.
+    ///
+    /// The large object is read within a transaction, which is committed when the stream ends.
     ///
     /// Example:
     /// ```no_run
+    /// use futures_util::stream::TryStreamExt;
     /// use sprattus::*;
-    /// use tokio::prelude::*;
-    ///
-    /// #[derive(FromSql, ToSql, Eq, PartialEq, Debug)]
-    /// struct Product {
-    ///     #[sql(primary_key)]
-    ///     prod_id: i32,
-    ///     title: String
-    /// }
     ///
     /// #[tokio::main]
     /// async fn main() -> Result<(), Error> {
     ///     let conn = Connection::new("postgresql://localhost?user=tg").await?;
-    ///     let new_products = vec!(
-    ///         Product {prod_id: 0, title: String::from("Sql insert lesson")},
-    ///         Product {prod_id: 0, title: String::from("Rust macro lesson")},
-    ///         Product {prod_id: 0, title: String::from("Postgres data types lesson")}
-    ///     );
-    ///     let products = conn.create_multiple(&new_products).await?;
+    ///     let oid: u32 = 16_400;
+    ///     let mut chunks = Box::pin(conn.read_large_object(oid).await?);
+    ///     let mut size = 0;
+    ///     while let Some(chunk) = chunks.try_next().await? {
+    ///         size += chunk.len();
+    ///     }
+    ///     dbg!(size);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn read_large_object(
+        &self,
+        oid: u32,
+    ) -> Result<impl Stream<Item = Result<Vec<u8>, Error>>, Error> {
+        crate::large_object::read(self, oid).await
+    }
+
     ///
-    ///     assert_eq!(&new_products, &products);
+    /// Creates a large object with the content of a stream of chunks, returning its oid.
     ///
-    ///     conn.delete_multiple(&products).await?;
+    /// The large object is written within a transaction, so it is removed again when the stream
+    /// returns an error, which is returned as [`Error::Source`](enum.Error.html#variant.Source).
+    ///
+    /// Example:
+    /// ```no_run
+    /// use futures_util::stream;
+    /// use sprattus::*;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Error> {
+    ///     let conn = Connection::new("postgresql://localhost?user=tg").await?;
+    ///     let chunks: Vec<Result<Vec<u8>, std::io::Error>> = vec![Ok(vec![1, 2]), Ok(vec![3])];
+    ///     let oid = conn.write_large_object(stream::iter(chunks)).await?;
+    ///     dbg!(oid);
     ///     Ok(())
     /// }
     /// ```
-    pub async fn create_multiple<T>(&self, items: &[T]) -> Result<Vec<T>, Error>
+    pub async fn write_large_object<S, B, E>(&self, chunks: S) -> Result<u32, Error>
     where
-        T: Sized + ToSql + FromSql,
+        S: Stream<Item = Result<B, E>>,
+        B: AsRef<[u8]>,
+        E: Into<Box<dyn std::error::Error + Sync + Send>>,
     {
-        let sql = format!(
-            "INSERT INTO {table_name} ({fields}) values {prepared_values} RETURNING *",
-            table_name = T::get_table_name(),
-            fields = T::get_fields(),
-            prepared_values =
-                generate_prepared_arguments_list(T::get_argument_count(), items.len()),
-        );
+        crate::large_object::write(self, chunks).await
+    }
 
-        let params: Vec<&(dyn ToSqlItem + Sync)> = items
-            .iter()
-            .map(|item| item.get_query_params())
-            .flatten()
-            .collect();
-        let client = &self.client;
-        client
-            .query(sql.as_str(), params.as_slice())
-            .map(|rows| rows?.iter().map(|row| T::from_row(row)).collect())
-            .await
+    ///
+    /// Removes a large object.
+    ///
+    pub async fn delete_large_object(&self, oid: u32) -> Result<(), Error> {
+        self.execute("SELECT lo_unlink($1)", &[&oid]).await?;
+        Ok(())
     }
 
     ///
-    /// Deletes a item.
+    /// Reads the `BYTEA` column of a field of the row with the given primary key, returning its
+    /// content as a stream of chunks, so it doesn't have to be held in memory at once.
+    ///
+    /// The chunks are read within a `REPEATABLE READ` transaction, so they belong to the same
+    /// version of the row. The transaction is committed when the stream ends. A column that is
+    /// `NULL` results in an empty stream.
+    ///
+    /// Postgres compresses large values by default, so every chunk decompresses the value up to
+    /// its end. Set the storage of the column to `EXTERNAL` to read chunks without decompressing.
+    ///
+    /// # Panics
+    ///
+    /// Panics when the struct has no field with the given name.
     ///
     /// Example:
     /// ```no_run
+    /// use futures_util::stream::TryStreamExt;
     /// use sprattus::*;
-    /// use tokio::prelude::*;
     ///
-    /// #[derive(FromSql, ToSql, Eq, PartialEq, Debug)]
-    /// struct Product {
-    ///     #[sql(primary_key)]
-    ///     prod_id: i32,
-    ///     title: String
+    /// #[derive(FromSql, ToSql, Debug)]
+    /// #[sprattus(table = "attachments")]
+    /// struct Attachment {
+    ///     #[sprattus(primary_key)]
+    ///     id: i32,
+    ///     #[sprattus(pg_type = "BYTEA")]
+    ///     content: Vec<u8>,
     /// }
     ///
     /// #[tokio::main]
     /// async fn main() -> Result<(), Error> {
     ///     let conn = Connection::new("postgresql://localhost?user=tg").await?;
-    ///
-    ///     let new_product = Product {prod_id: 0, title: String::from("Sql insert lesson")};
-    ///     let product = conn.create(&new_product).await?;
-    ///     let deleted_product = conn.delete(&product).await?;
-    ///
-    ///     assert_eq!(&product, &deleted_product);
+    ///     let mut chunks = Box::pin(conn.read_bytea::<Attachment>(&1, "content").await?);
+    ///     while let Some(chunk) = chunks.try_next().await? {
+    ///         dbg!(chunk.len());
+    ///     }
     ///     Ok(())
     /// }
     /// ```
-    pub async fn delete<T: traits::FromSql + traits::ToSql>(&self, item: &T) -> Result<T, Error>
+    pub async fn read_bytea<'a, T>(
+        &self,
+        primary_key: &'a T::PK,
+        field: &str,
+    ) -> Result<impl Stream<Item = Result<Vec<u8>, Error>> + 'a, Error>
     where
-        <T as traits::ToSql>::PK: tokio_postgres::types::ToSql + Sync,
+        T: Identifiable,
     {
-        let sql = format!(
-            "DELETE FROM {table_name} WHERE {primary_key} IN ($1) RETURNING *",
-            table_name = T::get_table_name(),
-            primary_key = T::get_primary_key()
-        );
-        let client = &self.client;
-        T::from_row(
-            &client
-                .query_one(sql.as_str(), &[&item.get_primary_key_value()])
-                .await?,
-        )
+        crate::large_object::read_bytea::<T>(self, primary_key, field).await
     }
 
     ///
-    /// Deletes a list of items.
+    /// Watches the table of T for changes.
+    ///
+    /// This installs a trigger on the table that sends every inserted, updated and deleted row as a
+    /// notification, which are returned as a stream of [`Change`](enum.Change.html) events.
+    /// Because the rows are sent as notification payloads, rows larger than 8000 bytes cannot be
+    /// watched.
     ///
     /// Example:
     /// ```no_run
     /// use sprattus::*;
-    /// use tokio::prelude::*;
+    /// use futures_util::stream::StreamExt;
     ///
     /// #[derive(FromSql, ToSql, Eq, PartialEq, Debug)]
     /// struct Product {
-    ///     #[sql(primary_key)]
+    ///     #[sprattus(primary_key)]
     ///     prod_id: i32,
     ///     title: String
     /// }
@@ -444,54 +3734,212 @@ impl Connection {
     /// #[tokio::main]
     /// async fn main() -> Result<(), Error> {
     ///     let conn = Connection::new("postgresql://localhost?user=tg").await?;
-    ///     let new_products = vec!(
-    ///         Product {prod_id: 0, title: String::from("Sql insert lesson")},
-    ///         Product {prod_id: 0, title: String::from("Rust macro lesson")},
-    ///         Product {prod_id: 0, title: String::from("Postgres data types lesson")}
-    ///     );
-    ///     let created_products = conn.create_multiple(&new_products).await?;
-    ///
-    ///     let deleted_products = conn.delete_multiple(&created_products).await?;
-    ///     assert_eq!(&created_products, &deleted_products);
+    ///     let mut changes = Box::pin(conn.watch::<Product>().await?);
+    ///     while let Some(change) = changes.next().await {
+    ///         match change? {
+    ///             Change::Inserted(product) => println!("inserted {:?}", product),
+    ///             Change::Updated(product) => println!("updated {:?}", product),
+    ///             Change::Deleted(prod_id) => println!("deleted {}", prod_id),
+    ///         }
+    ///     }
     ///     Ok(())
     /// }
     /// ```
-    pub async fn delete_multiple<P, T>(&self, items: &[T]) -> Result<Vec<T>, Error>
+    pub async fn watch<T>(&self) -> Result<impl Stream<Item = Result<Change<T>, Error>>, Error>
     where
-        P: tokio_postgres::types::ToSql,
-        T: traits::FromSql + traits::ToSql<PK = P>,
-        <T as traits::ToSql>::PK: Sync,
+        T: FromSql + ToSql,
+        T::PK: for<'a> FromSqlItem<'a>,
     {
-        let sql = format!(
-            "DELETE FROM {table_name} WHERE {primary_key} IN ({argument_list}) RETURNING *",
-            table_name = T::get_table_name(),
-            primary_key = T::get_primary_key(),
-            argument_list = generate_single_prepared_arguments_list(1, items.len())
-        );
-        let params: Vec<P> = items
-            .iter()
-            .map(|item| item.get_primary_key_value())
-            .collect();
-        let p = params
-            .iter()
-            .map(|i| i as &(dyn tokio_postgres::types::ToSql + Sync))
-            .collect::<Vec<_>>();
-        let client = &self.client;
-        client
-            .query(sql.as_str(), p.as_slice())
-            .map(|rows| rows?.iter().map(|row| T::from_row(row)).collect())
-            .await
+        crate::watch::watch(self).await
     }
 }
 ///
-/// Generates a string of prepared statement placeholder arguments.
+/// Generates the statement that inserts a single row with the given values, returning the
+/// created row.
 ///
-fn generate_prepared_arguments_list(item_length: usize, no_of_items: usize) -> String {
-    let mut arguments_list: String = String::new();
-    let range_end = item_length * no_of_items + 1;
+pub(crate) fn generate_insert_statement<T: ToSql>(
+    table_name: &str,
+    prepared_values: &str,
+    returning: Returning,
+) -> String {
+    format!(
+        "INSERT INTO {table_name} ({fields}) values {prepared_values} RETURNING {returning}",
+        table_name = table_name,
+        returning = returning.columns::<T>(None),
+        fields = generate_insert_fields::<T>(T::inserts_primary_key()),
+        prepared_values = prepared_values,
+    )
+}
 
-    complete_prepared_arguments_list(&mut arguments_list, 1, range_end, item_length);
-    arguments_list
+///
+/// Generates the row of placeholders of an insert of a single item, in which every value is
+/// bound to a parameter.
+///
+pub(crate) fn generate_insert_placeholders<T: ToSql>() -> String {
+    let primary_key_count = if T::inserts_primary_key() { 1 } else { 0 };
+    let mut row = vec![generate_single_prepared_arguments_list(
+        1,
+        T::get_argument_count() + primary_key_count,
+    )];
+    let timestamp_count = T::get_created_at_fields().len() + T::get_updated_at_fields().len();
+    row.extend(std::iter::repeat_n(String::from("now()"), timestamp_count));
+    row.retain(|value| !value.is_empty());
+    format!("({})", row.join(","))
+}
+
+/// Generates the statement that selects a single row by its primary key.
+pub(crate) fn generate_find_statement<T: Identifiable>(
+    table_name: &str,
+    masking: Masking,
+) -> String {
+    format!(
+        "SELECT {fields} FROM {table_name} WHERE {primary_key} = $1{tenant}",
+        fields = masking.columns::<T>(),
+        table_name = table_name,
+        primary_key = T::get_primary_key(),
+        tenant = tenant_filter::<T>(None),
+    )
+}
+
+/// Generates the statement that deletes a single row by its primary key, returning the deleted
+/// row.
+pub(crate) fn generate_delete_statement<T: ToSql>(
+    table_name: &str,
+    returning: Returning,
+) -> String {
+    let condition = format!(
+        "{} IN ($1){}",
+        T::get_primary_key(),
+        tenant_filter::<T>(None)
+    );
+    format!(
+        "{history}DELETE FROM {table_name} WHERE {primary_key} IN ($1){tenant} RETURNING {returning}",
+        history = history_clause::<T>(table_name, &condition),
+        table_name = table_name,
+        returning = returning.columns::<T>(None),
+        primary_key = T::get_primary_key(),
+        tenant = tenant_filter::<T>(None),
+    )
+}
+
+///
+/// Generates the list of columns of an insert, followed by the timestamp columns. With the primary
+/// key, the columns are the ones of `ToSql::get_values_of_all_fields()`, in the order in which the
+/// fields are declared.
+///
+fn generate_insert_fields<T: ToSql>(primary_key: bool) -> String {
+    if primary_key {
+        generate_insertable_fields::<T>(&generate_written_fields::<T>())
+    } else {
+        generate_insertable_fields::<T>(T::get_fields())
+    }
+}
+
+///
+/// Generates the list of columns of an insert of a struct that may not have a primary key, from
+/// the given written columns followed by the timestamp columns.
+///
+fn generate_insertable_fields<T: Insertable>(written_fields: &str) -> String {
+    let mut fields = vec![written_fields];
+    fields.extend(T::get_created_at_fields());
+    fields.extend(T::get_updated_at_fields());
+    fields.retain(|field| !field.is_empty());
+    fields.join(",")
+}
+
+///
+/// Generates the list of the columns of the values of `ToSql::get_values_of_all_fields()`: the
+/// primary key and the fields of `get_fields()`, in the order in which they are declared.
+///
+fn generate_written_fields<T: ToSql>() -> String {
+    T::get_columns()
+        .iter()
+        .filter(|column| column.primary_key || !column.generated)
+        .map(|column| ident(column.sql_name))
+        .collect::<Vec<String>>()
+        .join(",")
+}
+
+///
+/// Inserts whether the primary key is inserted as `DEFAULT` into the list of `DEFAULT` values
+/// of `get_insert_defaults()`, at the position of the primary key among the values of
+/// `ToSql::get_values_of_all_fields()`.
+///
+fn insert_primary_key_default<T: ToSql>(mut defaults: Vec<bool>, primary_key: bool) -> Vec<bool> {
+    if defaults.is_empty() && !primary_key {
+        return defaults;
+    }
+    let position = T::get_columns()
+        .iter()
+        .filter(|column| column.primary_key || !column.generated)
+        .position(|column| column.primary_key)
+        .unwrap_or(0);
+    if defaults.len() < position {
+        defaults.resize(position, false);
+    }
+    defaults.insert(position, primary_key);
+    defaults
+}
+
+///
+/// Generates the rows of an insert of the given items, together with the parameters of the
+/// statement. When the struct inserts its primary key, the primary key is inserted as `DEFAULT`
+/// when it is not set.
+///
+fn generate_item_insert_rows<T: ToSql>(items: &[T]) -> (String, Vec<&(dyn ToSqlItem + Sync)>) {
+    generate_insert_rows::<T, _>(items.iter().map(|item| {
+        if T::inserts_primary_key() {
+            let defaults = insert_primary_key_default::<T>(
+                item.get_insert_defaults(),
+                !item.has_primary_key_value(),
+            );
+            (item.get_values_of_all_fields(), defaults)
+        } else {
+            (item.get_query_params(), item.get_insert_defaults())
+        }
+    }))
+}
+
+///
+/// Generates the rows of an insert from the values of every row and whether each of them is
+/// inserted as `DEFAULT`, together with the parameters of the statement. The values that are not
+/// inserted as `DEFAULT` become placeholders, followed by `now()` for every timestamp column.
+///
+fn generate_insert_rows<'a, T, I>(rows: I) -> (String, Vec<&'a (dyn ToSqlItem + Sync)>)
+where
+    T: Insertable,
+    I: IntoIterator<Item = (Vec<&'a (dyn ToSqlItem + Sync)>, Vec<bool>)>,
+{
+    let timestamp_count = T::get_created_at_fields().len() + T::get_updated_at_fields().len();
+    let mut params = Vec::new();
+    let rows: Vec<String> = rows
+        .into_iter()
+        .map(|(values, defaults)| {
+            let mut row = Vec::with_capacity(values.len() + timestamp_count);
+            for (i, value) in values.into_iter().enumerate() {
+                if defaults.get(i).cloned().unwrap_or(false) {
+                    row.push(String::from("DEFAULT"));
+                } else {
+                    params.push(value);
+                    row.push(format!("${}", params.len()));
+                }
+            }
+            row.extend(std::iter::repeat_n(String::from("now()"), timestamp_count));
+            format!("({})", row.join(","))
+        })
+        .collect();
+    (rows.join(","), params)
+}
+
+///
+/// Generates the assignments setting the `updated_at` columns to `now()`, to be appended to the
+/// `SET` clause of an update.
+///
+fn generate_update_timestamps<T: Insertable>() -> String {
+    T::get_updated_at_fields()
+        .iter()
+        .map(|field| format!(", {} = now()", field))
+        .collect()
 }
 
 fn generate_prepared_arguments_list_with_types<T>(item_length: usize, no_of_items: usize) -> String
@@ -528,7 +3976,7 @@ fn complete_prepared_arguments_list(
             arguments_list.push(',');
         }
         arguments_list.push('$');
-        arguments_list.push_str(&*i.to_string());
+        arguments_list.push_str(&i.to_string());
     }
     arguments_list.push(')');
 }
@@ -537,10 +3985,134 @@ fn generate_single_prepared_arguments_list(start_num: usize, end_num: usize) ->
     let mut arguments_list: String = String::new();
     for i in start_num..=end_num {
         arguments_list.push('$');
-        arguments_list.push_str(&*i.to_string());
+        arguments_list.push_str(&i.to_string());
         if i != end_num {
             arguments_list.push(',');
         }
     }
     arguments_list
 }
+
+fn search_path_statement(schemas: &[String]) -> String {
+    if schemas.is_empty() {
+        return String::from("SET search_path TO DEFAULT");
+    }
+    let schemas: Vec<String> = schemas.iter().map(|schema| ident(schema)).collect();
+    format!("SET search_path TO {}", schemas.join(","))
+}
+
+/// Generates the condition matching the rows of which the primary key is in the array bound to
+/// `$1`.
+fn generate_any_condition<T: Identifiable>() -> String {
+    format!(
+        "{} = ANY($1){}",
+        T::get_primary_key(),
+        tenant_filter::<T>(None)
+    )
+}
+
+fn generate_field_condition<T: Table>(field: &str) -> String {
+    format!("{} = $1", column_name::<T>(field))
+}
+
+/// Generates the statement that updates a single row, returning the updated row, with an
+/// additional condition appended to the `WHERE` clause.
+pub(crate) fn generate_update_returning_statement<T: ToSql>(
+    table_name: &str,
+    condition: &str,
+    returning: Returning,
+) -> String {
+    format!(
+        "{} RETURNING {}",
+        generate_update_statement::<T>(table_name, condition),
+        returning.columns::<T>(None)
+    )
+}
+
+/// Converts the rows by key in the order of the keys, with `None` for a key without a row. A key
+/// that is given more than once gets a value at every position.
+fn in_key_order<K, R, T>(
+    keys: &[K],
+    rows: &HashMap<K, R>,
+    convert: impl Fn(&R) -> Result<T, Error>,
+) -> Result<Vec<Option<T>>, Error>
+where
+    K: Eq + Hash,
+{
+    keys.iter()
+        .map(|key| rows.get(key).map(&convert).transpose())
+        .collect()
+}
+
+/// Generates the statement that updates a single row, without a `RETURNING` clause, with an
+/// additional condition appended to the `WHERE` clause.
+fn generate_update_statement<T: ToSql>(table_name: &str, condition: &str) -> String {
+    // FIXME: change this to a const fn, see https://github.com/rust-lang/rust/issues/57563
+    let sql_template = if T::get_update_argument_count() == 1 {
+        "UPDATE {table_name} SET {fields} = {prepared_values}{timestamps} WHERE {primary_key} = $1{tenant}{condition}"
+    } else {
+        "UPDATE {table_name} SET ({fields}) = ({prepared_values}){timestamps} WHERE {primary_key} = $1{tenant}{condition}"
+    };
+    let timestamps = generate_update_timestamps::<T>();
+    let tenant = tenant_filter::<T>(None);
+    let mut sql_vars = HashMap::with_capacity(12);
+    sql_vars.insert(String::from("timestamps"), timestamps.as_str());
+    sql_vars.insert(String::from("tenant"), tenant.as_str());
+    sql_vars.insert(String::from("condition"), condition);
+    sql_vars.insert(String::from("table_name"), table_name);
+    sql_vars.insert(String::from("fields"), T::get_update_fields());
+    sql_vars.insert(String::from("primary_key"), T::get_primary_key());
+    let prepared_values =
+        generate_single_prepared_arguments_list(2, T::get_update_argument_count() + 1);
+    sql_vars.insert(String::from("prepared_values"), prepared_values.as_ref());
+    let history = history_clause::<T>(
+        table_name,
+        &format!("{} = $1{}{}", T::get_primary_key(), tenant, condition),
+    );
+    history + &strfmt(sql_template, &sql_vars).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn orders_rows_by_key() {
+        let rows = HashMap::from([(1, "one"), (2, "two"), (3, "three")]);
+        let ordered = in_key_order(&[3, 1, 2], &rows, |row| Ok(row.to_string())).unwrap();
+        assert_eq!(
+            ordered,
+            vec![
+                Some(String::from("three")),
+                Some(String::from("one")),
+                Some(String::from("two"))
+            ]
+        );
+    }
+
+    #[test]
+    fn repeats_rows_of_duplicate_keys() {
+        let rows = HashMap::from([(1, "one"), (2, "two")]);
+        let ordered = in_key_order(&[2, 1, 2], &rows, |row| Ok(*row)).unwrap();
+        assert_eq!(ordered, vec![Some("two"), Some("one"), Some("two")]);
+    }
+
+    #[test]
+    fn leaves_missing_keys_empty() {
+        let rows = HashMap::from([(1, "one")]);
+        let ordered = in_key_order(&[4, 1, 5], &rows, |row| Ok(*row)).unwrap();
+        assert_eq!(ordered, vec![None, Some("one"), None]);
+        let ordered = in_key_order(&[4], &HashMap::<i32, &str>::new(), |row| Ok(*row)).unwrap();
+        assert_eq!(ordered, vec![None]);
+    }
+
+    #[test]
+    fn fails_when_a_row_fails_to_convert() {
+        let rows = HashMap::from([(1, "one"), (2, "two")]);
+        let ordered = in_key_order(&[1, 2], &rows, |row| match *row {
+            "two" => Err(Error::Closed),
+            row => Ok(row),
+        });
+        assert!(matches!(ordered, Err(Error::Closed)));
+    }
+}
@@ -0,0 +1,170 @@
+//! Support for the `with` and `from` field attributes, which convert the value of a field with
+//! custom functions. Used by the code generated by the derive macros.
+use crate::*;
+use std::error::Error as StdError;
+use std::fmt;
+use std::marker::PhantomData;
+use tokio_postgres::types::{to_sql_checked, IsNull, Type};
+
+/// Converts the value of a field into a Postgres value, implemented by the derive macros for every
+/// field with the `with` attribute.
+pub trait ToSqlConversion<T> {
+    fn to_sql(
+        value: &T,
+        ty: &Type,
+        out: &mut export::BytesMut,
+    ) -> Result<IsNull, Box<dyn StdError + Sync + Send>>;
+
+    fn accepts(ty: &Type) -> bool;
+
+    fn fmt(value: &T, f: &mut fmt::Formatter<'_>) -> fmt::Result;
+}
+
+/// Converts a Postgres value into the value of a field, implemented by the derive macros for every
+/// field with the `from` attribute.
+pub trait FromSqlConversion<T> {
+    fn from_sql(ty: &Type, raw: &[u8]) -> Result<T, Box<dyn StdError + Sync + Send>>;
+
+    fn from_sql_null(ty: &Type) -> Result<T, Box<dyn StdError + Sync + Send>>;
+
+    fn accepts(ty: &Type) -> bool;
+}
+
+/// A field that is converted into a Postgres value by the conversion `C`.
+#[repr(transparent)]
+pub struct ToSqlConverted<T, C> {
+    value: T,
+    conversion: PhantomData<C>,
+}
+
+impl<T, C> ToSqlConverted<T, C> {
+    /// Views a reference to the value of a field as a converted value.
+    pub fn from_ref(value: &T) -> &Self {
+        // Safe, because the struct is transparent and only contains the value.
+        unsafe { &*(value as *const T as *const Self) }
+    }
+}
+
+impl<T, C: ToSqlConversion<T>> fmt::Debug for ToSqlConverted<T, C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        C::fmt(&self.value, f)
+    }
+}
+
+impl<T, C: ToSqlConversion<T>> ToSqlItem for ToSqlConverted<T, C> {
+    fn to_sql(
+        &self,
+        ty: &Type,
+        out: &mut export::BytesMut,
+    ) -> Result<IsNull, Box<dyn StdError + Sync + Send>> {
+        C::to_sql(&self.value, ty, out)
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        C::accepts(ty)
+    }
+
+    to_sql_checked!();
+}
+
+/// A field that is read from a Postgres value by the conversion `C`.
+pub struct FromSqlConverted<T, C> {
+    value: T,
+    conversion: PhantomData<C>,
+}
+
+impl<T, C> FromSqlConverted<T, C> {
+    /// Returns the converted value.
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+}
+
+impl<'a, T, C: FromSqlConversion<T>> FromSqlItem<'a> for FromSqlConverted<T, C> {
+    fn from_sql(ty: &Type, raw: &'a [u8]) -> Result<Self, Box<dyn StdError + Sync + Send>> {
+        Ok(Self {
+            value: C::from_sql(ty, raw)?,
+            conversion: PhantomData,
+        })
+    }
+
+    fn from_sql_null(ty: &Type) -> Result<Self, Box<dyn StdError + Sync + Send>> {
+        Ok(Self {
+            value: C::from_sql_null(ty)?,
+            conversion: PhantomData,
+        })
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        C::accepts(ty)
+    }
+}
+
+/// Writes the value returned by the conversion function `with`.
+pub fn to_sql<T, V, F>(
+    with: F,
+    value: &T,
+    ty: &Type,
+    out: &mut export::BytesMut,
+) -> Result<IsNull, Box<dyn StdError + Sync + Send>>
+where
+    V: ToSqlItem,
+    F: Fn(&T) -> V,
+{
+    with(value).to_sql(ty, out)
+}
+
+/// Returns whether the type returned by the conversion function `with` accepts the Postgres type.
+pub fn to_sql_accepts<T, V, F>(_with: F, ty: &Type) -> bool
+where
+    V: ToSqlItem,
+    F: Fn(&T) -> V,
+{
+    V::accepts(ty)
+}
+
+/// Formats the value returned by the conversion function `with`.
+pub fn fmt<T, V, F>(with: F, value: &T, f: &mut fmt::Formatter<'_>) -> fmt::Result
+where
+    V: ToSqlItem,
+    F: Fn(&T) -> V,
+{
+    fmt::Debug::fmt(&with(value), f)
+}
+
+/// Reads a value with the conversion function `from`.
+pub fn from_sql<'a, T, V, E, F>(
+    from: F,
+    ty: &Type,
+    raw: &'a [u8],
+) -> Result<T, Box<dyn StdError + Sync + Send>>
+where
+    V: FromSqlItem<'a>,
+    E: Into<Box<dyn StdError + Sync + Send>>,
+    F: Fn(V) -> Result<T, E>,
+{
+    from(V::from_sql(ty, raw)?).map_err(Into::into)
+}
+
+/// Reads a `NULL` value with the conversion function `from`.
+pub fn from_sql_null<'a, T, V, E, F>(
+    from: F,
+    ty: &Type,
+) -> Result<T, Box<dyn StdError + Sync + Send>>
+where
+    V: FromSqlItem<'a>,
+    E: Into<Box<dyn StdError + Sync + Send>>,
+    F: Fn(V) -> Result<T, E>,
+{
+    from(V::from_sql_null(ty)?).map_err(Into::into)
+}
+
+/// Returns whether the type taken by the conversion function `from` accepts the Postgres type.
+pub fn from_sql_accepts<'a, T, V, E, F>(_from: F, ty: &Type) -> bool
+where
+    V: FromSqlItem<'a>,
+    E: Into<Box<dyn StdError + Sync + Send>>,
+    F: Fn(V) -> Result<T, E>,
+{
+    V::accepts(ty)
+}
@@ -0,0 +1,242 @@
+use crate::select::Argument;
+use crate::*;
+
+/// Queries named with common table expressions, created by
+/// [`Connection::with`](struct.Connection.html#method.with).
+///
+/// The queries can be referred to by their names in the conditions of the query created with
+/// [`select`](#method.select). The names are quoted, so they are referred to in lowercase
+/// without quotes when they are lowercase.
+///
+/// The placeholders of every query start at `$1`, including those of the final query. They are
+/// renumbered when the queries are combined into a single statement.
+///
+/// Example:
+/// ```no_run
+/// use sprattus::*;
+///
+/// #[derive(FromSql, ToSql, Debug)]
+/// #[sprattus(table = "orders")]
+/// struct Order {
+///     #[sprattus(primary_key)]
+///     orderid: i32,
+///     customerid: i32,
+///     netamount: f64,
+/// }
+///
+/// #[derive(FromSql, ToSql, Debug)]
+/// #[sprattus(table = "customers")]
+/// struct Customer {
+///     #[sprattus(primary_key)]
+///     customerid: i32,
+///     firstname: String,
+///     country: String,
+/// }
+///
+/// #[tokio::main]
+/// async fn main() -> Result<(), Error> {
+///     let conn = Connection::new("postgresql://localhost?user=tg").await?;
+///     let large_orders = conn.select::<Order>().filter("netamount > $1", &[&300.0]);
+///     // Executes `WITH "large_orders" AS (SELECT "orderid","customerid","netamount"
+///     // FROM "orders" WHERE netamount > $1) SELECT "customerid","firstname","country"
+///     // FROM "customers" WHERE (customerid IN (SELECT customerid FROM large_orders))
+///     // AND (country = $2)`.
+///     let customers = conn
+///         .with("large_orders", large_orders)
+///         .select::<Customer>()
+///         .filter("customerid IN (SELECT customerid FROM large_orders)", &[])
+///         .filter("country = $1", &[&"US"])
+///         .fetch()
+///         .await?;
+///     dbg!(customers);
+///     Ok(())
+/// }
+/// ```
+pub struct With<'a> {
+    connection: &'a Connection,
+    queries: Vec<String>,
+    args: Vec<Argument<'a>>,
+}
+
+/// A query that can be named in a common table expression with
+/// [`Connection::with`](struct.Connection.html#method.with).
+///
+/// Created from a [`Select`](struct.Select.html) or an [`Aggregate`](struct.Aggregate.html).
+pub struct Subquery<'a> {
+    sql: String,
+    args: Vec<Argument<'a>>,
+}
+
+impl<'a, T> From<Select<'a, T>> for Subquery<'a>
+where
+    T: Table + FromSql,
+{
+    fn from(select: Select<'a, T>) -> Self {
+        let (sql, args) = select.into_parts();
+        Subquery { sql, args }
+    }
+}
+
+impl<'a, T> From<Aggregate<'a, T>> for Subquery<'a>
+where
+    T: Table,
+{
+    fn from(aggregate: Aggregate<'a, T>) -> Self {
+        let (sql, args) = aggregate.into_parts();
+        Subquery { sql, args }
+    }
+}
+
+impl<'a> With<'a> {
+    pub(crate) fn new(connection: &'a Connection) -> Self {
+        Self {
+            connection,
+            queries: Vec::new(),
+            args: Vec::new(),
+        }
+    }
+
+    /// Adds a query with the given name, which can refer to the queries added before it.
+    pub fn with<Q>(mut self, name: &str, query: Q) -> Self
+    where
+        Q: Into<Subquery<'a>>,
+    {
+        let query = query.into();
+        self.queries.push(format!(
+            "{} AS ({})",
+            ident(name),
+            shift_placeholders(&query.sql, self.args.len())
+        ));
+        self.args.extend(query.args);
+        self
+    }
+
+    /// Creates the final query, selecting rows of a table or view, see
+    /// [`Connection::select`](struct.Connection.html#method.select).
+    pub fn select<T>(self) -> Select<'a, T>
+    where
+        T: Table + FromSql,
+    {
+        Select::with_queries(self.connection, self.queries, self.args)
+    }
+}
+
+/// Returns the `WITH` clause of the named queries, followed by the statement, of which the
+/// placeholders are renumbered to follow the arguments of the named queries.
+pub(crate) fn with_clause(queries: &[String], arg_count: usize, sql: &str) -> String {
+    if queries.is_empty() {
+        return sql.to_string();
+    }
+    format!(
+        "WITH {} {}",
+        queries.join(", "),
+        shift_placeholders(sql, arg_count)
+    )
+}
+
+///
+/// Adds the offset to the numbers of all placeholders in the statement, like `$1`.
+///
+/// Placeholders within string constants, quoted identifiers and dollar quoted strings are left
+/// as they are.
+///
+pub(crate) fn shift_placeholders(sql: &str, offset: usize) -> String {
+    if offset == 0 {
+        return sql.to_string();
+    }
+    let chars: Vec<char> = sql.chars().collect();
+    let mut shifted = String::with_capacity(sql.len());
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        // A dollar sign within an identifier, like `price$1`, is part of the identifier.
+        let in_identifier = i > 0 && (chars[i - 1].is_alphanumeric() || chars[i - 1] == '_');
+        match c {
+            '\'' | '"' => {
+                // Quotes within the constant or identifier are doubled, which is handled as two
+                // adjacent constants.
+                let end = (i + 1..chars.len())
+                    .find(|&j| chars[j] == c)
+                    .unwrap_or(chars.len() - 1);
+                shifted.extend(&chars[i..=end]);
+                i = end + 1;
+            }
+            '$' if !in_identifier && chars.get(i + 1).is_some_and(char::is_ascii_digit) => {
+                let end = (i + 1..chars.len())
+                    .find(|&j| !chars[j].is_ascii_digit())
+                    .unwrap_or(chars.len());
+                let number: String = chars[i + 1..end].iter().collect();
+                shifted.push('$');
+                shifted.push_str(&(number.parse::<usize>().unwrap() + offset).to_string());
+                i = end;
+            }
+            '$' if !in_identifier => {
+                // A dollar quoted string starts with a tag like `$$` or `$body$`, and ends with
+                // the same tag.
+                let tag_end = (i + 1..chars.len())
+                    .find(|&j| !(chars[j].is_alphanumeric() || chars[j] == '_'))
+                    .filter(|&j| chars[j] == '$');
+                match tag_end {
+                    Some(tag_end) => {
+                        let tag = &chars[i..=tag_end];
+                        let end = (tag_end + 1..chars.len())
+                            .find(|&j| chars[j..].starts_with(tag))
+                            .map_or(chars.len(), |j| j + tag.len());
+                        shifted.extend(&chars[i..end]);
+                        i = end;
+                    }
+                    None => {
+                        shifted.push(c);
+                        i += 1;
+                    }
+                }
+            }
+            _ => {
+                shifted.push(c);
+                i += 1;
+            }
+        }
+    }
+    shifted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shifts_placeholders() {
+        assert_eq!(
+            shift_placeholders("SELECT * FROM products WHERE price > $1 AND stock < $2", 2),
+            "SELECT * FROM products WHERE price > $3 AND stock < $4"
+        );
+        assert_eq!(shift_placeholders("SELECT $9, $10", 1), "SELECT $10, $11");
+        assert_eq!(shift_placeholders("SELECT $1", 0), "SELECT $1");
+    }
+
+    #[test]
+    fn keeps_placeholders_in_constants_and_identifiers() {
+        assert_eq!(
+            shift_placeholders("SELECT '$1', 'it''s $2', \"$3\", $4", 1),
+            "SELECT '$1', 'it''s $2', \"$3\", $5"
+        );
+        assert_eq!(
+            shift_placeholders("SELECT price$1 FROM products WHERE id = $1", 1),
+            "SELECT price$1 FROM products WHERE id = $2"
+        );
+    }
+
+    #[test]
+    fn keeps_placeholders_in_dollar_quoted_strings() {
+        assert_eq!(
+            shift_placeholders("SELECT $$costs $1$$, $1", 1),
+            "SELECT $$costs $1$$, $2"
+        );
+        assert_eq!(
+            shift_placeholders("SELECT $body$ $1 $$ $2 $body$, $2", 2),
+            "SELECT $body$ $1 $$ $2 $body$, $4"
+        );
+        // An unterminated string runs to the end of the statement.
+        assert_eq!(shift_placeholders("SELECT $$ $1", 1), "SELECT $$ $1");
+    }
+}
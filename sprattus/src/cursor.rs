@@ -0,0 +1,91 @@
+use crate::*;
+use futures_util::stream::{self, Stream};
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static CURSOR_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// A server side cursor, fetching the result of a query in batches.
+///
+/// The cursor lives inside its own transaction, which is committed when the cursor is
+/// [closed](#method.close) and rolled back when the cursor is dropped.
+pub struct Cursor<T> {
+    transaction: Transaction,
+    name: String,
+    batch_size: u32,
+    exhausted: bool,
+    result_type: PhantomData<T>,
+}
+
+impl<T> Cursor<T>
+where
+    T: FromSql,
+{
+    pub(crate) async fn declare(
+        connection: &Connection,
+        sql: &str,
+        args: &[&(dyn ToSqlItem + Sync)],
+        batch_size: u32,
+    ) -> Result<Self, Error> {
+        let name = format!(
+            "sprattus_cursor_{}",
+            CURSOR_COUNTER.fetch_add(1, Ordering::Relaxed)
+        );
+        let transaction = connection.transaction().await?;
+        transaction
+            .execute(
+                format!("DECLARE {} NO SCROLL CURSOR FOR {}", name, sql).as_str(),
+                args,
+            )
+            .await?;
+        Ok(Self {
+            transaction,
+            name,
+            batch_size,
+            exhausted: false,
+            result_type: PhantomData,
+        })
+    }
+
+    /// Fetches the next batch of rows.
+    ///
+    /// Returns `None` when all rows have been fetched.
+    pub async fn next_batch(&mut self) -> Result<Option<Vec<T>>, Error> {
+        if self.exhausted {
+            return Ok(None);
+        }
+        let sql = format!("FETCH {} FROM {}", self.batch_size, self.name);
+        let batch: Vec<T> = self.transaction.query_multiple(sql.as_str(), &[]).await?;
+        if batch.len() < self.batch_size as usize {
+            self.exhausted = true;
+        }
+        if batch.is_empty() {
+            Ok(None)
+        } else {
+            Ok(Some(batch))
+        }
+    }
+
+    /// Closes the cursor and commits its transaction.
+    pub async fn close(self) -> Result<(), Error> {
+        self.transaction
+            .batch_execute(format!("CLOSE {}", self.name).as_str())
+            .await?;
+        self.transaction.commit().await
+    }
+
+    /// Turns the cursor into a stream of batches.
+    pub fn into_stream(self) -> impl Stream<Item = Result<Vec<T>, Error>> {
+        stream::unfold(Some(self), |cursor| async move {
+            let mut cursor = cursor?;
+            match cursor.next_batch().await {
+                Ok(Some(batch)) => Some((Ok(batch), Some(cursor))),
+                Ok(None) => match cursor.close().await {
+                    Ok(()) => None,
+                    Err(error) => Some((Err(error), None)),
+                },
+                Err(error) => Some((Err(error), None)),
+            }
+        })
+    }
+}
@@ -0,0 +1,60 @@
+use crate::connection::{
+    generate_delete_statement, generate_find_statement, generate_insert_placeholders,
+    generate_insert_statement, generate_update_returning_statement,
+};
+use crate::*;
+use std::fmt;
+
+/// The statements generated for a struct, returned by
+/// [`ToSql::describe`](trait.ToSql.html#method.describe).
+///
+/// The values are bound to placeholders like `$1`, so the statements can be reviewed or
+/// analyzed with `EXPLAIN` without running them. The statements return all columns, as they do
+/// with the default [`Returning::All`](enum.Returning.html#variant.All).
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Statements {
+    /// The statement of [`Connection::create`](struct.Connection.html#method.create), with the
+    /// values of `ToSql::get_fields()` bound to `$1`, `$2`, etc., preceded by the primary key
+    /// when `ToSql::inserts_primary_key()` returns `true`.
+    ///
+    /// A primary key that is `None` and fields with the `default_on_none` attribute that are
+    /// `None` are inserted as `DEFAULT` instead.
+    pub insert: String,
+    /// The statement of [`Connection::update`](struct.Connection.html#method.update), with the
+    /// primary key bound to `$1`, followed by the values of `ToSql::get_update_fields()`.
+    pub update: String,
+    /// The statement of [`Connection::delete`](struct.Connection.html#method.delete), with the
+    /// primary key bound to `$1`.
+    pub delete: String,
+    /// The statement of [`Connection::find`](struct.Connection.html#method.find), with the
+    /// primary key bound to `$1`.
+    pub select: String,
+}
+
+impl Statements {
+    pub(crate) fn new<T: ToSql>() -> Self {
+        Statements {
+            insert: generate_insert_statement::<T>(
+                T::get_table_name(),
+                &generate_insert_placeholders::<T>(),
+                Returning::All,
+            ),
+            update: generate_update_returning_statement::<T>(
+                T::get_table_name(),
+                "",
+                Returning::All,
+            ),
+            delete: generate_delete_statement::<T>(T::get_table_name(), Returning::All),
+            select: generate_find_statement::<T>(T::get_table_name(), Masking::Unmasked),
+        }
+    }
+}
+
+impl fmt::Display for Statements {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "insert: {}", self.insert)?;
+        writeln!(f, "update: {}", self.update)?;
+        writeln!(f, "delete: {}", self.delete)?;
+        write!(f, "select: {}", self.select)
+    }
+}
@@ -0,0 +1,63 @@
+use crate::*;
+
+/// A row of a query of which the columns are only known at runtime, returned by
+/// [`Connection::query_dynamic`](struct.Connection.html#method.query_dynamic).
+///
+/// Use this for exploratory queries and tooling where defining a struct per query is
+/// impractical.
+pub struct DynamicRow {
+    row: Row,
+}
+
+impl DynamicRow {
+    pub(crate) fn new(row: Row) -> Self {
+        Self { row }
+    }
+
+    /// Returns the names of the columns of the row.
+    pub fn columns(&self) -> Vec<&str> {
+        self.row
+            .columns()
+            .iter()
+            .map(|column| column.name())
+            .collect()
+    }
+
+    /// Returns the name of the Postgres type of a column, or `None` when the row has no column
+    /// with the name.
+    pub fn column_type(&self, column: &str) -> Option<&str> {
+        self.row
+            .columns()
+            .iter()
+            .find(|other| other.name() == column)
+            .map(|column| column.type_().name())
+    }
+
+    /// Returns the number of columns of the row.
+    pub fn len(&self) -> usize {
+        self.row.len()
+    }
+
+    /// Returns whether the row has no columns.
+    pub fn is_empty(&self) -> bool {
+        self.row.is_empty()
+    }
+
+    /// Returns the value of a column, converted into the given type.
+    ///
+    /// Returns an error when the row has no column with the name, or when the value can't be
+    /// converted into the type.
+    pub fn get<'a, T: FromSqlItem<'a>>(&'a self, column: &str) -> Result<T, Error> {
+        Ok(self.row.try_get(column)?)
+    }
+
+    /// Returns the value of the column at the given index, converted into the given type.
+    pub fn get_index<'a, T: FromSqlItem<'a>>(&'a self, index: usize) -> Result<T, Error> {
+        Ok(self.row.try_get(index)?)
+    }
+
+    /// Returns the underlying row.
+    pub fn into_row(self) -> Row {
+        self.row
+    }
+}
@@ -0,0 +1,453 @@
+use crate::{ToSql, ValidationError};
+use std::error;
+use std::fmt;
+use std::io;
+use std::sync::Arc;
+use tokio_postgres::error::{DbError, SqlState};
+use tokio_postgres::Row;
+
+/// Errors that can occur while communicating with the database.
+#[derive(Debug)]
+pub enum Error {
+    /// An error returned by Postgres or the client library.
+    Postgres(tokio_postgres::Error),
+    /// A column of a row could not be converted into a field of a struct.
+    Mapping(MappingError),
+    /// The database did not respond in time.
+    Timeout,
+    /// The rows of a fixture could not be deserialized.
+    Fixture(Box<dyn error::Error + Sync + Send>),
+    /// The connection is closed.
+    Closed,
+    /// A statement or transaction was started on a clone of a connection while another clone has
+    /// a transaction open.
+    TransactionInProgress,
+    /// The configuration of a connection is invalid.
+    Config(String),
+    /// No database with the given name is configured in a
+    /// [`ConnectionManager`](struct.ConnectionManager.html).
+    UnknownDatabase(String),
+    /// A row could not be written, because it violates a unique constraint declared with the
+    /// `unique` attribute.
+    UniqueViolation {
+        /// The unquoted Postgres names of the columns of the violated constraint.
+        columns: Vec<String>,
+        /// The error returned by Postgres.
+        source: tokio_postgres::Error,
+    },
+    /// The data to write to the database could not be read from its source, like the stream
+    /// given to [`Connection::write_large_object`](struct.Connection.html#method.write_large_object).
+    Source(Box<dyn error::Error + Sync + Send>),
+    /// The statement is not sent, because the
+    /// [`CircuitBreaker`](struct.CircuitBreaker.html) of the connection is open after too many
+    /// failed statements.
+    CircuitOpen,
+    /// The item is not written, because fields with the `validate` attribute don't meet their
+    /// rules.
+    Validation(ValidationError),
+    /// The columns returned by a statement can't fill the struct it is read into, found by
+    /// [`Prepare`](struct.Prepare.html) before the statement is run.
+    ColumnMismatch {
+        /// The statement.
+        sql: String,
+        /// The fields that can't be filled.
+        mismatches: Vec<ColumnMismatch>,
+    },
+    /// A file could not be read or written.
+    Io(io::Error),
+    /// The payload of a notification could not be decoded, returned by
+    /// [`Connection::listen_typed`](struct.Connection.html#method.listen_typed).
+    MalformedPayload {
+        /// The channel of the notification.
+        channel: String,
+        /// The payload of the notification.
+        payload: String,
+        /// The error returned while decoding the payload.
+        source: Box<dyn error::Error + Sync + Send>,
+    },
+    /// A change read from a replication slot could not be decoded, see the
+    /// [`replication`](replication/index.html) module.
+    Replication(String),
+    /// The query of a batch of [`Loader`](struct.Loader.html) loads failed, with the error shared
+    /// by all loads of the batch. The underlying error is returned by
+    /// [`Error::inner`](#method.inner).
+    Batch(Arc<Error>),
+    /// A statement generated for a struct failed, with the statement it failed in. The
+    /// underlying error is returned by [`Error::inner`](#method.inner).
+    Statement(StatementError),
+}
+
+/// The maximum number of characters of the statement in a
+/// [`StatementError`](struct.StatementError.html).
+const SNIPPET_LENGTH: usize = 200;
+
+/// The kind of a statement generated for a struct.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum StatementKind {
+    /// A `SELECT` of rows of the table.
+    Select,
+    /// An `INSERT` of rows into the table.
+    Insert,
+    /// An `UPDATE` of rows of the table.
+    Update,
+    /// An `INSERT` of rows into the table, updating the rows that already exist.
+    Upsert,
+    /// A `DELETE` of rows from the table.
+    Delete,
+}
+
+/// Describes a failed statement that was generated for a struct, returned as
+/// [`Error::Statement`](enum.Error.html#variant.Statement).
+#[derive(Debug)]
+pub struct StatementError {
+    /// The kind of the statement.
+    pub kind: StatementKind,
+    /// The quoted name of the table of the struct, like `"products"`.
+    pub table: String,
+    /// The start of the statement, truncated to 200 characters.
+    pub sql: String,
+    /// The error of the statement.
+    pub source: Box<Error>,
+}
+
+/// Describes the field of a struct that could not be filled with a column of a row.
+#[derive(Debug)]
+pub struct MappingError {
+    /// The name of the struct.
+    pub struct_name: &'static str,
+    /// The name of the field.
+    pub field_name: &'static str,
+    /// The Rust type of the field.
+    pub rust_type: &'static str,
+    /// The name of the column.
+    pub column_name: String,
+    /// The Postgres type of the column, `None` when the column is not part of the row.
+    pub column_type: Option<String>,
+    /// The error returned while converting the column.
+    pub source: tokio_postgres::Error,
+}
+
+/// Describes a field of a struct that can't be filled by the columns of a statement, returned by
+/// [`FromSql::check_columns`](trait.FromSql.html#method.check_columns).
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ColumnMismatch {
+    /// The name of the field.
+    pub field_name: &'static str,
+    /// The Rust type of the field.
+    pub rust_type: &'static str,
+    /// The name of the column.
+    pub column_name: &'static str,
+    /// The Postgres type of the column, `None` when the statement doesn't return the column.
+    pub column_type: Option<String>,
+}
+
+impl Error {
+    ///
+    /// Returns the error without the statement it occurred in, to match on the cause of an
+    /// [`Error::Statement`](enum.Error.html#variant.Statement) or an
+    /// [`Error::Batch`](enum.Error.html#variant.Batch). Other errors are returned as is.
+    ///
+    /// Example:
+    /// ```no_run
+    /// use sprattus::*;
+    ///
+    /// #[derive(FromSql, ToSql, Debug)]
+    /// #[sprattus(unique = "title")]
+    /// struct Product {
+    ///     #[sprattus(primary_key)]
+    ///     prod_id: i32,
+    ///     title: String,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Error> {
+    ///     let conn = Connection::new("postgresql://localhost?user=tg").await?;
+    ///     let product = Product { prod_id: 0, title: String::from("ACADEMY ACADEMY") };
+    ///     match conn.create(&product).await {
+    ///         Ok(product) => println!("created {:?}", product),
+    ///         Err(error) => match error.inner() {
+    ///             Error::UniqueViolation { .. } => println!("the title is taken"),
+    ///             // Logs like `insert into "product" failed: ... in `INSERT INTO "product" ...``.
+    ///             _ => return Err(error),
+    ///         },
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn inner(&self) -> &Error {
+        match self {
+            Error::Statement(error) => error.source.inner(),
+            Error::Batch(error) => error.inner(),
+            error => error,
+        }
+    }
+
+    /// Returns the error without the statement it occurred in, like
+    /// [`inner`](#method.inner). The error of a batch is only unwrapped when it is no longer
+    /// shared with other loads.
+    pub fn into_inner(self) -> Error {
+        match self {
+            Error::Statement(error) => error.source.into_inner(),
+            Error::Batch(error) => match Arc::try_unwrap(error) {
+                Ok(error) => error.into_inner(),
+                Err(error) => Error::Batch(error),
+            },
+            error => error,
+        }
+    }
+
+    ///
+    /// Creates a mapping error for a field that could not be read from the given row.
+    /// Used by the code generated by the `FromSql` derive macro.
+    ///
+    #[doc(hidden)]
+    pub fn mapping(
+        row: &Row,
+        column_name: &str,
+        struct_name: &'static str,
+        field_name: &'static str,
+        rust_type: &'static str,
+        source: tokio_postgres::Error,
+    ) -> Self {
+        let column_type = row
+            .columns()
+            .iter()
+            .find(|column| column.name() == column_name)
+            .map(|column| column.type_().name().to_string());
+        Error::Mapping(MappingError {
+            struct_name,
+            field_name,
+            rust_type,
+            column_name: column_name.to_string(),
+            column_type,
+            source,
+        })
+    }
+}
+
+///
+/// Converts the violation of a unique constraint declared on `T` into
+/// [`Error::UniqueViolation`](enum.Error.html#variant.UniqueViolation), other errors are returned
+/// unchanged.
+///
+/// The constraint is identified by the columns in the detail of the error, like
+/// `Key (title, actor)=(ACADEMY BAKED, PENELOPE) already exists.`
+///
+pub(crate) fn unique_violation<T: ToSql>(error: impl Into<Error>) -> Error {
+    let error = error.into();
+    let source = match error {
+        Error::Postgres(source) if source.code() == Some(&SqlState::UNIQUE_VIOLATION) => source,
+        error => return error,
+    };
+    let columns = error::Error::source(&source)
+        .and_then(|error| error.downcast_ref::<DbError>())
+        .and_then(DbError::detail)
+        .filter(|detail| detail.starts_with("Key ("))
+        .and_then(|detail| detail["Key (".len()..].split(")=(").next())
+        .map(|columns| {
+            columns
+                .split(", ")
+                .map(|column| column.trim_matches('"').replace("\"\"", "\""))
+                .collect::<Vec<String>>()
+        });
+    let constraint = columns.and_then(|columns| {
+        T::get_unique_constraints().iter().find(|constraint| {
+            constraint.len() == columns.len()
+                && constraint
+                    .iter()
+                    .all(|column| columns.iter().any(|other| other == column))
+        })
+    });
+    match constraint {
+        Some(constraint) => Error::UniqueViolation {
+            columns: constraint.iter().map(|column| column.to_string()).collect(),
+            source,
+        },
+        None => Error::Postgres(source),
+    }
+}
+
+///
+/// Returns a function that adds a statement generated for the given table to an error, to be
+/// passed to `map_err`. An error that already has a statement is returned unchanged.
+///
+pub(crate) fn statement_error<'a>(
+    kind: StatementKind,
+    table: &'a str,
+    sql: &'a str,
+) -> impl FnOnce(Error) -> Error + 'a {
+    move |error| match error {
+        Error::Statement(error) => Error::Statement(error),
+        error => Error::Statement(StatementError {
+            kind,
+            table: table.to_string(),
+            sql: snippet(sql),
+            source: Box::new(error),
+        }),
+    }
+}
+
+/// Truncates a statement to `SNIPPET_LENGTH` characters, marking a truncated statement with `...`.
+fn snippet(sql: &str) -> String {
+    match sql.char_indices().nth(SNIPPET_LENGTH) {
+        Some((end, _)) => format!("{}...", &sql[..end]),
+        None => sql.to_string(),
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Postgres(error) => error.fmt(f),
+            Error::Mapping(error) => error.fmt(f),
+            Error::Timeout => write!(f, "the database did not respond in time"),
+            Error::Fixture(error) => write!(f, "invalid fixture: {}", error),
+            Error::Closed => write!(f, "the connection is closed"),
+            Error::TransactionInProgress => write!(
+                f,
+                "a transaction is open on another clone of the connection"
+            ),
+            Error::Config(message) => write!(f, "invalid configuration: {}", message),
+            Error::UnknownDatabase(name) => write!(f, "no database named `{}` is configured", name),
+            Error::UniqueViolation { columns, .. } => write!(
+                f,
+                "a row with the same value for ({}) already exists",
+                columns.join(", ")
+            ),
+            Error::Source(error) => write!(f, "cannot read the data to write: {}", error),
+            Error::CircuitOpen => write!(f, "the database is not called while the circuit is open"),
+            Error::Validation(error) => error.fmt(f),
+            Error::ColumnMismatch { sql, mismatches } => {
+                let mismatches: Vec<String> = mismatches
+                    .iter()
+                    .map(|mismatch| mismatch.to_string())
+                    .collect();
+                write!(f, "{} in `{}`", mismatches.join(", "), sql)
+            }
+            Error::Io(error) => error.fmt(f),
+            Error::MalformedPayload {
+                channel,
+                payload,
+                source,
+            } => write!(
+                f,
+                "cannot decode the payload `{}` of a notification on channel `{}`: {}",
+                payload, channel, source
+            ),
+            Error::Replication(message) => write!(f, "invalid replication change: {}", message),
+            Error::Batch(error) => error.fmt(f),
+            Error::Statement(error) => error.fmt(f),
+        }
+    }
+}
+
+impl fmt::Display for StatementKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self {
+            StatementKind::Select => "select from",
+            StatementKind::Insert => "insert into",
+            StatementKind::Update => "update of",
+            StatementKind::Upsert => "upsert into",
+            StatementKind::Delete => "delete from",
+        };
+        f.write_str(kind)
+    }
+}
+
+impl fmt::Display for StatementError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} failed: {} in `{}`",
+            self.kind, self.table, self.source, self.sql
+        )
+    }
+}
+
+impl fmt::Display for ColumnMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.column_type {
+            Some(column_type) => write!(
+                f,
+                "column `{}` of type {} can't be converted into field `{}` of type `{}`",
+                self.column_name, column_type, self.field_name, self.rust_type
+            ),
+            None => write!(
+                f,
+                "column `{}` of field `{}` is not returned",
+                self.column_name, self.field_name
+            ),
+        }
+    }
+}
+
+impl fmt::Display for MappingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.column_type {
+            Some(column_type) => write!(
+                f,
+                "cannot convert column `{}` of type {} into field `{}.{}` of type `{}`: {}",
+                self.column_name,
+                column_type,
+                self.struct_name,
+                self.field_name,
+                self.rust_type,
+                self.source
+            ),
+            None => write!(
+                f,
+                "cannot find column `{}` for field `{}.{}` of type `{}`: {}",
+                self.column_name, self.struct_name, self.field_name, self.rust_type, self.source
+            ),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Error::Postgres(error) => Some(error),
+            Error::Mapping(error) => Some(error),
+            Error::Timeout
+            | Error::Closed
+            | Error::TransactionInProgress
+            | Error::Config(_)
+            | Error::UnknownDatabase(_)
+            | Error::CircuitOpen
+            | Error::Replication(_)
+            | Error::ColumnMismatch { .. } => None,
+            Error::Fixture(error)
+            | Error::Source(error)
+            | Error::MalformedPayload { source: error, .. } => Some(error.as_ref()),
+            Error::UniqueViolation { source, .. } => Some(source),
+            Error::Validation(error) => Some(error),
+            Error::Io(error) => Some(error),
+            Error::Batch(error) => Some(error.as_ref()),
+            Error::Statement(error) => Some(error),
+        }
+    }
+}
+
+impl error::Error for StatementError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        Some(self.source.as_ref())
+    }
+}
+
+impl error::Error for MappingError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        Some(&self.source)
+    }
+}
+
+impl From<tokio_postgres::Error> for Error {
+    fn from(error: tokio_postgres::Error) -> Self {
+        Error::Postgres(error)
+    }
+}
+
+impl From<ValidationError> for Error {
+    fn from(error: ValidationError) -> Self {
+        Error::Validation(error)
+    }
+}
@@ -0,0 +1,102 @@
+/// The result of a statement that doesn't return rows.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ExecuteResult {
+    /// The number of rows modified by the statement, or the number of rows returned by a `SELECT`
+    /// statement.
+    pub rows_affected: u64,
+    /// The first keyword of the SQL of the statement in upper case, after any comments, like
+    /// `UPDATE` or `DELETE`.
+    ///
+    /// This is read from the SQL, not the command tag reported by Postgres, which the driver
+    /// doesn't expose. A statement that starts with a common table expression has `WITH` as its
+    /// first keyword, whichever command follows it. This includes the statements generated for a
+    /// struct with a history table, which record the old row in a common table expression.
+    pub first_keyword: String,
+}
+
+impl ExecuteResult {
+    pub(crate) fn new(sql: &str, rows_affected: u64) -> Self {
+        ExecuteResult {
+            rows_affected,
+            first_keyword: first_keyword(sql),
+        }
+    }
+
+    /// Returns whether the statement matched no rows.
+    pub fn is_empty(&self) -> bool {
+        self.rows_affected == 0
+    }
+}
+
+/// Returns the first word of the SQL in upper case, skipping whitespace and comments.
+fn first_keyword(mut sql: &str) -> String {
+    loop {
+        sql = sql.trim_start();
+        if let Some(rest) = sql.strip_prefix("--") {
+            sql = rest.find('\n').map_or("", |end| &rest[end..]);
+        } else if sql.starts_with("/*") {
+            sql = skip_block_comment(sql);
+        } else {
+            break;
+        }
+    }
+    sql.chars()
+        .take_while(char::is_ascii_alphabetic)
+        .collect::<String>()
+        .to_ascii_uppercase()
+}
+
+/// Skips the block comment at the start of the SQL, which can contain nested block comments.
+fn skip_block_comment(sql: &str) -> &str {
+    let mut depth = 0;
+    let mut rest = sql;
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix("/*") {
+            depth += 1;
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix("*/") {
+            depth -= 1;
+            rest = after;
+            if depth == 0 {
+                return rest;
+            }
+        } else {
+            let next = rest.chars().next().map_or(0, char::len_utf8);
+            rest = &rest[next..];
+        }
+    }
+    rest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_the_first_keyword() {
+        assert_eq!(first_keyword("update products SET price = $1"), "UPDATE");
+        assert_eq!(first_keyword("\n  DELETE FROM products"), "DELETE");
+        assert_eq!(first_keyword(""), "");
+    }
+
+    #[test]
+    fn skips_comments() {
+        assert_eq!(
+            first_keyword("-- remove old orders\nDELETE FROM orders"),
+            "DELETE"
+        );
+        assert_eq!(
+            first_keyword("/* outer /* nested */ still a comment */ INSERT INTO orders"),
+            "INSERT"
+        );
+        assert_eq!(first_keyword("-- only a comment"), "");
+    }
+
+    #[test]
+    fn reports_with_for_common_table_expressions() {
+        assert_eq!(
+            first_keyword("WITH old AS (SELECT 1) UPDATE products SET price = 1"),
+            "WITH"
+        );
+    }
+}
@@ -0,0 +1,134 @@
+use crate::cte::shift_placeholders;
+use crate::select::Argument;
+use crate::*;
+use std::ops::Not;
+
+/// A condition of a `WHERE` clause together with the arguments of its placeholders, which can be
+/// combined with other filters.
+///
+/// The placeholders of every filter are numbered from `$1`, and are renumbered when filters are
+/// combined with [`and`](#method.and) and [`or`](#method.or), or negated with `!`. A filter is
+/// added to a query with [`Select::filter_with`](struct.Select.html#method.filter_with), which
+/// renumbers them once more after the arguments of the query.
+///
+/// [`Filter::all`](#method.all) matches all rows, and is left out when it is combined with `and`,
+/// so conditions of a search form can be added one by one.
+///
+/// Example:
+/// ```
+/// use sprattus::*;
+///
+/// let name = String::from("%sprattus%");
+/// let min_price = 10.0;
+/// let max_price = 20.0;
+/// let mut filter = Filter::all();
+/// filter = filter.and(Filter::new("title ILIKE $1", &[&name]));
+/// filter = filter.and(
+///     Filter::new("price < $1", &[&min_price]).or(Filter::new("price > $1", &[&max_price])),
+/// );
+/// filter = filter.and(!Filter::new("discontinued", &[]));
+/// assert_eq!(
+///     filter.condition(),
+///     "((title ILIKE $1) AND ((price < $2) OR (price > $3))) AND (NOT (discontinued))"
+/// );
+/// assert_eq!(filter.args().len(), 3);
+/// ```
+pub struct Filter<'a> {
+    // `None` for a filter matching all rows.
+    condition: Option<String>,
+    args: Vec<Argument<'a>>,
+}
+
+impl<'a> Filter<'a> {
+    /// Creates a filter of a condition with placeholders numbered from `$1`.
+    pub fn new(condition: &str, args: &[&'a (dyn ToSqlItem + Sync)]) -> Self {
+        Filter {
+            condition: Some(condition.to_string()),
+            args: args.iter().map(|arg| Argument::Borrowed(*arg)).collect(),
+        }
+    }
+
+    /// Creates a filter matching all rows.
+    pub fn all() -> Self {
+        Filter {
+            condition: None,
+            args: Vec::new(),
+        }
+    }
+
+    /// Returns whether the filter matches all rows, because it has no condition.
+    pub fn is_all(&self) -> bool {
+        self.condition.is_none()
+    }
+
+    /// Combines the filters, matching the rows that match both.
+    pub fn and(self, other: Filter<'a>) -> Self {
+        self.combine("AND", other)
+    }
+
+    /// Combines the filters, matching the rows that match either.
+    pub fn or(self, other: Filter<'a>) -> Self {
+        self.combine("OR", other)
+    }
+
+    fn combine(mut self, operator: &str, other: Filter<'a>) -> Self {
+        let (condition, other_condition) = match (self.condition, other.condition) {
+            (Some(condition), Some(other_condition)) => (condition, other_condition),
+            // A filter matching all rows is left out of `AND`, and makes `OR` match all rows.
+            (condition, None) if operator == "AND" => {
+                self.condition = condition;
+                return self;
+            }
+            (None, condition) if operator == "AND" => {
+                return Filter {
+                    condition,
+                    args: other.args,
+                }
+            }
+            _ => return Filter::all(),
+        };
+        self.condition = Some(format!(
+            "({}) {} ({})",
+            condition,
+            operator,
+            shift_placeholders(&other_condition, self.args.len())
+        ));
+        self.args.extend(other.args);
+        self
+    }
+
+    /// Returns the condition, with its placeholders numbered from `$1`, or `TRUE` for a filter
+    /// matching all rows.
+    pub fn condition(&self) -> &str {
+        self.condition.as_deref().unwrap_or("TRUE")
+    }
+
+    /// Returns the arguments of the placeholders of the condition.
+    pub fn args(&self) -> Vec<&(dyn ToSqlItem + Sync)> {
+        self.args.iter().map(Argument::as_sql_item).collect()
+    }
+
+    /// Returns the condition with its placeholders shifted by the given offset, and the arguments,
+    /// or `None` for a filter matching all rows.
+    pub(crate) fn into_parts(self, offset: usize) -> Option<(String, Vec<Argument<'a>>)> {
+        let condition = self.condition?;
+        Some((shift_placeholders(&condition, offset), self.args))
+    }
+}
+
+impl<'a> Not for Filter<'a> {
+    type Output = Filter<'a>;
+
+    /// Negates the filter, matching the rows that don't match it. Negating a filter matching all
+    /// rows matches no rows.
+    fn not(self) -> Self::Output {
+        let condition = match self.condition {
+            Some(condition) => format!("NOT ({})", condition),
+            None => String::from("FALSE"),
+        };
+        Filter {
+            condition: Some(condition),
+            args: self.args,
+        }
+    }
+}
@@ -0,0 +1,52 @@
+use crate::*;
+use serde::de::DeserializeOwned;
+
+/// The serialized rows of a fixture, to load with
+/// [`Connection::load_fixtures`](struct.Connection.html#method.load_fixtures).
+///
+/// Every variant contains a list of rows, with the fields of the struct as keys.
+#[derive(Clone, Copy, Debug)]
+pub enum FixtureSource<'a> {
+    /// A JSON array of objects.
+    #[cfg(feature = "fixtures-json")]
+    Json(&'a str),
+    /// A YAML sequence of mappings.
+    #[cfg(feature = "fixtures-yaml")]
+    Yaml(&'a str),
+}
+
+impl<'a> FixtureSource<'a> {
+    fn deserialize<T: DeserializeOwned>(self) -> Result<Vec<T>, Error> {
+        match self {
+            #[cfg(feature = "fixtures-json")]
+            FixtureSource::Json(source) => {
+                serde_json::from_str(source).map_err(|error| Error::Fixture(Box::new(error)))
+            }
+            #[cfg(feature = "fixtures-yaml")]
+            FixtureSource::Yaml(source) => {
+                serde_yaml::from_str(source).map_err(|error| Error::Fixture(Box::new(error)))
+            }
+        }
+    }
+}
+
+pub(crate) async fn load_fixtures<T>(
+    connection: &Connection,
+    source: FixtureSource<'_>,
+) -> Result<Vec<T>, Error>
+where
+    T: ToSql + FromSql + DeserializeOwned,
+{
+    let items: Vec<T> = source.deserialize()?;
+    if items.is_empty() {
+        return Ok(items);
+    }
+    let transaction = connection.transaction().await?;
+    // Deferrable foreign keys are only checked when the fixture is committed.
+    transaction
+        .batch_execute("SET CONSTRAINTS ALL DEFERRED")
+        .await?;
+    let created = transaction.create_multiple(&items).await?;
+    transaction.commit().await?;
+    Ok(created)
+}
@@ -0,0 +1,46 @@
+use crate::tenant::tenant_filter;
+use crate::*;
+
+/// The name of the common table expression that copies rows into the history table.
+pub(crate) const HISTORY_ALIAS: &str = "sprattus_history";
+
+/// The column of the history table that holds the time a row version was replaced.
+pub(crate) const CHANGED_AT_COLUMN: &str = "changed_at";
+
+/// Returns a common table expression copying the rows of `T` that match the condition into its
+/// history table, to be prepended to an update or delete, or an empty string when the table has
+/// no history table.
+///
+/// The copy is part of the same statement as the write, so it copies the rows as they were before
+/// the write, and is only committed together with the write.
+pub(crate) fn history_clause<T: Table>(table_name: &str, condition: &str) -> String {
+    match T::get_history_table() {
+        Some(history_table) => format!(
+            "WITH {alias} AS (INSERT INTO {history_table} ({fields}) \
+             SELECT {fields} FROM {table_name} WHERE {condition}) ",
+            alias = HISTORY_ALIAS,
+            history_table = history_table,
+            fields = T::get_all_fields(),
+            table_name = table_name,
+            condition = condition,
+        ),
+        None => String::new(),
+    }
+}
+
+/// Generates the statement that selects the previous versions of a row from the history table,
+/// the most recent version first.
+pub(crate) fn generate_history_statement<T: Identifiable>(
+    history_table: &str,
+    masking: Masking,
+) -> String {
+    format!(
+        "SELECT {fields} FROM {history_table} WHERE {primary_key} = $1{tenant} \
+         ORDER BY {changed_at} DESC",
+        fields = masking.columns::<T>(),
+        history_table = history_table,
+        primary_key = T::get_primary_key(),
+        tenant = tenant_filter::<T>(None),
+        changed_at = ident(CHANGED_AT_COLUMN),
+    )
+}
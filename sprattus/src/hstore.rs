@@ -0,0 +1,150 @@
+use crate::*;
+use std::collections::hash_map::{self, HashMap};
+use std::error::Error;
+use std::iter::FromIterator;
+use tokio_postgres::types::{to_sql_checked, IsNull, Type};
+
+/// The value of an `HSTORE` column: a map of text keys to text values that can be `NULL`.
+///
+/// The derive macros recognize the type, so a field of this type needs no `pg_type` annotation.
+/// Rows can be selected by the pairs of the column with
+/// [`Select::filter_hstore_contains`](struct.Select.html#method.filter_hstore_contains) and
+/// [`Select::filter_hstore_has_key`](struct.Select.html#method.filter_hstore_has_key).
+///
+/// Example:
+/// ```
+/// use sprattus::*;
+///
+/// #[derive(FromSql, ToSql, Debug)]
+/// #[sprattus(table = "products")]
+/// struct Product {
+///     #[sprattus(primary_key)]
+///     prod_id: i32,
+///     attributes: Hstore,
+/// }
+///
+/// let mut attributes = Hstore::new();
+/// attributes.insert("color", "red");
+/// attributes.insert_null("size");
+/// assert_eq!(attributes.get("color"), Some("red"));
+/// assert_eq!(attributes.get("size"), None);
+/// assert!(attributes.contains_key("size"));
+/// ```
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Hstore(HashMap<String, Option<String>>);
+
+impl Hstore {
+    /// Creates an empty map.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the value of a key, or `None` when the key is missing or its value is `NULL`.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(Option::as_deref)
+    }
+
+    /// Returns whether the map contains a key, also when its value is `NULL`.
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.0.contains_key(key)
+    }
+
+    /// Sets the value of a key, returning the previous value if the key was present.
+    pub fn insert(
+        &mut self,
+        key: impl Into<String>,
+        value: impl Into<String>,
+    ) -> Option<Option<String>> {
+        self.0.insert(key.into(), Some(value.into()))
+    }
+
+    /// Sets the value of a key to `NULL`, returning the previous value if the key was present.
+    pub fn insert_null(&mut self, key: impl Into<String>) -> Option<Option<String>> {
+        self.0.insert(key.into(), None)
+    }
+
+    /// Removes a key, returning its value if the key was present.
+    pub fn remove(&mut self, key: &str) -> Option<Option<String>> {
+        self.0.remove(key)
+    }
+
+    /// Returns the number of keys.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Returns whether the map has no keys.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Returns the pairs of the map, in arbitrary order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, Option<&str>)> {
+        self.0
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_deref()))
+    }
+
+    /// Returns the map of keys to values.
+    pub fn into_inner(self) -> HashMap<String, Option<String>> {
+        self.0
+    }
+}
+
+impl From<HashMap<String, Option<String>>> for Hstore {
+    fn from(map: HashMap<String, Option<String>>) -> Self {
+        Hstore(map)
+    }
+}
+
+impl From<Hstore> for HashMap<String, Option<String>> {
+    fn from(hstore: Hstore) -> Self {
+        hstore.0
+    }
+}
+
+impl<K: Into<String>, V: Into<String>> FromIterator<(K, V)> for Hstore {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(pairs: I) -> Self {
+        Hstore(
+            pairs
+                .into_iter()
+                .map(|(key, value)| (key.into(), Some(value.into())))
+                .collect(),
+        )
+    }
+}
+
+impl IntoIterator for Hstore {
+    type Item = (String, Option<String>);
+    type IntoIter = hash_map::IntoIter<String, Option<String>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl ToSqlItem for Hstore {
+    fn to_sql(
+        &self,
+        ty: &Type,
+        out: &mut export::BytesMut,
+    ) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
+        self.0.to_sql(ty, out)
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        <HashMap<String, Option<String>> as ToSqlItem>::accepts(ty)
+    }
+
+    to_sql_checked!();
+}
+
+impl<'a> FromSqlItem<'a> for Hstore {
+    fn from_sql(ty: &Type, raw: &'a [u8]) -> Result<Self, Box<dyn Error + Sync + Send>> {
+        HashMap::from_sql(ty, raw).map(Hstore)
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        <HashMap<String, Option<String>> as FromSqlItem>::accepts(ty)
+    }
+}
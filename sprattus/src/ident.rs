@@ -0,0 +1,72 @@
+use crate::Error;
+
+///
+/// Quotes a Postgres identifier, like the name of a table or column, for use in SQL.
+///
+/// The name is wrapped in double quotes and any double quotes within it are escaped, so the
+/// identifier keeps its casing, can be a reserved keyword, and can't be used to inject SQL.
+/// This is the same quoting the derive macros apply to table and column names.
+///
+/// Example:
+/// ```
+/// assert_eq!(sprattus::ident("Products"), "\"Products\"");
+/// assert_eq!(sprattus::ident("user"), "\"user\"");
+/// assert_eq!(sprattus::ident("say \"hi\""), "\"say \"\"hi\"\"\"");
+/// ```
+pub fn ident(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+///
+/// Quotes a table name, which may be qualified with a schema like `public.products`.
+///
+pub(crate) fn table_ident(name: &str) -> String {
+    name.split('.')
+        .map(ident)
+        .collect::<Vec<String>>()
+        .join(".")
+}
+
+///
+/// Splits a quoted table name like `"public"."products"` into its unquoted schema, when it is
+/// qualified with one, and its unquoted table name.
+///
+#[cfg(feature = "replication")]
+pub(crate) fn unquote_table(name: &str) -> (Option<String>, String) {
+    let mut parts = vec![String::new()];
+    let mut quoted = false;
+    let mut characters = name.chars().peekable();
+    while let Some(character) = characters.next() {
+        match character {
+            '"' if quoted && characters.peek() == Some(&'"') => {
+                characters.next();
+                parts.last_mut().unwrap().push('"');
+            }
+            '"' => quoted = !quoted,
+            '.' if !quoted => parts.push(String::new()),
+            character => parts.last_mut().unwrap().push(character),
+        }
+    }
+    let table = parts.pop().unwrap_or_default();
+    (parts.pop(), table)
+}
+
+/// The maximum length in bytes of a Postgres identifier, longer names are truncated by Postgres.
+const MAX_IDENTIFIER_LENGTH: usize = 63;
+
+///
+/// Checks that a table name, which may be qualified with a schema, is a valid identifier: a table
+/// name and an optional schema that are not empty, not longer than Postgres allows and without
+/// NUL characters.
+///
+pub(crate) fn validate_table_name(name: &str) -> Result<(), Error> {
+    let parts: Vec<&str> = name.split('.').collect();
+    let invalid = parts.len() > 2
+        || parts.iter().any(|part| {
+            part.is_empty() || part.len() > MAX_IDENTIFIER_LENGTH || part.contains('\0')
+        });
+    if invalid {
+        return Err(Error::Config(format!("invalid table name '{}'", name)));
+    }
+    Ok(())
+}
@@ -0,0 +1,186 @@
+use crate::connection::Access;
+use crate::select::column_name;
+use crate::*;
+use futures_util::stream::{self, Stream, StreamExt};
+use std::pin::Pin;
+
+/// The number of bytes read from the database at a time.
+pub(crate) const CHUNK_SIZE: i32 = 256 * 1024;
+
+/// The `lo_open` mode to open a large object for reading, `INV_READ`.
+const INV_READ: i32 = 0x0004_0000;
+
+/// The `lo_open` mode to open a large object for writing, `INV_WRITE`.
+const INV_WRITE: i32 = 0x0002_0000;
+
+/// A large object opened for reading, within a transaction of its own, because the descriptor of
+/// a large object is only valid within the transaction in which it is opened.
+struct LargeObjectReader {
+    transaction: Transaction,
+    descriptor: i32,
+    exhausted: bool,
+}
+
+impl LargeObjectReader {
+    async fn open(connection: &Connection, oid: u32) -> Result<Self, Error> {
+        let transaction = connection.transaction().await?;
+        let descriptor = transaction
+            .query_row("SELECT lo_open($1, $2)", &[&oid, &INV_READ], Access::Write)
+            .await?
+            .try_get(0)?;
+        Ok(Self {
+            transaction,
+            descriptor,
+            exhausted: false,
+        })
+    }
+
+    async fn next_chunk(&mut self) -> Result<Option<Vec<u8>>, Error> {
+        if self.exhausted {
+            return Ok(None);
+        }
+        let chunk: Vec<u8> = self
+            .transaction
+            .query_row(
+                "SELECT loread($1, $2)",
+                &[&self.descriptor, &CHUNK_SIZE],
+                Access::Write,
+            )
+            .await?
+            .try_get(0)?;
+        if chunk.len() < CHUNK_SIZE as usize {
+            self.exhausted = true;
+        }
+        if chunk.is_empty() {
+            Ok(None)
+        } else {
+            Ok(Some(chunk))
+        }
+    }
+
+    async fn close(self) -> Result<(), Error> {
+        self.transaction
+            .execute("SELECT lo_close($1)", &[&self.descriptor])
+            .await?;
+        self.transaction.commit().await
+    }
+}
+
+/// Returns the content of a large object as a stream of chunks.
+pub(crate) async fn read(
+    connection: &Connection,
+    oid: u32,
+) -> Result<impl Stream<Item = Result<Vec<u8>, Error>>, Error> {
+    let reader = LargeObjectReader::open(connection, oid).await?;
+    Ok(stream::unfold(Some(reader), |reader| async move {
+        let mut reader = reader?;
+        match reader.next_chunk().await {
+            Ok(Some(chunk)) => Some((Ok(chunk), Some(reader))),
+            Ok(None) => match reader.close().await {
+                Ok(()) => None,
+                Err(error) => Some((Err(error), None)),
+            },
+            Err(error) => Some((Err(error), None)),
+        }
+    }))
+}
+
+/// Creates a large object with the content of the stream, returning its oid.
+///
+/// The large object is created within a transaction, so it is removed again when writing fails.
+pub(crate) async fn write<S, B, E>(connection: &Connection, chunks: S) -> Result<u32, Error>
+where
+    S: Stream<Item = Result<B, E>>,
+    B: AsRef<[u8]>,
+    E: Into<Box<dyn std::error::Error + Sync + Send>>,
+{
+    let transaction = connection.transaction().await?;
+    let oid: u32 = transaction
+        .query_row("SELECT lo_create(0)", &[], Access::Write)
+        .await?
+        .try_get(0)?;
+    let descriptor: i32 = transaction
+        .query_row("SELECT lo_open($1, $2)", &[&oid, &INV_WRITE], Access::Write)
+        .await?
+        .try_get(0)?;
+    let mut chunks: Pin<Box<S>> = Box::pin(chunks);
+    while let Some(chunk) = chunks.next().await {
+        let chunk = chunk.map_err(|error| Error::Source(error.into()))?;
+        transaction
+            .execute("SELECT lowrite($1, $2)", &[&descriptor, &chunk.as_ref()])
+            .await?;
+    }
+    transaction
+        .execute("SELECT lo_close($1)", &[&descriptor])
+        .await?;
+    transaction.commit().await?;
+    Ok(oid)
+}
+
+/// Returns the content of the `BYTEA` column of a field of the row with the given primary key as
+/// a stream of chunks.
+///
+/// The chunks are read within a `REPEATABLE READ` transaction, so they all belong to the same
+/// version of the row.
+pub(crate) async fn read_bytea<'a, T>(
+    connection: &Connection,
+    primary_key: &'a T::PK,
+    field: &str,
+) -> Result<impl Stream<Item = Result<Vec<u8>, Error>> + 'a, Error>
+where
+    T: Identifiable,
+{
+    let sql = format!(
+        "SELECT substring({column} FROM $2 FOR $3) FROM {table_name} WHERE {primary_key} = $1",
+        column = column_name::<T>(field),
+        table_name = connection.table_name::<T>(),
+        primary_key = T::get_primary_key(),
+    );
+    let transaction = connection
+        .transaction_with(TransactionOptions {
+            isolation: Some(IsolationLevel::RepeatableRead),
+            ..TransactionOptions::default()
+        })
+        .await?;
+    // The state holds the transaction and the position of the next chunk, which is 1-based, or
+    // `None` when all chunks are read and the transaction is to be committed.
+    Ok(stream::unfold(Some((transaction, Some(1))), move |state| {
+        let sql = sql.clone();
+        async move {
+            let (transaction, position): (Transaction, Option<i32>) = state?;
+            let position = match position {
+                Some(position) => position,
+                None => {
+                    return match transaction.commit().await {
+                        Ok(()) => None,
+                        Err(error) => Some((Err(error), None)),
+                    }
+                }
+            };
+            let chunk = transaction
+                .query_row(
+                    sql.as_str(),
+                    &[primary_key, &position, &CHUNK_SIZE],
+                    Access::Read,
+                )
+                .await
+                .and_then(|row| Ok(row.try_get::<_, Option<Vec<u8>>>(0)?));
+            match chunk {
+                Ok(Some(chunk)) if !chunk.is_empty() => {
+                    let next = if chunk.len() < CHUNK_SIZE as usize {
+                        None
+                    } else {
+                        Some(position + CHUNK_SIZE)
+                    };
+                    Some((Ok(chunk), Some((transaction, next))))
+                }
+                // The column is `NULL` or all chunks are read.
+                Ok(_) => match transaction.commit().await {
+                    Ok(()) => None,
+                    Err(error) => Some((Err(error), None)),
+                },
+                Err(error) => Some((Err(error), None)),
+            }
+        }
+    }))
+}
@@ -26,9 +26,9 @@
 //! use sprattus::*;
 //!
 //! #[derive(ToSql, FromSql, Debug)]
-//! #[sql(table = "fruits")]
+//! #[sprattus(table = "fruits")]
 //! struct Fruit {
-//!     #[sql(primary_key)]
+//!     #[sprattus(primary_key)]
 //!     id: i32,
 //!     name: String
 //! }
@@ -36,13 +36,12 @@
 //! And now your ready to use the client in combination with you freshly created struct!
 //!
 //! ```no_run
-//! use tokio::prelude::*;
 //! use sprattus::*;
 //!
 //! #[derive(ToSql, FromSql, Debug)]
-//! #[sql(table = "fruits")]
+//! #[sprattus(table = "fruits")]
 //! struct Fruit {
-//!     #[sql(primary_key)]
+//!     #[sprattus(primary_key)]
 //!     id: i32,
 //!     name: String
 //! }
@@ -78,6 +77,7 @@
 //! | `&str`/`String`                   | VARCHAR, CHAR(n), TEXT, CITEXT, NAME, UNKNOWN |
 //! | `&[u8]`/`Vec<u8>`                 | BYTEA                                         |
 //! | `HashMap<String, Option<String>>` | HSTORE                                        |
+//! | [`Hstore`](struct.Hstore.html)    | HSTORE                                        |
 //! | `SystemTime`                      | TIMESTAMP, TIMESTAMP WITH TIME ZONE           |
 //! | `IpAddr`                          | INET                                          |
 //!
@@ -113,17 +113,19 @@
 //! # Annotations
 //!
 //! On user created structs, there are several options configurable by using annotiations.
+//! The annotations are written in a `#[sprattus(...)]` attribute. The old `#[sql(...)]` attribute
+//! is still accepted as an alias, but it is deprecated and results in a warning.
 //! ### Renaming fields
 //! In any case of having not the same name for a field in the database and in Rust, use the rename annotation.
 //! ```no_run
 //! # use sprattus::*;
 //! # #[derive(ToSql)]
 //! struct Product {
-//!     #[sql(primary_key)]
+//!     #[sprattus(primary_key)]
 //!     id: i32,
 //!     name: String,
 //!     // Renames the postgres field 'product_price' to costs.
-//!     #[sql(name = "product_price")]
+//!     #[sprattus(name = "product_price")]
 //!     costs: f64
 //! }
 //! ```
@@ -135,11 +137,72 @@
 //! # #[derive(ToSql)]
 //! struct User {
 //!     // Annotates id as primary key of the table.
-//!     #[sql(primary_key)]
+//!     #[sprattus(primary_key)]
 //!     id: i32,
 //!     name: String,
 //! }
 //! ```
+//! Structs with a field named `id` can opt in to using that field as the primary key with the
+//! `auto_primary_key` annotation on the struct:
+//! ```no_run
+//! # use sprattus::*;
+//! #[derive(ToSql)]
+//! #[sprattus(auto_primary_key)]
+//! struct User {
+//!     id: i32,
+//!     name: String,
+//! }
+//! ```
+//! The primary key is not inserted when a row is created, because it is expected to be generated
+//! by the database, like a `SERIAL` column. A primary key that is an `Option` is inserted when it
+//! is `Some`, and inserted as `DEFAULT` when it is `None`, so the database generates it. The created
+//! row returned by the database contains the generated key.
+//! [`Connection::save`](struct.Connection.html#method.save) creates a row when the primary key is
+//! `None`, and updates it otherwise:
+//! ```no_run
+//! # use sprattus::*;
+//! #[derive(ToSql, FromSql)]
+//! struct User {
+//!     #[sprattus(primary_key)]
+//!     id: Option<i32>,
+//!     name: String,
+//! }
+//! ```
+//! Tables without a primary key, like a log table, use the `no_primary_key` annotation instead.
+//! Their rows can't be identified, so they can't be updated or deleted through the derived
+//! implementation. They can still be read, e.g. with `select` or `query_multiple`, and inserted
+//! with `insert`:
+//! ```no_run
+//! # use sprattus::*;
+//! #[derive(ToSql, FromSql)]
+//! #[sprattus(table = "page_views", no_primary_key)]
+//! struct PageView {
+//!     url: String,
+//!     visitor: String,
+//! }
+//! ```
+//! ### Unique constraints
+//! The unique constraints of a table can be declared on the struct with the `unique` annotation,
+//! listing the fields of the constraint. It can be repeated for every constraint. Creating or
+//! updating a row that violates a declared constraint results in an
+//! [`Error::UniqueViolation`](enum.Error.html#variant.UniqueViolation) with the columns of the
+//! constraint, instead of a generic Postgres error. Like every error of a generated statement, it
+//! is wrapped in an [`Error::Statement`](enum.Error.html#variant.Statement) naming the statement,
+//! and is matched on [`Error::inner`](enum.Error.html#method.inner).
+//! ```no_run
+//! # use sprattus::*;
+//! #[derive(ToSql)]
+//! #[sprattus(table = "products", unique = "title, actor", unique = "isbn")]
+//! struct Product {
+//!     #[sprattus(primary_key)]
+//!     prod_id: i32,
+//!     title: String,
+//!     actor: String,
+//!     isbn: String,
+//! }
+//! ```
+//! Multiple annotations can be combined in a single attribute, like
+//! `#[sprattus(primary_key, name = "user_id")]`.
 //! ### Selecting a database table
 //! In many cases, the name of your Rust struct will not correspond with the table in Postgres.
 //! To solve that problem, there is a attribute to select the table belonging to the created struct:
@@ -147,21 +210,591 @@
 //! # use sprattus::*;
 //! // This tells sprattus to use the 'houses' table in Postgres.
 //! #[derive(ToSql)]
-//! #[sql(table = "houses")]
+//! #[sprattus(table = "houses")]
 //! struct House {
-//!     #[sql(primary_key)]
+//!     #[sprattus(primary_key)]
 //!     id: i32,
 //!     address: String,
 //!     city: String,
 //!     country: String,
 //! }
 //! ```
+//! Table and column names are always quoted in the generated SQL, so they keep their casing and
+//! can be reserved keywords: `#[sprattus(table = "MyTable")]` refers to `"MyTable"`, not to
+//! `mytable`. A table name can be qualified with a schema, like
+//! `#[sprattus(table = "public.houses")]`. Add the `quote` annotation to use a name containing a
+//! dot as a single identifier instead, like `#[sprattus(table = "sales.2020", quote)]` for the
+//! table `"sales.2020"`. Use [`ident`](fn.ident.html) to quote names in your own SQL.
+//! ### Default values
+//! When a struct is also used to query a subset of the columns, fields that may be missing from
+//! the queried rows can be annotated with `default`. These fields fall back on their
+//! `Default::default()` value when the column is not part of the row.
+//! ```no_run
+//! # use sprattus::*;
+//! #[derive(FromSql)]
+//! struct Product {
+//!     prod_id: i32,
+//!     title: String,
+//!     // Is 0.0 when the queried rows have no price column.
+//!     #[sprattus(default)]
+//!     price: f64,
+//! }
+//! ```
+//! To let the database fill in the default value of a column when a row is created, annotate an
+//! `Option` field with `default_on_none`. When the field is `None`, the insert uses `DEFAULT`
+//! instead of `NULL`. Other values are inserted as usual.
+//! ```no_run
+//! # use sprattus::*;
+//! #[derive(ToSql)]
+//! struct Product {
+//!     #[sprattus(primary_key)]
+//!     prod_id: i32,
+//!     title: String,
+//!     // Uses the default of the column when None.
+//!     #[sprattus(default_on_none)]
+//!     price: Option<f64>,
+//! }
+//! ```
+//! ### Immutable fields
+//! Fields annotated with `immutable` are written when a row is created, but never changed by
+//! [`update`](struct.Connection.html#method.update) or
+//! [`update_multiple`](struct.Connection.html#method.update_multiple).
+//! ```no_run
+//! # use sprattus::*;
+//! #[derive(ToSql, FromSql)]
+//! struct Account {
+//!     #[sprattus(primary_key)]
+//!     id: i32,
+//!     #[sprattus(immutable)]
+//!     tenant_id: i32,
+//!     name: String,
+//! }
+//! ```
+//! ### Timestamps
+//! Fields annotated with `created_at` are set to `now()` when a row is created, and fields annotated
+//! with `updated_at` are set to `now()` every time a row is created or updated.
+//! The values of these fields in the struct are never sent to the database, which makes it
+//! possible to keep audit timestamps without triggers.
+//! ```no_run
+//! # use sprattus::*;
+//! # use std::time::SystemTime;
+//! #[derive(ToSql, FromSql)]
+//! struct Document {
+//!     #[sprattus(primary_key)]
+//!     id: i32,
+//!     body: String,
+//!     #[sprattus(created_at)]
+//!     created_at: SystemTime,
+//!     #[sprattus(updated_at)]
+//!     updated_at: SystemTime,
+//! }
+//! ```
+//! ### Custom conversions
+//! A field of a type that can't be converted from and to Postgres values itself, like an enum
+//! stored as `TEXT`, can name conversion functions with the `with` and `from` attributes.
+//! The `with` function converts a reference to the field into a value implementing `ToSqlItem`,
+//! the `from` function converts a value implementing `FromSqlItem` into the field, or returns an
+//! error.
+//! ```no_run
+//! # use sprattus::*;
+//! #[derive(Debug)]
+//! enum Status {
+//!     Open,
+//!     Closed,
+//! }
+//!
+//! mod status {
+//!     # use super::Status;
+//!     pub fn to_sql(status: &Status) -> &'static str {
+//!         match status {
+//!             Status::Open => "open",
+//!             Status::Closed => "closed",
+//!         }
+//!     }
+//!
+//!     pub fn from_sql(status: String) -> Result<Status, String> {
+//!         match status.as_str() {
+//!             "open" => Ok(Status::Open),
+//!             "closed" => Ok(Status::Closed),
+//!             _ => Err(format!("unknown status {}", status)),
+//!         }
+//!     }
+//! }
+//!
+//! #[derive(ToSql, FromSql)]
+//! struct Ticket {
+//!     #[sprattus(primary_key)]
+//!     id: i32,
+//!     #[sprattus(with = "status::to_sql", from = "status::from_sql", pg_type = "TEXT")]
+//!     status: Status,
+//! }
+//! # fn main() {}
+//! ```
+//! ### Text enums
+//! Enums without fields can be stored in a `TEXT` column by deriving
+//! [`SqlTextEnum`](trait.SqlTextEnum.html). Every variant is stored as its name, unless another
+//! text is given with the `rename` annotation. Reading a text that belongs to no variant fails
+//! with an error naming the text. The Postgres type of the field is set with `pg_type`.
+//! ```no_run
+//! # use sprattus::*;
+//! #[derive(SqlTextEnum, Debug)]
+//! enum Status {
+//!     #[sprattus(rename = "paid")]
+//!     Paid,
+//!     #[sprattus(rename = "shipped")]
+//!     Shipped,
+//! }
+//!
+//! #[derive(ToSql, FromSql)]
+//! #[sprattus(table = "orders")]
+//! struct Order {
+//!     #[sprattus(primary_key)]
+//!     orderid: i32,
+//!     #[sprattus(pg_type = "TEXT")]
+//!     status: Status,
+//! }
+//! ```
+//! ### Unsigned integers
+//! Postgres has no unsigned integer types, so `u16` and `u64` fields need the `unsigned`
+//! annotation. With `unsigned = "int"`, a `u16` is stored as `INT4` and a `u64` as `INT8`, and
+//! with `unsigned = "numeric"` both are stored as `NUMERIC`. The values are checked when they are
+//! converted: writing a `u64` above `i64::MAX` to an `INT8` column, or reading a value that
+//! doesn't fit in the field, fails with an [`OutOfRange`](unsigned/struct.OutOfRange.html) error
+//! naming the value. Primary keys are passed to queries without conversion, so they can't be
+//! unsigned.
+//! ```no_run
+//! # use sprattus::*;
+//! #[derive(ToSql, FromSql)]
+//! #[sprattus(table = "accounts")]
+//! struct Account {
+//!     #[sprattus(primary_key)]
+//!     id: i64,
+//!     #[sprattus(unsigned = "int")]
+//!     owner_id: u64,
+//!     #[sprattus(unsigned = "int")]
+//!     region: Option<u16>,
+//!     #[sprattus(unsigned = "numeric")]
+//!     balance: u64,
+//! }
+//! ```
+//! ### Tenants
+//! A field annotated with `tenant_key` holds the tenant a row belongs to. All queries generated
+//! for the struct are restricted to the rows of the current tenant, which is set by
+//! [`with_tenant`](struct.Connection.html#method.with_tenant).
+//! ```no_run
+//! # use sprattus::*;
+//! #[derive(ToSql, FromSql)]
+//! struct Document {
+//!     #[sprattus(primary_key)]
+//!     id: i32,
+//!     #[sprattus(tenant_key, immutable)]
+//!     tenant_id: i32,
+//!     body: String,
+//! }
+//! ```
+//! ### Masked columns
+//! The values of fields annotated with `masked`, like credit card numbers, are selected as
+//! `'****'` followed by their last four characters, unless the connection reads them unmasked
+//! with [`with_masking`](struct.Connection.html#method.with_masking). Values of four characters or
+//! less are selected as just `'****'`. Only `String` fields can be masked, because the masked
+//! value is text.
+//! ```
+//! # use sprattus::*;
+//! #[derive(ToSql, FromSql)]
+//! #[sprattus(table = "customers")]
+//! struct Customer {
+//!     #[sprattus(primary_key)]
+//!     customerid: i32,
+//!     #[sprattus(masked)]
+//!     creditcard: String,
+//! }
+//!
+//! assert_eq!(Customer::get_masked_fields(), &["\"creditcard\""]);
+//! ```
+//! ### History tables
+//! Tables annotated with `history_table` keep the previous versions of their rows. Every update
+//! or delete copies the rows as they were before into the history table, in the same statement,
+//! so the copy is only kept when the change is committed. The history table has the columns of
+//! the struct and a `changed_at` column with a default value, and the versions of a row are read
+//! with [`history_of`](struct.Connection.html#method.history_of).
+//! ```
+//! # use sprattus::*;
+//! // CREATE TABLE products_history (
+//! //     prod_id INT NOT NULL,
+//! //     title TEXT NOT NULL,
+//! //     changed_at TIMESTAMPTZ NOT NULL DEFAULT clock_timestamp()
+//! // );
+//! #[derive(ToSql, FromSql)]
+//! #[sprattus(table = "products", history_table = "products_history")]
+//! struct Product {
+//!     #[sprattus(primary_key)]
+//!     prod_id: i32,
+//!     title: String,
+//! }
+//!
+//! assert_eq!(Product::get_history_table(), Some("\"products_history\""));
+//! ```
+//! ### Column names
+//! Next to the implementation of `ToSql`, the derive macro generates a `{Struct}Columns` struct
+//! containing the name of the table and a constant with the Postgres name of every field.
+//! These can be used to write raw SQL without repeating column names as strings.
+//! ```
+//! # use sprattus::*;
+//! #[derive(ToSql)]
+//! #[sprattus(table = "products")]
+//! struct Product {
+//!     #[sprattus(primary_key)]
+//!     prod_id: i32,
+//!     #[sprattus(name = "title")]
+//!     name: String,
+//! }
+//!
+//! assert_eq!(ProductColumns::TABLE, "products");
+//! assert_eq!(ProductColumns::PROD_ID, "prod_id");
+//! assert_eq!(ProductColumns::NAME, "title");
+//! ```
+//! ### Insert models
+//! With the `insert_model` annotation on the struct, the `ToSql` derive macro also generates a
+//! `New{Struct}` struct with all fields except the primary key. It can be inserted with
+//! [`create_new`](struct.Connection.html#method.create_new), so no placeholder value is needed for
+//! a primary key that is generated by the database.
+//! ```no_run
+//! # use sprattus::*;
+//! #[derive(ToSql, FromSql)]
+//! #[sprattus(table = "products")]
+//! #[sprattus(insert_model)]
+//! struct Product {
+//!     #[sprattus(primary_key)]
+//!     prod_id: i32,
+//!     title: String,
+//! }
+//!
+//! let new_product = NewProduct { title: String::from("Sql insert lesson") };
+//! ```
+//! ### Builders
+//! With the `builder` annotation, the `ToSql` derive macro also generates a `{Struct}Builder`,
+//! returned by `{Struct}::builder()`, which sets the fields one at a time. Fields that are an
+//! `Option`, have the `default` annotation, or are an integer primary key don't have to be set,
+//! and start as `None`, their default value and `0`. `build()` only compiles once all other
+//! fields are set, so a struct for [`create`](struct.Connection.html#method.create) needs no
+//! placeholder for its generated primary key.
+//! ```
+//! # use sprattus::*;
+//! #[derive(ToSql, FromSql, Debug)]
+//! #[sprattus(table = "products", builder)]
+//! struct Product {
+//!     #[sprattus(primary_key)]
+//!     prod_id: i32,
+//!     title: String,
+//!     price: f64,
+//!     special: Option<i16>,
+//! }
+//!
+//! let product = Product::builder().title(String::from("Rust ORM")).price(9.99).build();
+//! assert_eq!(product.prod_id, 0);
+//! assert_eq!(product.special, None);
+//! ```
+//! Leaving out a required field is a compile error:
+//! ```compile_fail
+//! # use sprattus::*;
+//! #[derive(ToSql, FromSql, Debug)]
+//! #[sprattus(table = "products", builder)]
+//! struct Product {
+//!     #[sprattus(primary_key)]
+//!     prod_id: i32,
+//!     title: String,
+//!     price: f64,
+//! }
+//!
+//! let product = Product::builder().title(String::from("Rust ORM")).build();
+//! ```
+//! ### Validation
+//! Fields with the `validate` annotation are checked before an item is created or updated.
+//! The `length` rule bounds the number of characters of a string or the number of elements of a
+//! `Vec`, the `range` rule bounds a value, with a `min` and/or a `max`. A bound that isn't a
+//! single literal, like a negative number, is written as a string. Fields that are `None` are
+//! not checked. When a field doesn't meet a rule, the item is not written and an
+//! [`Error::Validation`](enum.Error.html#variant.Validation) lists the failing fields. The insert
+//! model of a struct with the `insert_model` annotation is checked with the same rules.
+//! ```
+//! # use sprattus::*;
+//! #[derive(ToSql, FromSql)]
+//! #[sprattus(table = "products", insert_model)]
+//! struct Product {
+//!     #[sprattus(primary_key)]
+//!     prod_id: i32,
+//!     #[sprattus(validate(length(min = 1, max = 50)))]
+//!     title: String,
+//!     #[sprattus(validate(range(min = 0.0)))]
+//!     price: f64,
+//!     #[sprattus(validate(range(min = "-1", max = 1)))]
+//!     special: Option<i16>,
+//! }
+//!
+//! let product = Product { prod_id: 0, title: String::new(), price: -1.0, special: None };
+//! let error = product.validate().unwrap_err();
+//! assert_eq!(error.fields[0].field, "title");
+//! assert_eq!(error.fields[1].field, "price");
+//! assert_eq!(error.fields[1].message, "the value must be at least 0");
+//!
+//! let new_product = NewProduct { title: String::from("Rust ORM"), price: 9.99, special: Some(2) };
+//! assert_eq!(new_product.validate().unwrap_err().fields[0].field, "special");
+//! ```
+//! ### Lifecycle callbacks
+//! With the `lifecycle` annotation, the `ToSql` derive macro leaves the implementation of
+//! [`Lifecycle`](trait.Lifecycle.html) to the struct, so it can check items before they are
+//! written, or act on the rows after they are written. The callbacks can't change the items, so
+//! values are normalized before they are passed to the connection.
+//! ### Projections
+//! A struct with a subset of the fields of another struct can be derived as a `Projection`.
+//! It shares the table of the struct given with the `of` annotation, and can be queried with
+//! [`select_projection`](struct.Connection.html#method.select_projection).
+//! ```no_run
+//! # use sprattus::*;
+//! #[derive(ToSql, FromSql)]
+//! #[sprattus(table = "products")]
+//! struct Product {
+//!     #[sprattus(primary_key)]
+//!     prod_id: i32,
+//!     title: String,
+//!     price: f64,
+//! }
+//!
+//! #[derive(FromSql, Projection)]
+//! #[sprattus(of = "Product")]
+//! struct ProductTitle {
+//!     prod_id: i32,
+//!     title: String,
+//! }
+//! ```
+//! ### Generics
+//! Structs can have generic parameters. The derive macros require every type parameter to be
+//! convertible from and to a Postgres value, and fields with a generic type need the `pg_type`
+//! annotation.
+//! ```no_run
+//! # use sprattus::*;
+//! #[derive(ToSql, FromSql)]
+//! #[sprattus(table = "events")]
+//! struct Event<T: std::fmt::Debug> {
+//!     #[sprattus(primary_key)]
+//!     id: i32,
+//!     #[sprattus(pg_type = "JSONB")]
+//!     payload: T,
+//! }
+//! ```
+//! ### Borrowed fields
+//! Structs that are only written to the database, like the values of a new row, can borrow their
+//! fields with a lifetime parameter, so creating them does not need to allocate. Borrowed `&str`
+//! and `Cow<str>` fields have the same Postgres type as a `String`. The created rows can't be read
+//! into such a struct, so it is created with methods that only return the primary key, like
+//! [`create_returning_key`](struct.Connection.html#method.create_returning_key).
+//! ```no_run
+//! # use sprattus::*;
+//! # use std::borrow::Cow;
+//! #[derive(ToSql)]
+//! #[sprattus(table = "products")]
+//! struct NewProduct<'a> {
+//!     #[sprattus(primary_key)]
+//!     prod_id: i32,
+//!     title: &'a str,
+//!     description: Cow<'a, str>,
+//! }
+//! ```
+//! ### Views
+//! Structs for database views, or other tables that should not be written to, are annotated with
+//! `view` instead of `table`. They don't need a primary key and can only be read, using
+//! [`select`](struct.Connection.html#method.select). Creating, updating or deleting them is
+//! rejected at compile time.
+//! ```no_run
+//! # use sprattus::*;
+//! #[derive(ToSql, FromSql)]
+//! #[sprattus(view = "product_summary")]
+//! struct ProductSummary {
+//!     category: i32,
+//!     product_count: i64,
+//! }
+//! ```
+//!
+//! Materialized views are annotated with `materialized_view`. Besides being read, they can be
+//! refreshed with
+//! [`refresh_materialized_view`](struct.Connection.html#method.refresh_materialized_view), or on
+//! an interval with
+//! [`refresh_materialized_view_every`](struct.Connection.html#method.refresh_materialized_view_every).
+//! ```no_run
+//! # use sprattus::*;
+//! #[derive(ToSql, FromSql)]
+//! #[sprattus(materialized_view = "sales_per_month")]
+//! struct SalesPerMonth {
+//!     month: i32,
+//!     revenue: i64,
+//! }
+//!
+//! # async fn refresh(conn: &Connection) -> Result<(), Error> {
+//! conn.refresh_materialized_view::<SalesPerMonth>(false).await?;
+//! # Ok(())
+//! # }
+//! ```
+//! ### Newtypes
+//! Tuple structs with a single field can be used to give a column its own Rust type.
+//! Deriving `ToSql` and `FromSql` on such a newtype makes it usable as the type of a field,
+//! including the primary key. When a field has a type sprattus does not know, the Postgres type
+//! needs to be set with the `pg_type` annotation.
+//! ```no_run
+//! # use sprattus::*;
+//! #[derive(ToSql, FromSql, Clone, Copy, Debug)]
+//! struct ProductId(i32);
+//!
+//! #[derive(ToSql, FromSql)]
+//! struct Product {
+//!     #[sprattus(primary_key)]
+//!     #[sprattus(pg_type = "INT")]
+//!     prod_id: ProductId,
+//!     title: String,
+//! }
+//! ```
+//! ### Raw SQL
+//! The [`sql!`](macro.sql.html) macro writes a query for a struct in plain SQL, in which
+//! `{table}` and `{columns}` are replaced by the table and the columns of the struct. The number
+//! of arguments is checked against the `$n` placeholders at compile time.
+//! ```
+//! # use sprattus::*;
+//! #[derive(ToSql, FromSql)]
+//! struct Product {
+//!     #[sprattus(primary_key)]
+//!     prod_id: i32,
+//!     title: String,
+//! }
+//!
+//! let query = sql!(Product, "SELECT {columns} FROM {table} WHERE prod_id = $1", &1);
+//! assert_eq!(query.sql(), r#"SELECT "prod_id","title" FROM "Product" WHERE prod_id = $1"#);
+//! ```
+//! ```compile_fail
+//! # use sprattus::*;
+//! # #[derive(ToSql, FromSql)]
+//! # struct Product {
+//! #     #[sprattus(primary_key)]
+//! #     prod_id: i32,
+//! #     title: String,
+//! # }
+//! // The placeholder $2 has no argument.
+//! let query = sql!(Product, "SELECT {columns} FROM {table} WHERE prod_id BETWEEN $1 AND $2", &1);
+//! ```
+//!
+//! With the `offline` feature, the queries can be verified against a development database with
+//! [`Prepare`](struct.Prepare.html), which stores their columns in `sprattus-data.json`. Builds
+//! with `SPRATTUS_OFFLINE=true` check the queries and structs against that file, so they fail
+//! on changes that are not prepared without connecting to a database.
 
+#[cfg(feature = "with-actix-web-4")]
+pub mod actix;
+mod aggregate;
+#[cfg(feature = "runtime-tokio")]
+pub mod blocking;
+mod builder;
+mod circuit_breaker;
 mod connection;
+#[doc(hidden)]
+pub mod convert;
+mod cte;
+mod cursor;
+mod describe;
+mod dynamic;
+mod error;
+mod execute_result;
+mod filter;
+#[cfg(any(feature = "fixtures-json", feature = "fixtures-yaml"))]
+mod fixtures;
+mod history;
+mod hstore;
+mod ident;
+mod large_object;
+mod listen;
+mod loader;
+mod manager;
+mod masking;
+mod materialized_view;
+mod observer;
+#[cfg(feature = "offline")]
+mod offline;
+#[cfg(any(feature = "with-bb8-0_8", feature = "with-deadpool-0_9"))]
+pub mod pool;
+#[cfg(feature = "with-postgis")]
+pub mod postgis;
+mod prepared;
+#[cfg(feature = "queue")]
+pub mod queue;
+#[cfg(feature = "replication")]
+pub mod replication;
+#[cfg(feature = "runtime-tokio")]
+mod retry;
+mod returning;
+mod select;
+mod spawn;
+mod sql_log;
+mod sql_query;
+mod statement_cache;
+mod tenant;
+pub mod test;
+mod text_enum;
 mod traits;
+mod transaction;
+mod truncate;
+pub mod unsigned;
+mod upsert;
+pub mod validate;
+mod watch;
+mod write_hook;
 
+pub use self::aggregate::Aggregate;
+pub use self::builder::ConnectionBuilder;
+pub use self::circuit_breaker::{CircuitBreaker, CircuitState};
 pub use self::connection::Connection;
-pub use self::traits::{FromSql, ToSql};
-pub use sprattus_derive::{FromSql, ToSql};
+pub use self::cte::{Subquery, With};
+pub use self::cursor::Cursor;
+pub use self::describe::Statements;
+pub use self::dynamic::DynamicRow;
+pub use self::error::{ColumnMismatch, Error, MappingError, StatementError, StatementKind};
+pub use self::execute_result::ExecuteResult;
+pub use self::filter::Filter;
+#[cfg(any(feature = "fixtures-json", feature = "fixtures-yaml"))]
+pub use self::fixtures::FixtureSource;
+pub use self::hstore::Hstore;
+pub use self::ident::ident;
+pub use self::loader::Loader;
+pub use self::manager::{ConnectionManager, DATABASE_ENV_PREFIX};
+pub use self::masking::Masking;
+#[cfg(feature = "runtime-tokio")]
+pub use self::materialized_view::RefreshSchedule;
+pub use self::observer::ConnectionObserver;
+#[cfg(feature = "offline")]
+pub use self::offline::{Prepare, OFFLINE_DATA_FILE};
+pub use self::prepared::PreparedQuery;
+#[cfg(feature = "runtime-tokio")]
+pub use self::retry::RetryPolicy;
+pub use self::returning::Returning;
+pub use self::select::{Order, Select, TupleValues};
+pub use self::sql_log::LogParameters;
+pub use self::sql_query::SqlQuery;
+pub use self::text_enum::{SqlTextEnum, UnknownVariant};
+pub use self::traits::{
+    ColumnMeta, DynEntity, FieldValue, FromSql, Identifiable, InsertModel, Insertable, Lifecycle,
+    MaterializedView, Projection, Table, ToSql, Updatable,
+};
+pub use self::transaction::{IsolationLevel, Transaction, TransactionOptions};
+pub use self::truncate::Truncate;
+pub use self::upsert::{OnConflict, Upserted};
+pub use self::validate::{FieldError, ValidationError};
+pub use self::watch::Change;
+pub use self::write_hook::{WriteEvent, WriteOperation};
+pub use sprattus_derive::{sql, FromSql, Projection, SqlTextEnum, ToSql};
+pub use tokio_postgres::config::ChannelBinding;
+pub use tokio_postgres::types;
+pub use tokio_postgres::types::FromSql as FromSqlItem;
 pub use tokio_postgres::types::ToSql as ToSqlItem;
-pub use tokio_postgres::{Error, Row};
+pub use tokio_postgres::{Column, Notification, Row};
+
+/// Re-exports used by the code generated by the derive macros.
+#[doc(hidden)]
+pub mod export {
+    pub use bytes::BytesMut;
+}
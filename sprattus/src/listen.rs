@@ -0,0 +1,70 @@
+#[cfg(feature = "with-serde_json-1")]
+use crate::*;
+#[cfg(feature = "with-serde_json-1")]
+use serde::de::DeserializeOwned;
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc::UnboundedSender;
+use tokio_postgres::Notification;
+
+/// A subscription to the notifications of a single channel.
+pub(crate) struct Listener {
+    pub channel: String,
+    pub sender: UnboundedSender<Notification>,
+}
+
+/// The listeners of a connection, shared between the connection and its driver task.
+pub(crate) type Listeners = Arc<Mutex<Vec<Listener>>>;
+
+///
+/// Hands a notification to all listeners of its channel.
+/// Listeners of which the receiving side has been dropped are removed.
+///
+pub(crate) fn dispatch_notification(listeners: &Listeners, notification: Notification) {
+    let mut listeners = listeners.lock().unwrap();
+    listeners.retain(|listener| {
+        listener.channel != notification.channel()
+            || listener.sender.send(notification.clone()).is_ok()
+    });
+}
+
+/// Decodes the JSON payload of a notification on the channel, returning
+/// [`Error::MalformedPayload`](enum.Error.html#variant.MalformedPayload) when it isn't a valid T.
+#[cfg(feature = "with-serde_json-1")]
+pub(crate) fn decode_payload<T: DeserializeOwned>(
+    channel: &str,
+    payload: &str,
+) -> Result<T, Error> {
+    serde_json::from_str(payload).map_err(|error| Error::MalformedPayload {
+        channel: channel.to_string(),
+        payload: payload.to_string(),
+        source: Box::new(error),
+    })
+}
+
+#[cfg(all(test, feature = "with-serde_json-1"))]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn decodes_json_payloads() {
+        let order: HashMap<String, i32> =
+            decode_payload("orders", r#"{"id": 7, "quantity": 2}"#).unwrap();
+        assert_eq!(order["id"], 7);
+        assert_eq!(order["quantity"], 2);
+    }
+
+    #[test]
+    fn reports_malformed_payloads() {
+        let error = decode_payload::<HashMap<String, i32>>("orders", "7").unwrap_err();
+        match error {
+            Error::MalformedPayload {
+                channel, payload, ..
+            } => {
+                assert_eq!(channel, "orders");
+                assert_eq!(payload, "7");
+            }
+            error => panic!("unexpected error: {}", error),
+        }
+    }
+}
@@ -0,0 +1,181 @@
+use crate::*;
+use futures_util::future::FutureExt;
+use std::future::Future;
+use std::hash::Hash;
+use std::mem;
+#[cfg(not(feature = "runtime-tokio"))]
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+#[cfg(not(feature = "runtime-tokio"))]
+use std::task::{Context, Poll};
+#[cfg(feature = "runtime-tokio")]
+use std::time::Duration;
+use tokio::sync::oneshot;
+
+type Sender<T> = oneshot::Sender<Result<Option<T>, Error>>;
+
+///
+/// Loads rows by their primary keys, combining the loads that are requested at the same time into
+/// a single `WHERE pk = ANY($1)` query, like the data loaders of GraphQL servers.
+///
+/// The first load of a batch starts a task with the spawner of the connection, which waits until
+/// the next tick of the timer of tokio before it runs the query, so the loads requested by other
+/// tasks in the meantime are part of the batch, also on a runtime with multiple threads. The
+/// [`delay`](#method.delay) makes the task wait longer, to collect loads that are requested
+/// later. Without the `runtime-tokio` feature the task yields to the executor once instead, which
+/// only collects the loads of tasks that are ready to run on the same thread, like on an executor
+/// with a single thread. The rows are distributed to the loads with
+/// [`Connection::find_ordered`](struct.Connection.html#method.find_ordered). When the query
+/// fails, every load of the batch returns an
+/// [`Error::Batch`](enum.Error.html#variant.Batch) sharing the error.
+///
+/// The loader doesn't cache rows, so every batch reads the current rows. Clones of the loader
+/// share their batches.
+///
+/// Example:
+/// ```no_run
+/// use futures_util::future::try_join_all;
+/// use sprattus::*;
+///
+/// #[derive(FromSql, ToSql, Debug)]
+/// struct Product {
+///     #[sprattus(primary_key)]
+///     prod_id: i32,
+///     title: String
+/// }
+///
+/// #[tokio::main]
+/// async fn main() -> Result<(), Error> {
+///     let conn = Connection::new("postgresql://localhost?user=tg").await?;
+///     let loader = Loader::<Product>::new(&conn);
+///     // Executes a single query for the three products.
+///     let products = try_join_all((1..=3).map(|prod_id| loader.load(prod_id))).await?;
+///     assert_eq!(products.len(), 3);
+///     Ok(())
+/// }
+/// ```
+///
+pub struct Loader<T: Identifiable> {
+    connection: Connection,
+    batch: Arc<Mutex<Batch<T>>>,
+    #[cfg(feature = "runtime-tokio")]
+    delay: Duration,
+}
+
+/// The loads waiting for the next query.
+struct Batch<T: Identifiable> {
+    primary_keys: Vec<T::PK>,
+    senders: Vec<Sender<T>>,
+}
+
+impl<T> Loader<T>
+where
+    T: Identifiable + FromSql + Send + 'static,
+    T::PK: for<'a> FromSqlItem<'a> + Eq + Hash + Send + 'static,
+{
+    /// Creates a loader that queries the rows with the connection.
+    pub fn new(connection: &Connection) -> Self {
+        Self {
+            connection: connection.clone(),
+            batch: Arc::new(Mutex::new(Batch {
+                primary_keys: Vec::new(),
+                senders: Vec::new(),
+            })),
+            #[cfg(feature = "runtime-tokio")]
+            delay: Duration::ZERO,
+        }
+    }
+
+    /// Sets the time the first load of a batch waits for other loads before the query is run.
+    /// Without a delay the query is run on the next tick of the timer.
+    #[cfg(feature = "runtime-tokio")]
+    pub fn delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    /// Loads the row with the primary key, returning `None` when the table has no such row.
+    pub async fn load(&self, primary_key: T::PK) -> Result<Option<T>, Error> {
+        let (sender, receiver) = oneshot::channel();
+        let first = {
+            let mut batch = self.batch.lock().unwrap();
+            batch.primary_keys.push(primary_key);
+            batch.senders.push(sender);
+            batch.senders.len() == 1
+        };
+        if first {
+            (self.connection.spawner)(self.run_batch().boxed());
+        }
+        // The sender is only dropped without a result when the task is dropped by the runtime.
+        receiver.await.unwrap_or(Err(Error::Closed))
+    }
+
+    /// Returns the task that runs the query of the current batch, once the loads requested in the
+    /// meantime are added to it.
+    fn run_batch(&self) -> impl Future<Output = ()> + Send + 'static {
+        let connection = self.connection.clone();
+        let batch = self.batch.clone();
+        #[cfg(feature = "runtime-tokio")]
+        let delay = self.delay;
+        async move {
+            // Yielding only lets the tasks that are ready on this thread run first, while the
+            // timer fires on a later turn of the runtime, after the other tasks are polled.
+            #[cfg(feature = "runtime-tokio")]
+            {
+                tokio::task::yield_now().await;
+                tokio::time::sleep(delay).await;
+            }
+            #[cfg(not(feature = "runtime-tokio"))]
+            YieldNow(false).await;
+            let (primary_keys, senders) = {
+                let mut batch = batch.lock().unwrap();
+                (
+                    mem::take(&mut batch.primary_keys),
+                    mem::take(&mut batch.senders),
+                )
+            };
+            match connection.find_ordered::<T>(&primary_keys).await {
+                Ok(rows) => {
+                    for (sender, row) in senders.into_iter().zip(rows) {
+                        let _ = sender.send(Ok(row));
+                    }
+                }
+                Err(error) => {
+                    let error = Arc::new(error);
+                    for sender in senders {
+                        let _ = sender.send(Err(Error::Batch(error.clone())));
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl<T: Identifiable> Clone for Loader<T> {
+    fn clone(&self) -> Self {
+        Self {
+            connection: self.connection.clone(),
+            batch: self.batch.clone(),
+            #[cfg(feature = "runtime-tokio")]
+            delay: self.delay,
+        }
+    }
+}
+
+/// Returns pending once, after waking the task, so other tasks run before it continues.
+#[cfg(not(feature = "runtime-tokio"))]
+struct YieldNow(bool);
+
+#[cfg(not(feature = "runtime-tokio"))]
+impl Future for YieldNow {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, context: &mut Context<'_>) -> Poll<()> {
+        if self.0 {
+            return Poll::Ready(());
+        }
+        self.0 = true;
+        context.waker().wake_by_ref();
+        Poll::Pending
+    }
+}
@@ -0,0 +1,193 @@
+use crate::*;
+use std::collections::HashMap;
+use std::env;
+use tokio::sync::Mutex;
+
+/// The prefix of the environment variables read by
+/// [`ConnectionManager::from_env`](struct.ConnectionManager.html#method.from_env).
+pub const DATABASE_ENV_PREFIX: &str = "SPRATTUS_DATABASE_";
+
+/// Holds named connections to several databases, like `"primary"` and `"analytics"`.
+///
+/// The connection to a database is established the first time it is requested with
+/// [`get`](#method.get), and shared by all later requests.
+///
+/// Example:
+/// ```no_run
+/// use sprattus::*;
+///
+///# #[tokio::main]
+///# async fn main() -> Result<(), Error> {
+/// let manager = ConnectionManager::new()
+///     .add("primary", "postgresql://localhost?user=tg")
+///     .add("analytics", "postgresql://analytics.local?user=tg");
+/// let conn = manager.get("analytics").await?;
+/// conn.ping().await?;
+///# return Ok(())
+///# }
+/// ```
+#[derive(Default)]
+pub struct ConnectionManager {
+    databases: HashMap<String, Database>,
+}
+
+struct Database {
+    source: DatabaseSource,
+    state: Mutex<DatabaseState>,
+}
+
+enum DatabaseSource {
+    ConnectionString(String),
+    Builder(Box<ConnectionBuilder>),
+}
+
+#[derive(Default)]
+struct DatabaseState {
+    connection: Option<Connection>,
+    // The search path of a dropped connection, applied again when reconnecting.
+    search_path: Option<Vec<String>>,
+    // Whether a connection was established before, so the next one is a reconnect.
+    connected: bool,
+}
+
+impl ConnectionManager {
+    /// Creates a manager without databases.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a manager with the databases of a map from names to connection strings.
+    pub fn from_map<I, N, S>(databases: I) -> Self
+    where
+        I: IntoIterator<Item = (N, S)>,
+        N: Into<String>,
+        S: Into<String>,
+    {
+        databases
+            .into_iter()
+            .fold(Self::new(), |manager, (name, connection_string)| {
+                manager.add(name, connection_string)
+            })
+    }
+
+    ///
+    /// Creates a manager with the databases configured in the environment.
+    ///
+    /// Every variable starting with `SPRATTUS_DATABASE_` is a connection string. The rest of the
+    /// name of the variable, in lower case, is the name of the database, so
+    /// `SPRATTUS_DATABASE_ANALYTICS` configures the `"analytics"` database.
+    ///
+    pub fn from_env() -> Self {
+        Self::from_map(env::vars().filter_map(|(key, value)| {
+            if key.starts_with(DATABASE_ENV_PREFIX) && key.len() > DATABASE_ENV_PREFIX.len() {
+                Some((key[DATABASE_ENV_PREFIX.len()..].to_lowercase(), value))
+            } else {
+                None
+            }
+        }))
+    }
+
+    /// Adds a database, replacing an earlier database with the same name.
+    pub fn add(self, name: impl Into<String>, connection_string: impl Into<String>) -> Self {
+        self.insert(
+            name.into(),
+            DatabaseSource::ConnectionString(connection_string.into()),
+        )
+    }
+
+    /// Adds a database that is connected to with a builder, replacing an earlier database with
+    /// the same name. The builder is used again for every reconnect.
+    pub fn add_builder(self, name: impl Into<String>, builder: ConnectionBuilder) -> Self {
+        self.insert(name.into(), DatabaseSource::Builder(Box::new(builder)))
+    }
+
+    fn insert(mut self, name: String, source: DatabaseSource) -> Self {
+        self.databases.insert(
+            name,
+            Database {
+                source,
+                state: Mutex::new(DatabaseState::default()),
+            },
+        );
+        self
+    }
+
+    /// Returns the names of the configured databases.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.databases.keys().map(String::as_str)
+    }
+
+    ///
+    /// Returns the connection to a database, connecting to it when this is the first request.
+    ///
+    /// Returns [`Error::UnknownDatabase`](enum.Error.html#variant.UnknownDatabase) when no
+    /// database with the name is configured.
+    ///
+    pub async fn get(&self, name: &str) -> Result<Connection, Error> {
+        let database = self
+            .databases
+            .get(name)
+            .ok_or_else(|| Error::UnknownDatabase(name.to_string()))?;
+        let mut state = database.state.lock().await;
+        if let Some(connection) = &state.connection {
+            return Ok(connection.clone());
+        }
+        let mut builder = match &database.source {
+            DatabaseSource::ConnectionString(connection_string) => {
+                ConnectionBuilder::from_connection_string(connection_string)?
+            }
+            DatabaseSource::Builder(builder) => (**builder).clone(),
+        };
+        if let Some(search_path) = &state.search_path {
+            let schemas: Vec<&str> = search_path.iter().map(String::as_str).collect();
+            builder = builder.search_path(&schemas);
+        }
+        let connection = builder.connect().await?;
+        if state.connected {
+            connection.notify_reconnect();
+        }
+        state.connected = true;
+        state.connection = Some(connection.clone());
+        Ok(connection)
+    }
+
+    ///
+    /// Pings every database that is connected, returning whether it responds by name.
+    /// Databases that have not been requested yet are not connected to and reported as healthy.
+    ///
+    /// A connection that doesn't respond is dropped, so the next request of the database
+    /// reconnects to it.
+    ///
+    /// Example:
+    /// ```no_run
+    /// use sprattus::*;
+    ///
+    ///# #[tokio::main]
+    ///# async fn main() -> Result<(), Error> {
+    /// let manager = ConnectionManager::from_env();
+    /// for (name, healthy) in manager.health().await {
+    ///     if !healthy {
+    ///         eprintln!("the {} database is not available", name);
+    ///     }
+    /// }
+    ///# return Ok(())
+    ///# }
+    /// ```
+    pub async fn health(&self) -> HashMap<String, bool> {
+        let mut health = HashMap::with_capacity(self.databases.len());
+        for (name, database) in &self.databases {
+            let mut state = database.state.lock().await;
+            let healthy = match &state.connection {
+                Some(connection) => connection.is_healthy().await,
+                None => true,
+            };
+            if !healthy {
+                if let Some(connection) = state.connection.take() {
+                    state.search_path = connection.search_path();
+                }
+            }
+            health.insert(name.clone(), healthy);
+        }
+        health
+    }
+}
@@ -0,0 +1,40 @@
+use crate::*;
+
+/// Whether the columns of fields with the `masked` attribute are masked when rows are selected.
+///
+/// Set it with [`Connection::with_masking`](struct.Connection.html#method.with_masking).
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Masking {
+    /// Select masked columns as `'****'` followed by their last four characters, or as just
+    /// `'****'` when the value has no more than four characters. This is the default.
+    #[default]
+    Masked,
+    /// Select the full values of masked columns.
+    Unmasked,
+}
+
+impl Masking {
+    /// Returns the list of columns of a `SELECT` of the fields of `T`, in which the masked
+    /// columns are replaced by an expression masking their value.
+    pub(crate) fn columns<T: Table>(self) -> String {
+        let masked = T::get_masked_fields();
+        if self == Masking::Unmasked || masked.is_empty() {
+            return T::get_all_fields().to_string();
+        }
+        T::get_all_fields()
+            .split(',')
+            .map(|column| {
+                if masked.contains(&column) {
+                    format!(
+                        "CASE WHEN length({column}::text) <= 4 THEN '****' \
+                         ELSE '****' || right({column}::text, 4) END AS {column}",
+                        column = column
+                    )
+                } else {
+                    column.to_string()
+                }
+            })
+            .collect::<Vec<String>>()
+            .join(",")
+    }
+}
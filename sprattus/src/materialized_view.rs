@@ -0,0 +1,79 @@
+use crate::*;
+#[cfg(feature = "runtime-tokio")]
+use futures_util::future::{self, Either};
+#[cfg(feature = "runtime-tokio")]
+use std::time::Duration;
+#[cfg(feature = "runtime-tokio")]
+use tokio::sync::oneshot;
+
+/// Returns the statement that refreshes a materialized view.
+pub(crate) fn refresh_statement<T: MaterializedView>(concurrently: bool) -> String {
+    format!(
+        "REFRESH MATERIALIZED VIEW {concurrently}{view_name}",
+        concurrently = if concurrently { "CONCURRENTLY " } else { "" },
+        view_name = T::get_table_name(),
+    )
+}
+
+/// Refreshes a materialized view on an interval, created by
+/// [`Connection::refresh_materialized_view_every`](struct.Connection.html#method.refresh_materialized_view_every).
+///
+/// The refreshes stop when the schedule is stopped or dropped, or when the connection is closed.
+#[cfg(feature = "runtime-tokio")]
+#[must_use = "the refreshes stop when the schedule is dropped"]
+#[derive(Debug)]
+pub struct RefreshSchedule {
+    stop: Option<oneshot::Sender<()>>,
+}
+
+#[cfg(feature = "runtime-tokio")]
+impl RefreshSchedule {
+    /// Stops refreshing the view. A refresh that is running is finished first.
+    pub fn stop(mut self) {
+        self.send_stop();
+    }
+
+    fn send_stop(&mut self) {
+        if let Some(stop) = self.stop.take() {
+            let _ = stop.send(());
+        }
+    }
+}
+
+#[cfg(feature = "runtime-tokio")]
+impl Drop for RefreshSchedule {
+    fn drop(&mut self) {
+        self.send_stop();
+    }
+}
+
+/// Spawns the task refreshing a materialized view after every interval.
+#[cfg(feature = "runtime-tokio")]
+pub(crate) fn schedule<T: MaterializedView>(
+    connection: &Connection,
+    interval: Duration,
+    concurrently: bool,
+) -> RefreshSchedule {
+    let (stop, mut stopped) = oneshot::channel();
+    let connection = connection.clone();
+    let sql = refresh_statement::<T>(concurrently);
+    let spawner = connection.spawner.clone();
+    spawner(Box::pin(async move {
+        let mut ticks = tokio::time::interval(interval);
+        // The first tick completes immediately, the view is first refreshed after an interval.
+        ticks.tick().await;
+        loop {
+            match future::select(stopped, Box::pin(ticks.tick())).await {
+                Either::Left(_) => return,
+                Either::Right((_, pending)) => stopped = pending,
+            }
+            if connection.is_closed() {
+                return;
+            }
+            if let Err(error) = connection.execute(sql.as_str(), &[]).await {
+                log::warn!("cannot refresh a materialized view: {}", error);
+            }
+        }
+    }));
+    RefreshSchedule { stop: Some(stop) }
+}
@@ -0,0 +1,121 @@
+use crate::*;
+use std::sync::Arc;
+use std::time::Duration;
+
+///
+/// Receives the events of a connection, to raise alerts or collect diagnostics. Set with
+/// [`ConnectionBuilder::observer`](struct.ConnectionBuilder.html#method.observer), and shared by
+/// all clones of the connections the builder establishes.
+///
+/// All methods do nothing by default, so an observer only implements the events it needs. The
+/// methods are called on the task running the statement or driving the connection, so they
+/// should return quickly.
+///
+/// Example:
+/// ```no_run
+/// use sprattus::*;
+/// use std::time::Duration;
+///
+/// struct Alerts;
+///
+/// impl ConnectionObserver for Alerts {
+///     fn on_disconnect(&self, error: Option<&Error>) {
+///         if let Some(error) = error {
+///             eprintln!("lost the connection to the database: {}", error);
+///         }
+///     }
+///
+///     fn on_slow_query(&self, sql: &str, duration: Duration) {
+///         eprintln!("`{}` took {:?}", sql, duration);
+///     }
+/// }
+///
+///# #[tokio::main]
+///# async fn main() -> Result<(), Error> {
+/// let conn = Connection::builder()
+///     .host("localhost")
+///     .user("tg")
+///     .observer(Alerts)
+///     .slow_query_threshold(Duration::from_millis(500))
+///     .connect()
+///     .await?;
+///# return Ok(())
+///# }
+/// ```
+///
+pub trait ConnectionObserver: Send + Sync {
+    /// Called when a connection to the database is established.
+    fn on_connect(&self) {}
+
+    /// Called after [`on_connect`](#method.on_connect) when a
+    /// [`ConnectionManager`](struct.ConnectionManager.html) connects again to a database of which
+    /// the connection was dropped.
+    fn on_reconnect(&self) {}
+
+    /// Called when the connection to the database is closed, with the error that closed it, or
+    /// `None` when it is closed without an error, like by
+    /// [`Connection::close`](struct.Connection.html#method.close).
+    fn on_disconnect(&self, _error: Option<&Error>) {}
+
+    /// Called when a statement took longer than the
+    /// [slow query threshold](struct.ConnectionBuilder.html#method.slow_query_threshold).
+    fn on_slow_query(&self, _sql: &str, _duration: Duration) {}
+}
+
+/// The observer of a connection with its slow query threshold.
+#[derive(Clone)]
+pub(crate) struct Observer {
+    pub(crate) observer: Arc<dyn ConnectionObserver>,
+    pub(crate) slow_query_threshold: Option<Duration>,
+}
+
+impl Observer {
+    /// Reports the statement as a slow query when it took longer than the threshold.
+    pub(crate) fn statement_finished(&self, sql: &str, duration: Duration) {
+        if self
+            .slow_query_threshold
+            .is_some_and(|threshold| duration > threshold)
+        {
+            self.observer.on_slow_query(sql, duration);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct SlowQueries(Mutex<Vec<String>>);
+
+    impl ConnectionObserver for SlowQueries {
+        fn on_slow_query(&self, sql: &str, _duration: Duration) {
+            self.0.lock().unwrap().push(sql.to_string());
+        }
+    }
+
+    #[test]
+    fn reports_statements_slower_than_the_threshold() {
+        let slow_queries = Arc::new(SlowQueries::default());
+        let observer = Observer {
+            observer: slow_queries.clone(),
+            slow_query_threshold: Some(Duration::from_millis(100)),
+        };
+        observer.statement_finished("SELECT 1", Duration::from_millis(10));
+        observer.statement_finished("SELECT 2", Duration::from_millis(100));
+        observer.statement_finished("SELECT 3", Duration::from_millis(101));
+        assert_eq!(*slow_queries.0.lock().unwrap(), ["SELECT 3"]);
+    }
+
+    #[test]
+    fn reports_no_slow_queries_without_a_threshold() {
+        let slow_queries = Arc::new(SlowQueries::default());
+        let observer = Observer {
+            observer: slow_queries.clone(),
+            slow_query_threshold: None,
+        };
+        observer.statement_finished("SELECT 1", Duration::from_secs(60));
+        assert!(slow_queries.0.lock().unwrap().is_empty());
+    }
+}
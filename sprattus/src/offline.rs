@@ -0,0 +1,139 @@
+use crate::*;
+use serde_json::{json, Value};
+use std::any::type_name;
+use std::path::Path;
+
+/// The name of the file with the offline data, in the root of the crate that uses the queries.
+pub const OFFLINE_DATA_FILE: &str = "sprattus-data.json";
+
+/// Verifies queries against a development database and stores their metadata, created by
+/// [`Connection::prepare_offline`](struct.Connection.html#method.prepare_offline).
+///
+/// Every query is prepared on the database, and the columns it returns are checked against the
+/// struct it is read into with [`FromSql::check_columns`](trait.FromSql.html#method.check_columns).
+/// The columns of the queries are written to [`OFFLINE_DATA_FILE`](constant.OFFLINE_DATA_FILE.html).
+///
+/// When the crate is built with the environment variable `SPRATTUS_OFFLINE=true`, the macros
+/// check the code against this file instead of the database: every [`sql!`](macro.sql.html) query
+/// must be prepared, and the fields of a struct deriving `FromSql` must be returned by its
+/// prepared queries. A build without database access, like on CI, then fails when a query or
+/// struct changed since the queries were prepared.
+///
+/// Example:
+/// ```no_run
+/// use sprattus::*;
+///
+/// #[derive(FromSql, ToSql, Debug)]
+/// #[sprattus(table = "products")]
+/// struct Product {
+///     #[sprattus(primary_key)]
+///     prod_id: i32,
+///     title: String,
+/// }
+///
+/// #[tokio::main]
+/// async fn main() -> Result<(), Error> {
+///     let conn = Connection::new("postgresql://localhost?user=tg").await?;
+///     conn.prepare_offline()
+///         .query(&sql!(Product, "SELECT {columns} FROM {table} WHERE prod_id > $1", &0))
+///         .select(&conn.select::<Product>().filter("title LIKE $1", &[&"A%"]))
+///         .write(OFFLINE_DATA_FILE)
+///         .await?;
+///     Ok(())
+/// }
+/// ```
+pub struct Prepare<'a> {
+    connection: &'a Connection,
+    queries: Vec<PrepareQuery>,
+}
+
+/// A query that is verified by [`Prepare`](struct.Prepare.html).
+struct PrepareQuery {
+    entity: &'static str,
+    template: Option<&'static str>,
+    sql: String,
+    check_columns: fn(&[Column]) -> Vec<ColumnMismatch>,
+}
+
+impl<'a> Prepare<'a> {
+    pub(crate) fn new(connection: &'a Connection) -> Self {
+        Prepare {
+            connection,
+            queries: Vec::new(),
+        }
+    }
+
+    /// Adds a query created by the [`sql!`](macro.sql.html) macro.
+    pub fn query<T: FromSql>(mut self, query: &SqlQuery<'_, T>) -> Self {
+        self.queries.push(PrepareQuery {
+            entity: entity_name::<T>(),
+            template: Some(query.template()),
+            sql: query.sql().to_string(),
+            check_columns: T::check_columns,
+        });
+        self
+    }
+
+    /// Adds a query created with [`Connection::select`](struct.Connection.html#method.select).
+    pub fn select<T: Table + FromSql>(mut self, select: &Select<'_, T>) -> Self {
+        self.queries.push(PrepareQuery {
+            entity: entity_name::<T>(),
+            template: None,
+            sql: select.sql(),
+            check_columns: T::check_columns,
+        });
+        self
+    }
+
+    /// Prepares the queries on the database and writes their columns to the file at the given
+    /// path, which is usually [`OFFLINE_DATA_FILE`](constant.OFFLINE_DATA_FILE.html) in the root
+    /// of the crate.
+    ///
+    /// Returns [`Error::ColumnMismatch`](enum.Error.html#variant.ColumnMismatch) for the first
+    /// query that doesn't return the columns of its struct, without writing the file.
+    pub async fn write(mut self, path: impl AsRef<Path>) -> Result<(), Error> {
+        // Sorted, so the file only changes when the queries change.
+        self.queries
+            .sort_by(|a, b| (a.entity, a.template, &a.sql).cmp(&(b.entity, b.template, &b.sql)));
+        self.queries
+            .dedup_by(|a, b| a.sql == b.sql && a.entity == b.entity);
+        let client = self.connection.client().await?;
+        let mut queries = Vec::with_capacity(self.queries.len());
+        for query in &self.queries {
+            self.connection.log_sql(&query.sql, &[]);
+            let statement = client.prepare(&query.sql).await?;
+            let mismatches = (query.check_columns)(statement.columns());
+            if !mismatches.is_empty() {
+                return Err(Error::ColumnMismatch {
+                    sql: query.sql.clone(),
+                    mismatches,
+                });
+            }
+            let columns: Vec<Value> = statement
+                .columns()
+                .iter()
+                .map(|column| json!({ "name": column.name(), "type": column.type_().name() }))
+                .collect();
+            let params: Vec<&str> = statement.params().iter().map(|ty| ty.name()).collect();
+            queries.push(json!({
+                "entity": query.entity,
+                "template": query.template,
+                "sql": query.sql,
+                "columns": columns,
+                "params": params,
+            }));
+        }
+        let data = json!({ "queries": queries });
+        let mut contents = serde_json::to_string_pretty(&data).expect("JSON values serialize");
+        contents.push('\n');
+        std::fs::write(path, contents).map_err(Error::Io)
+    }
+}
+
+/// Returns the name of a struct without its module path and type parameters, which is how the
+/// macros refer to it.
+fn entity_name<T>() -> &'static str {
+    let name = type_name::<T>();
+    let name = name.split('<').next().unwrap_or(name);
+    name.rsplit("::").next().unwrap_or(name)
+}
@@ -0,0 +1,93 @@
+//! Managers for external connection pools, so a pool of [bb8](https://docs.rs/bb8) or
+//! [deadpool](https://docs.rs/deadpool) holds [`Connection`](../struct.Connection.html)s.
+//!
+//! The [`PoolManager`](struct.PoolManager.html) implements `bb8::ManageConnection` with the
+//! `with-bb8-0_8` feature, and `deadpool::managed::Manager` with the `with-deadpool-0_9` feature.
+//!
+//! The pool validates a connection with a [`ping`](../struct.Connection.html#method.ping). A
+//! connection that is [closed](../struct.Connection.html#method.close), or of which the socket to
+//! the database is closed, is broken and never handed out again.
+//!
+//! Example:
+//! ```no_run
+//! use sprattus::pool::PoolManager;
+//! use sprattus::*;
+//!
+//! #[tokio::main]
+//! async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//!     let manager = PoolManager::new("postgresql://localhost?user=tg")?;
+//!     let pool = bb8::Pool::builder().max_size(8).build(manager).await?;
+//!     let conn = pool.get().await?;
+//!     conn.ping().await?;
+//!     Ok(())
+//! }
+//! ```
+use crate::*;
+use async_trait::async_trait;
+
+/// Connects to the database for a pool, with a
+/// [`ConnectionBuilder`](../struct.ConnectionBuilder.html).
+#[derive(Clone, Debug)]
+pub struct PoolManager {
+    builder: ConnectionBuilder,
+}
+
+impl PoolManager {
+    /// Creates a manager connecting with a connection string, see
+    /// [`Connection::new`](../struct.Connection.html#method.new).
+    pub fn new(connection_string: &str) -> Result<Self, Error> {
+        Ok(Self::from_builder(
+            ConnectionBuilder::from_connection_string(connection_string)?,
+        ))
+    }
+
+    /// Creates a manager connecting with a builder, which is used again for every connection.
+    pub fn from_builder(builder: ConnectionBuilder) -> Self {
+        Self { builder }
+    }
+}
+
+#[cfg(feature = "with-bb8-0_8")]
+#[async_trait]
+impl bb8::ManageConnection for PoolManager {
+    type Connection = Connection;
+    type Error = Error;
+
+    async fn connect(&self) -> Result<Connection, Error> {
+        self.builder.connect().await
+    }
+
+    async fn is_valid(&self, connection: &mut Connection) -> Result<(), Error> {
+        connection.ping().await
+    }
+
+    fn has_broken(&self, connection: &mut Connection) -> bool {
+        connection.is_broken()
+    }
+}
+
+#[cfg(feature = "with-deadpool-0_9")]
+#[async_trait]
+impl deadpool::managed::Manager for PoolManager {
+    type Type = Connection;
+    type Error = Error;
+
+    async fn create(&self) -> Result<Connection, Error> {
+        self.builder.connect().await
+    }
+
+    async fn recycle(
+        &self,
+        connection: &mut Connection,
+    ) -> deadpool::managed::RecycleResult<Error> {
+        if connection.is_broken() {
+            return Err(deadpool::managed::RecycleError::Message(String::from(
+                "the connection is closed",
+            )));
+        }
+        connection
+            .ping()
+            .await
+            .map_err(deadpool::managed::RecycleError::Backend)
+    }
+}
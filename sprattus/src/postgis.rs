@@ -0,0 +1,276 @@
+//! Support for the `geometry` and `geography` types of [PostGIS](https://postgis.net).
+//!
+//! The [`Geometry`](struct.Geometry.html) and [`Geography`](struct.Geography.html) wrappers map a
+//! [`geo_types::Point`] or [`geo_types::Polygon`] to a PostGIS column, encoded as EWKB.
+//!
+//! Example:
+//! ```no_run
+//! use sprattus::*;
+//! use sprattus::postgis::Geography;
+//! use geo_types::Point;
+//!
+//! #[derive(FromSql, ToSql, Debug)]
+//! #[sprattus(table = "shops")]
+//! struct Shop {
+//!     #[sprattus(primary_key)]
+//!     id: i32,
+//!     location: Geography<Point<f64>>,
+//! }
+//!
+//! #[tokio::main]
+//! async fn main() -> Result<(), Error> {
+//!     let conn = Connection::new("postgresql://localhost?user=tg").await?;
+//!     // Executes `SELECT "id","location" FROM "shops"
+//!     // WHERE ST_DWithin("location"::geography, $1::geography, $2)`.
+//!     let shops = conn
+//!         .select::<Shop>()
+//!         .filter_within_distance("location", Point::new(4.89, 52.37), 500.0)
+//!         .fetch()
+//!         .await?;
+//!     dbg!(shops);
+//!     Ok(())
+//! }
+//! ```
+//!
+//! [`geo_types::Point`]: https://docs.rs/geo-types/0.4/geo_types/struct.Point.html
+//! [`geo_types::Polygon`]: https://docs.rs/geo-types/0.4/geo_types/struct.Polygon.html
+use bytes::{BufMut, BytesMut};
+use geo_types::{Coordinate, LineString, Point, Polygon};
+use std::error::Error;
+use std::fmt;
+use tokio_postgres::types::{to_sql_checked, FromSql, IsNull, ToSql, Type};
+
+/// The SRID of WGS 84 longitude and latitude, the default of the `geography` type.
+const WGS_84: u32 = 4326;
+
+const EWKB_Z_FLAG: u32 = 0x8000_0000;
+const EWKB_M_FLAG: u32 = 0x4000_0000;
+const EWKB_SRID_FLAG: u32 = 0x2000_0000;
+
+/// A value of a PostGIS `geometry` column, written without a SRID.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Geometry<G>(pub G);
+
+/// A value of a PostGIS `geography` column, written with the SRID of WGS 84 longitude and
+/// latitude (4326).
+#[derive(Clone, Debug, PartialEq)]
+pub struct Geography<G>(pub G);
+
+/// A shape that can be encoded as (E)WKB.
+pub trait Shape: Sized {
+    #[doc(hidden)]
+    const WKB_TYPE: u32;
+
+    #[doc(hidden)]
+    fn write_wkb(&self, out: &mut BytesMut);
+
+    #[doc(hidden)]
+    fn read_wkb(reader: &mut WkbReader<'_>) -> Result<Self, Box<dyn Error + Sync + Send>>;
+}
+
+impl Shape for Point<f64> {
+    const WKB_TYPE: u32 = 1;
+
+    fn write_wkb(&self, out: &mut BytesMut) {
+        write_coordinate(out, self.0);
+    }
+
+    fn read_wkb(reader: &mut WkbReader<'_>) -> Result<Self, Box<dyn Error + Sync + Send>> {
+        Ok(Point(reader.read_coordinate()?))
+    }
+}
+
+impl Shape for Polygon<f64> {
+    const WKB_TYPE: u32 = 3;
+
+    fn write_wkb(&self, out: &mut BytesMut) {
+        let rings: Vec<&LineString<f64>> = std::iter::once(self.exterior())
+            .chain(self.interiors())
+            .collect();
+        out.put_u32_le(rings.len() as u32);
+        for ring in rings {
+            out.put_u32_le(ring.0.len() as u32);
+            for coordinate in &ring.0 {
+                write_coordinate(out, *coordinate);
+            }
+        }
+    }
+
+    fn read_wkb(reader: &mut WkbReader<'_>) -> Result<Self, Box<dyn Error + Sync + Send>> {
+        let ring_count = reader.read_u32()?;
+        let mut rings = Vec::with_capacity(ring_count as usize);
+        for _ in 0..ring_count {
+            let point_count = reader.read_u32()?;
+            let mut coordinates = Vec::with_capacity(point_count as usize);
+            for _ in 0..point_count {
+                coordinates.push(reader.read_coordinate()?);
+            }
+            rings.push(LineString(coordinates));
+        }
+        if rings.is_empty() {
+            return Ok(Polygon::new(LineString(Vec::new()), Vec::new()));
+        }
+        let exterior = rings.remove(0);
+        Ok(Polygon::new(exterior, rings))
+    }
+}
+
+fn write_coordinate(out: &mut BytesMut, coordinate: Coordinate<f64>) {
+    out.put_f64_le(coordinate.x);
+    out.put_f64_le(coordinate.y);
+}
+
+/// Writes the EWKB header of a shape, followed by the shape itself.
+fn write_ewkb<G: Shape>(shape: &G, srid: Option<u32>, out: &mut BytesMut) {
+    // Little endian.
+    out.put_u8(1);
+    match srid {
+        Some(srid) => {
+            out.put_u32_le(G::WKB_TYPE | EWKB_SRID_FLAG);
+            out.put_u32_le(srid);
+        }
+        None => out.put_u32_le(G::WKB_TYPE),
+    }
+    shape.write_wkb(out);
+}
+
+/// Reads a shape from EWKB, ignoring its SRID.
+fn read_ewkb<G: Shape>(raw: &[u8]) -> Result<G, Box<dyn Error + Sync + Send>> {
+    let mut reader = WkbReader {
+        raw,
+        little_endian: true,
+    };
+    reader.little_endian = match reader.read_u8()? {
+        0 => false,
+        1 => true,
+        byte_order => return Err(format!("invalid WKB byte order {}", byte_order).into()),
+    };
+    let wkb_type = reader.read_u32()?;
+    if wkb_type & (EWKB_Z_FLAG | EWKB_M_FLAG) != 0 {
+        return Err("geometries with Z or M coordinates are not supported".into());
+    }
+    if wkb_type & EWKB_SRID_FLAG != 0 {
+        reader.read_u32()?;
+    }
+    let base_type = wkb_type & !EWKB_SRID_FLAG;
+    if base_type != G::WKB_TYPE {
+        return Err(WrongShape {
+            expected: G::WKB_TYPE,
+            actual: base_type,
+        }
+        .into());
+    }
+    G::read_wkb(&mut reader)
+}
+
+/// Reads the values of a WKB encoded shape.
+#[doc(hidden)]
+pub struct WkbReader<'a> {
+    raw: &'a [u8],
+    little_endian: bool,
+}
+
+impl<'a> WkbReader<'a> {
+    fn take(&mut self, length: usize) -> Result<&'a [u8], Box<dyn Error + Sync + Send>> {
+        if self.raw.len() < length {
+            return Err("unexpected end of WKB".into());
+        }
+        let (head, tail) = self.raw.split_at(length);
+        self.raw = tail;
+        Ok(head)
+    }
+
+    fn read_u8(&mut self) -> Result<u8, Box<dyn Error + Sync + Send>> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn read_u32(&mut self) -> Result<u32, Box<dyn Error + Sync + Send>> {
+        let mut bytes = [0; 4];
+        bytes.copy_from_slice(self.take(4)?);
+        Ok(if self.little_endian {
+            u32::from_le_bytes(bytes)
+        } else {
+            u32::from_be_bytes(bytes)
+        })
+    }
+
+    fn read_f64(&mut self) -> Result<f64, Box<dyn Error + Sync + Send>> {
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(self.take(8)?);
+        Ok(if self.little_endian {
+            f64::from_le_bytes(bytes)
+        } else {
+            f64::from_be_bytes(bytes)
+        })
+    }
+
+    fn read_coordinate(&mut self) -> Result<Coordinate<f64>, Box<dyn Error + Sync + Send>> {
+        let x = self.read_f64()?;
+        let y = self.read_f64()?;
+        Ok(Coordinate { x, y })
+    }
+}
+
+#[derive(Debug)]
+struct WrongShape {
+    expected: u32,
+    actual: u32,
+}
+
+impl fmt::Display for WrongShape {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "expected a shape of WKB type {}, found WKB type {}",
+            self.expected, self.actual
+        )
+    }
+}
+
+impl Error for WrongShape {}
+
+impl<G: Shape + fmt::Debug> ToSql for Geometry<G> {
+    fn to_sql(&self, _: &Type, out: &mut BytesMut) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
+        write_ewkb(&self.0, None, out);
+        Ok(IsNull::No)
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        ty.name() == "geometry"
+    }
+
+    to_sql_checked!();
+}
+
+impl<'a, G: Shape> FromSql<'a> for Geometry<G> {
+    fn from_sql(_: &Type, raw: &'a [u8]) -> Result<Self, Box<dyn Error + Sync + Send>> {
+        Ok(Geometry(read_ewkb(raw)?))
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        ty.name() == "geometry"
+    }
+}
+
+impl<G: Shape + fmt::Debug> ToSql for Geography<G> {
+    fn to_sql(&self, _: &Type, out: &mut BytesMut) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
+        write_ewkb(&self.0, Some(WGS_84), out);
+        Ok(IsNull::No)
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        ty.name() == "geography"
+    }
+
+    to_sql_checked!();
+}
+
+impl<'a, G: Shape> FromSql<'a> for Geography<G> {
+    fn from_sql(_: &Type, raw: &'a [u8]) -> Result<Self, Box<dyn Error + Sync + Send>> {
+        Ok(Geography(read_ewkb(raw)?))
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        ty.name() == "geography"
+    }
+}
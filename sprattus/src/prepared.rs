@@ -0,0 +1,160 @@
+use crate::statement_cache::is_stale_statement;
+use crate::traits::from_rows;
+use crate::*;
+use std::marker::PhantomData;
+use std::sync::{Arc, Mutex};
+use tokio_postgres::Statement;
+
+/// A statement that is prepared once, created by
+/// [`Connection::prepare_typed`](struct.Connection.html#method.prepare_typed).
+///
+/// The statement is executed with new arguments every time, without preparing it again. Clones
+/// share the prepared statement, so a clone can be moved into every task that executes it.
+///
+/// When the tables the statement uses change, like an added column, the statement is prepared
+/// again, unless the connection is within a transaction, of which the failed statement aborted
+/// the transaction.
+///
+/// Example:
+/// ```no_run
+/// use sprattus::*;
+///
+/// #[derive(FromSql, Debug)]
+/// struct Product {
+///     prod_id: i32,
+///     title: String,
+/// }
+///
+/// #[tokio::main]
+/// async fn main() -> Result<(), Error> {
+///     let conn = Connection::new("postgresql://localhost?user=tg").await?;
+///     let by_id = conn
+///         .prepare_typed::<Product>("SELECT prod_id, title FROM Products WHERE prod_id = $1")
+///         .await?;
+///     let tasks: Vec<_> = (1..=3)
+///         .map(|id| {
+///             let by_id = by_id.clone();
+///             tokio::spawn(async move { by_id.fetch_one(&[&id]).await })
+///         })
+///         .collect();
+///     for task in tasks {
+///         dbg!(task.await.unwrap()?);
+///     }
+///     Ok(())
+/// }
+/// ```
+pub struct PreparedQuery<T> {
+    connection: Connection,
+    sql: String,
+    statement: Arc<Mutex<Statement>>,
+    result_type: PhantomData<T>,
+}
+
+impl<T> Clone for PreparedQuery<T> {
+    fn clone(&self) -> Self {
+        Self {
+            connection: self.connection.clone(),
+            sql: self.sql.clone(),
+            statement: self.statement.clone(),
+            result_type: PhantomData,
+        }
+    }
+}
+
+impl<T> PreparedQuery<T> {
+    pub(crate) async fn prepare(connection: &Connection, sql: &str) -> Result<Self, Error> {
+        let statement = connection.client().await?.prepare(sql).await?;
+        Ok(Self {
+            connection: connection.clone(),
+            sql: sql.to_string(),
+            statement: Arc::new(Mutex::new(statement)),
+            result_type: PhantomData,
+        })
+    }
+
+    /// Returns the SQL of the statement.
+    pub fn sql(&self) -> &str {
+        self.sql.as_str()
+    }
+
+    /// Executes the statement, returning the number of modified rows, see
+    /// [`Connection::execute`](struct.Connection.html#method.execute).
+    pub async fn execute(&self, args: &[&(dyn ToSqlItem + Sync)]) -> Result<ExecuteResult, Error> {
+        self.connection.log_sql(&self.sql, args);
+        let rows_affected = self
+            .connection
+            .guard(&self.sql, async {
+                let client = self.connection.client().await?;
+                match client.execute(&self.statement(), args).await {
+                    Err(error) if is_stale_statement(&error) => {
+                        let statement = self.prepare_again(error).await?;
+                        Ok(client.execute(&statement, args).await?)
+                    }
+                    result => Ok(result?),
+                }
+            })
+            .await?;
+        Ok(ExecuteResult::new(&self.sql, rows_affected))
+    }
+
+    /// Returns the statement that is prepared last.
+    fn statement(&self) -> Statement {
+        self.statement.lock().unwrap().clone()
+    }
+
+    /// Prepares the statement again after the tables it uses changed. Within a transaction, the
+    /// failed statement aborted the transaction, so the error is returned instead.
+    async fn prepare_again(&self, error: tokio_postgres::Error) -> Result<Statement, Error> {
+        if self.connection.in_transaction() {
+            return Err(error.into());
+        }
+        let statement = self.connection.client().await?.prepare(&self.sql).await?;
+        *self.statement.lock().unwrap() = statement.clone();
+        Ok(statement)
+    }
+}
+
+impl<T> PreparedQuery<T>
+where
+    T: FromSql,
+{
+    /// Executes the statement, returning all rows.
+    pub async fn fetch(&self, args: &[&(dyn ToSqlItem + Sync)]) -> Result<Vec<T>, Error> {
+        self.connection.log_sql(&self.sql, args);
+        let rows = self
+            .connection
+            .guard(&self.sql, async {
+                let client = self.connection.client().await?;
+                match client.query(&self.statement(), args).await {
+                    Err(error) if is_stale_statement(&error) => {
+                        let statement = self.prepare_again(error).await?;
+                        Ok(client.query(&statement, args).await?)
+                    }
+                    result => Ok(result?),
+                }
+            })
+            .await?;
+        from_rows(&rows)
+    }
+
+    /// Executes the statement, returning exactly one row.
+    ///
+    /// Returns an error when the statement returns no rows, or more than one.
+    pub async fn fetch_one(&self, args: &[&(dyn ToSqlItem + Sync)]) -> Result<T, Error> {
+        self.connection.log_sql(&self.sql, args);
+        let row = self
+            .connection
+            .guard(&self.sql, async {
+                let client = self.connection.client().await?;
+                match client.query_one(&self.statement(), args).await {
+                    Err(error) if is_stale_statement(&error) => {
+                        let statement = self.prepare_again(error).await?;
+                        Ok(client.query_one(&statement, args).await?)
+                    }
+                    result => Ok(result?),
+                }
+            })
+            .await?;
+        T::from_row(&row)
+    }
+}
@@ -0,0 +1,232 @@
+//! A job queue stored in a Postgres table.
+//!
+//! Jobs are serialized as JSON into a table created with
+//! [`create_table`](struct.Queue.html#method.create_table). A worker takes a job with
+//! [`dequeue`](struct.Queue.html#method.dequeue), which locks the row with
+//! `FOR UPDATE SKIP LOCKED`, so concurrent workers never receive the same job.
+//!
+//! A dequeued job is invisible to other workers for the visibility timeout of the queue. The
+//! worker removes it with [`complete`](struct.Queue.html#method.complete) once it is done, or
+//! schedules a retry with [`fail`](struct.Queue.html#method.fail). When the worker does neither
+//! within the visibility timeout, for example because it crashed, the job becomes visible again.
+//! A worker that took longer than the visibility timeout has lost the job to the next worker that
+//! dequeued it, so its `complete` and `fail` leave the job alone and return `false`.
+//! Jobs that were dequeued the maximum number of attempts are kept in the table, but are not
+//! dequeued anymore.
+//!
+//! Example:
+//! ```no_run
+//! use serde::{Deserialize, Serialize};
+//! use sprattus::queue::Queue;
+//! use sprattus::*;
+//! use std::time::Duration;
+//!
+//! #[derive(Serialize, Deserialize, Debug)]
+//! struct SendMail {
+//!     to: String,
+//!     subject: String,
+//! }
+//!
+//! #[tokio::main]
+//! async fn main() -> Result<(), Error> {
+//!     let conn = Connection::new("postgresql://localhost?user=tg").await?;
+//!     let queue = Queue::new(&conn, "mail_jobs").visibility_timeout(Duration::from_secs(60));
+//!     queue.create_table().await?;
+//!     queue
+//!         .enqueue(&SendMail {
+//!             to: String::from("info@example.com"),
+//!             subject: String::from("Hello"),
+//!         })
+//!         .await?;
+//!     while let Some(job) = queue.dequeue::<SendMail>().await? {
+//!         println!("sending {:?}", job.payload);
+//!         queue.complete(&job).await?;
+//!     }
+//!     Ok(())
+//! }
+//! ```
+use crate::ident::table_ident;
+use crate::types::Json;
+use crate::*;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fmt::Debug;
+use std::time::Duration;
+
+/// The default time a dequeued job is invisible to other workers.
+pub const DEFAULT_VISIBILITY_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The default number of times a job is dequeued before it is given up.
+pub const DEFAULT_MAX_ATTEMPTS: u32 = 5;
+
+/// A job queue stored in a Postgres table.
+#[derive(Clone)]
+pub struct Queue {
+    connection: Connection,
+    table: String,
+    index: String,
+    visibility_timeout: Duration,
+    max_attempts: u32,
+    retry_delay: Duration,
+}
+
+/// A job taken from a [`Queue`](struct.Queue.html).
+#[derive(Clone, Debug)]
+pub struct Job<J> {
+    /// The id of the row of the job.
+    pub id: i64,
+    /// The number of times the job has been dequeued, including this time.
+    pub attempts: i32,
+    /// The error of the last failed attempt.
+    pub last_error: Option<String>,
+    /// The job itself.
+    pub payload: J,
+}
+
+impl Queue {
+    /// Creates a queue stored in the given table, which may be qualified with a schema.
+    pub fn new(connection: &Connection, table: &str) -> Self {
+        Self {
+            connection: connection.clone(),
+            table: table_ident(table),
+            index: ident(&format!(
+                "{}_visible_at",
+                table.rsplit('.').next().unwrap_or(table)
+            )),
+            visibility_timeout: DEFAULT_VISIBILITY_TIMEOUT,
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+            retry_delay: Duration::from_secs(0),
+        }
+    }
+
+    /// Sets the time a dequeued job is invisible to other workers.
+    pub fn visibility_timeout(mut self, timeout: Duration) -> Self {
+        self.visibility_timeout = timeout;
+        self
+    }
+
+    /// Sets the number of times a job is dequeued before it is given up.
+    pub fn max_attempts(mut self, attempts: u32) -> Self {
+        self.max_attempts = attempts;
+        self
+    }
+
+    /// Sets the time a failed job waits before it is dequeued again.
+    pub fn retry_delay(mut self, delay: Duration) -> Self {
+        self.retry_delay = delay;
+        self
+    }
+
+    /// Creates the table of the queue, when it does not exist yet.
+    pub async fn create_table(&self) -> Result<(), Error> {
+        let sql = format!(
+            "CREATE TABLE IF NOT EXISTS {table} (\
+             id BIGSERIAL PRIMARY KEY, \
+             payload JSONB NOT NULL, \
+             attempts INT NOT NULL DEFAULT 0, \
+             last_error TEXT, \
+             visible_at TIMESTAMPTZ NOT NULL DEFAULT now(), \
+             created_at TIMESTAMPTZ NOT NULL DEFAULT now()); \
+             CREATE INDEX IF NOT EXISTS {index} ON {table} (visible_at)",
+            table = self.table,
+            index = self.index
+        );
+        self.connection.batch_execute(sql.as_str()).await
+    }
+
+    /// Adds a job to the queue, returning its id.
+    pub async fn enqueue<J>(&self, job: &J) -> Result<i64, Error>
+    where
+        J: Serialize + Debug + Sync,
+    {
+        let sql = format!(
+            "INSERT INTO {} (payload) VALUES ($1) RETURNING id",
+            self.table
+        );
+        let rows = self.connection.query_dynamic(&sql, &[&Json(job)]).await?;
+        rows[0].get_index(0)
+    }
+
+    ///
+    /// Takes the job that has been waiting the longest, or `None` when no job is visible.
+    ///
+    /// The job is hidden from other workers for the visibility timeout of the queue.
+    ///
+    pub async fn dequeue<J>(&self) -> Result<Option<Job<J>>, Error>
+    where
+        J: DeserializeOwned,
+    {
+        let sql = format!(
+            "UPDATE {table} SET attempts = attempts + 1, \
+             visible_at = now() + make_interval(secs => $1) \
+             WHERE id = (SELECT id FROM {table} \
+             WHERE visible_at <= now() AND attempts < $2 \
+             ORDER BY visible_at, id LIMIT 1 FOR UPDATE SKIP LOCKED) \
+             RETURNING id, attempts, last_error, payload",
+            table = self.table
+        );
+        let max_attempts = self.max_attempts as i32;
+        let rows = self
+            .connection
+            .query_dynamic(
+                &sql,
+                &[&self.visibility_timeout.as_secs_f64(), &max_attempts],
+            )
+            .await?;
+        match rows.first() {
+            Some(row) => {
+                let Json(payload) = row.get("payload")?;
+                Ok(Some(Job {
+                    id: row.get("id")?,
+                    attempts: row.get("attempts")?,
+                    last_error: row.get("last_error")?,
+                    payload,
+                }))
+            }
+            None => Ok(None),
+        }
+    }
+
+    ///
+    /// Removes a finished job from the queue.
+    ///
+    /// Returns `false` when the job was not removed, because it was dequeued again after the
+    /// visibility timeout, or was already removed.
+    ///
+    pub async fn complete<J>(&self, job: &Job<J>) -> Result<bool, Error> {
+        let sql = format!("DELETE FROM {} WHERE id = $1 AND attempts = $2", self.table);
+        let result = self
+            .connection
+            .execute(&sql, &[&job.id, &job.attempts])
+            .await?;
+        Ok(!result.is_empty())
+    }
+
+    ///
+    /// Records the error of a failed job, which is dequeued again after the retry delay of the
+    /// queue, unless it reached the maximum number of attempts.
+    ///
+    /// Returns `false` when the error was not recorded, because the job was dequeued again after
+    /// the visibility timeout, or was removed.
+    ///
+    pub async fn fail<J>(&self, job: &Job<J>, error: &str) -> Result<bool, Error> {
+        let sql = format!(
+            "UPDATE {} SET last_error = $3, visible_at = now() + make_interval(secs => $4) \
+             WHERE id = $1 AND attempts = $2",
+            self.table
+        );
+        let result = self
+            .connection
+            .execute(
+                &sql,
+                &[
+                    &job.id,
+                    &job.attempts,
+                    &error,
+                    &self.retry_delay.as_secs_f64(),
+                ],
+            )
+            .await?;
+        Ok(!result.is_empty())
+    }
+}
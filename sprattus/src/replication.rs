@@ -0,0 +1,553 @@
+//! Streams the changes to a table from a logical replication slot.
+//!
+//! A replication slot keeps the changes of all tables in the write-ahead log of the database until
+//! they are read, so no change is missed while the consumer is not running, unlike the
+//! notifications of [`Connection::watch`](../struct.Connection.html#method.watch). The slot
+//! decodes the changes with an output plugin: either the wal2json extension, or pgoutput, which is
+//! built into Postgres and streams the tables of a publication.
+//!
+//! The changes are read with the SQL functions of logical decoding, which requires Postgres 11 or
+//! later, `wal_level = logical`, and a user with the `REPLICATION` attribute. A batch of changes is
+//! only removed from the slot when it is decoded, so a change that fails to decode is read again.
+//! Every slot reads the changes of the whole database. Changes of other tables than the one
+//! requested are skipped and removed from the slot, so a slot is used for a single table.
+//!
+//! Values that are too large to be stored inline and are not changed by an update are not written
+//! to the log, and are `NULL` in the updated row.
+//!
+//! Example:
+//! ```no_run
+//! use futures_util::stream::StreamExt;
+//! use sprattus::replication::{OutputPlugin, ReplicationSlot};
+//! use sprattus::*;
+//! use std::time::Duration;
+//!
+//! #[derive(FromSql, ToSql, Debug)]
+//! #[sprattus(table = "products")]
+//! struct Product {
+//!     #[sprattus(primary_key)]
+//!     prod_id: i32,
+//!     title: String,
+//! }
+//!
+//! #[tokio::main]
+//! async fn main() -> Result<(), Error> {
+//!     let conn = Connection::new("postgresql://localhost?user=tg").await?;
+//!     let slot = ReplicationSlot::new(&conn, "products_changes", OutputPlugin::Wal2Json);
+//!     slot.create().await?;
+//!     let mut changes = Box::pin(slot.stream::<Product>(Duration::from_secs(1)));
+//!     while let Some(change) = changes.next().await {
+//!         match change? {
+//!             Change::Inserted(product) => println!("{} was added", product.title),
+//!             Change::Updated(product) => println!("{} was changed", product.title),
+//!             Change::Deleted(prod_id) => println!("product {} was removed", prod_id),
+//!         }
+//!     }
+//!     Ok(())
+//! }
+//! ```
+use crate::ident::unquote_table;
+use crate::watch::{decode_primary_key, decode_row};
+use crate::*;
+use bytes::Buf;
+use futures_util::stream::{self, Stream};
+use serde_json::{Map, Value};
+use std::collections::{HashMap, VecDeque};
+use std::time::Duration;
+
+/// The maximum number of changes read at once, rounded up to whole transactions by Postgres.
+const BATCH_SIZE: i32 = 1000;
+
+/// The output plugin that decodes the changes of a [`ReplicationSlot`](struct.ReplicationSlot.html).
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum OutputPlugin {
+    /// The wal2json extension, which must be installed on the database server.
+    Wal2Json,
+    /// The plugin built into Postgres, streaming the changes to the tables of a publication,
+    /// created with `CREATE PUBLICATION`.
+    PgOutput {
+        /// The name of the publication.
+        publication: String,
+    },
+}
+
+impl OutputPlugin {
+    fn name(&self) -> &'static str {
+        match self {
+            OutputPlugin::Wal2Json => "wal2json",
+            OutputPlugin::PgOutput { .. } => "pgoutput",
+        }
+    }
+}
+
+/// A logical replication slot, from which the changes to a table are read.
+#[derive(Clone)]
+pub struct ReplicationSlot {
+    connection: Connection,
+    name: String,
+    plugin: OutputPlugin,
+}
+
+/// A change to a row, before it is converted into a struct.
+struct RowChange {
+    action: Action,
+    schema: String,
+    table: String,
+    // The new row, or the key of a deleted row, by column name.
+    row: Map<String, Value>,
+}
+
+enum Action {
+    Insert,
+    Update,
+    Delete,
+}
+
+/// A table described by a relation message of pgoutput.
+struct Relation {
+    schema: String,
+    table: String,
+    columns: Vec<String>,
+}
+
+impl ReplicationSlot {
+    /// Refers to the replication slot with the given name, which is decoded with the plugin.
+    pub fn new(connection: &Connection, name: &str, plugin: OutputPlugin) -> Self {
+        Self {
+            connection: connection.clone(),
+            name: name.to_string(),
+            plugin,
+        }
+    }
+
+    /// Returns the name of the slot.
+    pub fn name(&self) -> &str {
+        self.name.as_str()
+    }
+
+    /// Creates the slot, when it does not exist yet. Only changes made after the slot is created
+    /// are read from it.
+    pub async fn create(&self) -> Result<(), Error> {
+        self.connection
+            .execute(
+                "SELECT pg_create_logical_replication_slot($1, $2) WHERE NOT EXISTS \
+                 (SELECT 1 FROM pg_replication_slots WHERE slot_name = $1)",
+                &[&self.name, &self.plugin.name()],
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Drops the slot, when it exists, so the database no longer keeps its changes.
+    pub async fn drop_slot(&self) -> Result<(), Error> {
+        self.connection
+            .execute(
+                "SELECT pg_drop_replication_slot(slot_name) FROM pg_replication_slots \
+                 WHERE slot_name = $1",
+                &[&self.name],
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Reads the next batch of changes from the slot, returning the changes to the table of T.
+    ///
+    /// The batch is removed from the slot once all of its changes are decoded. An empty batch
+    /// means that there are no changes yet.
+    pub async fn changes<T>(&self) -> Result<Vec<Change<T>>, Error>
+    where
+        T: FromSql + ToSql,
+        T::PK: for<'a> FromSqlItem<'a>,
+    {
+        let (row_changes, last_lsn) = self.read().await?;
+        let (schema, table) = unquote_table(self.connection.table_name::<T>());
+        let mut changes = Vec::new();
+        for change in row_changes {
+            if change.table != table
+                || schema
+                    .as_ref()
+                    .is_some_and(|schema| change.schema != *schema)
+            {
+                continue;
+            }
+            let row = Value::Object(change.row).to_string();
+            changes.push(match change.action {
+                Action::Insert => Change::Inserted(decode_row(&self.connection, &row).await?),
+                Action::Update => Change::Updated(decode_row(&self.connection, &row).await?),
+                Action::Delete => {
+                    Change::Deleted(decode_primary_key::<T>(&self.connection, &row).await?)
+                }
+            });
+        }
+        if let Some(lsn) = last_lsn {
+            self.connection
+                .execute(
+                    "SELECT pg_replication_slot_advance($1, $2::pg_lsn)",
+                    &[&self.name, &lsn],
+                )
+                .await?;
+        }
+        Ok(changes)
+    }
+
+    ///
+    /// Streams the changes to the table of T, reading the slot again after the interval when it
+    /// has no changes.
+    ///
+    /// The stream ends after an error, and can be started again to read the changes that were not
+    /// removed from the slot.
+    ///
+    pub fn stream<T>(&self, interval: Duration) -> impl Stream<Item = Result<Change<T>, Error>>
+    where
+        T: FromSql + ToSql,
+        T::PK: for<'a> FromSqlItem<'a>,
+    {
+        let state = Some((self.clone(), VecDeque::new()));
+        stream::unfold(state, move |state| async move {
+            let (slot, mut pending) = state?;
+            loop {
+                if let Some(change) = pending.pop_front() {
+                    return Some((Ok(change), Some((slot, pending))));
+                }
+                match slot.changes::<T>().await {
+                    Ok(changes) if changes.is_empty() => tokio::time::sleep(interval).await,
+                    Ok(changes) => pending.extend(changes),
+                    Err(error) => return Some((Err(error), None)),
+                }
+            }
+        })
+    }
+
+    /// Reads the next batch of changes without removing it from the slot, returning the changes
+    /// to rows and the position in the log up to which the batch is read.
+    async fn read(&self) -> Result<(Vec<RowChange>, Option<String>), Error> {
+        let mut changes = Vec::new();
+        let mut last_lsn = None;
+        match &self.plugin {
+            OutputPlugin::Wal2Json => {
+                let rows = self
+                    .connection
+                    .query_dynamic(
+                        "SELECT lsn::text, data FROM pg_logical_slot_peek_changes($1, NULL, $2, \
+                         'format-version', '2')",
+                        &[&self.name, &BATCH_SIZE],
+                    )
+                    .await?;
+                for row in rows {
+                    last_lsn = Some(row.get::<String>("lsn")?);
+                    changes.extend(decode_wal2json(&row.get::<String>("data")?)?);
+                }
+            }
+            OutputPlugin::PgOutput { publication } => {
+                let rows = self
+                    .connection
+                    .query_dynamic(
+                        "SELECT lsn::text, data FROM pg_logical_slot_peek_binary_changes($1, NULL, \
+                         $2, 'proto_version', '1', 'publication_names', $3)",
+                        &[&self.name, &BATCH_SIZE, publication],
+                    )
+                    .await?;
+                let mut relations = HashMap::new();
+                for row in rows {
+                    last_lsn = Some(row.get::<String>("lsn")?);
+                    changes.extend(decode_pgoutput(
+                        &row.get::<Vec<u8>>("data")?,
+                        &mut relations,
+                    )?);
+                }
+            }
+        }
+        Ok((changes, last_lsn))
+    }
+}
+
+/// Decodes a change in format version 2 of wal2json, returning `None` for the start and the end
+/// of a transaction and other messages that don't change a row.
+fn decode_wal2json(data: &str) -> Result<Option<RowChange>, Error> {
+    let malformed = || Error::Replication(format!("malformed wal2json change `{}`", data));
+    let change: Value = serde_json::from_str(data).map_err(|_| malformed())?;
+    let (action, columns) = match change["action"].as_str() {
+        Some("I") => (Action::Insert, "columns"),
+        Some("U") => (Action::Update, "columns"),
+        Some("D") => (Action::Delete, "identity"),
+        _ => return Ok(None),
+    };
+    let text = |key: &str| {
+        change[key]
+            .as_str()
+            .map(str::to_string)
+            .ok_or_else(malformed)
+    };
+    let mut row = Map::new();
+    for column in change[columns].as_array().ok_or_else(malformed)? {
+        let name = column["name"].as_str().ok_or_else(malformed)?;
+        row.insert(name.to_string(), column["value"].clone());
+    }
+    Ok(Some(RowChange {
+        action,
+        schema: text("schema")?,
+        table: text("table")?,
+        row,
+    }))
+}
+
+/// Decodes a message of version 1 of the pgoutput protocol, remembering the tables described by
+/// relation messages. Returns `None` for messages that don't change a row.
+fn decode_pgoutput(
+    mut data: &[u8],
+    relations: &mut HashMap<u32, Relation>,
+) -> Result<Option<RowChange>, Error> {
+    let malformed = || Error::Replication(String::from("malformed pgoutput message"));
+    if !data.has_remaining() {
+        return Err(malformed());
+    }
+    let action = match data.get_u8() {
+        b'R' => {
+            let id = get_u32(&mut data).ok_or_else(malformed)?;
+            let schema = get_string(&mut data).ok_or_else(malformed)?;
+            let table = get_string(&mut data).ok_or_else(malformed)?;
+            // The replica identity, followed by the number of columns.
+            if data.remaining() < 3 {
+                return Err(malformed());
+            }
+            data.advance(1);
+            let count = data.get_i16();
+            let mut columns = Vec::with_capacity(count.max(0) as usize);
+            for _ in 0..count {
+                // The flags, followed by the name, the type and the type modifier.
+                if !data.has_remaining() {
+                    return Err(malformed());
+                }
+                data.advance(1);
+                columns.push(get_string(&mut data).ok_or_else(malformed)?);
+                if data.remaining() < 8 {
+                    return Err(malformed());
+                }
+                data.advance(8);
+            }
+            relations.insert(
+                id,
+                Relation {
+                    schema,
+                    table,
+                    columns,
+                },
+            );
+            return Ok(None);
+        }
+        b'I' => Action::Insert,
+        b'U' => Action::Update,
+        b'D' => Action::Delete,
+        _ => return Ok(None),
+    };
+    let relation = get_u32(&mut data)
+        .and_then(|id| relations.get(&id))
+        .ok_or_else(malformed)?;
+    let mut row = None;
+    // An update starts with the old key or row when the key changed, which is skipped, and a
+    // delete only has the old key or row.
+    while data.has_remaining() {
+        let kind = data.get_u8();
+        let tuple = decode_tuple(&mut data, relation).ok_or_else(malformed)?;
+        if kind == b'N' || matches!(action, Action::Delete) {
+            row = Some(tuple);
+        }
+    }
+    Ok(Some(RowChange {
+        action,
+        schema: relation.schema.clone(),
+        table: relation.table.clone(),
+        row: row.ok_or_else(malformed)?,
+    }))
+}
+
+/// Decodes the values of a row in a pgoutput message, by column name. Values are sent as text,
+/// values that are not sent are left out.
+fn decode_tuple(data: &mut &[u8], relation: &Relation) -> Option<Map<String, Value>> {
+    if data.remaining() < 2 {
+        return None;
+    }
+    let count = data.get_i16();
+    let mut row = Map::new();
+    for index in 0..count.max(0) as usize {
+        if !data.has_remaining() {
+            return None;
+        }
+        let value = match data.get_u8() {
+            b'n' => Value::Null,
+            b't' => {
+                let length = get_u32(data)? as usize;
+                if data.remaining() < length {
+                    return None;
+                }
+                let value = String::from_utf8(data[..length].to_vec()).ok()?;
+                data.advance(length);
+                Value::String(value)
+            }
+            // A value stored out of line that didn't change.
+            _ => continue,
+        };
+        row.insert(relation.columns.get(index)?.clone(), value);
+    }
+    Some(row)
+}
+
+fn get_u32(data: &mut &[u8]) -> Option<u32> {
+    if data.remaining() < 4 {
+        return None;
+    }
+    Some(data.get_u32())
+}
+
+/// Reads a string terminated by a null byte.
+fn get_string(data: &mut &[u8]) -> Option<String> {
+    let end = data.iter().position(|byte| *byte == 0)?;
+    let string = String::from_utf8(data[..end].to_vec()).ok()?;
+    data.advance(end + 1);
+    Some(string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn decodes_wal2json_changes() {
+        let change = decode_wal2json(
+            r#"{"action":"I","schema":"public","table":"products","columns":[{"name":"prod_id","type":"integer","value":1},{"name":"title","type":"text","value":"Chair"}]}"#,
+        )
+        .unwrap()
+        .unwrap();
+        assert!(matches!(change.action, Action::Insert));
+        assert_eq!(change.schema, "public");
+        assert_eq!(change.table, "products");
+        assert_eq!(change.row["prod_id"], json!(1));
+        assert_eq!(change.row["title"], json!("Chair"));
+
+        let change = decode_wal2json(
+            r#"{"action":"D","schema":"public","table":"products","identity":[{"name":"prod_id","type":"integer","value":1}]}"#,
+        )
+        .unwrap()
+        .unwrap();
+        assert!(matches!(change.action, Action::Delete));
+        assert_eq!(change.row.len(), 1);
+        assert_eq!(change.row["prod_id"], json!(1));
+    }
+
+    #[test]
+    fn skips_wal2json_messages_without_rows() {
+        assert!(decode_wal2json(r#"{"action":"B"}"#).unwrap().is_none());
+        assert!(decode_wal2json(r#"{"action":"C"}"#).unwrap().is_none());
+    }
+
+    #[test]
+    fn rejects_malformed_wal2json_changes() {
+        assert!(decode_wal2json("not json").is_err());
+        assert!(decode_wal2json(r#"{"action":"I","schema":"public","table":"products"}"#).is_err());
+    }
+
+    fn relation_message(id: u32, columns: &[&str]) -> Vec<u8> {
+        let mut message = vec![b'R'];
+        message.extend(id.to_be_bytes());
+        message.extend(b"public\0products\0");
+        message.push(b'd');
+        message.extend((columns.len() as i16).to_be_bytes());
+        for column in columns {
+            message.push(0);
+            message.extend(column.as_bytes());
+            message.push(0);
+            // The type and the type modifier.
+            message.extend([0; 8]);
+        }
+        message
+    }
+
+    fn tuple(kind: u8, values: &[Option<&str>]) -> Vec<u8> {
+        let mut tuple = vec![kind];
+        tuple.extend((values.len() as i16).to_be_bytes());
+        for value in values {
+            match value {
+                Some(value) => {
+                    tuple.push(b't');
+                    tuple.extend((value.len() as u32).to_be_bytes());
+                    tuple.extend(value.as_bytes());
+                }
+                None => tuple.push(b'n'),
+            }
+        }
+        tuple
+    }
+
+    fn change_message(action: u8, id: u32, tuples: &[Vec<u8>]) -> Vec<u8> {
+        let mut message = vec![action];
+        message.extend(id.to_be_bytes());
+        for tuple in tuples {
+            message.extend(tuple);
+        }
+        message
+    }
+
+    #[test]
+    fn decodes_pgoutput_changes() {
+        let mut relations = HashMap::new();
+        let relation = relation_message(16384, &["prod_id", "title"]);
+        assert!(decode_pgoutput(&relation, &mut relations)
+            .unwrap()
+            .is_none());
+
+        let insert = change_message(b'I', 16384, &[tuple(b'N', &[Some("1"), None])]);
+        let change = decode_pgoutput(&insert, &mut relations).unwrap().unwrap();
+        assert!(matches!(change.action, Action::Insert));
+        assert_eq!(change.schema, "public");
+        assert_eq!(change.table, "products");
+        assert_eq!(change.row["prod_id"], json!("1"));
+        assert_eq!(change.row["title"], Value::Null);
+    }
+
+    #[test]
+    fn decodes_the_new_row_of_pgoutput_updates() {
+        let mut relations = HashMap::new();
+        decode_pgoutput(&relation_message(1, &["prod_id", "title"]), &mut relations).unwrap();
+        let update = change_message(
+            b'U',
+            1,
+            &[
+                tuple(b'K', &[Some("1"), None]),
+                tuple(b'N', &[Some("2"), Some("Table")]),
+            ],
+        );
+        let change = decode_pgoutput(&update, &mut relations).unwrap().unwrap();
+        assert!(matches!(change.action, Action::Update));
+        assert_eq!(change.row["prod_id"], json!("2"));
+        assert_eq!(change.row["title"], json!("Table"));
+
+        let delete = change_message(b'D', 1, &[tuple(b'K', &[Some("2"), None])]);
+        let change = decode_pgoutput(&delete, &mut relations).unwrap().unwrap();
+        assert!(matches!(change.action, Action::Delete));
+        assert_eq!(change.row["prod_id"], json!("2"));
+    }
+
+    #[test]
+    fn rejects_malformed_pgoutput_messages() {
+        let mut relations = HashMap::new();
+        assert!(decode_pgoutput(&[], &mut relations).is_err());
+        // A change to a table that wasn't described by a relation message.
+        let insert = change_message(b'I', 1, &[tuple(b'N', &[Some("1")])]);
+        assert!(decode_pgoutput(&insert, &mut relations).is_err());
+        // A value that is longer than the message.
+        decode_pgoutput(&relation_message(1, &["prod_id"]), &mut relations).unwrap();
+        let mut truncated = change_message(b'I', 1, &[tuple(b'N', &[Some("12")])]);
+        truncated.pop();
+        assert!(decode_pgoutput(&truncated, &mut relations).is_err());
+    }
+
+    #[test]
+    fn skips_pgoutput_messages_without_rows() {
+        let mut relations = HashMap::new();
+        // A begin message.
+        assert!(decode_pgoutput(&[b'B', 0, 0], &mut relations)
+            .unwrap()
+            .is_none());
+    }
+}
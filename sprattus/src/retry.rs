@@ -0,0 +1,174 @@
+use crate::circuit_breaker::is_failure;
+use crate::connection::Access;
+use crate::*;
+use std::future::Future;
+use std::time::Duration;
+
+/// Retries statements that fail because the database can't be reached or does not respond in
+/// time. Enabled with [`Connection::with_retry`](struct.Connection.html#method.with_retry).
+///
+/// Between the attempts the connection waits, starting with the initial backoff, which is
+/// doubled after every attempt up to the maximum backoff. Errors caused by the statement itself,
+/// like a violated constraint, are returned immediately. So is every error of a connection that
+/// is closed, as it can't recover, and of a statement within a transaction, which is aborted by
+/// the failure.
+///
+/// Only statements that can safely be sent twice are retried. The statements generated to read
+/// rows, like those of `find`, `fetch_all` and `select` without a lock, are always retried. Other
+/// statements might have been executed before the failure was noticed, so they are only retried
+/// when they are tagged idempotent, like an upsert that writes the same row every time. This
+/// includes queries written by hand, even a `SELECT`, as it can have side effects, like
+/// `SELECT nextval('ids')`. Statements are tagged per call with
+/// [`Connection::idempotent`](struct.Connection.html#method.idempotent), or for all statements of
+/// the connection with [`retry_writes`](#method.retry_writes).
+///
+/// Example:
+/// ```no_run
+/// use sprattus::*;
+/// use std::time::Duration;
+///
+/// #[derive(FromSql, ToSql, Debug)]
+/// struct Product {
+///     #[sprattus(primary_key)]
+///     prod_id: i32,
+///     title: String,
+/// }
+///
+/// #[tokio::main]
+/// async fn main() -> Result<(), Error> {
+///     let retry = RetryPolicy::new(3).backoff(Duration::from_millis(100), Duration::from_secs(1));
+///     let conn = Connection::new("postgresql://localhost?user=tg")
+///         .await?
+///         .with_retry(retry);
+///     // Retried, as it only reads.
+///     let mut product = conn.find::<Product>(&1).await?;
+///     product.title = String::from("ACADEMY ACADEMY");
+///     // Retried, as writing the same title twice has the same effect as writing it once.
+///     conn.idempotent().update(&product).await?;
+///     Ok(())
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    retry_writes: bool,
+}
+
+impl RetryPolicy {
+    /// Creates a policy that sends a statement at most the given number of times, including the
+    /// first attempt, waiting 50 milliseconds before the first retry and at most 2 seconds
+    /// between later ones.
+    pub fn new(max_attempts: u32) -> Self {
+        Self {
+            max_attempts: max_attempts.max(1),
+            initial_backoff: Duration::from_millis(50),
+            max_backoff: Duration::from_secs(2),
+            retry_writes: false,
+        }
+    }
+
+    /// Sets the time to wait before the first retry, and the maximum time to wait between
+    /// attempts.
+    pub fn backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.initial_backoff = initial;
+        self.max_backoff = max.max(initial);
+        self
+    }
+
+    /// Treats all statements of the connection as idempotent, so writes are retried as well.
+    pub fn retry_writes(mut self, retry_writes: bool) -> Self {
+        self.retry_writes = retry_writes;
+        self
+    }
+
+    /// Returns whether a statement may be sent again, given whether it only reads and whether it
+    /// is tagged idempotent.
+    pub(crate) fn may_retry(&self, access: Access, idempotent: bool) -> bool {
+        idempotent || self.retry_writes || access == Access::Read
+    }
+
+    /// Returns the times to wait before every retry.
+    fn backoffs(&self) -> impl Iterator<Item = Duration> {
+        let max_backoff = self.max_backoff;
+        std::iter::successors(Some(self.initial_backoff), move |backoff| {
+            Some((*backoff * 2).min(max_backoff))
+        })
+        .take(self.max_attempts as usize - 1)
+    }
+
+    /// Runs a statement until it succeeds, fails with an error that isn't transient, or the
+    /// attempts run out.
+    pub(crate) async fn run<R, F, Fut>(&self, statement: F) -> Result<R, Error>
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = Result<R, Error>>,
+    {
+        let mut backoffs = self.backoffs();
+        loop {
+            match statement().await {
+                Err(error) if is_transient(&error) => match backoffs.next() {
+                    Some(backoff) => {
+                        log::warn!("retrying a statement in {:?}: {}", backoff, error);
+                        tokio::time::sleep(backoff).await;
+                    }
+                    None => return Err(error),
+                },
+                result => return result,
+            }
+        }
+    }
+}
+
+/// Returns whether the error might not occur when the statement is sent again, because the
+/// database was unavailable while the connection is still open.
+fn is_transient(error: &Error) -> bool {
+    match error {
+        Error::Postgres(error) if error.is_closed() => false,
+        error => is_failure(error),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retries_reads_and_idempotent_statements() {
+        let policy = RetryPolicy::new(3);
+        assert!(policy.may_retry(Access::Read, false));
+        assert!(!policy.may_retry(Access::Write, false));
+        assert!(policy.may_retry(Access::Write, true));
+        let policy = policy.retry_writes(true);
+        assert!(policy.may_retry(Access::Write, false));
+    }
+
+    #[test]
+    fn doubles_the_backoff_up_to_the_maximum() {
+        let policy =
+            RetryPolicy::new(5).backoff(Duration::from_millis(100), Duration::from_millis(300));
+        let backoffs: Vec<Duration> = policy.backoffs().collect();
+        assert_eq!(
+            backoffs,
+            [100, 200, 300, 300].map(Duration::from_millis).to_vec()
+        );
+        // The maximum is never below the initial backoff.
+        let policy = RetryPolicy::new(3).backoff(Duration::from_secs(1), Duration::ZERO);
+        let backoffs: Vec<Duration> = policy.backoffs().collect();
+        assert_eq!(backoffs, vec![Duration::from_secs(1); 2]);
+    }
+
+    #[test]
+    fn sends_a_statement_at_least_once() {
+        assert_eq!(RetryPolicy::new(0).backoffs().count(), 0);
+        assert_eq!(RetryPolicy::new(1).backoffs().count(), 0);
+    }
+
+    #[test]
+    fn retries_only_transient_errors() {
+        assert!(is_transient(&Error::Timeout));
+        assert!(!is_transient(&Error::Closed));
+        assert!(!is_transient(&Error::Config(String::from("invalid port"))));
+    }
+}
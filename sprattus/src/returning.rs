@@ -0,0 +1,30 @@
+use crate::*;
+
+/// The columns returned by the statements that create, update and delete rows.
+///
+/// Set it with [`Connection::with_returning`](struct.Connection.html#method.with_returning).
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Returning {
+    /// Return all columns of the table with `RETURNING *`. This is the default.
+    #[default]
+    All,
+    /// Return only the columns of the fields of the struct. Use this when the table has columns
+    /// that are not part of the struct.
+    Fields,
+}
+
+impl Returning {
+    /// Returns the list of columns following `RETURNING`, prefixed with the alias of the table
+    /// when the statement has one.
+    pub(crate) fn columns<T: Table>(self, alias: Option<&str>) -> String {
+        match (self, alias) {
+            (Returning::All, _) => String::from("*"),
+            (Returning::Fields, None) => T::get_all_fields().to_string(),
+            (Returning::Fields, Some(alias)) => format!(
+                "{alias}.{fields}",
+                alias = alias,
+                fields = T::get_all_fields().replace(",", format!(",{}.", alias).as_str())
+            ),
+        }
+    }
+}
@@ -0,0 +1,938 @@
+use crate::connection::Access;
+use crate::cte::with_clause;
+use crate::tenant::tenant_condition;
+use crate::*;
+use futures_util::future::try_join;
+use std::marker::PhantomData;
+
+/// A query selecting rows of a table or view, created by
+/// [`Connection::select`](struct.Connection.html#method.select).
+///
+/// Conditions added with [`filter`](#method.filter) are combined with `AND`.
+pub struct Select<'a, T> {
+    connection: &'a Connection,
+    conditions: Vec<String>,
+    args: Vec<Argument<'a>>,
+    order: Vec<(&'static str, Order)>,
+    limit: Option<u64>,
+    offset: Option<u64>,
+    distinct: Distinct,
+    lock: Option<Lock>,
+    lock_wait: LockWait,
+    // The queries named with `WITH`, and their arguments, which precede those of the query.
+    named_queries: Vec<String>,
+    named_query_args: Vec<Argument<'a>>,
+    result_type: PhantomData<T>,
+}
+
+/// An argument of a query, either borrowed from the caller or owned by the query.
+pub(crate) enum Argument<'a> {
+    Borrowed(&'a (dyn ToSqlItem + Sync)),
+    Owned(Box<dyn ToSqlItem + Sync + Send>),
+}
+
+/// The rows that are removed as duplicates by a query.
+enum Distinct {
+    None,
+    // Rows of which all selected columns are equal.
+    All,
+    // Rows of which the given columns are equal.
+    On(Vec<&'static str>),
+}
+
+/// The lock taken on the selected rows.
+#[derive(Clone, Copy)]
+enum Lock {
+    Update,
+    Share,
+}
+
+/// What a query does when a selected row is locked by another transaction.
+#[derive(Clone, Copy)]
+enum LockWait {
+    Wait,
+    SkipLocked,
+    NoWait,
+}
+
+impl<'a> Argument<'a> {
+    pub(crate) fn as_sql_item(&self) -> &(dyn ToSqlItem + Sync) {
+        match self {
+            Argument::Borrowed(arg) => *arg,
+            Argument::Owned(arg) => arg.as_ref(),
+        }
+    }
+}
+
+impl<'a, T> Select<'a, T>
+where
+    T: Table + FromSql,
+{
+    pub(crate) fn new(connection: &'a Connection) -> Self {
+        Self::with_queries(connection, Vec::new(), Vec::new())
+    }
+
+    pub(crate) fn with_queries(
+        connection: &'a Connection,
+        named_queries: Vec<String>,
+        named_query_args: Vec<Argument<'a>>,
+    ) -> Self {
+        Self {
+            connection,
+            conditions: Vec::new(),
+            args: Vec::new(),
+            order: Vec::new(),
+            limit: None,
+            offset: None,
+            distinct: Distinct::None,
+            lock: None,
+            lock_wait: LockWait::Wait,
+            named_queries,
+            named_query_args,
+            result_type: PhantomData,
+        }
+    }
+
+    /// Adds a condition to the `WHERE` clause of the query.
+    ///
+    /// The placeholders in the condition are numbered over all conditions of the query,
+    /// so the first argument of a second filter is referred to with `$2` when the first filter
+    /// has a single argument.
+    pub fn filter(mut self, condition: &str, args: &[&'a (dyn ToSqlItem + Sync)]) -> Self {
+        self.conditions.push(condition.to_string());
+        self.args
+            .extend(args.iter().map(|arg| Argument::Borrowed(*arg)));
+        self
+    }
+
+    /// Adds the condition of a [`Filter`](struct.Filter.html) to the `WHERE` clause of the query,
+    /// renumbering its placeholders after the arguments of the earlier conditions.
+    ///
+    /// A filter matching all rows adds no condition.
+    ///
+    /// Example:
+    /// ```no_run
+    /// use sprattus::*;
+    ///
+    /// #[derive(FromSql, ToSql, Debug)]
+    /// #[sprattus(table = "products")]
+    /// struct Product {
+    ///     #[sprattus(primary_key)]
+    ///     prod_id: i32,
+    ///     title: String,
+    ///     price: f64,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Error> {
+    ///     let conn = Connection::new("postgresql://localhost?user=tg").await?;
+    ///     let title: Option<String> = Some(String::from("%ACADEMY%"));
+    ///     let max_price: Option<f64> = None;
+    ///     let mut filter = Filter::all();
+    ///     if let Some(title) = &title {
+    ///         filter = filter.and(Filter::new("title LIKE $1", &[title]));
+    ///     }
+    ///     if let Some(max_price) = &max_price {
+    ///         filter = filter.and(Filter::new("price <= $1", &[max_price]));
+    ///     }
+    ///     // Executes `SELECT "prod_id","title","price" FROM "products"
+    ///     // WHERE (prod_id > $1) AND (title LIKE $2)`.
+    ///     let products = conn
+    ///         .select::<Product>()
+    ///         .filter("prod_id > $1", &[&100])
+    ///         .filter_with(filter)
+    ///         .fetch()
+    ///         .await?;
+    ///     dbg!(products);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn filter_with(mut self, filter: Filter<'a>) -> Self {
+        if let Some((condition, args)) = filter.into_parts(self.args.len()) {
+            self.conditions.push(condition);
+            self.args.extend(args);
+        }
+        self
+    }
+
+    /// Adds a condition that the columns of the given fields, as a row, are one of the given
+    /// tuples, like `("order_id", "line_no") IN (VALUES ($1, $2), ($3, $4))`.
+    ///
+    /// A condition without tuples matches no rows.
+    ///
+    /// # Panics
+    ///
+    /// Panics when the struct has no field with one of the given names, or when the number of
+    /// fields differs from the length of the tuples.
+    ///
+    /// Example:
+    /// ```no_run
+    /// use sprattus::*;
+    ///
+    /// #[derive(FromSql, ToSql, Debug)]
+    /// #[sprattus(table = "order_lines", no_primary_key)]
+    /// struct OrderLine {
+    ///     order_id: i32,
+    ///     line_no: i32,
+    ///     quantity: i32,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Error> {
+    ///     let conn = Connection::new("postgresql://localhost?user=tg").await?;
+    ///     let keys = vec![(1, 1), (1, 2), (2, 1)];
+    ///     // Executes `SELECT "order_id","line_no","quantity" FROM "order_lines"
+    ///     // WHERE ("order_id","line_no") IN (VALUES ($1,$2),($3,$4),($5,$6))`.
+    ///     let lines = conn
+    ///         .select::<OrderLine>()
+    ///         .filter_in(&["order_id", "line_no"], &keys)
+    ///         .fetch()
+    ///         .await?;
+    ///     dbg!(lines);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn filter_in<K: TupleValues>(mut self, fields: &[&str], tuples: &'a [K]) -> Self {
+        if fields.len() != K::LEN {
+            panic!(
+                "filter_in got {} fields for tuples with {} values",
+                fields.len(),
+                K::LEN
+            );
+        }
+        let columns: Vec<&str> = fields.iter().map(|field| column_name::<T>(field)).collect();
+        if tuples.is_empty() {
+            self.conditions.push(String::from("FALSE"));
+            return self;
+        }
+        let rows: Vec<String> = tuples
+            .iter()
+            .map(|tuple| {
+                let placeholders: Vec<String> = tuple
+                    .values()
+                    .into_iter()
+                    .map(|value| {
+                        self.args.push(Argument::Borrowed(value));
+                        format!("${}", self.args.len())
+                    })
+                    .collect();
+                format!("({})", placeholders.join(","))
+            })
+            .collect();
+        self.conditions.push(format!(
+            "({}) IN (VALUES {})",
+            columns.join(","),
+            rows.join(",")
+        ));
+        self
+    }
+
+    /// Adds a condition that the JSONB column of a field contains the given value, using the `@>`
+    /// operator.
+    ///
+    /// # Panics
+    ///
+    /// Panics when the struct has no field with the given name.
+    ///
+    /// Example:
+    /// ```no_run
+    /// use sprattus::*;
+    /// use serde_json::json;
+    ///
+    /// #[derive(FromSql, ToSql, Debug)]
+    /// #[sprattus(table = "products")]
+    /// struct Product {
+    ///     #[sprattus(primary_key)]
+    ///     prod_id: i32,
+    ///     #[sprattus(pg_type = "JSONB")]
+    ///     metadata: serde_json::Value,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Error> {
+    ///     let conn = Connection::new("postgresql://localhost?user=tg").await?;
+    ///     // Executes `SELECT "prod_id","metadata" FROM "products" WHERE "metadata" @> $1`.
+    ///     let products = conn
+    ///         .select::<Product>()
+    ///         .filter_json_contains("metadata", json!({"tag": "new"}))
+    ///         .fetch()
+    ///         .await?;
+    ///     dbg!(products);
+    ///     Ok(())
+    /// }
+    /// ```
+    #[cfg(feature = "with-serde_json-1")]
+    pub fn filter_json_contains(mut self, field: &str, value: serde_json::Value) -> Self {
+        let condition = format!("{} @> ${}", column_name::<T>(field), self.args.len() + 1);
+        self.conditions.push(condition);
+        self.args.push(Argument::Owned(Box::new(value)));
+        self
+    }
+
+    /// Adds a condition comparing the value at a JSON path of the JSONB column of a field with the
+    /// given value, using `jsonb_path_exists`.
+    ///
+    /// The operator is one of the comparison operators of SQL/JSON path expressions:
+    /// `==`, `!=`, `<>`, `<`, `<=`, `>` or `>=`. The value is passed as a parameter.
+    ///
+    /// # Panics
+    ///
+    /// Panics when the struct has no field with the given name, or when the operator is not a
+    /// comparison operator.
+    ///
+    /// Example:
+    /// ```no_run
+    /// use sprattus::*;
+    ///
+    /// #[derive(FromSql, ToSql, Debug)]
+    /// #[sprattus(table = "products")]
+    /// struct Product {
+    ///     #[sprattus(primary_key)]
+    ///     prod_id: i32,
+    ///     #[sprattus(pg_type = "JSONB")]
+    ///     metadata: serde_json::Value,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Error> {
+    ///     let conn = Connection::new("postgresql://localhost?user=tg").await?;
+    ///     // Executes `SELECT "prod_id","metadata" FROM "products"
+    ///     // WHERE jsonb_path_exists("metadata", $1::text::jsonpath, $2)`.
+    ///     let products = conn
+    ///         .select::<Product>()
+    ///         .filter_json_path("metadata", "$.price", ">", 10)
+    ///         .fetch()
+    ///         .await?;
+    ///     dbg!(products);
+    ///     Ok(())
+    /// }
+    /// ```
+    #[cfg(feature = "with-serde_json-1")]
+    pub fn filter_json_path<V>(mut self, field: &str, path: &str, operator: &str, value: V) -> Self
+    where
+        V: Into<serde_json::Value>,
+    {
+        const OPERATORS: &[&str] = &["==", "!=", "<>", "<", "<=", ">", ">="];
+        if !OPERATORS.contains(&operator) {
+            panic!("'{}' is not a comparison operator of JSON paths", operator);
+        }
+        let condition = format!(
+            "jsonb_path_exists({}, ${}::text::jsonpath, ${})",
+            column_name::<T>(field),
+            self.args.len() + 1,
+            self.args.len() + 2
+        );
+        self.conditions.push(condition);
+        // The value is passed as a variable of the path, so it is never part of the path itself.
+        let path = format!("{} ? (@ {} $value)", path, operator);
+        let variables = serde_json::json!({ "value": value.into() });
+        self.args.push(Argument::Owned(Box::new(path)));
+        self.args.push(Argument::Owned(Box::new(variables)));
+        self
+    }
+
+    /// Adds a condition that the HSTORE column of a field contains the given key with the given
+    /// value, using the `@>` operator.
+    ///
+    /// # Panics
+    ///
+    /// Panics when the struct has no field with the given name.
+    ///
+    /// Example:
+    /// ```no_run
+    /// use sprattus::*;
+    ///
+    /// #[derive(FromSql, ToSql, Debug)]
+    /// #[sprattus(table = "products")]
+    /// struct Product {
+    ///     #[sprattus(primary_key)]
+    ///     prod_id: i32,
+    ///     attributes: Hstore,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Error> {
+    ///     let conn = Connection::new("postgresql://localhost?user=tg").await?;
+    ///     // Executes `SELECT "prod_id","attributes" FROM "products"
+    ///     // WHERE "attributes" @> hstore($1::text, $2::text)`.
+    ///     let products = conn
+    ///         .select::<Product>()
+    ///         .filter_hstore_contains("attributes", "color", "red")
+    ///         .fetch()
+    ///         .await?;
+    ///     dbg!(products);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn filter_hstore_contains(mut self, field: &str, key: &str, value: &str) -> Self {
+        let condition = format!(
+            "{} @> hstore(${}::text, ${}::text)",
+            column_name::<T>(field),
+            self.args.len() + 1,
+            self.args.len() + 2
+        );
+        self.conditions.push(condition);
+        self.args.push(Argument::Owned(Box::new(key.to_string())));
+        self.args.push(Argument::Owned(Box::new(value.to_string())));
+        self
+    }
+
+    /// Adds a condition that the HSTORE column of a field contains the given key, whatever its
+    /// value, using the `?` operator.
+    ///
+    /// # Panics
+    ///
+    /// Panics when the struct has no field with the given name.
+    pub fn filter_hstore_has_key(mut self, field: &str, key: &str) -> Self {
+        let condition = format!("{} ? ${}", column_name::<T>(field), self.args.len() + 1);
+        self.conditions.push(condition);
+        self.args.push(Argument::Owned(Box::new(key.to_string())));
+        self
+    }
+
+    /// Sorts the rows by the column of a field, after the columns of earlier calls.
+    ///
+    /// # Panics
+    ///
+    /// Panics when the struct has no field with the given name.
+    pub fn order_by(mut self, field: &str, order: Order) -> Self {
+        self.order.push((column_name::<T>(field), order));
+        self
+    }
+
+    /// Sorts the rows by the columns of several fields, in the given order.
+    ///
+    /// # Panics
+    ///
+    /// Panics when the struct has no field with one of the given names.
+    ///
+    /// Example:
+    /// ```no_run
+    /// use sprattus::*;
+    /// use sprattus::Order::*;
+    ///
+    /// #[derive(FromSql, ToSql, Debug)]
+    /// #[sprattus(table = "products")]
+    /// struct Product {
+    ///     #[sprattus(primary_key)]
+    ///     prod_id: i32,
+    ///     title: String,
+    ///     price: Option<f64>,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Error> {
+    ///     let conn = Connection::new("postgresql://localhost?user=tg").await?;
+    ///     // Executes `SELECT "prod_id","title","price" FROM "products"
+    ///     // ORDER BY "price" DESC NULLS LAST,"prod_id" ASC`.
+    ///     let products = conn
+    ///         .select::<Product>()
+    ///         .order_by_many(&[("price", Desc.nulls_last()), ("prod_id", Asc)])
+    ///         .fetch()
+    ///         .await?;
+    ///     dbg!(products);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn order_by_many(mut self, fields: &[(&str, Order)]) -> Self {
+        self.order.extend(
+            fields
+                .iter()
+                .map(|(field, order)| (column_name::<T>(field), *order)),
+        );
+        self
+    }
+
+    /// Returns at most the given number of rows, with `LIMIT`.
+    pub fn limit(mut self, limit: u64) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Skips the given number of rows, with `OFFSET`. The rows should be sorted with
+    /// [`order_by`](#method.order_by), so every page skips the same rows.
+    pub fn offset(mut self, offset: u64) -> Self {
+        self.offset = Some(offset);
+        self
+    }
+
+    /// Removes duplicate rows, of which all columns are equal, with `SELECT DISTINCT`.
+    ///
+    /// Replaces the columns of an earlier call to [`distinct_on`](#method.distinct_on).
+    pub fn distinct(mut self) -> Self {
+        self.distinct = Distinct::All;
+        self
+    }
+
+    /// Keeps only the first row of every set of rows of which the columns of the given fields are
+    /// equal, with `SELECT DISTINCT ON`.
+    ///
+    /// Postgres requires the `ORDER BY` clause to start with these columns, so they are moved to
+    /// the front of the order. Columns that are not ordered by with
+    /// [`order_by`](#method.order_by) are sorted in ascending order. The order of the remaining
+    /// columns decides which row of a set comes first.
+    ///
+    /// # Panics
+    ///
+    /// Panics when the struct has no field with one of the given names.
+    ///
+    /// Example:
+    /// ```no_run
+    /// use sprattus::*;
+    ///
+    /// #[derive(FromSql, ToSql, Debug)]
+    /// #[sprattus(table = "orders")]
+    /// struct Order {
+    ///     #[sprattus(primary_key)]
+    ///     orderid: i32,
+    ///     customerid: i32,
+    ///     orderdate: String,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Error> {
+    ///     let conn = Connection::new("postgresql://localhost?user=tg").await?;
+    ///     // Executes `SELECT DISTINCT ON ("customerid") "orderid","customerid","orderdate"
+    ///     // FROM "orders" ORDER BY "customerid" ASC,"orderdate" DESC`.
+    ///     let latest_orders = conn
+    ///         .select::<Order>()
+    ///         .distinct_on(&["customerid"])
+    ///         .order_by("orderdate", sprattus::Order::Desc)
+    ///         .fetch()
+    ///         .await?;
+    ///     dbg!(latest_orders);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn distinct_on(mut self, fields: &[&str]) -> Self {
+        self.distinct = Distinct::On(fields.iter().map(|field| column_name::<T>(field)).collect());
+        self
+    }
+
+    /// Locks the selected rows against updates and deletes by other transactions, with
+    /// `FOR UPDATE`, until the end of the current transaction.
+    ///
+    /// Example:
+    /// ```no_run
+    /// use sprattus::*;
+    ///
+    /// #[derive(FromSql, ToSql, Debug)]
+    /// #[sprattus(table = "inventory")]
+    /// struct Inventory {
+    ///     #[sprattus(primary_key)]
+    ///     prod_id: i32,
+    ///     quan_in_stock: i32,
+    ///     sales: i32,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Error> {
+    ///     let conn = Connection::new("postgresql://localhost?user=tg").await?;
+    ///     let transaction = conn.transaction().await?;
+    ///     // Executes `SELECT "prod_id","quan_in_stock","sales" FROM "inventory"
+    ///     // WHERE quan_in_stock > $1 FOR UPDATE SKIP LOCKED`.
+    ///     let available = transaction
+    ///         .select::<Inventory>()
+    ///         .filter("quan_in_stock > $1", &[&0])
+    ///         .for_update()
+    ///         .skip_locked()
+    ///         .fetch()
+    ///         .await?;
+    ///     for mut item in available {
+    ///         item.quan_in_stock -= 1;
+    ///         item.sales += 1;
+    ///         transaction.update(&item).await?;
+    ///     }
+    ///     transaction.commit().await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn for_update(mut self) -> Self {
+        self.lock = Some(Lock::Update);
+        self
+    }
+
+    /// Locks the selected rows against updates and deletes by other transactions, with
+    /// `FOR SHARE`, while other transactions can still lock them with `FOR SHARE` themselves.
+    pub fn for_share(mut self) -> Self {
+        self.lock = Some(Lock::Share);
+        self
+    }
+
+    /// Skips the rows that are locked by another transaction, with `SKIP LOCKED`, instead of
+    /// waiting for them.
+    ///
+    /// Locks the rows with `FOR UPDATE` when neither [`for_update`](#method.for_update) nor
+    /// [`for_share`](#method.for_share) is called.
+    pub fn skip_locked(mut self) -> Self {
+        self.lock_wait = LockWait::SkipLocked;
+        self
+    }
+
+    /// Fails with an error when a selected row is locked by another transaction, with `NOWAIT`,
+    /// instead of waiting for it.
+    ///
+    /// Locks the rows with `FOR UPDATE` when neither [`for_update`](#method.for_update) nor
+    /// [`for_share`](#method.for_share) is called.
+    pub fn nowait(mut self) -> Self {
+        self.lock_wait = LockWait::NoWait;
+        self
+    }
+
+    /// Returns the SQL of the query.
+    pub fn sql(&self) -> String {
+        let mut sql = self.unordered_sql();
+        let order = self.order();
+        if !order.is_empty() {
+            let order: Vec<String> = order
+                .iter()
+                .map(|(column, order)| format!("{} {}", column, order.sql()))
+                .collect();
+            sql.push_str(" ORDER BY ");
+            sql.push_str(order.join(",").as_str());
+        }
+        if let Some(limit) = self.limit {
+            sql.push_str(&format!(" LIMIT {}", limit));
+        }
+        if let Some(offset) = self.offset {
+            sql.push_str(&format!(" OFFSET {}", offset));
+        }
+        sql.push_str(self.lock_clause());
+        with_clause(&self.named_queries, self.named_query_args.len(), &sql)
+    }
+
+    /// Returns the SQL counting all rows of the query, without its order, limit and offset.
+    fn count_sql(&self) -> String {
+        let sql = format!(
+            "SELECT count(*) FROM ({}) AS sprattus_count",
+            self.unordered_sql()
+        );
+        with_clause(&self.named_queries, self.named_query_args.len(), &sql)
+    }
+
+    /// Returns the SQL of the query up to its `WHERE` clause.
+    fn unordered_sql(&self) -> String {
+        let distinct = match &self.distinct {
+            Distinct::None => String::new(),
+            Distinct::All => String::from("DISTINCT "),
+            Distinct::On(columns) => format!("DISTINCT ON ({}) ", columns.join(",")),
+        };
+        let mut sql = format!(
+            "SELECT {distinct}{fields} FROM {table_name}",
+            distinct = distinct,
+            fields = self.connection.masking.columns::<T>(),
+            table_name = self.connection.table_name::<T>()
+        );
+        sql.push_str(where_clause::<T>(&self.conditions).as_str());
+        sql
+    }
+
+    /// Returns whether the query only reads: it doesn't lock the rows, and has no named queries,
+    /// which might write.
+    fn access(&self) -> Access {
+        if self.lock_clause().is_empty() && self.named_queries.is_empty() {
+            Access::Read
+        } else {
+            Access::Write
+        }
+    }
+
+    /// Returns the locking clause of the query, or an empty string when the rows are not locked.
+    fn lock_clause(&self) -> &'static str {
+        let lock = match (self.lock, self.lock_wait) {
+            (None, LockWait::Wait) => return "",
+            (Some(lock), _) => lock,
+            (None, _) => Lock::Update,
+        };
+        match (lock, self.lock_wait) {
+            (Lock::Update, LockWait::Wait) => " FOR UPDATE",
+            (Lock::Update, LockWait::SkipLocked) => " FOR UPDATE SKIP LOCKED",
+            (Lock::Update, LockWait::NoWait) => " FOR UPDATE NOWAIT",
+            (Lock::Share, LockWait::Wait) => " FOR SHARE",
+            (Lock::Share, LockWait::SkipLocked) => " FOR SHARE SKIP LOCKED",
+            (Lock::Share, LockWait::NoWait) => " FOR SHARE NOWAIT",
+        }
+    }
+
+    /// Returns the columns of the `ORDER BY` clause, starting with the columns of `DISTINCT ON`.
+    fn order(&self) -> Vec<(&'static str, Order)> {
+        let distinct_columns = match &self.distinct {
+            Distinct::On(columns) => columns.as_slice(),
+            _ => return self.order.clone(),
+        };
+        let mut order: Vec<(&'static str, Order)> = distinct_columns
+            .iter()
+            .map(|column| {
+                self.order
+                    .iter()
+                    .find(|(ordered, _)| ordered == column)
+                    .cloned()
+                    .unwrap_or((column, Order::Asc))
+            })
+            .collect();
+        order.extend(
+            self.order
+                .iter()
+                .filter(|(column, _)| !distinct_columns.contains(column)),
+        );
+        order
+    }
+
+    /// Executes the query, returning all matching rows.
+    pub async fn fetch(self) -> Result<Vec<T>, Error> {
+        let sql = self.sql();
+        self.connection
+            .query_multiple_with(sql.as_str(), self.sql_args().as_slice(), self.access())
+            .await
+            .map_err(
+                self.connection
+                    .in_statement::<T>(StatementKind::Select, &sql),
+            )
+    }
+
+    /// Executes the query, returning the rows of a page together with the number of rows of all
+    /// pages.
+    ///
+    /// The rows are counted with `SELECT count(*)` over the query without its order, limit and
+    /// offset, so the count doesn't depend on the page. The count is sent together with the query
+    /// of the page, so it costs no extra round trip to the database.
+    ///
+    /// Example:
+    /// ```no_run
+    /// use sprattus::*;
+    ///
+    /// #[derive(FromSql, ToSql, Debug)]
+    /// #[sprattus(table = "products")]
+    /// struct Product {
+    ///     #[sprattus(primary_key)]
+    ///     prod_id: i32,
+    ///     title: String,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Error> {
+    ///     let conn = Connection::new("postgresql://localhost?user=tg").await?;
+    ///     // Executes `SELECT "prod_id","title" FROM "products" WHERE title LIKE $1
+    ///     // ORDER BY "title" ASC LIMIT 20 OFFSET 40` and `SELECT count(*) FROM
+    ///     // (SELECT "prod_id","title" FROM "products" WHERE title LIKE $1) AS sprattus_count`.
+    ///     let (products, total) = conn
+    ///         .select::<Product>()
+    ///         .filter("title LIKE $1", &[&"A%"])
+    ///         .order_by("title", Order::Asc)
+    ///         .limit(20)
+    ///         .offset(40)
+    ///         .fetch_with_count()
+    ///         .await?;
+    ///     println!("{} of {} products", products.len(), total);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn fetch_with_count(self) -> Result<(Vec<T>, i64), Error> {
+        let sql = self.sql();
+        let count_sql = self.count_sql();
+        let args = self.sql_args();
+        self.connection.log_sql(count_sql.as_str(), args.as_slice());
+        // Both statements are pipelined on the connection.
+        let rows = async {
+            self.connection
+                .query_multiple_with(sql.as_str(), args.as_slice(), self.access())
+                .await
+                .map_err(
+                    self.connection
+                        .in_statement::<T>(StatementKind::Select, &sql),
+                )
+        };
+        let count = async {
+            self.connection
+                .query_row(count_sql.as_str(), args.as_slice(), self.access())
+                .await
+                .and_then(|row| Ok(row.try_get(0)?))
+                .map_err(
+                    self.connection
+                        .in_statement::<T>(StatementKind::Select, &count_sql),
+                )
+        };
+        let (rows, count) = try_join(rows, count).await?;
+        Ok((rows, count))
+    }
+
+    /// Executes the query, returning exactly one row.
+    ///
+    /// Returns an error when the query does not return exactly one row.
+    pub async fn fetch_one(self) -> Result<T, Error> {
+        let sql = self.sql();
+        self.connection
+            .query_with(sql.as_str(), self.sql_args().as_slice(), self.access())
+            .await
+            .map_err(
+                self.connection
+                    .in_statement::<T>(StatementKind::Select, &sql),
+            )
+    }
+
+    /// Adds a condition that the PostGIS column of a field is within the given distance in meters
+    /// of a point, using `ST_DWithin`.
+    ///
+    /// The column and the point are compared as `geography`, so the distance is in meters for
+    /// `geometry` columns as well.
+    ///
+    /// # Panics
+    ///
+    /// Panics when the struct has no field with the given name.
+    ///
+    /// See the [`postgis`](postgis/index.html) module for an example.
+    #[cfg(feature = "with-postgis")]
+    pub fn filter_within_distance(
+        mut self,
+        field: &str,
+        point: geo_types::Point<f64>,
+        meters: f64,
+    ) -> Self {
+        let condition = format!(
+            "ST_DWithin({}::geography, ${}::geography, ${})",
+            column_name::<T>(field),
+            self.args.len() + 1,
+            self.args.len() + 2
+        );
+        self.conditions.push(condition);
+        self.args
+            .push(Argument::Owned(Box::new(crate::postgis::Geography(point))));
+        self.args.push(Argument::Owned(Box::new(meters)));
+        self
+    }
+
+    fn sql_args(&self) -> Vec<&(dyn ToSqlItem + Sync)> {
+        self.named_query_args
+            .iter()
+            .chain(self.args.iter())
+            .map(Argument::as_sql_item)
+            .collect()
+    }
+
+    /// Returns the SQL of the query, together with its arguments.
+    pub(crate) fn into_parts(self) -> (String, Vec<Argument<'a>>) {
+        let sql = self.sql();
+        let mut args = self.named_query_args;
+        args.extend(self.args);
+        (sql, args)
+    }
+}
+
+/// The order in which [`Select::order_by`](struct.Select.html#method.order_by) sorts the rows by
+/// a column.
+///
+/// Without an explicit placement of `NULL` values, Postgres sorts them as if they are larger than
+/// any other value, so they come last in ascending order and first in descending order.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Order {
+    /// Ascending order, `ASC`.
+    Asc,
+    /// Descending order, `DESC`.
+    Desc,
+    /// Ascending order with `NULL` values first, `ASC NULLS FIRST`.
+    AscNullsFirst,
+    /// Ascending order with `NULL` values last, `ASC NULLS LAST`.
+    AscNullsLast,
+    /// Descending order with `NULL` values first, `DESC NULLS FIRST`.
+    DescNullsFirst,
+    /// Descending order with `NULL` values last, `DESC NULLS LAST`.
+    DescNullsLast,
+}
+
+impl Order {
+    /// Returns the same direction, with `NULL` values first.
+    pub fn nulls_first(self) -> Self {
+        match self {
+            Order::Asc | Order::AscNullsFirst | Order::AscNullsLast => Order::AscNullsFirst,
+            Order::Desc | Order::DescNullsFirst | Order::DescNullsLast => Order::DescNullsFirst,
+        }
+    }
+
+    /// Returns the same direction, with `NULL` values last.
+    pub fn nulls_last(self) -> Self {
+        match self {
+            Order::Asc | Order::AscNullsFirst | Order::AscNullsLast => Order::AscNullsLast,
+            Order::Desc | Order::DescNullsFirst | Order::DescNullsLast => Order::DescNullsLast,
+        }
+    }
+
+    fn sql(self) -> &'static str {
+        match self {
+            Order::Asc => "ASC",
+            Order::Desc => "DESC",
+            Order::AscNullsFirst => "ASC NULLS FIRST",
+            Order::AscNullsLast => "ASC NULLS LAST",
+            Order::DescNullsFirst => "DESC NULLS FIRST",
+            Order::DescNullsLast => "DESC NULLS LAST",
+        }
+    }
+}
+
+/// A tuple of values compared with the columns of several fields by
+/// [`Select::filter_in`](struct.Select.html#method.filter_in).
+///
+/// Implemented for tuples of up to six values.
+pub trait TupleValues {
+    /// The number of values in the tuple.
+    const LEN: usize;
+
+    /// Returns references to the values, in order.
+    fn values(&self) -> Vec<&(dyn ToSqlItem + Sync)>;
+}
+
+macro_rules! impl_tuple_values {
+    ($len:expr => $($name:ident . $index:tt),+) => {
+        impl<$($name),+> TupleValues for ($($name,)+)
+        where
+            $($name: ToSqlItem + Sync),+
+        {
+            const LEN: usize = $len;
+
+            fn values(&self) -> Vec<&(dyn ToSqlItem + Sync)> {
+                vec![$(&self.$index),+]
+            }
+        }
+    };
+}
+
+impl_tuple_values!(1 => A.0);
+impl_tuple_values!(2 => A.0, B.1);
+impl_tuple_values!(3 => A.0, B.1, C.2);
+impl_tuple_values!(4 => A.0, B.1, C.2, D.3);
+impl_tuple_values!(5 => A.0, B.1, C.2, D.3, E.4);
+impl_tuple_values!(6 => A.0, B.1, C.2, D.3, E.4, F.5);
+
+/// Returns the `WHERE` clause combining the conditions with the tenant condition of `T`, or an
+/// empty string when there are no conditions.
+pub(crate) fn where_clause<T: Table>(conditions: &[String]) -> String {
+    let mut conditions = conditions.to_vec();
+    conditions.extend(tenant_condition::<T>(None));
+    if conditions.len() == 1 {
+        format!(" WHERE {}", conditions[0])
+    } else if !conditions.is_empty() {
+        // Parenthesize the conditions, so an `OR` in one of them can't escape the others.
+        let conditions: Vec<String> = conditions
+            .iter()
+            .map(|condition| format!("({})", condition))
+            .collect();
+        format!(" WHERE {}", conditions.join(" AND "))
+    } else {
+        String::new()
+    }
+}
+
+/// Returns the quoted name of the column of a field.
+///
+/// # Panics
+///
+/// Panics when the struct has no field with the given name.
+pub(crate) fn column_name<T: Table>(field: &str) -> &'static str {
+    match T::get_column_name(field) {
+        Some(column) => column,
+        None => panic!(
+            "{} has no field named '{}'",
+            std::any::type_name::<T>(),
+            field
+        ),
+    }
+}
@@ -0,0 +1,23 @@
+use crate::*;
+use futures_util::future::BoxFuture;
+use std::sync::Arc;
+
+/// Runs a task of a connection in the background on an async runtime, like the task driving the
+/// connection to the database.
+pub(crate) type Spawner = Arc<dyn Fn(BoxFuture<'static, ()>) + Send + Sync>;
+
+/// Returns the spawner used when no spawner is set, which spawns on the tokio runtime.
+#[cfg(feature = "runtime-tokio")]
+pub(crate) fn default_spawner() -> Result<Spawner, Error> {
+    Ok(Arc::new(|task| {
+        tokio::spawn(task);
+    }))
+}
+
+/// Returns the spawner used when no spawner is set, which is an error without a runtime.
+#[cfg(not(feature = "runtime-tokio"))]
+pub(crate) fn default_spawner() -> Result<Spawner, Error> {
+    Err(Error::Config(String::from(
+        "no spawner is set, use `ConnectionBuilder::spawner` or enable the `runtime-tokio` feature",
+    )))
+}
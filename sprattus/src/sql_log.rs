@@ -0,0 +1,54 @@
+use crate::*;
+use log::{log, log_enabled, Level};
+
+/// How the parameters of statements are written to the SQL log.
+///
+/// See [`Connection::enable_sql_log`](struct.Connection.html#method.enable_sql_log).
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum LogParameters {
+    /// Replace the placeholders in the SQL with the debug representation of the parameters.
+    /// Useful while debugging, but it writes the values of the parameters to the log.
+    Inline,
+    /// Log the SQL with its placeholders, and only the number of parameters.
+    Redacted,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct SqlLog {
+    level: Level,
+    parameters: LogParameters,
+}
+
+impl SqlLog {
+    pub(crate) fn new(level: Level, parameters: LogParameters) -> Self {
+        Self { level, parameters }
+    }
+
+    pub(crate) fn log(&self, sql: &str, args: &[&(dyn ToSqlItem + Sync)]) {
+        if !log_enabled!(self.level) {
+            return;
+        }
+        match self.parameters {
+            LogParameters::Inline => log!(self.level, "{}", inline_parameters(sql, args)),
+            LogParameters::Redacted => log!(
+                self.level,
+                "{} -- {} parameter(s) redacted",
+                sql,
+                args.len()
+            ),
+        }
+    }
+}
+
+/// Replaces the placeholders in the SQL with the parameters, starting with the highest number so
+/// `$1` does not match the start of `$10`.
+fn inline_parameters(sql: &str, args: &[&(dyn ToSqlItem + Sync)]) -> String {
+    let mut sql = sql.to_string();
+    for (index, arg) in args.iter().enumerate().rev() {
+        sql = sql.replace(
+            format!("${}", index + 1).as_str(),
+            format!("{:?}", arg).as_str(),
+        );
+    }
+    sql
+}
@@ -0,0 +1,89 @@
+use crate::*;
+use std::marker::PhantomData;
+
+/// A query returning rows of a struct, created by the [`sql!`](macro.sql.html) macro.
+///
+/// The keys `{table}` and `{columns}` in the SQL are replaced by the quoted name of the table and
+/// the columns of the struct. The macro checks at compile time that the `$n` placeholders are
+/// numbered from `$1` up to the number of given arguments. Keys and placeholders within string
+/// literals and quoted identifiers are left as they are.
+///
+/// Example:
+/// ```no_run
+/// use sprattus::*;
+///
+/// #[derive(FromSql, ToSql, Debug)]
+/// #[sprattus(table = "products")]
+/// struct Product {
+///     #[sprattus(primary_key)]
+///     prod_id: i32,
+///     title: String,
+/// }
+///
+/// #[tokio::main]
+/// async fn main() -> Result<(), Error> {
+///     let conn = Connection::new("postgresql://localhost?user=tg").await?;
+///     let min_id = 10;
+///     // Executes `SELECT "prod_id","title" FROM "products" WHERE prod_id > $1`.
+///     let query = sql!(Product, "SELECT {columns} FROM {table} WHERE prod_id > $1", &min_id);
+///     let products = query.fetch(&conn).await?;
+///     dbg!(products);
+///     Ok(())
+/// }
+/// ```
+pub struct SqlQuery<'a, T> {
+    template: &'static str,
+    sql: String,
+    args: Vec<&'a (dyn ToSqlItem + Sync)>,
+    result_type: PhantomData<T>,
+}
+
+impl<'a, T> SqlQuery<'a, T> {
+    #[doc(hidden)]
+    pub fn new(template: &'static str, sql: String, args: Vec<&'a (dyn ToSqlItem + Sync)>) -> Self {
+        Self {
+            template,
+            sql,
+            args,
+            result_type: PhantomData,
+        }
+    }
+
+    /// Returns the SQL as it is written in the macro, with the keys.
+    pub fn template(&self) -> &'static str {
+        self.template
+    }
+
+    /// Returns the SQL of the query, with the keys replaced.
+    pub fn sql(&self) -> &str {
+        self.sql.as_str()
+    }
+
+    /// Returns the arguments of the placeholders.
+    pub fn args(&self) -> &[&'a (dyn ToSqlItem + Sync)] {
+        self.args.as_slice()
+    }
+
+    /// Executes the query, returning the number of modified rows, see
+    /// [`Connection::execute`](struct.Connection.html#method.execute).
+    pub async fn execute(&self, connection: &Connection) -> Result<ExecuteResult, Error> {
+        connection.execute(self.sql(), self.args()).await
+    }
+}
+
+impl<'a, T> SqlQuery<'a, T>
+where
+    T: FromSql,
+{
+    /// Executes the query, returning all rows.
+    pub async fn fetch(&self, connection: &Connection) -> Result<Vec<T>, Error> {
+        connection.query_multiple(self.sql(), self.args()).await
+    }
+
+    /// Executes the query, returning exactly one row.
+    ///
+    /// Returns an error when the query returns no rows, or more than one.
+    pub async fn fetch_one(&self, connection: &Connection) -> Result<T, Error> {
+        connection.query(self.sql(), self.args()).await
+    }
+}
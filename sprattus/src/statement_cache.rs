@@ -0,0 +1,95 @@
+use std::collections::{HashMap, VecDeque};
+use std::error;
+use std::sync::Mutex;
+use tokio_postgres::error::{DbError, SqlState};
+use tokio_postgres::{Client, Statement};
+
+/// The message of the error Postgres returns when a prepared statement is executed after a change
+/// of the tables it selects from, like an added column.
+const STALE_PLAN_MESSAGE: &str = "cached plan must not change result type";
+
+/// Prepared statements of a connection, by their SQL.
+///
+/// Statements are prepared the first time they are used. When the cache is full, the statement
+/// that was prepared first is removed.
+pub(crate) struct StatementCache {
+    capacity: usize,
+    statements: Mutex<CachedStatements>,
+}
+
+#[derive(Default)]
+struct CachedStatements {
+    by_sql: HashMap<String, Statement>,
+    // The SQL of the statements, in the order in which they were prepared.
+    order: VecDeque<String>,
+}
+
+impl StatementCache {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            statements: Mutex::new(CachedStatements::default()),
+        }
+    }
+
+    ///
+    /// Returns the prepared statement of the SQL, preparing it when it is not cached yet.
+    ///
+    /// The lock is not held while preparing, so statements are prepared concurrently. When the
+    /// same statement is prepared twice at the same time, the one that finished first is kept.
+    ///
+    pub(crate) async fn prepare(
+        &self,
+        client: &Client,
+        sql: &str,
+    ) -> Result<Statement, tokio_postgres::Error> {
+        if let Some(statement) = self.statements.lock().unwrap().by_sql.get(sql) {
+            return Ok(statement.clone());
+        }
+        let statement = client.prepare(sql).await?;
+        Ok(self.insert(sql, statement))
+    }
+
+    fn insert(&self, sql: &str, statement: Statement) -> Statement {
+        let mut statements = self.statements.lock().unwrap();
+        if let Some(existing) = statements.by_sql.get(sql) {
+            return existing.clone();
+        }
+        if self.capacity == 0 {
+            return statement;
+        }
+        while statements.order.len() >= self.capacity {
+            match statements.order.pop_front() {
+                Some(oldest) => statements.by_sql.remove(&oldest),
+                None => break,
+            };
+        }
+        statements.order.push_back(sql.to_string());
+        statements.by_sql.insert(sql.to_string(), statement.clone());
+        statement
+    }
+
+    /// Removes the statement of the SQL, so it is prepared again the next time it is used.
+    pub(crate) fn invalidate(&self, sql: &str) {
+        let mut statements = self.statements.lock().unwrap();
+        if statements.by_sql.remove(sql).is_some() {
+            statements.order.retain(|other| other != sql);
+        }
+    }
+
+    /// Removes all statements.
+    pub(crate) fn clear(&self) {
+        let mut statements = self.statements.lock().unwrap();
+        statements.by_sql.clear();
+        statements.order.clear();
+    }
+}
+
+/// Returns whether the error is returned because a prepared statement no longer matches the
+/// tables it uses, after which it needs to be prepared again.
+pub(crate) fn is_stale_statement(error: &tokio_postgres::Error) -> bool {
+    error.code() == Some(&SqlState::FEATURE_NOT_SUPPORTED)
+        && error::Error::source(error)
+            .and_then(|error| error.downcast_ref::<DbError>())
+            .is_some_and(|error| error.message() == STALE_PLAN_MESSAGE)
+}
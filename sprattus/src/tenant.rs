@@ -0,0 +1,28 @@
+use crate::*;
+
+/// The setting that holds the tenant of the current transaction.
+pub(crate) const TENANT_SETTING: &str = "app.tenant_id";
+
+/// Returns the condition restricting the rows of `T` to the current tenant, or `None` when the
+/// table has no tenant key.
+pub(crate) fn tenant_condition<T: Table>(alias: Option<&str>) -> Option<String> {
+    T::get_tenant_key().map(|(column, pg_type)| match alias {
+        Some(alias) => format!(
+            "{}.{} = current_setting('{}')::{}",
+            alias, column, TENANT_SETTING, pg_type
+        ),
+        None => format!(
+            "{} = current_setting('{}')::{}",
+            column, TENANT_SETTING, pg_type
+        ),
+    })
+}
+
+/// Returns the tenant condition of `T` prefixed with `AND`, to be appended to a `WHERE` clause,
+/// or an empty string when the table has no tenant key.
+pub(crate) fn tenant_filter<T: Table>(alias: Option<&str>) -> String {
+    match tenant_condition::<T>(alias) {
+        Some(condition) => format!(" AND {}", condition),
+        None => String::new(),
+    }
+}
@@ -0,0 +1,76 @@
+//! Utilities for integration tests against a real database.
+//!
+//! A [`TestDb`](struct.TestDb.html) runs everything within a transaction that is rolled back when
+//! the test is done, so tests don't leave data behind and can't see each other's changes.
+//!
+//! Example:
+//! ```no_run
+//! use sprattus::*;
+//! use sprattus::test::TestDb;
+//!
+//! #[derive(FromSql, ToSql, Eq, PartialEq, Debug)]
+//! #[sprattus(table = "products")]
+//! struct Product {
+//!     #[sprattus(primary_key)]
+//!     prod_id: i32,
+//!     title: String
+//! }
+//!
+//! #[tokio::test]
+//! async fn creates_a_product() -> Result<(), Error> {
+//!     let db = TestDb::new("postgresql://localhost?user=tg").await?;
+//!     let product = Product { prod_id: 1, title: String::from("Rust ORM") };
+//!     assert_eq!(db.create(&product).await?, product);
+//!     // Discards the product again.
+//!     db.rollback().await
+//! }
+//! ```
+
+use crate::*;
+use std::ops::Deref;
+
+/// A connection to a test database, where all changes are rolled back at the end of the test.
+///
+/// The test database dereferences to a [`Connection`](../struct.Connection.html), so it offers the
+/// same API. Transactions started within a test become savepoints of the transaction of the test.
+///
+/// Call [`rollback`](#method.rollback) at the end of the test to discard the changes and check for
+/// errors. When the test database is dropped instead, for example because the test panics, the
+/// changes are discarded as well.
+pub struct TestDb {
+    transaction: Transaction,
+}
+
+impl TestDb {
+    /// Connects to the database and starts the transaction of the test.
+    pub async fn new(connection_string: &str) -> Result<Self, Error> {
+        let connection = Connection::new(connection_string).await?;
+        Self::with_connection(&connection).await
+    }
+
+    /// Starts the transaction of the test on an existing connection.
+    pub async fn with_connection(connection: &Connection) -> Result<Self, Error> {
+        Ok(Self {
+            transaction: connection.transaction().await?,
+        })
+    }
+
+    /// Returns a connection taking part in the transaction of the test, for code that takes an
+    /// owned connection.
+    pub fn connection(&self) -> Connection {
+        self.transaction.deref().clone()
+    }
+
+    /// Ends the test, discarding all changes made within it.
+    pub async fn rollback(self) -> Result<(), Error> {
+        self.transaction.rollback().await
+    }
+}
+
+impl Deref for TestDb {
+    type Target = Connection;
+
+    fn deref(&self) -> &Connection {
+        &self.transaction
+    }
+}
@@ -0,0 +1,66 @@
+use std::error;
+use std::fmt;
+
+/// An enum of which the variants are stored as text, implemented by the `SqlTextEnum` derive
+/// macro.
+///
+/// Every variant is stored as its name, or as the text given with the `rename` attribute. The
+/// derive macro implements `ToSqlItem` and `FromSqlItem` as well, so the enum can be the type of
+/// a field of a `TEXT` or `VARCHAR` column.
+///
+/// Example:
+/// ```
+/// use sprattus::*;
+///
+/// #[derive(SqlTextEnum, Debug, PartialEq)]
+/// enum Status {
+///     #[sprattus(rename = "pending")]
+///     Pending,
+///     #[sprattus(rename = "shipped")]
+///     Shipped,
+///     Cancelled,
+/// }
+///
+/// assert_eq!(Status::Shipped.to_text(), "shipped");
+/// assert_eq!(Status::from_text("Cancelled"), Ok(Status::Cancelled));
+/// assert_eq!(
+///     Status::from_text("lost").unwrap_err().to_string(),
+///     "unexpected value `lost` for Status, expected one of: pending, shipped, Cancelled"
+/// );
+/// ```
+pub trait SqlTextEnum: Sized {
+    /// The texts of all variants, in the order of the variants.
+    const VARIANTS: &'static [&'static str];
+
+    /// Returns the text a variant is stored as.
+    fn to_text(&self) -> &'static str;
+
+    /// Returns the variant that is stored as the given text.
+    fn from_text(text: &str) -> Result<Self, UnknownVariant>;
+}
+
+/// A text that is not stored by any variant of an enum deriving `SqlTextEnum`, returned by
+/// [`SqlTextEnum::from_text`](trait.SqlTextEnum.html#tymethod.from_text).
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct UnknownVariant {
+    /// The name of the enum.
+    pub enum_name: &'static str,
+    /// The unexpected text.
+    pub value: String,
+    /// The texts of the variants.
+    pub expected: &'static [&'static str],
+}
+
+impl fmt::Display for UnknownVariant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "unexpected value `{}` for {}, expected one of: {}",
+            self.value,
+            self.enum_name,
+            self.expected.join(", ")
+        )
+    }
+}
+
+impl error::Error for UnknownVariant {}
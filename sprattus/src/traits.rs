@@ -1,5 +1,6 @@
+use crate::{ColumnMismatch, Error, Statements, ValidationError};
 use tokio_postgres::types::ToSql as ToSqlItem;
-use tokio_postgres::{Error, Row};
+use tokio_postgres::{Column, Row};
 
 /// Arranges deserialization from Postgres table values to a Rust struct.
 pub trait FromSql {
@@ -9,45 +10,158 @@ pub trait FromSql {
     fn from_row(row: &Row) -> Result<Self, Error>
     where
         Self: Sized;
+
+    ///
+    /// Returns the position of the column of every field among the given columns, or `None` for
+    /// a field without a column. All rows returned by a statement have the same columns, so the
+    /// positions are looked up once and passed to [`from_row_at`](#method.from_row_at) for every
+    /// row, instead of looking up every column by name in every row.
+    ///
+    fn column_positions(_columns: &[Column]) -> Vec<Option<usize>>
+    where
+        Self: Sized,
+    {
+        Vec::new()
+    }
+
+    ///
+    /// Creates an instance of Self with the content of a row, of which the columns are at the
+    /// positions returned by [`column_positions`](#method.column_positions).
+    ///
+    fn from_row_at(row: &Row, _positions: &[Option<usize>]) -> Result<Self, Error>
+    where
+        Self: Sized,
+    {
+        Self::from_row(row)
+    }
+
+    ///
+    /// Returns the fields that can't be filled by the given columns of a statement, because a
+    /// column is missing or has a type that can't be converted into the type of the field.
+    ///
+    /// The derived implementation checks the fields of structs with named fields, a missing
+    /// column is fine for a field with the `default` attribute.
+    ///
+    fn check_columns(_columns: &[Column]) -> Vec<ColumnMismatch>
+    where
+        Self: Sized,
+    {
+        Vec::new()
+    }
 }
 
-/// All required methods to create, update and delete the struct it's implemented for.
-pub trait ToSql {
+/// Converts rows returned by a statement, looking up the positions of the columns only once.
+pub(crate) fn from_rows<T: FromSql>(rows: &[Row]) -> Result<Vec<T>, Error> {
+    let positions = match rows.first() {
+        Some(row) => T::column_positions(row.columns()),
+        None => return Ok(Vec::new()),
+    };
+    rows.iter()
+        .map(|row| T::from_row_at(row, &positions))
+        .collect()
+}
+
+/// A table or view in the database, from which rows can be selected.
+pub trait Table {
     ///
     /// Returns the name of the table.
     ///
     fn get_table_name() -> &'static str;
+
+    /// Returns a comma separated list with the Postgres names of all fields.
+    fn get_all_fields() -> &'static str;
+
+    /// Returns the quoted Postgres name of the column of a field, or `None` when the struct has no
+    /// field with the given name.
+    fn get_column_name(field: &str) -> Option<&'static str>;
+
+    /// Returns the quoted Postgres name and the Postgres type of the column that holds the tenant
+    /// of a row, or `None` when the rows are not separated by tenant.
+    fn get_tenant_key() -> Option<(&'static str, &'static str)> {
+        None
+    }
+
+    /// Returns the quoted Postgres names of the columns of the fields with the `masked`
+    /// attribute, which are masked when rows are selected, see
+    /// [`Masking`](enum.Masking.html).
+    fn get_masked_fields() -> &'static [&'static str] {
+        &[]
+    }
+
+    /// Returns the quoted name of the table that receives the previous version of every row that
+    /// is updated or deleted, or `None` when the previous versions are not kept.
+    fn get_history_table() -> Option<&'static str> {
+        None
+    }
+}
+
+/// A materialized view, of which the rows are stored and only change when the view is refreshed
+/// with [`Connection::refresh_materialized_view`](struct.Connection.html#method.refresh_materialized_view).
+///
+/// Implemented by the `ToSql` derive macro for structs with the `materialized_view` annotation.
+pub trait MaterializedView: Table {}
+
+/// A table of which every row is identified by a primary key, so a single row can be found,
+/// updated or deleted.
+pub trait Identifiable: Table {
     ///
     /// Returns the Postgres name of the primary key.
     ///
     fn get_primary_key() -> &'static str;
 
     /// Represents the Rust type of the primary key.
-    type PK;
+    type PK: ToSqlItem + Sync;
 
-    /// Returns the value of the primary key.
-    fn get_primary_key_value(&self) -> Self::PK
-    where
-        Self::PK: ToSqlItem + Sized + Sync;
+    /// Returns a reference to the value of the primary key.
+    fn get_primary_key_value(&self) -> &Self::PK;
 
     ///
-    /// The fields that contain the data of the table.
-    /// The primary key is excluded from this list.
+    /// Returns whether the primary key is set, which means the row already exists.
     ///
-    fn get_fields() -> &'static str;
+    /// The derived implementation returns `false` for a primary key that is `None` or an integer
+    /// that is `0`, and `true` for primary keys of other types.
+    ///
+    fn has_primary_key_value(&self) -> bool {
+        true
+    }
 
-    /// Returns a comma separated list with the Postgres names of all fields.
-    fn get_all_fields() -> &'static str;
+    ///
+    /// Returns whether the primary key is inserted when a row is created, as `DEFAULT` when
+    /// `has_primary_key_value()` returns `false`. Otherwise the primary key is never inserted,
+    /// and is always generated by the database.
+    ///
+    /// The derived implementation returns `true` for a primary key that is an `Option`.
+    ///
+    fn inserts_primary_key() -> bool {
+        false
+    }
+}
 
-    /// Returns a vector of references to all values of the implemented struct.
-    fn get_values_of_all_fields(&self) -> Vec<&(dyn ToSqlItem + Sync)>;
+/// A struct of which the values can be inserted as a new row, without its primary key.
+///
+/// Implemented by the `ToSql` derive macro, also for tables without a primary key, which can
+/// be written to with [`Connection::insert`](struct.Connection.html#method.insert).
+pub trait Insertable: Table {
+    ///
+    /// The fields that contain the data of the table.
+    /// The primary key and the timestamp fields are excluded from this list.
+    ///
+    fn get_fields() -> &'static str;
 
     ///
-    /// The method that implements converting the fields
-    /// into a array of items that implement the ToSql trait of rust_postgres.
+    /// Returns references to the values of the fields of `get_fields()`, in that order.
     ///
     fn get_query_params(&self) -> Vec<&(dyn ToSqlItem + Sync)>;
 
+    ///
+    /// Returns, for every value of `get_query_params()`, whether it is inserted as `DEFAULT`
+    /// instead, because the field has the `default_on_none` attribute and is `None`.
+    /// An empty list means that all values are inserted.
+    ///
+    fn get_insert_defaults(&self) -> Vec<bool> {
+        Vec::new()
+    }
+
     ///
     /// Returns the formatted prepared statement list.
     ///
@@ -55,13 +169,355 @@ pub trait ToSql {
     ///
     fn get_prepared_arguments_list() -> &'static str;
 
+    /// Returns the amount of fields excluding the primary key.
+    fn get_argument_count() -> usize;
+
+    /// Returns the quoted Postgres names of the columns that are set to `now()` when a row is
+    /// created.
+    fn get_created_at_fields() -> &'static [&'static str] {
+        &[]
+    }
+
+    /// Returns the quoted Postgres names of the columns that are set to `now()` when a row is
+    /// created or updated.
+    fn get_updated_at_fields() -> &'static [&'static str] {
+        &[]
+    }
+
+    /// Checks the fields with the `validate` attribute, which is done before the item is created
+    /// or updated.
+    fn validate(&self) -> Result<(), ValidationError> {
+        Ok(())
+    }
+}
+
+/// A struct of which the values can be written to the existing row with its primary key.
+pub trait Updatable: Identifiable + Insertable {
     ///
-    /// Returns the formatted prepared statement list with Postgres types.
+    /// The fields that are set when a row is updated.
+    /// The primary key and immutable fields are excluded from this list.
+    ///
+    fn get_update_fields() -> &'static str {
+        Self::get_fields()
+    }
+
+    /// Returns the value of the primary key, followed by the values of the fields that are set
+    /// when a row is updated.
+    fn get_update_params(&self) -> Vec<&(dyn ToSqlItem + Sync)> {
+        let mut params: Vec<&(dyn ToSqlItem + Sync)> = vec![self.get_primary_key_value()];
+        params.extend(self.get_query_params());
+        params
+    }
+
+    /// Returns the amount of fields that are set when a row is updated.
+    fn get_update_argument_count() -> usize {
+        Self::get_argument_count()
+    }
+}
+
+/// Callbacks that the [`Connection`](struct.Connection.html) invokes around the statements that
+/// create, update and delete the rows of a struct, so validation or cache invalidation can live
+/// next to the definition of the struct.
+///
+/// All methods do nothing by default. The `ToSql` derive macro implements the trait with the
+/// defaults, unless the struct has the `lifecycle` annotation, in which case it is implemented by
+/// hand.
+///
+/// The `before_*` methods receive the item that is about to be written, and abort the write when
+/// they return an error. The `after_*` methods receive the row returned by the database, or the
+/// item that was written when no row is returned, like by `update_count`. An upsert runs
+/// `before_insert`, followed by `after_insert` or `after_update` depending on the outcome.
+/// [`increment`](struct.Connection.html#method.increment) doesn't have the item, so it reads the
+/// row first and passes it to `before_update` as it is before the increment.
+/// [`create_new`](struct.Connection.html#method.create_new) runs `before_insert` of the
+/// [insert model](trait.InsertModel.html), and `after_insert` of the created row.
+///
+/// The callbacks receive the item by reference, so they can check it, but not change it.
+/// Normalizing values, like trimming a title, is not done by the callbacks, and has to happen
+/// before the item is passed to the connection.
+///
+/// Example:
+/// ```
+/// use sprattus::*;
+///
+/// #[derive(ToSql, FromSql, Debug)]
+/// #[sprattus(table = "products", lifecycle)]
+/// struct Product {
+///     #[sprattus(primary_key)]
+///     prod_id: i32,
+///     title: String,
+/// }
+///
+/// impl Lifecycle for Product {
+///     fn before_insert(&self) -> Result<(), Error> {
+///         if self.title.trim().is_empty() {
+///             return Err(Error::Source("a product needs a title".into()));
+///         }
+///         Ok(())
+///     }
+/// }
+///
+/// let product = Product { prod_id: 0, title: String::from(" ") };
+/// assert!(product.before_insert().is_err());
+/// ```
+pub trait Lifecycle {
+    /// Invoked before the item is inserted.
+    fn before_insert(&self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Invoked with the created row.
+    fn after_insert(&self) {}
+
+    /// Invoked before the item is updated.
+    fn before_update(&self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Invoked with the updated row.
+    fn after_update(&self) {}
+
+    /// Invoked before the item is deleted.
+    fn before_delete(&self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Invoked with the deleted row.
+    fn after_delete(&self) {}
+}
+
+/// All required methods to create, update and delete the struct it's implemented for.
+///
+/// The methods are divided over the traits it extends, so structs that are only read, inserted
+/// or identified can implement just the traits they need: [`Table`](trait.Table.html),
+/// [`Identifiable`](trait.Identifiable.html), [`Insertable`](trait.Insertable.html) and
+/// [`Updatable`](trait.Updatable.html). The derive macro implements all of them, together with
+/// [`Lifecycle`](trait.Lifecycle.html).
+pub trait ToSql: Updatable + Lifecycle {
+    /// Returns references to the values of the primary key and the fields of `get_fields()`, in
+    /// the order of `get_all_fields()`, which is the order in which the fields are declared.
+    ///
+    /// The timestamp fields are skipped, as their values are set by the database.
+    fn get_values_of_all_fields(&self) -> Vec<&(dyn ToSqlItem + Sync)>;
+
+    /// Returns the values of all fields together with the names of their columns, in the order in
+    /// which the fields are declared, which is the order of `get_all_fields()`.
+    fn get_field_values(&self) -> Vec<FieldValue<'_>>;
+
+    ///
+    /// Returns the formatted prepared statement list with Postgres types, of the values of
+    /// `get_values_of_all_fields()`.
     ///
     /// Example return value: `$1::INT, $2::VARCHAR`
     ///
     fn get_prepared_arguments_list_with_types() -> &'static str;
 
-    /// Returns the amount of fields excluding the primary key.
-    fn get_argument_count() -> usize;
+    /// Returns the metadata of the columns of all fields, in the order in which the fields are
+    /// declared.
+    fn get_columns() -> &'static [ColumnMeta];
+
+    /// Returns the unique constraints declared with the `unique` attribute, each as the unquoted
+    /// Postgres names of its columns.
+    fn get_unique_constraints() -> &'static [&'static [&'static str]] {
+        &[]
+    }
+
+    /// Returns the statements that are generated to insert, update, delete and select a single
+    /// row, with placeholders for the values.
+    ///
+    /// Example:
+    /// ```
+    /// use sprattus::*;
+    ///
+    /// #[derive(ToSql)]
+    /// #[sprattus(table = "products")]
+    /// struct Product {
+    ///     #[sprattus(primary_key)]
+    ///     prod_id: i32,
+    ///     title: String,
+    ///     price: f64,
+    /// }
+    ///
+    /// let statements = Product::describe();
+    /// assert_eq!(
+    ///     statements.insert,
+    ///     r#"INSERT INTO "products" ("title","price") values ($1,$2) RETURNING *"#
+    /// );
+    /// assert_eq!(
+    ///     statements.update,
+    ///     r#"UPDATE "products" SET ("title","price") = ($2,$3) WHERE "prod_id" = $1 RETURNING *"#
+    /// );
+    /// assert_eq!(
+    ///     statements.delete,
+    ///     r#"DELETE FROM "products" WHERE "prod_id" IN ($1) RETURNING *"#
+    /// );
+    /// assert_eq!(
+    ///     statements.select,
+    ///     r#"SELECT "prod_id","title","price" FROM "products" WHERE "prod_id" = $1"#
+    /// );
+    /// ```
+    fn describe() -> Statements
+    where
+        Self: Sized,
+    {
+        Statements::new::<Self>()
+    }
+}
+
+/// An object safe view of a struct implementing [`ToSql`](trait.ToSql.html), so structs of
+/// different tables can be handled as trait objects, e.g. in a `Vec<Box<dyn DynEntity>>`.
+///
+/// Implemented for every type that implements `ToSql`.
+///
+/// Example:
+/// ```
+/// use sprattus::*;
+///
+/// #[derive(ToSql)]
+/// #[sprattus(table = "products")]
+/// struct Product {
+///     #[sprattus(primary_key)]
+///     prod_id: i32,
+///     title: String,
+/// }
+///
+/// #[derive(ToSql)]
+/// #[sprattus(table = "categories")]
+/// struct Category {
+///     #[sprattus(primary_key)]
+///     category: i32,
+///     categoryname: String,
+/// }
+///
+/// let entities: Vec<Box<dyn DynEntity>> = vec![
+///     Box::new(Product { prod_id: 1, title: "Cheese".to_string() }),
+///     Box::new(Category { category: 2, categoryname: "Food".to_string() }),
+/// ];
+/// let tables: Vec<&str> = entities.iter().map(|entity| entity.table_name()).collect();
+/// assert_eq!(tables, vec!["\"products\"", "\"categories\""]);
+/// assert_eq!(entities[1].field_values()[1].name, "categoryname");
+/// ```
+pub trait DynEntity {
+    /// Returns the quoted name of the table, see `Table::get_table_name()`.
+    fn table_name(&self) -> &'static str;
+
+    /// Returns the quoted names of all columns, in the order in which the fields are declared.
+    fn columns(&self) -> Vec<&'static str>;
+
+    /// Returns the quoted name of the column of the primary key.
+    fn primary_key(&self) -> &'static str;
+
+    /// Returns a reference to the value of the primary key.
+    fn primary_key_value(&self) -> &(dyn ToSqlItem + Sync);
+
+    /// Returns the values of all fields, see `ToSql::get_field_values()`.
+    fn field_values(&self) -> Vec<FieldValue<'_>>;
+}
+
+impl<T: ToSql> DynEntity for T {
+    fn table_name(&self) -> &'static str {
+        T::get_table_name()
+    }
+
+    fn columns(&self) -> Vec<&'static str> {
+        T::get_all_fields().split(',').collect()
+    }
+
+    fn primary_key(&self) -> &'static str {
+        T::get_primary_key()
+    }
+
+    fn primary_key_value(&self) -> &(dyn ToSqlItem + Sync) {
+        self.get_primary_key_value()
+    }
+
+    fn field_values(&self) -> Vec<FieldValue<'_>> {
+        self.get_field_values()
+    }
+}
+
+/// Describes the column of a field of a struct, returned by
+/// [`ToSql::get_columns`](trait.ToSql.html#tymethod.get_columns).
+///
+/// Example:
+/// ```
+/// use sprattus::*;
+///
+/// #[derive(ToSql)]
+/// struct Product {
+///     #[sprattus(primary_key)]
+///     prod_id: i32,
+///     #[sprattus(name = "title")]
+///     name: Option<String>,
+/// }
+///
+/// let title = &Product::get_columns()[1];
+/// assert_eq!(title.rust_name, "name");
+/// assert_eq!(title.sql_name, "title");
+/// assert_eq!(title.pg_type, Some("VARCHAR"));
+/// assert!(title.nullable);
+/// ```
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ColumnMeta {
+    /// The name of the field in the Rust struct.
+    pub rust_name: &'static str,
+    /// The Postgres name of the column, without quotes.
+    pub sql_name: &'static str,
+    /// The Postgres type of the column, `None` when it is not known.
+    pub pg_type: Option<&'static str>,
+    /// Whether the column is the primary key.
+    pub primary_key: bool,
+    /// Whether the column can be `NULL`, because the type of the field is an `Option`.
+    pub nullable: bool,
+    /// Whether the value of the column is generated by the database, like the `created_at` and
+    /// `updated_at` timestamps.
+    pub generated: bool,
+}
+
+/// The value of a field, together with the name of its column.
+///
+/// Returned by [`ToSql::get_field_values`](trait.ToSql.html#tymethod.get_field_values).
+#[derive(Clone, Copy, Debug)]
+pub struct FieldValue<'a> {
+    /// The Postgres name of the column, without quotes.
+    pub name: &'static str,
+    /// A reference to the value of the field.
+    pub value: &'a (dyn ToSqlItem + Sync),
+}
+
+/// A struct containing a subset of the fields of another struct, the parent.
+///
+/// The projection shares the table of its parent, so it can be queried without writing SQL.
+pub trait Projection: FromSql {
+    /// The struct of which this struct is a projection.
+    type Parent: ToSql;
+
+    /// Returns a comma separated list with the Postgres names of the projected fields.
+    fn get_projected_fields() -> &'static str;
+}
+
+/// A struct with the values needed to insert a new row of its model, excluding the primary key.
+///
+/// Implementations are generated by the `ToSql` derive macro when the `insert_model` annotation is
+/// present on the struct. With the `lifecycle` annotation as well,
+/// [`Lifecycle`](trait.Lifecycle.html) is implemented by hand for both the struct and its insert
+/// model.
+pub trait InsertModel: Lifecycle {
+    /// The struct that is created by inserting this model.
+    type Model: ToSql + FromSql;
+
+    /// Returns references to the values of all fields, in the order of `Model::get_fields()`.
+    fn get_query_params(&self) -> Vec<&(dyn ToSqlItem + Sync)>;
+
+    /// Returns, for every value of `get_query_params()`, whether it is inserted as `DEFAULT`,
+    /// like `ToSql::get_insert_defaults()`.
+    fn get_insert_defaults(&self) -> Vec<bool> {
+        Vec::new()
+    }
+
+    /// Checks the fields with the `validate` attribute of the model, which is done before the
+    /// row is created.
+    fn validate(&self) -> Result<(), ValidationError> {
+        Ok(())
+    }
 }
@@ -0,0 +1,366 @@
+use crate::*;
+use futures_util::future::FutureExt;
+use std::future::Future;
+use std::ops::Deref;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::{Notify, OwnedRwLockReadGuard, OwnedRwLockWriteGuard, RwLock};
+use tokio_postgres::error::SqlState;
+use tokio_postgres::Client;
+
+/// The number of times a serializable transaction is attempted by
+/// [`Connection::retry_serializable`](struct.Connection.html#method.retry_serializable).
+pub(crate) const SERIALIZABLE_ATTEMPTS: usize = 5;
+
+static TRANSACTION_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// The transaction state shared by all clones of a connection.
+///
+/// The clones share a single session on the server, so while a transaction is open only the
+/// clones taking part in it may execute statements. The statements of other clones hold a read
+/// lock while they run, which a transaction acquires exclusively before sending `BEGIN`, so
+/// statements that are already running finish outside of the transaction.
+pub(crate) struct Transactions {
+    state: Mutex<TransactionState>,
+    lock: Arc<RwLock<()>>,
+    // Notified when the rollback of a dropped transaction is sent.
+    rolled_back: Notify,
+}
+
+#[derive(Default)]
+struct TransactionState {
+    // The id of the open transaction.
+    open: Option<usize>,
+    // The number of open savepoints within the open transaction.
+    savepoints: usize,
+    // The number of dropped transactions of which the rollback is not sent yet.
+    pending_rollbacks: usize,
+}
+
+impl Transactions {
+    pub(crate) fn new() -> Self {
+        Self {
+            state: Mutex::new(TransactionState::default()),
+            lock: Arc::new(RwLock::new(())),
+            rolled_back: Notify::new(),
+        }
+    }
+
+    /// Returns whether the given transaction is the open transaction.
+    pub(crate) fn is_open(&self, transaction: Option<usize>) -> bool {
+        transaction.is_some() && self.state.lock().unwrap().open == transaction
+    }
+
+    ///
+    /// Returns the client for a statement of a clone taking part in the given transaction.
+    ///
+    /// Waits until the rollbacks of dropped transactions are sent, so the statement can't end up
+    /// in the transaction that is rolled back. Returns
+    /// [`Error::TransactionInProgress`](enum.Error.html#variant.TransactionInProgress) when
+    /// another transaction is open.
+    ///
+    pub(crate) async fn statement<'a>(
+        &self,
+        client: &'a Client,
+        transaction: Option<usize>,
+    ) -> Result<ClientGuard<'a>, Error> {
+        self.when_rolled_back(|state| {
+            let statement = if transaction.is_some() && state.open == transaction {
+                None
+            } else if state.open.is_some() {
+                return Err(Error::TransactionInProgress);
+            } else {
+                let statement = self.lock.clone().try_read_owned();
+                Some(statement.map_err(|_| Error::TransactionInProgress)?)
+            };
+            Ok(ClientGuard {
+                client,
+                _statement: statement,
+            })
+        })
+        .await
+    }
+
+    /// Runs a function with the state, once the rollbacks of dropped transactions are sent.
+    async fn when_rolled_back<R>(&self, function: impl FnOnce(&mut TransactionState) -> R) -> R {
+        loop {
+            // Created before the state is checked, so a rollback sent in between wakes it.
+            let rolled_back = self.rolled_back.notified();
+            {
+                let mut state = self.state.lock().unwrap();
+                if state.pending_rollbacks == 0 {
+                    return function(&mut state);
+                }
+            }
+            rolled_back.await;
+        }
+    }
+}
+
+/// The client of a connection, holding off transactions of other clones while a statement runs.
+pub(crate) struct ClientGuard<'a> {
+    client: &'a Client,
+    _statement: Option<OwnedRwLockReadGuard<()>>,
+}
+
+impl Deref for ClientGuard<'_> {
+    type Target = Client;
+
+    fn deref(&self) -> &Client {
+        self.client
+    }
+}
+
+/// Releases a transaction or savepoint from the shared state when it is dropped.
+struct Release {
+    transactions: Arc<Transactions>,
+    transaction: usize,
+    // The exclusive lock of a transaction, `None` for a savepoint.
+    lock: Option<OwnedRwLockWriteGuard<()>>,
+}
+
+impl Drop for Release {
+    fn drop(&mut self) {
+        let mut state = self.transactions.state.lock().unwrap();
+        if state.open != Some(self.transaction) {
+            return;
+        }
+        if self.lock.is_some() {
+            state.open = None;
+            state.savepoints = 0;
+            // Released while the state is locked, so no statement sees the transaction closed
+            // while the lock is still held.
+            self.lock = None;
+        } else {
+            state.savepoints = state.savepoints.saturating_sub(1);
+        }
+    }
+}
+
+/// The isolation level of a transaction.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum IsolationLevel {
+    /// `SERIALIZABLE`
+    Serializable,
+    /// `REPEATABLE READ`
+    RepeatableRead,
+    /// `READ COMMITTED`
+    ReadCommitted,
+}
+
+/// The options of a transaction started with
+/// [`Connection::transaction_with`](struct.Connection.html#method.transaction_with).
+///
+/// The default options start a read-write transaction with the default isolation level of the
+/// server.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct TransactionOptions {
+    /// The isolation level, `None` to use the default of the server.
+    pub isolation: Option<IsolationLevel>,
+    /// Whether the transaction can only read data.
+    pub read_only: bool,
+    /// Whether a serializable, read-only transaction waits until it can run without the risk of a
+    /// serialization failure.
+    pub deferrable: bool,
+}
+
+impl TransactionOptions {
+    /// Returns the `BEGIN` statement starting a transaction with these options.
+    pub(crate) fn begin_statement(&self) -> String {
+        let mut statement = String::from("BEGIN");
+        match self.isolation {
+            Some(IsolationLevel::Serializable) => {
+                statement.push_str(" ISOLATION LEVEL SERIALIZABLE")
+            }
+            Some(IsolationLevel::RepeatableRead) => {
+                statement.push_str(" ISOLATION LEVEL REPEATABLE READ")
+            }
+            Some(IsolationLevel::ReadCommitted) => {
+                statement.push_str(" ISOLATION LEVEL READ COMMITTED")
+            }
+            None => {}
+        }
+        if self.read_only {
+            statement.push_str(" READ ONLY");
+        }
+        if self.deferrable {
+            statement.push_str(" DEFERRABLE");
+        }
+        statement
+    }
+}
+
+/// A database transaction.
+///
+/// The transaction dereferences to the [`Connection`](struct.Connection.html) it was started on,
+/// so all its methods can be used within the transaction.
+/// When the transaction is dropped without calling [`commit`](#method.commit), it is rolled back.
+///
+/// Starting a transaction while another one is open creates a savepoint within the open
+/// transaction, which is released on commit and rolled back to on rollback.
+///
+/// # Note
+///
+/// A connection can only have one transaction at a time. While the transaction is open,
+/// statements executed on clones of the connection that are not part of the transaction fail
+/// with [`Error::TransactionInProgress`](enum.Error.html#variant.TransactionInProgress), and so
+/// does starting a transaction on them.
+pub struct Transaction {
+    connection: Connection,
+    savepoint: Option<String>,
+    release: Option<Release>,
+    done: bool,
+}
+
+impl Transaction {
+    pub(crate) async fn begin(connection: &Connection, statement: &str) -> Result<Self, Error> {
+        let transactions = connection.transactions.clone();
+        let member = connection.transaction;
+        let (transaction, savepoint) = transactions
+            .when_rolled_back(|state| {
+                if member.is_some() && state.open == member {
+                    state.savepoints += 1;
+                    let savepoint = format!("sprattus_savepoint_{}", state.savepoints);
+                    Ok((state.open.unwrap(), Some(savepoint)))
+                } else if state.open.is_some() {
+                    Err(Error::TransactionInProgress)
+                } else {
+                    let transaction = TRANSACTION_COUNTER.fetch_add(1, Ordering::Relaxed);
+                    state.open = Some(transaction);
+                    Ok((transaction, None))
+                }
+            })
+            .await?;
+        let mut release = Release {
+            transactions: transactions.clone(),
+            transaction,
+            lock: None,
+        };
+        if savepoint.is_none() {
+            // Waits for the statements of other clones that are still running.
+            release.lock = Some(transactions.lock.clone().write_owned().await);
+        }
+        let mut transaction = Self {
+            connection: connection.joining(transaction),
+            savepoint,
+            release: Some(release),
+            done: false,
+        };
+        let result = match &transaction.savepoint {
+            Some(savepoint) => {
+                transaction
+                    .connection
+                    .batch_execute(format!("SAVEPOINT {}", savepoint).as_str())
+                    .await
+            }
+            None => transaction.connection.batch_execute(statement).await,
+        };
+        if let Err(error) = result {
+            transaction.done = true;
+            return Err(error);
+        }
+        Ok(transaction)
+    }
+
+    /// Consumes the transaction, committing all changes made within it.
+    pub async fn commit(mut self) -> Result<(), Error> {
+        let statement = match &self.savepoint {
+            Some(savepoint) => format!("RELEASE SAVEPOINT {}", savepoint),
+            None => String::from("COMMIT"),
+        };
+        let result = self.connection.batch_execute(statement.as_str()).await;
+        self.done = true;
+        result
+    }
+
+    /// Rolls the transaction back, discarding all changes made within it.
+    pub async fn rollback(mut self) -> Result<(), Error> {
+        let statement = self.rollback_statement();
+        let result = self.connection.batch_execute(statement.as_str()).await;
+        self.done = true;
+        result
+    }
+
+    fn rollback_statement(&self) -> String {
+        match &self.savepoint {
+            Some(savepoint) => format!(
+                "ROLLBACK TO SAVEPOINT {savepoint}; RELEASE SAVEPOINT {savepoint}",
+                savepoint = savepoint
+            ),
+            None => String::from("ROLLBACK"),
+        }
+    }
+}
+
+/// Runs a function within serializable transactions until it succeeds, or until it failed with
+/// a serialization failure `SERIALIZABLE_ATTEMPTS` times.
+pub(crate) async fn retry_serializable<F, Fut, R>(
+    connection: &Connection,
+    mut function: F,
+) -> Result<R, Error>
+where
+    F: FnMut(Connection) -> Fut,
+    Fut: Future<Output = Result<R, Error>>,
+{
+    let options = TransactionOptions {
+        isolation: Some(IsolationLevel::Serializable),
+        ..TransactionOptions::default()
+    };
+    let mut attempt = 1;
+    loop {
+        let transaction = connection.transaction_with(options).await?;
+        let result = match function(transaction.connection.clone()).await {
+            Ok(value) => transaction.commit().await.map(|_| value),
+            Err(error) => {
+                transaction.rollback().await?;
+                Err(error)
+            }
+        };
+        match result {
+            Err(error) if is_serialization_failure(&error) && attempt < SERIALIZABLE_ATTEMPTS => {
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+fn is_serialization_failure(error: &Error) -> bool {
+    match error.inner() {
+        Error::Postgres(error) => error.code() == Some(&SqlState::T_R_SERIALIZATION_FAILURE),
+        _ => false,
+    }
+}
+
+impl Deref for Transaction {
+    type Target = Connection;
+
+    fn deref(&self) -> &Connection {
+        &self.connection
+    }
+}
+
+impl Drop for Transaction {
+    fn drop(&mut self) {
+        if self.done {
+            return;
+        }
+        // The transaction stays open until the rollback is sent, and statements and transactions
+        // wait for it, so they can't be sent before it and be rolled back with it.
+        let statement = self.rollback_statement();
+        let release = self.release.take();
+        let connection = self.connection.clone();
+        let transactions = connection.transactions.clone();
+        transactions.state.lock().unwrap().pending_rollbacks += 1;
+        (connection.spawner.clone())(
+            async move {
+                connection.log_sql(statement.as_str(), &[]);
+                let _ = connection.client.batch_execute(statement.as_str()).await;
+                drop(release);
+                transactions.state.lock().unwrap().pending_rollbacks -= 1;
+                transactions.rolled_back.notify_waiters();
+            }
+            .boxed(),
+        );
+    }
+}
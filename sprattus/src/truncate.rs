@@ -0,0 +1,18 @@
+/// What happens with tables referencing a truncated table, see
+/// [`Connection::truncate_tables`](struct.Connection.html#method.truncate_tables).
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Truncate {
+    /// Refuse to truncate when other tables reference one of the tables.
+    Restrict,
+    /// Truncate the tables referencing one of the tables as well.
+    Cascade,
+}
+
+impl Truncate {
+    pub(crate) fn as_sql(self) -> &'static str {
+        match self {
+            Truncate::Restrict => "RESTRICT",
+            Truncate::Cascade => "CASCADE",
+        }
+    }
+}
@@ -0,0 +1,300 @@
+//! Support for the `unsigned` field attribute, which stores `u16` and `u64` fields in the signed
+//! integer types of Postgres, or as `NUMERIC`. Used by the code generated by the derive macros.
+//!
+//! Postgres has no unsigned integer types, so the values are checked when they are converted:
+//!
+//! | Rust type | `unsigned = "int"` | `unsigned = "numeric"` |
+//! |-----------|--------------------|------------------------|
+//! | `u16`     | `INT4`             | `NUMERIC`              |
+//! | `u64`     | `INT8`             | `NUMERIC`              |
+//!
+//! Writing a `u64` above `i64::MAX` to an `INT8` column fails, just like reading a negative value
+//! or a value that doesn't fit in the field. Both fail with an [`OutOfRange`](struct.OutOfRange.html)
+//! error naming the value. `NUMERIC` columns store every `u64`, only the values read are checked.
+//!
+//! Example:
+//! ```
+//! use sprattus::unsigned::{CheckedInt8, Numeric};
+//! use sprattus::types::Type;
+//! use sprattus::{FromSqlItem, ToSqlItem};
+//! use sprattus::export::BytesMut;
+//!
+//! let mut out = BytesMut::new();
+//! assert!(CheckedInt8(u64::MAX).to_sql(&Type::INT8, &mut out).is_err());
+//!
+//! Numeric(u64::MAX).to_sql(&Type::NUMERIC, &mut out).unwrap();
+//! let numeric = Numeric::from_sql(&Type::NUMERIC, &out).unwrap();
+//! assert_eq!(numeric, Numeric(u64::MAX));
+//! ```
+use crate::*;
+use bytes::{Buf, BufMut};
+use std::convert::TryFrom;
+use std::error;
+use std::fmt;
+use tokio_postgres::types::{to_sql_checked, IsNull, Type};
+
+/// An unsigned value that doesn't fit in the type it is converted into.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct OutOfRange {
+    /// The value as text, like `-1` or `18446744073709551615`.
+    pub value: String,
+    /// The type the value doesn't fit in, like `u16` or `INT8`.
+    pub target: &'static str,
+}
+
+impl fmt::Display for OutOfRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "the value {} is out of range for {}",
+            self.value, self.target
+        )
+    }
+}
+
+impl error::Error for OutOfRange {}
+
+impl OutOfRange {
+    fn new(value: impl fmt::Display, target: &'static str) -> Self {
+        OutOfRange {
+            value: value.to_string(),
+            target,
+        }
+    }
+}
+
+/// A `u64` that is written to an `INT8` column, which fails when it is above `i64::MAX`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct CheckedInt8(pub u64);
+
+impl ToSqlItem for CheckedInt8 {
+    fn to_sql(
+        &self,
+        ty: &Type,
+        out: &mut export::BytesMut,
+    ) -> Result<IsNull, Box<dyn error::Error + Sync + Send>> {
+        let value = i64::try_from(self.0).map_err(|_| OutOfRange::new(self.0, "INT8"))?;
+        value.to_sql(ty, out)
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        <i64 as ToSqlItem>::accepts(ty)
+    }
+
+    to_sql_checked!();
+}
+
+/// A `u64` stored in a `NUMERIC` column.
+///
+/// Reading a value that is negative, has a fractional part or is above `u64::MAX` fails.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Numeric(pub u64);
+
+/// The sign of a `NUMERIC` value in the binary format of Postgres.
+const NUMERIC_POSITIVE: u16 = 0x0000;
+const NUMERIC_NEGATIVE: u16 = 0x4000;
+const NUMERIC_NAN: u16 = 0xC000;
+
+impl ToSqlItem for Numeric {
+    fn to_sql(
+        &self,
+        _ty: &Type,
+        out: &mut export::BytesMut,
+    ) -> Result<IsNull, Box<dyn error::Error + Sync + Send>> {
+        // The digits are in base 10000, with the most significant digit first.
+        let mut digits = Vec::new();
+        let mut value = self.0;
+        while value > 0 {
+            digits.push((value % 10000) as i16);
+            value /= 10000;
+        }
+        digits.reverse();
+        let weight = digits.len().saturating_sub(1) as i16;
+        while digits.last() == Some(&0) {
+            digits.pop();
+        }
+        out.put_i16(digits.len() as i16);
+        out.put_i16(weight);
+        out.put_u16(NUMERIC_POSITIVE);
+        out.put_u16(0);
+        for digit in digits {
+            out.put_i16(digit);
+        }
+        Ok(IsNull::No)
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        *ty == Type::NUMERIC
+    }
+
+    to_sql_checked!();
+}
+
+impl<'a> FromSqlItem<'a> for Numeric {
+    fn from_sql(
+        _ty: &Type,
+        mut raw: &'a [u8],
+    ) -> Result<Self, Box<dyn error::Error + Sync + Send>> {
+        if raw.len() < 8 {
+            return Err("invalid NUMERIC value".into());
+        }
+        let count = raw.get_i16();
+        let weight = raw.get_i16();
+        let sign = raw.get_u16();
+        let _scale = raw.get_u16();
+        if raw.len() != count.max(0) as usize * 2 {
+            return Err("invalid NUMERIC value".into());
+        }
+        let digits: Vec<i16> = (0..count).map(|_| raw.get_i16()).collect();
+        let text = numeric_text(sign, weight, &digits);
+        match text.parse() {
+            Ok(value) => Ok(Numeric(value)),
+            Err(_) => Err(Box::new(OutOfRange::new(text, "u64"))),
+        }
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        *ty == Type::NUMERIC
+    }
+}
+
+/// Formats a `NUMERIC` value in the binary format of Postgres as text, without trailing zeros
+/// in its fractional part.
+fn numeric_text(sign: u16, weight: i16, digits: &[i16]) -> String {
+    if sign == NUMERIC_NAN {
+        return String::from("NaN");
+    }
+    let mut text = String::new();
+    if sign == NUMERIC_NEGATIVE {
+        text.push('-');
+    }
+    let digit = |position: i32| -> i16 {
+        usize::try_from(position)
+            .ok()
+            .and_then(|position| digits.get(position))
+            .copied()
+            .unwrap_or(0)
+    };
+    let weight = i32::from(weight);
+    if weight < 0 {
+        text.push('0');
+    } else {
+        text.push_str(&digit(0).to_string());
+        for position in 1..=weight {
+            text.push_str(&format!("{:04}", digit(position)));
+        }
+    }
+    let fraction: String = (weight + 1..digits.len() as i32)
+        .map(|position| format!("{:04}", digit(position)))
+        .collect();
+    let fraction = fraction.trim_end_matches('0');
+    if !fraction.is_empty() {
+        text.push('.');
+        text.push_str(fraction);
+    }
+    text
+}
+
+/// An unsigned integer type that can be stored with the `unsigned` attribute.
+pub trait Unsigned: Sized {
+    /// The value written to an integer column.
+    type Int: ToSqlItem;
+    /// The value read from an integer column.
+    type IntColumn: for<'a> FromSqlItem<'a>;
+    /// The value written to and read from a `NUMERIC` column.
+    type Numeric: ToSqlItem + for<'a> FromSqlItem<'a>;
+
+    fn to_int(&self) -> Self::Int;
+
+    fn from_int(value: Self::IntColumn) -> Result<Self, OutOfRange>;
+
+    fn to_numeric(&self) -> Self::Numeric;
+
+    fn from_numeric(value: Self::Numeric) -> Result<Self, OutOfRange>;
+}
+
+impl Unsigned for u16 {
+    type Int = i32;
+    type IntColumn = i32;
+    type Numeric = Numeric;
+
+    fn to_int(&self) -> i32 {
+        i32::from(*self)
+    }
+
+    fn from_int(value: i32) -> Result<Self, OutOfRange> {
+        u16::try_from(value).map_err(|_| OutOfRange::new(value, "u16"))
+    }
+
+    fn to_numeric(&self) -> Numeric {
+        Numeric(u64::from(*self))
+    }
+
+    fn from_numeric(value: Numeric) -> Result<Self, OutOfRange> {
+        u16::try_from(value.0).map_err(|_| OutOfRange::new(value.0, "u16"))
+    }
+}
+
+impl Unsigned for u64 {
+    type Int = CheckedInt8;
+    type IntColumn = i64;
+    type Numeric = Numeric;
+
+    fn to_int(&self) -> CheckedInt8 {
+        CheckedInt8(*self)
+    }
+
+    fn from_int(value: i64) -> Result<Self, OutOfRange> {
+        u64::try_from(value).map_err(|_| OutOfRange::new(value, "u64"))
+    }
+
+    fn to_numeric(&self) -> Numeric {
+        Numeric(*self)
+    }
+
+    fn from_numeric(value: Numeric) -> Result<Self, OutOfRange> {
+        Ok(value.0)
+    }
+}
+
+impl<T: Unsigned> Unsigned for Option<T> {
+    type Int = Option<T::Int>;
+    type IntColumn = Option<T::IntColumn>;
+    type Numeric = Option<T::Numeric>;
+
+    fn to_int(&self) -> Self::Int {
+        self.as_ref().map(T::to_int)
+    }
+
+    fn from_int(value: Self::IntColumn) -> Result<Self, OutOfRange> {
+        value.map(T::from_int).transpose()
+    }
+
+    fn to_numeric(&self) -> Self::Numeric {
+        self.as_ref().map(T::to_numeric)
+    }
+
+    fn from_numeric(value: Self::Numeric) -> Result<Self, OutOfRange> {
+        value.map(T::from_numeric).transpose()
+    }
+}
+
+/// The `with` conversion of `unsigned = "int"`.
+pub fn to_int<T: Unsigned>(value: &T) -> T::Int {
+    value.to_int()
+}
+
+/// The `from` conversion of `unsigned = "int"`.
+pub fn from_int<T: Unsigned>(value: T::IntColumn) -> Result<T, OutOfRange> {
+    T::from_int(value)
+}
+
+/// The `with` conversion of `unsigned = "numeric"`.
+pub fn to_numeric<T: Unsigned>(value: &T) -> T::Numeric {
+    value.to_numeric()
+}
+
+/// The `from` conversion of `unsigned = "numeric"`.
+pub fn from_numeric<T: Unsigned>(value: T::Numeric) -> Result<T, OutOfRange> {
+    T::from_numeric(value)
+}
@@ -0,0 +1,83 @@
+use crate::select::column_name;
+use crate::tenant::tenant_condition;
+use crate::*;
+
+/// The alias of the table in an upsert, used to refer to the existing row of a conflict.
+pub(crate) const UPSERT_ALIAS: &str = "U";
+
+/// The column that tells whether an upserted row was inserted or updated.
+pub(crate) const INSERTED_COLUMN: &str = "sprattus_inserted";
+
+/// What to do with a row that has the primary key of an existing row, see
+/// [`Connection::upsert_multiple_with`](struct.Connection.html#method.upsert_multiple_with).
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum OnConflict<'a> {
+    /// Keep the existing row, the conflicting row is skipped.
+    DoNothing,
+    /// Update the given fields of the existing row with the values of the conflicting row.
+    /// The fields are referred to by the names of the fields of the struct.
+    /// An empty list of fields keeps the existing row, like `DoNothing`.
+    UpdateColumns(&'a [&'a str]),
+}
+
+/// A row written by an upsert, which is either inserted or updated.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Upserted<T> {
+    /// The row did not exist and was inserted.
+    Inserted(T),
+    /// The row already existed and was updated.
+    Updated(T),
+}
+
+impl<T> Upserted<T> {
+    /// Returns whether the row was inserted.
+    pub fn is_inserted(&self) -> bool {
+        match self {
+            Upserted::Inserted(_) => true,
+            Upserted::Updated(_) => false,
+        }
+    }
+
+    /// Returns the written row.
+    pub fn into_inner(self) -> T {
+        match self {
+            Upserted::Inserted(row) | Upserted::Updated(row) => row,
+        }
+    }
+}
+
+/// Generates the `ON CONFLICT` clause of an upsert of `T`, with the primary key as conflict
+/// target.
+///
+/// # Panics
+///
+/// Panics when an updated field does not exist.
+pub(crate) fn generate_conflict_clause<T: ToSql>(on_conflict: OnConflict<'_>) -> String {
+    let fields = match on_conflict {
+        OnConflict::UpdateColumns(fields) if !fields.is_empty() => fields,
+        _ => return format!("ON CONFLICT ({}) DO NOTHING", T::get_primary_key()),
+    };
+    let assignments: Vec<String> = fields
+        .iter()
+        .map(|field| {
+            let column = column_name::<T>(field);
+            format!("{} = EXCLUDED.{}", column, column)
+        })
+        .chain(
+            T::get_updated_at_fields()
+                .iter()
+                .map(|column| format!("{} = now()", column)),
+        )
+        .collect();
+    // Rows of other tenants are never overwritten.
+    let tenant = match tenant_condition::<T>(Some(UPSERT_ALIAS)) {
+        Some(condition) => format!(" WHERE {}", condition),
+        None => String::new(),
+    };
+    format!(
+        "ON CONFLICT ({}) DO UPDATE SET {}{}",
+        T::get_primary_key(),
+        assignments.join(", "),
+        tenant
+    )
+}
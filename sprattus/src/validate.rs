@@ -0,0 +1,102 @@
+//! Support for the `validate` field attribute, which checks the values of the fields before a row
+//! is written. Used by the code generated by the derive macros.
+use std::error;
+use std::fmt;
+
+/// The fields of an item that failed their validation, returned by
+/// [`Insertable::validate`](../trait.Insertable.html#method.validate).
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ValidationError {
+    /// A failure for every rule that is not met, in the order of the fields.
+    pub fields: Vec<FieldError>,
+}
+
+/// A validation rule that a field does not meet.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FieldError {
+    /// The name of the field in the Rust struct.
+    pub field: &'static str,
+    /// Describes the rule, like `the length must be at most 255`.
+    pub message: String,
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let fields: Vec<String> = self
+            .fields
+            .iter()
+            .map(|error| format!("`{}`: {}", error.field, error.message))
+            .collect();
+        write!(f, "invalid fields: {}", fields.join(", "))
+    }
+}
+
+impl error::Error for ValidationError {}
+
+impl ValidationError {
+    /// Returns `Ok` when no field failed, and the failures otherwise.
+    pub fn check(fields: Vec<FieldError>) -> Result<(), ValidationError> {
+        if fields.is_empty() {
+            Ok(())
+        } else {
+            Err(ValidationError { fields })
+        }
+    }
+}
+
+/// A value of which the length can be validated with `length(min = .., max = ..)`.
+pub trait Length {
+    /// Returns the length, which is the number of characters of a string.
+    fn length(&self) -> usize;
+}
+
+impl Length for str {
+    fn length(&self) -> usize {
+        self.chars().count()
+    }
+}
+
+impl Length for String {
+    fn length(&self) -> usize {
+        self.as_str().length()
+    }
+}
+
+impl<T> Length for [T] {
+    fn length(&self) -> usize {
+        self.len()
+    }
+}
+
+impl<T> Length for Vec<T> {
+    fn length(&self) -> usize {
+        self.len()
+    }
+}
+
+/// Checks the length of a value, returning the failed rule.
+pub fn length<T: Length + ?Sized>(
+    value: &T,
+    min: Option<usize>,
+    max: Option<usize>,
+) -> Option<String> {
+    let length = value.length();
+    match (min, max) {
+        (Some(min), _) if length < min => Some(format!("the length must be at least {}", min)),
+        (_, Some(max)) if length > max => Some(format!("the length must be at most {}", max)),
+        _ => None,
+    }
+}
+
+/// Checks that a value lies within the bounds, returning the failed rule.
+pub fn range<T: PartialOrd + fmt::Display>(
+    value: &T,
+    min: Option<T>,
+    max: Option<T>,
+) -> Option<String> {
+    match (min, max) {
+        (Some(min), _) if *value < min => Some(format!("the value must be at least {}", min)),
+        (_, Some(max)) if *value > max => Some(format!("the value must be at most {}", max)),
+        _ => None,
+    }
+}
@@ -0,0 +1,104 @@
+use crate::*;
+use futures_util::stream::{Stream, StreamExt};
+
+/// A change made to a row of a watched table, see [`Connection::watch`](struct.Connection.html#method.watch).
+#[derive(Debug)]
+pub enum Change<T: ToSql> {
+    /// A row was inserted.
+    Inserted(T),
+    /// A row was updated, contains the new version of the row.
+    Updated(T),
+    /// A row was deleted, contains its primary key.
+    Deleted(T::PK),
+}
+
+/// Trigger function sending every changed row as a notification on the channel given as argument.
+const NOTIFY_FUNCTION: &str =
+    "CREATE OR REPLACE FUNCTION sprattus_notify_change() RETURNS trigger AS $$
+BEGIN
+    IF TG_OP = 'DELETE' THEN
+        PERFORM pg_notify(TG_ARGV[0], TG_OP || ':' || row_to_json(OLD)::text);
+        RETURN OLD;
+    END IF;
+    PERFORM pg_notify(TG_ARGV[0], TG_OP || ':' || row_to_json(NEW)::text);
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql";
+
+pub(crate) async fn watch<T>(
+    connection: &Connection,
+) -> Result<impl Stream<Item = Result<Change<T>, Error>>, Error>
+where
+    T: FromSql + ToSql,
+    T::PK: for<'a> FromSqlItem<'a>,
+{
+    let table_name = connection.table_name::<T>();
+    let channel = format!("sprattus_watch_{}", table_name.replace('"', ""));
+    connection
+        .batch_execute(
+            format!(
+                "{function};
+                DROP TRIGGER IF EXISTS sprattus_watch ON {table_name};
+                CREATE TRIGGER sprattus_watch AFTER INSERT OR UPDATE OR DELETE ON {table_name}
+                FOR EACH ROW EXECUTE PROCEDURE sprattus_notify_change('{channel}')",
+                function = NOTIFY_FUNCTION,
+                table_name = table_name,
+                channel = channel.replace('\'', "''"),
+            )
+            .as_str(),
+        )
+        .await?;
+    let notifications = connection.listen(channel.as_str()).await?;
+    let connection = connection.clone();
+
+    Ok(notifications.filter_map(move |notification| {
+        let connection = connection.clone();
+        async move {
+            let (operation, row) = notification.payload().split_once(':')?;
+            let change = match operation {
+                "INSERT" => decode_row(&connection, row).await.map(Change::Inserted),
+                "UPDATE" => decode_row(&connection, row).await.map(Change::Updated),
+                "DELETE" => decode_primary_key::<T>(&connection, row)
+                    .await
+                    .map(Change::Deleted),
+                _ => return None,
+            };
+            Some(change)
+        }
+    }))
+}
+
+/// Converts a row encoded as JSON back into a row of the table, and from there into T.
+pub(crate) async fn decode_row<T>(connection: &Connection, row: &str) -> Result<T, Error>
+where
+    T: FromSql + ToSql,
+{
+    let sql = format!(
+        "SELECT * FROM json_populate_record(NULL::{table_name}, $1::json)",
+        table_name = connection.table_name::<T>()
+    );
+    connection.query(sql.as_str(), &[&row]).await
+}
+
+/// Extracts the primary key from a row encoded as JSON.
+pub(crate) async fn decode_primary_key<T>(
+    connection: &Connection,
+    row: &str,
+) -> Result<T::PK, Error>
+where
+    T: ToSql,
+    T::PK: for<'a> FromSqlItem<'a>,
+{
+    let sql = format!(
+        "SELECT r.{primary_key} FROM json_populate_record(NULL::{table_name}, $1::json) AS r",
+        primary_key = T::get_primary_key(),
+        table_name = connection.table_name::<T>()
+    );
+    connection.log_sql(sql.as_str(), &[&row]);
+    Ok(connection
+        .client()
+        .await?
+        .query_one(sql.as_str(), &[&row])
+        .await?
+        .try_get(0)?)
+}
@@ -0,0 +1,54 @@
+use crate::*;
+use std::fmt;
+use std::sync::Arc;
+
+/// The kind of write operation of a [`WriteEvent`](struct.WriteEvent.html).
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum WriteOperation {
+    /// A row was inserted.
+    Create,
+    /// A row was updated.
+    Update,
+    /// A row was deleted.
+    Delete,
+}
+
+/// A row that was created, updated or deleted by a [`Connection`](struct.Connection.html).
+///
+/// See [`Connection::set_write_hook`](struct.Connection.html#method.set_write_hook).
+#[derive(Clone, Debug)]
+pub struct WriteEvent {
+    /// The quoted name of the table.
+    pub table: &'static str,
+    /// The kind of write operation.
+    pub operation: WriteOperation,
+    /// The debug representation of the value of the primary key.
+    pub primary_key: String,
+    /// The values of the row before the operation, `None` for created rows.
+    pub before: Option<String>,
+    /// The values of the row after the operation, `None` for deleted rows.
+    pub after: Option<String>,
+}
+
+pub(crate) type WriteHook = Arc<dyn Fn(WriteEvent) + Send + Sync>;
+
+/// Serializes the values of a row as a map of column names to the debug representation of their
+/// values, e.g. `{"id": 1, "name": "apple"}`.
+pub(crate) fn serialize_values(values: &[FieldValue<'_>]) -> String {
+    struct Values<'a>(&'a [FieldValue<'a>]);
+
+    impl fmt::Debug for Values<'_> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.debug_map()
+                .entries(self.0.iter().map(|field| (field.name, field.value)))
+                .finish()
+        }
+    }
+
+    format!("{:?}", Values(values))
+}
+
+/// Serializes all values of a row.
+pub(crate) fn serialize<T: ToSql>(item: &T) -> String {
+    serialize_values(&item.get_field_values())
+}